@@ -28,6 +28,7 @@
 #include <rust/rust.h>
 #include <secp256k1_ecdsa_s2c.h>
 #include <secp256k1_extrakeys.h>
+#include <secp256k1_musig.h>
 #include <secp256k1_schnorrsig.h>
 
 // This number of KDF iterations on the 2nd kdf slot when stretching the device
@@ -36,6 +37,8 @@
 
 // Change this ONLY via keystore_unlock() or keystore_lock()
 static bool _is_unlocked_device = false;
+// Incremented on every keystore_lock() call, see keystore_lock_generation().
+static uint32_t _lock_generation = 0;
 // Stores a random key after unlock which, after stretching, is used to encrypt the retained seed.
 static uint8_t _unstretched_retained_seed_encryption_key[32] = {0};
 // Must be defined if is_unlocked is true. ONLY ACCESS THIS WITH keystore_copy_seed().
@@ -533,9 +536,15 @@ void keystore_lock(void)
 {
     _is_unlocked_device = false;
     _is_unlocked_bip39 = false;
+    _lock_generation++;
     _delete_retained_seeds();
 }
 
+uint32_t keystore_lock_generation(void)
+{
+    return _lock_generation;
+}
+
 bool keystore_is_locked(void)
 {
     bool unlocked = _is_unlocked_device && _is_unlocked_bip39;
@@ -662,6 +671,19 @@ bool keystore_get_xpub(
     return true;
 }
 
+bool keystore_secp256k1_get_private_key(
+    const uint32_t* keypath,
+    size_t keypath_len,
+    uint8_t* key_out)
+{
+    struct ext_key xprv __attribute__((__cleanup__(keystore_zero_xkey))) = {0};
+    if (!_get_xprv_twice(keypath, keypath_len, &xprv)) {
+        return false;
+    }
+    memcpy(key_out, xprv.priv_key + 1, 32); // first byte is 0
+    return true;
+}
+
 void keystore_zero_xkey(struct ext_key* xkey)
 {
     util_zero(xkey, sizeof(struct ext_key));
@@ -941,7 +963,7 @@ bool keystore_secp256k1_schnorr_bip86_pubkey(const uint8_t* pubkey33, uint8_t* p
     return secp256k1_xonly_pubkey_serialize(ctx, pubkey_out, &tweaked_xonly_pubkey) == 1;
 }
 
-static bool _schnorr_bip86_keypair(
+static bool _schnorr_keypair(
     const uint32_t* keypath,
     size_t keypath_len,
     secp256k1_keypair* keypair_out,
@@ -959,9 +981,19 @@ static bool _schnorr_bip86_keypair(
     if (!secp256k1_keypair_create(ctx, keypair_out, secret_key)) {
         return false;
     }
-    if (!secp256k1_keypair_xonly_pub(ctx, pubkey_out, NULL, keypair_out)) {
+    return secp256k1_keypair_xonly_pub(ctx, pubkey_out, NULL, keypair_out) == 1;
+}
+
+static bool _schnorr_bip86_keypair(
+    const uint32_t* keypath,
+    size_t keypath_len,
+    secp256k1_keypair* keypair_out,
+    secp256k1_xonly_pubkey* pubkey_out)
+{
+    if (!_schnorr_keypair(keypath, keypath_len, keypair_out, pubkey_out)) {
         return false;
     }
+    const secp256k1_context* ctx = wally_get_secp_context();
     uint8_t pubkey_serialized[32] = {0};
     if (!secp256k1_xonly_pubkey_serialize(ctx, pubkey_serialized, pubkey_out)) {
         return false;
@@ -1000,6 +1032,149 @@ bool keystore_secp256k1_schnorr_bip86_sign(
     return secp256k1_schnorrsig_verify(ctx, sig64_out, msg32, 32, &pubkey) == 1;
 }
 
+bool keystore_secp256k1_schnorr_sign(
+    const uint32_t* keypath,
+    size_t keypath_len,
+    const uint8_t* msg32,
+    uint8_t* sig64_out)
+{
+    secp256k1_keypair __attribute__((__cleanup__(_cleanup_keypair))) keypair = {0};
+    secp256k1_xonly_pubkey pubkey = {0};
+    if (!_schnorr_keypair(keypath, keypath_len, &keypair, &pubkey)) {
+        return false;
+    }
+    const secp256k1_context* ctx = wally_get_secp_context();
+    uint8_t aux_rand[32] = {0};
+    random_32_bytes(aux_rand);
+    if (secp256k1_schnorrsig_sign32(ctx, sig64_out, msg32, &keypair, aux_rand) != 1) {
+        return false;
+    }
+    return secp256k1_schnorrsig_verify(ctx, sig64_out, msg32, 32, &pubkey) == 1;
+}
+
+#define MUSIG_MAX_SIGNERS 15
+
+// MuSig2 secret nonce state. The secret nonce is generated in
+// `keystore_secp256k1_musig_nonce_gen()` and consumed (and zeroed) in
+// `keystore_secp256k1_musig_partial_sign()`. It must never be used to sign more than once.
+static secp256k1_musig_secnonce _musig_secnonce = {0};
+static bool _musig_secnonce_valid = false;
+
+bool keystore_secp256k1_musig_nonce_gen(
+    const uint32_t* keypath,
+    size_t keypath_len,
+    const uint8_t* msg32,
+    uint8_t* pubnonce66_out)
+{
+    // Invalidate any previously generated nonce.
+    _musig_secnonce_valid = false;
+    util_zero(&_musig_secnonce, sizeof(_musig_secnonce));
+
+    if (keystore_is_locked()) {
+        return false;
+    }
+    struct ext_key xprv __attribute__((__cleanup__(keystore_zero_xkey))) = {0};
+    if (!_get_xprv(keypath, keypath_len, &xprv)) {
+        return false;
+    }
+    const uint8_t* secret_key = xprv.priv_key + 1; // first byte is 0;
+    const secp256k1_context* ctx = wally_get_secp_context();
+    secp256k1_pubkey pubkey = {0};
+    if (!secp256k1_ec_pubkey_create(ctx, &pubkey, secret_key)) {
+        return false;
+    }
+    uint8_t session_id[32] = {0};
+    random_32_bytes(session_id);
+    secp256k1_musig_pubnonce pubnonce = {0};
+    if (secp256k1_musig_nonce_gen(
+            ctx, &_musig_secnonce, &pubnonce, session_id, secret_key, &pubkey, msg32, NULL, NULL) !=
+        1) {
+        return false;
+    }
+    if (secp256k1_musig_pubnonce_serialize(ctx, pubnonce66_out, &pubnonce) != 1) {
+        util_zero(&_musig_secnonce, sizeof(_musig_secnonce));
+        return false;
+    }
+    _musig_secnonce_valid = true;
+    return true;
+}
+
+bool keystore_secp256k1_musig_partial_sign(
+    const uint32_t* keypath,
+    size_t keypath_len,
+    const uint8_t* msg32,
+    const uint8_t* pubkeys33,
+    size_t num_signers,
+    const uint8_t* pubnonces66,
+    uint8_t* partial_sig32_out)
+{
+    if (!_musig_secnonce_valid) {
+        return false;
+    }
+    if (num_signers < 2 || num_signers > MUSIG_MAX_SIGNERS) {
+        return false;
+    }
+    secp256k1_keypair __attribute__((__cleanup__(_cleanup_keypair))) keypair = {0};
+    secp256k1_xonly_pubkey our_pubkey = {0};
+    if (!_schnorr_keypair(keypath, keypath_len, &keypair, &our_pubkey)) {
+        return false;
+    }
+    const secp256k1_context* ctx = wally_get_secp_context();
+
+    secp256k1_pubkey pubkeys[MUSIG_MAX_SIGNERS] = {0};
+    const secp256k1_pubkey* pubkey_ptrs[MUSIG_MAX_SIGNERS] = {0};
+    for (size_t i = 0; i < num_signers; i++) {
+        if (!secp256k1_ec_pubkey_parse(ctx, &pubkeys[i], &pubkeys33[i * 33], 33)) {
+            return false;
+        }
+        pubkey_ptrs[i] = &pubkeys[i];
+    }
+    secp256k1_musig_keyagg_cache keyagg_cache = {0};
+    secp256k1_xonly_pubkey agg_pubkey = {0};
+    if (secp256k1_musig_pubkey_agg(
+            ctx, NULL, &agg_pubkey, &keyagg_cache, pubkey_ptrs, num_signers) != 1) {
+        return false;
+    }
+    // Apply the taproot tweak so that the final signature verifies against the tweaked output key
+    // (BIP-341, no script tree; same as BIP-86).
+    uint8_t agg_pubkey_serialized[32] = {0};
+    if (!secp256k1_xonly_pubkey_serialize(ctx, agg_pubkey_serialized, &agg_pubkey)) {
+        return false;
+    }
+    uint8_t tweak[32] = {0};
+    _tagged_hash("TapTweak", agg_pubkey_serialized, sizeof(agg_pubkey_serialized), tweak);
+    if (secp256k1_musig_pubkey_xonly_tweak_add(ctx, NULL, &keyagg_cache, tweak) != 1) {
+        return false;
+    }
+
+    secp256k1_musig_pubnonce pubnonces[MUSIG_MAX_SIGNERS] = {0};
+    const secp256k1_musig_pubnonce* pubnonce_ptrs[MUSIG_MAX_SIGNERS] = {0};
+    for (size_t i = 0; i < num_signers; i++) {
+        if (secp256k1_musig_pubnonce_parse(ctx, &pubnonces[i], &pubnonces66[i * 66]) != 1) {
+            return false;
+        }
+        pubnonce_ptrs[i] = &pubnonces[i];
+    }
+    secp256k1_musig_aggnonce aggnonce = {0};
+    if (secp256k1_musig_nonce_agg(ctx, &aggnonce, pubnonce_ptrs, num_signers) != 1) {
+        return false;
+    }
+    secp256k1_musig_session session = {0};
+    if (secp256k1_musig_nonce_process(ctx, &session, &aggnonce, msg32, &keyagg_cache, NULL) != 1) {
+        return false;
+    }
+
+    // The secret nonce is consumed (and zeroed by secp256k1_musig_partial_sign()) below; it must
+    // never be used to sign more than once.
+    _musig_secnonce_valid = false;
+    secp256k1_musig_partial_sig partial_sig = {0};
+    if (secp256k1_musig_partial_sign(
+            ctx, &partial_sig, &_musig_secnonce, &keypair, &keyagg_cache, &session) != 1) {
+        return false;
+    }
+    return secp256k1_musig_partial_sig_serialize(ctx, partial_sig32_out, &partial_sig) == 1;
+}
+
 #ifdef TESTING
 void keystore_mock_unlocked(const uint8_t* seed, size_t seed_len, const uint8_t* bip39_seed)
 {
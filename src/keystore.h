@@ -115,6 +115,12 @@ void keystore_lock(void);
  */
 USE_RESULT bool keystore_is_locked(void);
 
+/**
+ * @return a counter that is incremented on every keystore_lock() call. Can be used to invalidate
+ * session caches that must not outlive a lock.
+ */
+USE_RESULT uint32_t keystore_lock_generation(void);
+
 /**
  * @param[out] mnemonic_out resulting mnemonic
  * @param[in] mnemonic_out_size size of mnemonic_out. Should be at least 216 bytes (longest possible
@@ -221,6 +227,20 @@ USE_RESULT bool keystore_secp256k1_sign(
     uint8_t* sig_compact_out,
     int* recid_out);
 
+/**
+ * Copies the secp256k1 private key at the given keypath. Only meant for keys which by design leave
+ * the device, such as the BIP-352 silent payments scan key - never expose a key which can spend
+ * funds through this.
+ * @param[in] keypath derivation keypath
+ * @param[in] keypath_len size of keypath buffer
+ * @param[out] key_out resulting private key, must be 32 bytes.
+ * @return true on success, false if the keystore is locked.
+ */
+USE_RESULT bool keystore_secp256k1_get_private_key(
+    const uint32_t* keypath,
+    size_t keypath_len,
+    uint8_t* key_out);
+
 /**
  * Get the seed to be used for u2f
  * @param seed_out Buffer for seed, must be KEYSTORE_U2F_SEED_LENGTH
@@ -303,6 +323,63 @@ USE_RESULT bool keystore_secp256k1_schnorr_bip86_sign(
     const uint8_t* msg32,
     uint8_t* sig64_out);
 
+/**
+ * Sign a message with the untweaked private key at the keypath (BIP-340 Schnorr signature). Used
+ * for taproot script path spends, where the key is not tweaked as in BIP-86.
+ *
+ * @param[in] keypath derivation keypath
+ * @param[in] keypath_len number of elements in keypath
+ * @param[in] msg32 32 byte message to sign
+ * @param[out] sig64_out resulting 64 byte signature
+ */
+USE_RESULT bool keystore_secp256k1_schnorr_sign(
+    const uint32_t* keypath,
+    size_t keypath_len,
+    const uint8_t* msg32,
+    uint8_t* sig64_out);
+
+/**
+ * Generate a fresh MuSig2 (BIP-327) nonce pair for the key at the keypath and the message to be
+ * signed. The secret nonce is retained in the keystore until it is consumed by
+ * `keystore_secp256k1_musig_partial_sign()`; generating a new nonce invalidates any previously
+ * generated one. A secret nonce is used at most once.
+ *
+ * @param[in] keypath derivation keypath
+ * @param[in] keypath_len number of elements in keypath
+ * @param[in] msg32 32 byte message that will be signed
+ * @param[out] pubnonce66_out resulting 66 byte serialized public nonce
+ */
+USE_RESULT bool keystore_secp256k1_musig_nonce_gen(
+    const uint32_t* keypath,
+    size_t keypath_len,
+    const uint8_t* msg32,
+    uint8_t* pubnonce66_out);
+
+/**
+ * Create a MuSig2 (BIP-327) partial signature using the secret nonce generated by
+ * `keystore_secp256k1_musig_nonce_gen()`. The aggregate key of all signers is tweaked with the
+ * taproot tweak of BIP-341 (without a script tree), so the final signature verifies against the
+ * corresponding taproot output key. The secret nonce is invalidated by this call.
+ *
+ * @param[in] keypath derivation keypath, deriving the same key as the signer's entry in pubkeys33
+ * @param[in] keypath_len number of elements in keypath
+ * @param[in] msg32 32 byte message to sign
+ * @param[in] pubkeys33 `num_signers` serialized 33 byte pubkeys of all signers, in key aggregation
+ * order
+ * @param[in] num_signers number of signers, at least 2 and at most 15
+ * @param[in] pubnonces66 `num_signers` serialized 66 byte public nonces of all signers, in the same
+ * order as pubkeys33
+ * @param[out] partial_sig32_out resulting 32 byte partial signature
+ */
+USE_RESULT bool keystore_secp256k1_musig_partial_sign(
+    const uint32_t* keypath,
+    size_t keypath_len,
+    const uint8_t* msg32,
+    const uint8_t* pubkeys33,
+    size_t num_signers,
+    const uint8_t* pubnonces66,
+    uint8_t* partial_sig32_out);
+
 #ifdef TESTING
 /**
  * convenience to mock the keystore state (locked, seed) in tests.
@@ -147,6 +147,7 @@ static_assert(sizeof(((chunk_7_t*)0)->fields) <= (size_t)CHUNK_SIZE, "chunk too
 #define BITMASK_SEEDED ((uint8_t)(1u << 0u))
 #define BITMASK_INITIALIZED ((uint8_t)(1u << 1u))
 #define BITMASK_ENABLE_MNEMONIC_PASSPHRASE ((uint8_t)(1u << 2u))
+#define BITMASK_REQUIRE_ANTIKLEPTO ((uint8_t)(1u << 3u))
 
 static void _clean_chunk(uint8_t** chunk_bytes)
 {
@@ -426,6 +427,26 @@ bool memory_set_mnemonic_passphrase_enabled(bool enabled)
     return _write_chunk(CHUNK_1, chunk.bytes);
 }
 
+bool memory_is_antiklepto_required(void)
+{
+    return _is_bitmask_flag_set(BITMASK_REQUIRE_ANTIKLEPTO);
+}
+
+bool memory_set_antiklepto_required(bool required)
+{
+    chunk_1_t chunk = {0};
+    CLEANUP_CHUNK(chunk);
+    _read_chunk(CHUNK_1, chunk_bytes);
+    uint8_t bitmask = ~chunk.fields.bitmask;
+    if (required) {
+        bitmask |= BITMASK_REQUIRE_ANTIKLEPTO;
+    } else {
+        bitmask &= ~BITMASK_REQUIRE_ANTIKLEPTO;
+    }
+    chunk.fields.bitmask = ~bitmask;
+    return _write_chunk(CHUNK_1, chunk.bytes);
+}
+
 uint8_t memory_get_failed_unlock_attempts(void)
 {
     chunk_1_t chunk = {0};
@@ -826,6 +847,28 @@ memory_result_t memory_multisig_set_by_hash(const uint8_t* hash, const char* nam
     return MEMORY_OK;
 }
 
+bool memory_multisig_erase_by_hash(const uint8_t* hash)
+{
+    if (hash == NULL) {
+        return false;
+    }
+    chunk_2_t chunk = {0};
+    CLEANUP_CHUNK(chunk);
+    _read_chunk(CHUNK_2, chunk.bytes);
+
+    for (size_t i = 0; i < MEMORY_MULTISIG_NUM_ENTRIES; i++) {
+        multisig_configuration_t* multisig = &chunk.fields.multisig_configs[i];
+        if (MEMEQ(multisig->hash, hash, sizeof(multisig->hash))) {
+            // Restore the erased state: the entry is considered empty/unset if the hash is
+            // filled with 0xFF.
+            memset(multisig->hash, 0xFF, sizeof(multisig->hash));
+            memset(multisig->name, 0xFF, sizeof(multisig->name));
+            return _write_chunk(CHUNK_2, chunk.bytes);
+        }
+    }
+    return false;
+}
+
 bool memory_multisig_get_by_hash(const uint8_t* hash, char* name_out)
 {
     chunk_2_t chunk = {0};
@@ -112,6 +112,17 @@ USE_RESULT bool memory_is_mnemonic_passphrase_enabled(void);
  */
 USE_RESULT bool memory_set_mnemonic_passphrase_enabled(bool enabled);
 
+/**
+ * Returns true if the anti-klepto protocol is required for every signature.
+ */
+USE_RESULT bool memory_is_antiklepto_required(void);
+
+/**
+ * Activates or deactivates the requirement that every signature uses the anti-klepto protocol.
+ * @return true on success, false on failure.
+ */
+USE_RESULT bool memory_set_antiklepto_required(bool required);
+
 /**
  * @return The number of failed unlock attempts.
  */
@@ -258,4 +269,11 @@ USE_RESULT memory_result_t memory_multisig_set_by_hash(const uint8_t* hash, cons
  */
 USE_RESULT bool memory_multisig_get_by_hash(const uint8_t* hash, char* name_out);
 
+/**
+ * Erases a previously stored multisig config identified by `hash`, freeing its slot.
+ * @param[in] hash hash identifying the multisig config.
+ * @return true if the multisig config was found and erased, false otherwise.
+ */
+USE_RESULT bool memory_multisig_erase_by_hash(const uint8_t* hash);
+
 #endif // _MEMORY_H_
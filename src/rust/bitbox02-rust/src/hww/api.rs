@@ -33,6 +33,7 @@ mod reset;
 mod restore;
 mod rootfingerprint;
 mod sdcard;
+mod set_antiklepto_required;
 mod set_device_name;
 mod set_mnemonic_passphrase_enabled;
 mod set_password;
@@ -131,6 +132,7 @@ fn can_call(request: &Request) -> bool {
 
         Request::CheckBackup(_) => matches!(state, State::Initialized),
         Request::SetMnemonicPassphraseEnabled(_) => matches!(state, State::Initialized),
+        Request::SetAntikleptoRequired(_) => matches!(state, State::Initialized),
         Request::Eth(_) => matches!(state, State::Initialized),
         Request::Reset(_) => matches!(state, State::Initialized),
         Request::Cardano(_) => matches!(state, State::Initialized),
@@ -149,6 +151,9 @@ async fn process_api(request: &Request) -> Result<Response, Error> {
         Request::SetMnemonicPassphraseEnabled(ref request) => {
             set_mnemonic_passphrase_enabled::process(request).await
         }
+        Request::SetAntikleptoRequired(ref request) => {
+            set_antiklepto_required::process(request).await
+        }
         Request::InsertRemoveSdcard(ref request) => sdcard::process(request).await,
         Request::ListBackups(_) => backup::list(),
         Request::CheckSdcard(_) => Ok(Response::CheckSdcard(pb::CheckSdCardResponse {
@@ -15,11 +15,16 @@
 #[cfg(not(feature = "app-bitcoin"))]
 compile_error!("Bitcoin code is being compiled even though the app-bitcoin feature is not enabled");
 
+mod accounts;
+mod bech32;
 mod bip143;
 mod bip341;
 pub mod common;
+mod descriptor;
 pub mod keypath;
+mod legacy;
 mod multisig;
+mod musig2;
 pub mod params;
 mod payment_request;
 mod policies;
@@ -32,7 +37,7 @@ pub mod signtx;
 use super::pb;
 use super::Error;
 
-use crate::workflow::confirm;
+use crate::workflow::{confirm, transaction};
 
 use util::bip32::HARDENED;
 
@@ -41,12 +46,13 @@ use crate::keystore;
 use pb::btc_pub_request::{Output, XPubType};
 use pb::btc_request::Request;
 use pb::btc_script_config::{Config, SimpleType};
-use pb::btc_script_config::{Multisig, Policy};
+use pb::btc_script_config::{Multisig, Musig2, Policy};
 use pb::response::Response;
 use pb::BtcCoin;
 use pb::BtcScriptConfig;
 
 use alloc::string::String;
+use alloc::vec::Vec;
 
 /// Like `hww::next_request`, but for Bitcoin requests/responses.
 pub async fn next_request(response: pb::btc_response::Response) -> Result<Request, Error> {
@@ -88,7 +94,7 @@ pub async fn antiklepto_get_host_nonce(
 fn coin_enabled(coin: pb::BtcCoin) -> Result<(), Error> {
     use pb::BtcCoin::*;
     #[cfg(feature = "app-bitcoin")]
-    if let Btc | Tbtc = coin {
+    if let Btc | Tbtc | Rbtc | Sbtc = coin {
         return Ok(());
     }
     #[cfg(feature = "app-litecoin")]
@@ -98,22 +104,121 @@ fn coin_enabled(coin: pb::BtcCoin) -> Result<(), Error> {
     Err(Error::Disabled)
 }
 
+/// Warns about an account index above the range wallets normally create (see
+/// `keypath::is_unusual_account`). Such accounts are accepted so that coins sent to them remain
+/// accessible, but only after an explicit confirmation, as they usually indicate a mistake by the
+/// host.
+async fn confirm_unusual_account(keypath_account: &[u32]) -> Result<(), Error> {
+    confirm::confirm(&confirm::Params {
+        title: "Warning",
+        body: &format!(
+            "Unusual account number in keypath {}. Proceed only if you know what you are doing.",
+            util::bip32::to_string(keypath_account)
+        ),
+        scrollable: true,
+        longtouch: true,
+        ..Default::default()
+    })
+    .await?;
+    Ok(())
+}
+
+/// Returns `Err` if the SLIP-132 `xpub_type` does not fit the keypath's BIP-44 purpose, e.g. a
+/// zpub (P2WPKH) requested for a 49' (P2WPKH-P2SH) keypath. Plain xpub/tpub are accepted for any
+/// keypath.
+fn validate_xpub_type(xpub_type: XPubType, keypath: &[u32]) -> Result<(), Error> {
+    use XPubType::*;
+    let purpose = keypath.first().copied();
+    match xpub_type {
+        Xpub | Tpub => Ok(()),
+        Ypub | Upub if purpose == Some(49 + HARDENED) => Ok(()),
+        Zpub | Vpub if purpose == Some(84 + HARDENED) => Ok(()),
+        CapitalYpub | CapitalUpub | CapitalZpub | CapitalVpub
+            if purpose == Some(48 + HARDENED) =>
+        {
+            Ok(())
+        }
+        _ => Err(Error::InvalidInput),
+    }
+}
+
+/// Number of confirmed xpub exports remembered until the keystore is locked.
+const XPUB_EXPORT_CACHE_SIZE: usize = 5;
+
+struct XpubExportCacheEntry {
+    /// Value of `keystore::lock_generation()` at the time of the confirmation. Entries from
+    /// previous sessions never match again, as the generation changes with every lock.
+    lock_generation: u32,
+    coin: BtcCoin,
+    xpub_type: XPubType,
+    keypath: Vec<u32>,
+}
+
+struct XpubExportCache(core::cell::RefCell<Vec<XpubExportCacheEntry>>);
+
+// Safety: must not be accessed concurrently.
+unsafe impl Sync for XpubExportCache {}
+
+/// Caches which xpub exports the user confirmed since the last keystore lock, so wallet setup
+/// flows exporting the same xpub repeatedly do not train the user to click through confirmations.
+/// Tagging the entries with the lock generation clears the cache on every lock, which also covers
+/// reset and passphrase changes, as those require a fresh unlock.
+static XPUB_EXPORT_CACHE: XpubExportCache = XpubExportCache(core::cell::RefCell::new(Vec::new()));
+
+/// Returns true if this exact export was confirmed by the user since the last keystore lock.
+fn is_xpub_export_cached(coin: BtcCoin, xpub_type: XPubType, keypath: &[u32]) -> bool {
+    let lock_generation = bitbox02::keystore::lock_generation();
+    XPUB_EXPORT_CACHE.0.borrow().iter().any(|entry| {
+        entry.lock_generation == lock_generation
+            && entry.coin == coin
+            && entry.xpub_type == xpub_type
+            && entry.keypath == keypath
+    })
+}
+
+/// Remembers a confirmed xpub export for the current session, evicting the oldest entry if the
+/// cache is full.
+fn cache_xpub_export(coin: BtcCoin, xpub_type: XPubType, keypath: &[u32]) {
+    let lock_generation = bitbox02::keystore::lock_generation();
+    let mut cache = XPUB_EXPORT_CACHE.0.borrow_mut();
+    cache.retain(|entry| entry.lock_generation == lock_generation);
+    if cache.len() >= XPUB_EXPORT_CACHE_SIZE {
+        cache.remove(0);
+    }
+    cache.push(XpubExportCacheEntry {
+        lock_generation,
+        coin,
+        xpub_type,
+        keypath: keypath.to_vec(),
+    });
+}
+
 /// Processes an xpub api call.
 async fn xpub(
     coin: BtcCoin,
     xpub_type: XPubType,
     keypath: &[u32],
     display: bool,
+    allow_nonstandard: bool,
 ) -> Result<Response, Error> {
+    validate_xpub_type(xpub_type, keypath)?;
     let params = params::get(coin);
     let is_unusual =
         keypath::validate_xpub(keypath, params.bip44_coin, params.taproot_support).is_err();
+    // If the user already confirmed this exact export since the last keystore lock, we don't ask
+    // again. Unusual keypaths are never cached and always need a fresh confirmation.
+    let is_cached = display && !is_unusual && is_xpub_export_cached(coin, xpub_type, keypath);
     if is_unusual {
-        // For unusual keypaths, we allow export after a confirmation.
+        // Nonstandard keypaths must be requested explicitly by the host and pass the relaxed
+        // structural checks; the user then confirms an explicit warning.
+        if !allow_nonstandard {
+            return Err(Error::InvalidInput);
+        }
+        keypath::validate_xpub_nonstandard(keypath).or(Err(Error::InvalidInput))?;
         confirm::confirm(&confirm::Params {
             title: if display { "xpub" } else { "Export xpub" },
             body: &format!(
-                "Warning: unusual keypath {}. Proceed only if you know what you are doing.",
+                "Warning: keypath {} does not match a known standard. Proceed only if you know what you are doing.",
                 util::bip32::to_string(keypath)
             ),
             scrollable: true,
@@ -121,17 +226,24 @@ async fn xpub(
             ..Default::default()
         })
         .await?
+    } else if keypath::is_unusual_account(keypath) && !is_cached {
+        confirm_unusual_account(keypath).await?;
     }
     let xpub = keystore::get_xpub(keypath)
         .or(Err(Error::InvalidInput))?
         .serialize_str(xpub_type)?;
-    if display {
+    // Nonstandard exports always show the xpub so the user can verify what is being exported.
+    if (display || is_unusual) && !is_cached {
         let title = if is_unusual {
             "".into()
         } else if keypath == [45 + HARDENED] {
             format!("{}\nat\n{}", params.name, util::bip32::to_string(keypath))
         } else {
-            format!("{}\naccount #{}", params.name, keypath[2] - HARDENED + 1)
+            // A registered account name replaces the account number.
+            match accounts::get_name(coin, keypath) {
+                Some(name) => format!("{}\n{}", params.name, name),
+                None => format!("{}\naccount #{}", params.name, keypath[2] - HARDENED + 1),
+            }
         };
 
         let confirm_params = confirm::Params {
@@ -141,15 +253,21 @@ async fn xpub(
             ..Default::default()
         };
         confirm::confirm(&confirm_params).await?;
+        if !is_unusual {
+            cache_xpub_export(coin, xpub_type, keypath);
+        }
     }
-    Ok(Response::Pub(pb::PubResponse { r#pub: xpub }))
+    Ok(Response::Pub(pb::PubResponse {
+        r#pub: xpub,
+        pub_key_script: Vec::new(),
+    }))
 }
 
-pub fn derive_address_simple(
+fn payload_simple(
     coin: BtcCoin,
     simple_type: SimpleType,
     keypath: &[u32],
-) -> Result<String, Error> {
+) -> Result<common::Payload, Error> {
     let coin_params = params::get(coin);
     keypath::validate_address_simple(
         keypath,
@@ -159,13 +277,20 @@ pub fn derive_address_simple(
         keypath::ReceiveSpend::Receive,
     )
     .or(Err(Error::InvalidInput))?;
-    Ok(common::Payload::from_simple(
+    common::Payload::from_simple(
         &mut crate::xpubcache::XpubCache::new(),
         coin_params,
         simple_type,
         keypath,
-    )?
-    .address(coin_params)?)
+    )
+}
+
+pub fn derive_address_simple(
+    coin: BtcCoin,
+    simple_type: SimpleType,
+    keypath: &[u32],
+) -> Result<String, Error> {
+    Ok(payload_simple(coin, simple_type, keypath)?.address(params::get(coin))?)
 }
 
 /// Processes a SimpleType (single-sig) address api call.
@@ -175,17 +300,30 @@ async fn address_simple(
     keypath: &[u32],
     display: bool,
 ) -> Result<Response, Error> {
-    let address = derive_address_simple(coin, simple_type, keypath)?;
+    let payload = payload_simple(coin, simple_type, keypath)?;
+    let address = payload.address(params::get(coin))?;
+    if keypath::is_unusual_account(keypath) {
+        confirm_unusual_account(&keypath[..keypath.len() - 2]).await?;
+    }
     if display {
+        // A registered account name is shown in the title, so the user can tell which of their
+        // accounts the address belongs to.
+        let title = match accounts::get_name(coin, &keypath[..keypath.len() - 2]) {
+            Some(name) => format!("{}\n{}", params::get(coin).name, name),
+            None => params::get(coin).name.into(),
+        };
         let confirm_params = confirm::Params {
-            title: params::get(coin).name,
+            title: &title,
             body: &address,
             scrollable: true,
             ..Default::default()
         };
         confirm::confirm(&confirm_params).await?;
     }
-    Ok(Response::Pub(pb::PubResponse { r#pub: address }))
+    Ok(Response::Pub(pb::PubResponse {
+        r#pub: address,
+        pub_key_script: payload.pk_script(params::get(coin))?,
+    }))
 }
 
 /// Processes a multisig address api call.
@@ -208,13 +346,57 @@ pub async fn address_multisig(
     if display {
         multisig::confirm(title, coin_params, &name, multisig).await?;
     }
-    let address = common::Payload::from_multisig(
+    let payload = common::Payload::from_multisig(
         coin_params,
         multisig,
         keypath[keypath.len() - 2],
         keypath[keypath.len() - 1],
-    )?
-    .address(coin_params)?;
+    )?;
+    let address = payload.address(coin_params)?;
+    if display {
+        confirm::confirm(&confirm::Params {
+            title,
+            body: &address,
+            scrollable: true,
+            ..Default::default()
+        })
+        .await?;
+    }
+    Ok(Response::Pub(pb::PubResponse {
+        r#pub: address,
+        pub_key_script: payload.pk_script(coin_params)?,
+    }))
+}
+
+/// Processes a MuSig2 address api call.
+pub async fn address_musig2(
+    coin: BtcCoin,
+    musig2: &Musig2,
+    keypath: &[u32],
+    display: bool,
+) -> Result<Response, Error> {
+    let coin_params = params::get(coin);
+    if !coin_params.taproot_support {
+        return Err(Error::InvalidInput);
+    }
+    keypath::validate_address_policy(keypath, keypath::ReceiveSpend::Receive)
+        .or(Err(Error::InvalidInput))?;
+    let account_keypath = &keypath[..keypath.len() - 2];
+    musig2::validate(musig2, account_keypath)?;
+    let name = match musig2::get_name(coin, musig2, account_keypath)? {
+        Some(name) => name,
+        None => return Err(Error::InvalidInput),
+    };
+    let title = "Receive to";
+    if display {
+        musig2::confirm(title, coin_params, &name, musig2).await?;
+    }
+    let payload = common::Payload::from_musig2(
+        musig2,
+        keypath[keypath.len() - 2],
+        keypath[keypath.len() - 1],
+    )?;
+    let address = payload.address(coin_params)?;
     if display {
         confirm::confirm(&confirm::Params {
             title,
@@ -224,7 +406,10 @@ pub async fn address_multisig(
         })
         .await?;
     }
-    Ok(Response::Pub(pb::PubResponse { r#pub: address }))
+    Ok(Response::Pub(pb::PubResponse {
+        r#pub: address,
+        pub_key_script: payload.pk_script(coin_params)?,
+    }))
 }
 
 /// Processes a policy address api call.
@@ -254,7 +439,8 @@ async fn address_policy(
             .await?;
     }
 
-    let address = common::Payload::from_policy(&parsed, keypath)?.address(coin_params)?;
+    let payload = common::Payload::from_policy(&parsed, keypath)?;
+    let address = payload.address(coin_params)?;
     if display {
         confirm::confirm(&confirm::Params {
             title,
@@ -264,7 +450,10 @@ async fn address_policy(
         })
         .await?;
     }
-    Ok(Response::Pub(pb::PubResponse { r#pub: address }))
+    Ok(Response::Pub(pb::PubResponse {
+        r#pub: address,
+        pub_key_script: payload.pk_script(coin_params)?,
+    }))
 }
 
 /// Handle a Bitcoin xpub/address protobuf api call.
@@ -275,7 +464,14 @@ pub async fn process_pub(request: &pb::BtcPubRequest) -> Result<Response, Error>
         None => Err(Error::InvalidInput),
         Some(Output::XpubType(xpub_type)) => {
             let xpub_type = XPubType::try_from(xpub_type)?;
-            xpub(coin, xpub_type, &request.keypath, request.display).await
+            xpub(
+                coin,
+                xpub_type,
+                &request.keypath,
+                request.display,
+                request.allow_nonstandard,
+            )
+            .await
         }
         Some(Output::ScriptConfig(BtcScriptConfig {
             config: Some(Config::SimpleType(simple_type)),
@@ -289,10 +485,304 @@ pub async fn process_pub(request: &pb::BtcPubRequest) -> Result<Response, Error>
         Some(Output::ScriptConfig(BtcScriptConfig {
             config: Some(Config::Policy(ref policy)),
         })) => address_policy(coin, policy, &request.keypath, request.display).await,
+        Some(Output::ScriptConfig(BtcScriptConfig {
+            config: Some(Config::Musig2(ref musig2)),
+        })) => address_musig2(coin, musig2, &request.keypath, request.display).await,
+        Some(Output::ScriptConfig(BtcScriptConfig {
+            config: Some(Config::Miniscript(ref miniscript)),
+        })) => {
+            let policy = policies::wrap_miniscript(miniscript);
+            address_policy(coin, &policy, &request.keypath, request.display).await
+        }
         _ => Err(Error::InvalidInput),
     }
 }
 
+/// Number of addresses a single `BTCAddressesRequest` can derive at most.
+const ADDRESSES_MAX_COUNT: u32 = 100;
+
+/// Handles a batch address derivation api call, used by hosts for wallet rescan during account
+/// discovery. The account is confirmed once; the derived addresses and their scriptPubKeys are
+/// returned without per-address dialogs, so the host does not have to derive them silently from
+/// the exported xpub. With `silent`, the account confirmation is skipped as well, which is only
+/// allowed for change addresses of non-unusual accounts.
+async fn process_addresses(
+    request: &pb::BtcAddressesRequest,
+) -> Result<pb::btc_response::Response, Error> {
+    let coin = BtcCoin::try_from(request.coin)?;
+    coin_enabled(coin)?;
+    let coin_params = params::get(coin);
+    if request.count == 0 || request.count > ADDRESSES_MAX_COUNT {
+        return Err(Error::InvalidInput);
+    }
+    let end_index = request
+        .start_index
+        .checked_add(request.count)
+        .ok_or(Error::InvalidInput)?;
+    let simple_type = match request.script_config {
+        Some(BtcScriptConfig {
+            config: Some(Config::SimpleType(simple_type)),
+        }) => SimpleType::try_from(simple_type)?,
+        _ => return Err(Error::InvalidInput),
+    };
+    keypath::validate_account_simple(
+        &request.keypath,
+        coin_params.bip44_coin,
+        simple_type,
+        coin_params.taproot_support,
+    )
+    .or(Err(Error::InvalidInput))?;
+    if request.silent {
+        // Silent derivation is restricted to the change branch so it cannot be used to skip
+        // verification of receive addresses; unusual accounts always require the warning.
+        if !request.change || keypath::is_unusual_account(&request.keypath) {
+            return Err(Error::InvalidInput);
+        }
+    } else {
+        if keypath::is_unusual_account(&request.keypath) {
+            confirm_unusual_account(&request.keypath).await?;
+        }
+        confirm::confirm(&confirm::Params {
+            title: "Export addresses",
+            body: &format!(
+                "{}\naccount #{}",
+                coin_params.name,
+                request.keypath[2] - HARDENED + 1
+            ),
+            ..Default::default()
+        })
+        .await?;
+    }
+    let change = if request.change { 1 } else { 0 };
+    let mut xpub_cache = crate::xpubcache::XpubCache::new();
+    let mut addresses = Vec::with_capacity(request.count as usize);
+    for address_index in request.start_index..end_index {
+        let keypath: Vec<u32> = request
+            .keypath
+            .iter()
+            .copied()
+            .chain([change, address_index])
+            .collect();
+        // Strict keypath validation applies to every derived path, also capping the address
+        // index.
+        keypath::validate_address_simple(
+            &keypath,
+            coin_params.bip44_coin,
+            simple_type,
+            coin_params.taproot_support,
+            keypath::ReceiveSpend::Receive,
+        )
+        .or(Err(Error::InvalidInput))?;
+        let payload = common::Payload::from_simple(&mut xpub_cache, coin_params, simple_type, &keypath)?;
+        addresses.push(pb::btc_addresses_response::Address {
+            address: payload.address(coin_params)?,
+            pk_script: payload.pk_script(coin_params)?,
+        });
+    }
+    Ok(pb::btc_response::Response::Addresses(
+        pb::BtcAddressesResponse { addresses },
+    ))
+}
+
+/// Handles a coin params api call, exposing the device's parameter table so hosts do not have to
+/// hard-code HRPs, version bytes etc. and always agree with the device about formatting. This is
+/// public data, so no user confirmation is needed.
+fn process_coin_params(
+    request: &pb::BtcCoinParamsRequest,
+) -> Result<pb::btc_response::Response, Error> {
+    let coin = BtcCoin::try_from(request.coin)?;
+    coin_enabled(coin)?;
+    let coin_params = params::get(coin);
+    let unit = match coin {
+        BtcCoin::Btc => "BTC",
+        BtcCoin::Tbtc => "TBTC",
+        BtcCoin::Rbtc => "RBTC",
+        BtcCoin::Sbtc => "SBTC",
+        BtcCoin::Ltc => "LTC",
+        BtcCoin::Tltc => "TLTC",
+    };
+    Ok(pb::btc_response::Response::CoinParams(
+        pb::BtcCoinParamsResponse {
+            name: coin_params.name.into(),
+            unit: unit.into(),
+            bech32_hrp: coin_params.bech32_hrp.into(),
+            base58_version_p2pkh: coin_params.base58_version_p2pkh as _,
+            base58_version_p2sh: coin_params.base58_version_p2sh as _,
+            slip44: coin_params.slip44(),
+            taproot_support: coin_params.taproot_support,
+            rbf_support: coin_params.rbf_support,
+        },
+    ))
+}
+
+/// Handles an address api call for a registered account, referenced by its registration hash. The
+/// registration details are supplied again by the host and must hash to the referenced hash, so
+/// beyond picking the registration, only the change and address index are host-controlled; all
+/// other keypath components are fixed by the registration. The address is always shown for
+/// verification, together with the name given by the user during registration.
+async fn process_registered_address(
+    request: &pb::BtcRegisteredAddressRequest,
+) -> Result<pb::btc_response::Response, Error> {
+    let registration = request.registration.as_ref().ok_or(Error::InvalidInput)?;
+    let coin = BtcCoin::try_from(registration.coin)?;
+    coin_enabled(coin)?;
+    let coin_params = params::get(coin);
+    keypath::validate_change_address(
+        request.change,
+        request.address_index,
+        keypath::ReceiveSpend::Receive,
+    )
+    .or(Err(Error::InvalidInput))?;
+    let title = "Receive to";
+    let (name, payload) = match registration.script_config {
+        Some(BtcScriptConfig {
+            config: Some(Config::Multisig(ref multisig)),
+        }) => {
+            let account_keypath: &[u32] = &registration.keypath;
+            multisig::validate(multisig, account_keypath)?;
+            // Either xpub order must hash to the referenced registration, matching the lookup in
+            // `multisig::get_name`.
+            if request.registration_hash
+                != multisig::get_hash(coin, multisig, multisig::SortXpubs::Yes, account_keypath)?
+                && request.registration_hash
+                    != multisig::get_hash(coin, multisig, multisig::SortXpubs::No, account_keypath)?
+            {
+                return Err(Error::InvalidInput);
+            }
+            let name =
+                multisig::get_name(coin, multisig, account_keypath)?.ok_or(Error::InvalidInput)?;
+            multisig::confirm(title, coin_params, &name, multisig).await?;
+            let payload = common::Payload::from_multisig(
+                coin_params,
+                multisig,
+                request.change,
+                request.address_index,
+            )?;
+            (name, payload)
+        }
+        Some(BtcScriptConfig {
+            config: Some(Config::Policy(ref policy)),
+        }) => {
+            let parsed = policies::parse(policy, coin)?;
+            if request.registration_hash != policies::get_hash(coin, policy)? {
+                return Err(Error::InvalidInput);
+            }
+            let name = policies::get_name(coin, policy)?.ok_or(Error::InvalidInput)?;
+            parsed
+                .confirm(title, coin_params, &name, policies::Mode::Basic)
+                .await?;
+            let payload = common::Payload::from_policy_derived(
+                &parsed,
+                request.change == 1,
+                request.address_index,
+            )?;
+            (name, payload)
+        }
+        _ => return Err(Error::InvalidInput),
+    };
+    let address = payload.address(coin_params)?;
+    confirm::confirm(&confirm::Params {
+        title,
+        body: &address,
+        scrollable: true,
+        ..Default::default()
+    })
+    .await?;
+    Ok(pb::btc_response::Response::RegisteredAddress(
+        pb::BtcRegisteredAddressResponse { address, name },
+    ))
+}
+
+/// Returns whether `address` is a syntactically valid address for the given coin: a
+/// base58check-encoded P2PKH/P2SH address with the coin's version byte, or a bech32/bech32m
+/// segwit address with the coin's HRP. The checksum is verified in both cases.
+fn is_valid_address(coin_params: &params::Params, address: &str) -> bool {
+    if let Ok(decoded) = bitcoin::base58::decode_check(address) {
+        return decoded.len() == 21
+            && (decoded[0] == coin_params.base58_version_p2pkh
+                || decoded[0] == coin_params.base58_version_p2sh);
+    }
+    match ::bech32::segwit::decode(address) {
+        Ok((hrp, _witness_version, _witness_program)) => {
+            hrp.to_lowercase() == coin_params.bech32_hrp
+        }
+        Err(_) => false,
+    }
+}
+
+/// Handles a standalone address verification api call: shows a host-provided address (and
+/// optional amount) on screen so the user can verify a destination before a transaction is even
+/// built, e.g. while reading it to someone over the phone. No keys are involved; the address
+/// checksum is verified so a mistyped address is never displayed as if it were valid. If no
+/// amount is provided, the coin name is shown in its place.
+async fn process_verify_address(
+    request: &pb::BtcVerifyAddressRequest,
+) -> Result<pb::btc_response::Response, Error> {
+    let coin = BtcCoin::try_from(request.coin)?;
+    coin_enabled(coin)?;
+    let coin_params = params::get(coin);
+    if !is_valid_address(coin_params, &request.address) {
+        return Err(Error::InvalidInput);
+    }
+    let amount = match request.amount {
+        Some(amount) => common::format_amount(
+            coin_params,
+            pb::btc_sign_init_request::FormatUnit::Default,
+            amount,
+        )?,
+        None => coin_params.name.into(),
+    };
+    transaction::verify_recipient(&request.address, &amount).await?;
+    Ok(pb::btc_response::Response::Success(pb::BtcSuccess {}))
+}
+
+/// Exports the BIP-352 silent payments scan key at m/352'/coin'/account'/1'/0 so the host can
+/// scan for incoming silent payments. A fingerprint of the scan public key (first four bytes of
+/// its hash160) is shown for verification. The scan public key is always returned; the scan
+/// private key only on request and behind a warning, as it lets its holder watch incoming
+/// payments but not spend them. The spend key is at a different keypath, which
+/// `validate_scan_key` rejects - it never leaves the device.
+async fn process_silent_payment_scan_key(
+    request: &pb::BtcSilentPaymentScanKeyRequest,
+) -> Result<pb::btc_response::Response, Error> {
+    let coin = BtcCoin::try_from(request.coin)?;
+    coin_enabled(coin)?;
+    let coin_params = params::get(coin);
+    keypath::validate_scan_key(&request.keypath, coin_params.bip44_coin)
+        .or(Err(Error::InvalidInput))?;
+
+    let scan_xpub = keystore::get_xpub(&request.keypath).or(Err(Error::InvalidInput))?;
+    confirm::confirm(&confirm::Params {
+        title: coin_params.name,
+        body: &format!(
+            "Silent payments\nscan key\nfingerprint:\n{}",
+            hex::encode(scan_xpub.pubkey_hash160().get(..4).ok_or(Error::Generic)?)
+        ),
+        accept_is_nextarrow: true,
+        ..Default::default()
+    })
+    .await?;
+
+    let scan_private_key = if request.export_private_key {
+        confirm::confirm(&confirm::Params {
+            title: "Warning",
+            body: "Export scan\nprivate key?\nIt can watch\nincoming funds\nbut cannot\nspend them",
+            longtouch: true,
+            ..Default::default()
+        })
+        .await?;
+        bitbox02::keystore::secp256k1_get_private_key(&request.keypath)?.to_vec()
+    } else {
+        Vec::new()
+    };
+    Ok(pb::btc_response::Response::SilentPaymentScanKey(
+        pb::BtcSilentPaymentScanKeyResponse {
+            scan_public_key: scan_xpub.public_key().to_vec(),
+            scan_private_key,
+        },
+    ))
+}
+
 /// Handle a nexted Bitcoin protobuf api call.
 pub async fn process_api(request: &Request) -> Result<pb::btc_response::Response, Error> {
     match request {
@@ -303,13 +793,39 @@ pub async fn process_api(request: &Request) -> Result<pb::btc_response::Response
             registration::process_register_script_config(request).await
         }
         Request::SignMessage(ref request) => signmsg::process(request).await,
+        Request::Addresses(ref request) => process_addresses(request).await,
+        Request::DeleteScriptConfig(ref request) => {
+            registration::process_delete_script_config(request).await
+        }
+        Request::RenameScriptConfig(ref request) => {
+            registration::process_rename_script_config(request).await
+        }
+        Request::CoinParams(ref request) => process_coin_params(request),
+        Request::RegisteredAddress(ref request) => process_registered_address(request).await,
+        Request::Descriptor(ref request) => descriptor::process(request).await,
+        Request::IsScriptConfigValid(ref request) => {
+            signtx::process_is_script_config_valid(request).await
+        }
+        Request::VerifyAddress(ref request) => process_verify_address(request).await,
+        Request::SilentPaymentScanKey(ref request) => {
+            process_silent_payment_scan_key(request).await
+        }
+        Request::BackupRegistrations(ref request) => {
+            registration::process_backup_registrations(request).await
+        }
+        Request::RestoreRegistrations(_) => registration::process_restore_registrations().await,
         // These are streamed asynchronously using the `next_request()` primitive in
         // bitcoin/signtx.rs and are not handled directly.
         Request::PrevtxInit(_)
         | Request::PrevtxInput(_)
         | Request::PrevtxOutput(_)
+        | Request::PrevtxInputs(_)
+        | Request::PrevtxOutputs(_)
+        | Request::SignInputs(_)
         | Request::AntikleptoSignature(_)
-        | Request::PaymentRequest(_) => Err(Error::InvalidState),
+        | Request::PaymentRequest(_)
+        | Request::Musig2Nonces(_)
+        | Request::SignMessageChunk(_) => Err(Error::InvalidState),
     }
 }
 
@@ -327,6 +843,28 @@ mod tests {
     use pb::btc_script_config::multisig::ScriptType as MultisigScriptType;
     use util::bip32::HARDENED;
 
+    /// Decodes an address into the scriptPubKey it pays to, independently of the payload code
+    /// under test.
+    fn address_pk_script(address: &str) -> Vec<u8> {
+        if let Ok(decoded) = bitcoin::base58::decode_check(address) {
+            let hash = &decoded[1..];
+            assert_eq!(hash.len(), 20);
+            return match decoded[0] {
+                // P2PKH version bytes of BTC/LTC and their testnets.
+                0x00 | 0x30 | 0x6f => [&[0x76u8, 0xa9, 0x14][..], hash, &[0x88, 0xac]].concat(),
+                // P2SH version bytes.
+                0x05 | 0x32 | 0xc4 => [&[0xa9u8, 0x14][..], hash, &[0x87]].concat(),
+                version => panic!("unknown base58 version {}", version),
+            };
+        }
+        let (_hrp, version, program) = ::bech32::segwit::decode(address).unwrap();
+        let opcode = match version.to_u8() {
+            0 => 0x00,
+            v => 0x50 + v, // OP_1..OP_16
+        };
+        [&[opcode, program.len() as u8][..], &program].concat()
+    }
+
     #[test]
     pub fn test_xpub() {
         struct Test<'a> {
@@ -356,14 +894,6 @@ mod tests {
                 expected_xpub: "ypub6WZPko64H2zo7WoUefaRXjNvF2NgwvJJcGYW8FvQ2P6TcFHrdA5jDcUXohadMXdgzF4vR1otQjG9eBcnB5qp2EWbaYmFtxkSdsJt6svswWd",
                 expected_display_title: "Bitcoin\naccount #1",
             },
-            Test {
-                mnemonic: TEST_MNEMONIC,
-                coin: BtcCoin::Btc,
-                keypath: &[49 + HARDENED, 0 + HARDENED, 0 + HARDENED],
-                xpub_type: XPubType::Zpub,
-                expected_xpub: "zpub6qPf4TkyRiYGxozbV2N3jpURQzX8tYHoXP4iuepHQPULfM75spFHqg8fpuYDMSHcPtBjAVQSsPchXUELtnFppUCCStTgUsZvubNXVPGWjcc",
-                expected_display_title: "Bitcoin\naccount #1",
-            },
             Test {
                 mnemonic: TEST_MNEMONIC,
                 coin: BtcCoin::Btc,
@@ -469,6 +999,7 @@ mod tests {
                 coin: test.coin as _,
                 keypath: test.keypath.to_vec(),
                 display: false,
+                allow_nonstandard: false,
                 output: Some(Output::XpubType(test.xpub_type as _)),
             };
 
@@ -476,6 +1007,7 @@ mod tests {
                 block_on(process_pub(&req)),
                 Ok(Response::Pub(pb::PubResponse {
                     r#pub: test.expected_xpub.into(),
+                    pub_key_script: vec![],
                 })),
             );
 
@@ -497,18 +1029,52 @@ mod tests {
                 block_on(process_pub(&req)),
                 Ok(Response::Pub(pb::PubResponse {
                     r#pub: test.expected_xpub.into(),
+                    pub_key_script: vec![],
+                })),
+            );
+        }
+
+        {
+            // --- Nonstandard keypath without the flag: rejected outright, no dialogs.
+            mock(Data {
+                ..Default::default()
+            });
+            mock_unlocked();
+            assert_eq!(
+                block_on(process_pub(&pb::BtcPubRequest {
+                    coin: BtcCoin::Btc as _,
+                    keypath: [1 + HARDENED, 2 + HARDENED, 3 + HARDENED, 4].to_vec(),
+                    display: false,
+                    allow_nonstandard: false,
+                    output: Some(Output::XpubType(XPubType::Xpub as _)),
                 })),
+                Err(Error::InvalidInput)
             );
         }
 
         {
-            // --- Unusual keypath, no display (still forces confirmation of unusual keypath)
+            // --- Nonstandard keypath, no display: warning plus the xpub is always shown for
+            // verification.
+            static mut UI_COUNTER: u32 = 0;
             mock(Data {
                 ui_confirm_create: Some(Box::new(move |params| {
-                    assert_eq!(params.title, "Export xpub");
-                    assert_eq!(params.body, "Warning: unusual keypath m/1'/2'/3'/4. Proceed only if you know what you are doing.");
-                    assert!(params.scrollable);
-                    assert!(params.longtouch);
+                    match unsafe {
+                        UI_COUNTER += 1;
+                        UI_COUNTER
+                    } {
+                        1 => {
+                            assert_eq!(params.title, "Export xpub");
+                            assert_eq!(params.body, "Warning: keypath m/1'/2'/3'/4 does not match a known standard. Proceed only if you know what you are doing.");
+                            assert!(params.scrollable);
+                            assert!(params.longtouch);
+                        }
+                        2 => {
+                            assert_eq!(params.title, "");
+                            assert_eq!(params.body, "xpub6DdW7n2P4Ht8m9DNumbzVKPU4yXoBMR9mm39q6tGp8PHGgNTJWL3fBdoUS4E8tP9XmyK4F85ApxLEBTB6f3fJf3Ujk5PaqssRuTLsRVTn6E");
+                            assert!(params.scrollable);
+                        }
+                        _ => panic!("too many dialogs"),
+                    }
                     true
                 })),
                 ..Default::default()
@@ -519,16 +1085,19 @@ mod tests {
                     coin: BtcCoin::Btc as _,
                     keypath: [1 + HARDENED, 2 + HARDENED, 3 + HARDENED, 4].to_vec(),
                     display: false,
+                    allow_nonstandard: true,
                     output: Some(Output::XpubType(XPubType::Xpub as _)),
                 })),
                 Ok(Response::Pub(pb::PubResponse {
                     r#pub: "xpub6DdW7n2P4Ht8m9DNumbzVKPU4yXoBMR9mm39q6tGp8PHGgNTJWL3fBdoUS4E8tP9XmyK4F85ApxLEBTB6f3fJf3Ujk5PaqssRuTLsRVTn6E".into(),
+                    pub_key_script: vec![],
                 }))
             );
+            assert_eq!(unsafe { UI_COUNTER }, 2);
         }
 
         {
-            // --- Unusual keypath, with display
+            // --- Nonstandard keypath, with display
             static mut UI_COUNTER: u32 = 0;
             mock(Data {
                 ui_confirm_create: Some(Box::new(move |params| {
@@ -538,7 +1107,7 @@ mod tests {
                     } {
                         1 => {
                             assert_eq!(params.title, "xpub");
-                            assert_eq!(params.body, "Warning: unusual keypath m/1'/2'/3'/4. Proceed only if you know what you are doing.");
+                            assert_eq!(params.body, "Warning: keypath m/1'/2'/3'/4 does not match a known standard. Proceed only if you know what you are doing.");
                             assert!(params.scrollable);
                             assert!(params.longtouch);
                         }
@@ -559,18 +1128,103 @@ mod tests {
                     coin: BtcCoin::Btc as _,
                     keypath: [1 + HARDENED, 2 + HARDENED, 3 + HARDENED, 4].to_vec(),
                     display: true,
+                    allow_nonstandard: true,
                     output: Some(Output::XpubType(XPubType::Xpub as _)),
                 })),
                 Ok(Response::Pub(pb::PubResponse {
                     r#pub: "xpub6DdW7n2P4Ht8m9DNumbzVKPU4yXoBMR9mm39q6tGp8PHGgNTJWL3fBdoUS4E8tP9XmyK4F85ApxLEBTB6f3fJf3Ujk5PaqssRuTLsRVTn6E".into(),
+                    pub_key_script: vec![],
                 }))
             );
         }
 
+        {
+            // --- Keypaths failing even the relaxed structural checks are rejected despite the
+            // flag: too deep, hardened after unhardened, unhardened start, empty.
+            mock(Data {
+                ..Default::default()
+            });
+            mock_unlocked();
+            for keypath in [
+                &[1 + HARDENED, 2, 3, 4, 5, 6, 7, 8, 9][..],
+                &[1 + HARDENED, 2, 3 + HARDENED][..],
+                &[1, 2 + HARDENED][..],
+                &[][..],
+            ] {
+                assert_eq!(
+                    block_on(process_pub(&pb::BtcPubRequest {
+                        coin: BtcCoin::Btc as _,
+                        keypath: keypath.to_vec(),
+                        display: false,
+                        allow_nonstandard: true,
+                        output: Some(Output::XpubType(XPubType::Xpub as _)),
+                    })),
+                    Err(Error::InvalidInput)
+                );
+            }
+        }
+
+        {
+            // --- Unusual account number: accepted after an explicit warning.
+            static mut UI_COUNTER: u32 = 0;
+            mock(Data {
+                ui_confirm_create: Some(Box::new(move |params| {
+                    match unsafe {
+                        UI_COUNTER += 1;
+                        UI_COUNTER
+                    } {
+                        1 => {
+                            assert_eq!(params.title, "Warning");
+                            assert_eq!(params.body, "Unusual account number in keypath m/84'/0'/100'. Proceed only if you know what you are doing.");
+                            assert!(params.scrollable);
+                            assert!(params.longtouch);
+                        }
+                        2 => {
+                            assert_eq!(params.title, "Bitcoin\naccount #101");
+                            assert!(params.scrollable);
+                        }
+                        _ => panic!("too many dialogs"),
+                    }
+                    true
+                })),
+                ..Default::default()
+            });
+            mock_unlocked();
+            assert!(block_on(process_pub(&pb::BtcPubRequest {
+                coin: BtcCoin::Btc as _,
+                keypath: [84 + HARDENED, 0 + HARDENED, 100 + HARDENED].to_vec(),
+                display: true,
+                allow_nonstandard: false,
+                output: Some(Output::XpubType(XPubType::Xpub as _)),
+            }))
+            .is_ok());
+            assert_eq!(unsafe { UI_COUNTER }, 2);
+        }
+
+        {
+            // --- Unusual account number: rejecting the warning aborts, also without display.
+            mock(Data {
+                ui_confirm_create: Some(Box::new(|_params| false)),
+                ..Default::default()
+            });
+            mock_unlocked();
+            assert_eq!(
+                block_on(process_pub(&pb::BtcPubRequest {
+                    coin: BtcCoin::Btc as _,
+                    keypath: [84 + HARDENED, 0 + HARDENED, 100 + HARDENED].to_vec(),
+                    display: false,
+                    allow_nonstandard: false,
+                    output: Some(Output::XpubType(XPubType::Xpub as _)),
+                })),
+                Err(Error::UserAbort)
+            );
+        }
+
         let req = pb::BtcPubRequest {
             coin: BtcCoin::Btc as _,
             keypath: [49 + HARDENED, 0 + HARDENED, 0 + HARDENED].to_vec(),
             display: false,
+            allow_nonstandard: false,
             output: Some(Output::XpubType(XPubType::Xpub as _)),
         };
 
@@ -580,18 +1234,121 @@ mod tests {
         assert!(block_on(process_pub(&req_invalid)).is_err());
         // -- Wrong coin: MAX + 1
         let mut req_invalid = req.clone();
-        req_invalid.coin = BtcCoin::Tltc as i32 + 1;
+        req_invalid.coin = BtcCoin::Sbtc as i32 + 1;
         assert!(block_on(process_pub(&req_invalid)).is_err());
     }
 
+    /// An xpub export confirmed by the user is not confirmed again in the same session, until the
+    /// keystore is locked.
     #[test]
-    pub fn test_address_simple() {
-        struct Test<'a> {
-            mnemonic: &'a str,
-            coin: BtcCoin,
-            keypath: &'a [u32],
-            simple_type: SimpleType,
-            expected_address: &'a str,
+    fn test_xpub_export_cache() {
+        static mut CONFIRM_COUNTER: u32 = 0;
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|_params| {
+                unsafe { CONFIRM_COUNTER += 1 }
+                true
+            })),
+            ..Default::default()
+        });
+        mock_unlocked();
+
+        let request = |account: u32| pb::BtcPubRequest {
+            coin: BtcCoin::Btc as _,
+            keypath: vec![84 + HARDENED, 0 + HARDENED, account + HARDENED],
+            display: true,
+            allow_nonstandard: false,
+            output: Some(Output::XpubType(XPubType::Zpub as _)),
+        };
+
+        // The first export shows a confirmation...
+        assert!(block_on(process_pub(&request(0))).is_ok());
+        assert_eq!(unsafe { CONFIRM_COUNTER }, 1);
+        // ...repeating the same export does not.
+        assert!(block_on(process_pub(&request(0))).is_ok());
+        assert_eq!(unsafe { CONFIRM_COUNTER }, 1);
+
+        // A different xpub format of the same account is confirmed separately.
+        let mut req = request(0);
+        req.output = Some(Output::XpubType(XPubType::Xpub as _));
+        assert!(block_on(process_pub(&req)).is_ok());
+        assert_eq!(unsafe { CONFIRM_COUNTER }, 2);
+
+        // Locking invalidates the cache.
+        bitbox02::keystore::lock();
+        mock_unlocked();
+        assert!(block_on(process_pub(&request(0))).is_ok());
+        assert_eq!(unsafe { CONFIRM_COUNTER }, 3);
+        assert!(block_on(process_pub(&request(0))).is_ok());
+        assert_eq!(unsafe { CONFIRM_COUNTER }, 3);
+
+        // Confirming `XPUB_EXPORT_CACHE_SIZE` other exports evicts the oldest entry.
+        for account in 1..=XPUB_EXPORT_CACHE_SIZE as u32 {
+            assert!(block_on(process_pub(&request(account))).is_ok());
+        }
+        assert_eq!(unsafe { CONFIRM_COUNTER }, 8);
+        // The most recent export is still cached...
+        assert!(block_on(process_pub(&request(XPUB_EXPORT_CACHE_SIZE as u32))).is_ok());
+        assert_eq!(unsafe { CONFIRM_COUNTER }, 8);
+        // ...but the first one was evicted and is confirmed again.
+        assert!(block_on(process_pub(&request(0))).is_ok());
+        assert_eq!(unsafe { CONFIRM_COUNTER }, 9);
+    }
+
+    /// SLIP-132 xpub types are only produced for the matching BIP-44 purpose.
+    #[test]
+    fn test_xpub_type_mismatch() {
+        mock_unlocked();
+        for (keypath, xpub_type) in [
+            (
+                &[49 + HARDENED, 0 + HARDENED, 0 + HARDENED][..],
+                XPubType::Zpub,
+            ),
+            (
+                &[84 + HARDENED, 0 + HARDENED, 0 + HARDENED][..],
+                XPubType::Ypub,
+            ),
+            (
+                &[86 + HARDENED, 0 + HARDENED, 0 + HARDENED][..],
+                XPubType::Vpub,
+            ),
+            (
+                &[48 + HARDENED, 0 + HARDENED, 0 + HARDENED, 2 + HARDENED][..],
+                XPubType::Upub,
+            ),
+            (
+                &[84 + HARDENED, 0 + HARDENED, 0 + HARDENED][..],
+                XPubType::CapitalZpub,
+            ),
+        ] {
+            let req = pb::BtcPubRequest {
+                coin: BtcCoin::Btc as _,
+                keypath: keypath.to_vec(),
+                display: false,
+                allow_nonstandard: false,
+                output: Some(Output::XpubType(xpub_type as _)),
+            };
+            assert_eq!(block_on(process_pub(&req)), Err(Error::InvalidInput));
+        }
+
+        // The matching purpose is accepted, e.g. Zpub for a 48' multisig account keypath.
+        let req = pb::BtcPubRequest {
+            coin: BtcCoin::Btc as _,
+            keypath: vec![48 + HARDENED, 0 + HARDENED, 0 + HARDENED, 2 + HARDENED],
+            display: false,
+            allow_nonstandard: false,
+            output: Some(Output::XpubType(XPubType::CapitalZpub as _)),
+        };
+        assert!(block_on(process_pub(&req)).is_ok());
+    }
+
+    #[test]
+    pub fn test_address_simple() {
+        struct Test<'a> {
+            mnemonic: &'a str,
+            coin: BtcCoin,
+            keypath: &'a [u32],
+            simple_type: SimpleType,
+            expected_address: &'a str,
             expected_display_title: &'a str,
         }
 
@@ -690,6 +1447,42 @@ mod tests {
                 expected_address: "tb1qnlyrq9pshg0v0lsuudjgga4nvmjxhcvketqwdg",
                 expected_display_title: "BTC Testnet",
             },
+            // SBTC P2WPKH-P2SH (same addresses as TBTC, but displayed as Signet)
+            Test {
+                mnemonic: TEST_MNEMONIC,
+                coin: BtcCoin::Sbtc,
+                keypath: &[49 + HARDENED, 1 + HARDENED, 0 + HARDENED, 0, 0],
+                simple_type: SimpleType::P2wpkhP2sh,
+                expected_address: "2N5Tjwx5Htk7gLbv7nWqXUgpg5K2Uf4TacQ",
+                expected_display_title: "Signet",
+            },
+            // SBTC P2WPKH
+            Test {
+                mnemonic: TEST_MNEMONIC,
+                coin: BtcCoin::Sbtc,
+                keypath: &[84 + HARDENED, 1 + HARDENED, 0 + HARDENED, 0, 0],
+                simple_type: SimpleType::P2wpkh,
+                expected_address: "tb1qnlyrq9pshg0v0lsuudjgga4nvmjxhcvketqwdg",
+                expected_display_title: "Signet",
+            },
+            // RBTC P2WPKH-P2SH
+            Test {
+                mnemonic: TEST_MNEMONIC,
+                coin: BtcCoin::Rbtc,
+                keypath: &[49 + HARDENED, 1 + HARDENED, 0 + HARDENED, 0, 0],
+                simple_type: SimpleType::P2wpkhP2sh,
+                expected_address: "2N5Tjwx5Htk7gLbv7nWqXUgpg5K2Uf4TacQ",
+                expected_display_title: "BTC Regtest",
+            },
+            // RBTC P2WPKH
+            Test {
+                mnemonic: TEST_MNEMONIC,
+                coin: BtcCoin::Rbtc,
+                keypath: &[84 + HARDENED, 1 + HARDENED, 0 + HARDENED, 0, 0],
+                simple_type: SimpleType::P2wpkh,
+                expected_address: "bcrt1qnlyrq9pshg0v0lsuudjgga4nvmjxhcvkmzer6p",
+                expected_display_title: "BTC Regtest",
+            },
             // LTC P2WPKH-P2SH
             Test {
                 mnemonic: TEST_MNEMONIC,
@@ -740,6 +1533,31 @@ mod tests {
                 expected_address: "ltc1qwsz89auhpezjfllq9y9qegpfgdwpw5vesppsz0",
                 expected_display_title: "Litecoin",
             },
+            // LTC P2TR
+            Test {
+                mnemonic: "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+                coin: BtcCoin::Ltc,
+                keypath: &[86 + HARDENED, 2 + HARDENED, 0 + HARDENED, 0, 0],
+                simple_type: SimpleType::P2tr,
+                expected_address: "ltc1puht8rk95c53q3u9w3pf9h3jfcutcrl9lxc7rqsdthjrse4k6sn7q9tuqm9",
+                expected_display_title: "Litecoin",
+            },
+            Test {
+                mnemonic: "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+                coin: BtcCoin::Ltc,
+                keypath: &[86 + HARDENED, 2 + HARDENED, 0 + HARDENED, 0, 1],
+                simple_type: SimpleType::P2tr,
+                expected_address: "ltc1p4m4d6s554w3lhamw6pt5je23xvzsqxnz58wc24gc8g9n328yc3xsg3antm",
+                expected_display_title: "Litecoin",
+            },
+            Test {
+                mnemonic: "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+                coin: BtcCoin::Ltc,
+                keypath: &[86 + HARDENED, 2 + HARDENED, 0 + HARDENED, 1, 0],
+                simple_type: SimpleType::P2tr,
+                expected_address: "ltc1pehskafcqerg3hqvx005ywevqta7r0q980xgnencjgez0fyf7lt7s8r36hx",
+                expected_display_title: "Litecoin",
+            },
             // TLTC P2WPKH-P2SH
             Test {
                 mnemonic: TEST_MNEMONIC,
@@ -763,6 +1581,7 @@ mod tests {
                 coin: test.coin as _,
                 keypath: test.keypath.to_vec(),
                 display: false,
+                allow_nonstandard: false,
                 output: Some(Output::ScriptConfig(BtcScriptConfig {
                     config: Some(Config::SimpleType(test.simple_type as _)),
                 })),
@@ -774,6 +1593,7 @@ mod tests {
                 block_on(process_pub(&req)),
                 Ok(Response::Pub(pb::PubResponse {
                     r#pub: test.expected_address.into(),
+                    pub_key_script: address_pk_script(test.expected_address),
                 })),
             );
 
@@ -794,8 +1614,69 @@ mod tests {
             assert_eq!(
                 block_on(process_pub(&req)),
                 Ok(Response::Pub(pb::PubResponse {
-                    r#pub: test.expected_address.into()
+                    r#pub: test.expected_address.into(),
+                    pub_key_script: address_pk_script(test.expected_address),
+                })),
+            );
+        }
+
+        {
+            // --- Unusual account number: accepted after an explicit warning.
+            static mut UI_COUNTER: u32 = 0;
+            mock(Data {
+                ui_confirm_create: Some(Box::new(move |params| {
+                    match unsafe {
+                        UI_COUNTER += 1;
+                        UI_COUNTER
+                    } {
+                        1 => {
+                            assert_eq!(params.title, "Warning");
+                            assert_eq!(params.body, "Unusual account number in keypath m/84'/0'/100'. Proceed only if you know what you are doing.");
+                            assert!(params.scrollable);
+                            assert!(params.longtouch);
+                        }
+                        2 => {
+                            assert_eq!(params.title, "Bitcoin");
+                            assert!(params.scrollable);
+                        }
+                        _ => panic!("too many dialogs"),
+                    }
+                    true
                 })),
+                ..Default::default()
+            });
+            mock_unlocked();
+            assert!(block_on(process_pub(&pb::BtcPubRequest {
+                coin: BtcCoin::Btc as _,
+                keypath: [84 + HARDENED, 0 + HARDENED, 100 + HARDENED, 0, 0].to_vec(),
+                display: true,
+                allow_nonstandard: false,
+                output: Some(Output::ScriptConfig(BtcScriptConfig {
+                    config: Some(Config::SimpleType(SimpleType::P2wpkh as _)),
+                })),
+            }))
+            .is_ok());
+            assert_eq!(unsafe { UI_COUNTER }, 2);
+        }
+
+        {
+            // --- Unusual account number: rejecting the warning aborts, also without display.
+            mock(Data {
+                ui_confirm_create: Some(Box::new(|_params| false)),
+                ..Default::default()
+            });
+            mock_unlocked();
+            assert_eq!(
+                block_on(process_pub(&pb::BtcPubRequest {
+                    coin: BtcCoin::Btc as _,
+                    keypath: [84 + HARDENED, 0 + HARDENED, 100 + HARDENED, 0, 0].to_vec(),
+                    display: false,
+                    allow_nonstandard: false,
+                    output: Some(Output::ScriptConfig(BtcScriptConfig {
+                        config: Some(Config::SimpleType(SimpleType::P2wpkh as _)),
+                    })),
+                })),
+                Err(Error::UserAbort)
             );
         }
 
@@ -806,6 +1687,7 @@ mod tests {
             coin: BtcCoin::Btc as _,
             keypath: [49 + HARDENED, 0 + HARDENED, 1 + HARDENED, 1, 100].to_vec(),
             display: false,
+            allow_nonstandard: false,
             output: Some(Output::ScriptConfig(BtcScriptConfig {
                 config: Some(Config::SimpleType(SimpleType::P2wpkhP2sh as _)),
             })),
@@ -817,22 +1699,565 @@ mod tests {
         assert!(block_on(process_pub(&req_invalid)).is_err());
         // -- Wrong coin: MAX + 1
         let mut req_invalid = req.clone();
-        req_invalid.coin = BtcCoin::Tltc as i32 + 1;
+        req_invalid.coin = BtcCoin::Sbtc as i32 + 1;
         assert!(block_on(process_pub(&req_invalid)).is_err());
         // -- Wrong keypath
         let mut req_invalid = req.clone();
         req_invalid.keypath = [49 + HARDENED, 0 + HARDENED, 1 + HARDENED, 1, 10000].to_vec();
         assert!(block_on(process_pub(&req_invalid)).is_err());
-        // -- No taproot in Litecoin
-        assert!(block_on(process_pub(&pb::BtcPubRequest {
-            coin: BtcCoin::Ltc as _,
-            keypath: [86 + HARDENED, 0 + HARDENED, 0 + HARDENED, 0, 0].to_vec(),
-            display: false,
-            output: Some(Output::ScriptConfig(BtcScriptConfig {
-                config: Some(Config::SimpleType(SimpleType::P2tr as _)),
+        // -- Taproot is active on Litecoin.
+        assert_eq!(
+            block_on(process_pub(&pb::BtcPubRequest {
+                coin: BtcCoin::Ltc as _,
+                keypath: [86 + HARDENED, 2 + HARDENED, 0 + HARDENED, 0, 0].to_vec(),
+                display: false,
+                allow_nonstandard: false,
+                output: Some(Output::ScriptConfig(BtcScriptConfig {
+                    config: Some(Config::SimpleType(SimpleType::P2tr as _)),
+                })),
+            })),
+            Ok(Response::Pub(pb::PubResponse {
+                r#pub: "ltc1pl6ekvgsh04g0xdv896czhdma96wg4j730huqcg6zv4pmfuhpjmhsf2f9gk".into(),
+                pub_key_script: address_pk_script(
+                    "ltc1pl6ekvgsh04g0xdv896czhdma96wg4j730huqcg6zv4pmfuhpjmhsf2f9gk"
+                ),
             })),
+        );
+    }
+
+    /// Batch address derivation returns the same addresses as individual derivations, after a
+    /// single confirmation of the account.
+    #[test]
+    fn test_process_addresses() {
+        static mut UI_COUNTER: u32 = 0;
+
+        let request = pb::BtcAddressesRequest {
+            coin: BtcCoin::Btc as _,
+            script_config: Some(BtcScriptConfig {
+                config: Some(Config::SimpleType(SimpleType::P2wpkh as _)),
+            }),
+            keypath: vec![84 + HARDENED, 0 + HARDENED, 0 + HARDENED],
+            change: false,
+            start_index: 5,
+            count: 10,
+            silent: false,
+        };
+
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|params| {
+                match unsafe {
+                    UI_COUNTER += 1;
+                    UI_COUNTER
+                } {
+                    1 => {
+                        assert_eq!(params.title, "Export addresses");
+                        assert_eq!(params.body, "Bitcoin\naccount #1");
+                        true
+                    }
+                    _ => panic!("too many dialogs"),
+                }
+            })),
+            ..Default::default()
+        });
+        mock_unlocked();
+        let addresses = match block_on(process_addresses(&request)).unwrap() {
+            pb::btc_response::Response::Addresses(pb::BtcAddressesResponse { addresses }) => {
+                addresses
+            }
+            _ => panic!("wrong response type"),
+        };
+        assert_eq!(unsafe { UI_COUNTER }, 1);
+        assert_eq!(addresses.len(), 10);
+        for (i, address) in addresses.iter().enumerate() {
+            let keypath = [
+                84 + HARDENED,
+                0 + HARDENED,
+                0 + HARDENED,
+                0,
+                5 + i as u32,
+            ];
+            assert_eq!(
+                address.address,
+                derive_address_simple(BtcCoin::Btc, SimpleType::P2wpkh, &keypath).unwrap()
+            );
+            assert_eq!(
+                address.pk_script,
+                common::Payload::from_simple(
+                    &mut crate::xpubcache::XpubCache::new(),
+                    params::get(BtcCoin::Btc),
+                    SimpleType::P2wpkh,
+                    &keypath,
+                )
+                .unwrap()
+                .pk_script(params::get(BtcCoin::Btc))
+                .unwrap()
+            );
+        }
+
+        // Change addresses.
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|_params| true)),
+            ..Default::default()
+        });
+        mock_unlocked();
+        let addresses = match block_on(process_addresses(&pb::BtcAddressesRequest {
+            change: true,
+            start_index: 0,
+            count: 2,
+            ..request.clone()
         }))
-        .is_err());
+        .unwrap()
+        {
+            pb::btc_response::Response::Addresses(pb::BtcAddressesResponse { addresses }) => {
+                addresses
+            }
+            _ => panic!("wrong response type"),
+        };
+        assert_eq!(
+            addresses[1].address,
+            derive_address_simple(
+                BtcCoin::Btc,
+                SimpleType::P2wpkh,
+                &[84 + HARDENED, 0 + HARDENED, 0 + HARDENED, 1, 1]
+            )
+            .unwrap()
+        );
+    }
+
+    /// Silent derivation returns change addresses without any dialog; receive addresses and
+    /// unusual accounts are rejected instead of silently derived.
+    #[test]
+    fn test_process_addresses_silent() {
+        let request = pb::BtcAddressesRequest {
+            coin: BtcCoin::Btc as _,
+            script_config: Some(BtcScriptConfig {
+                config: Some(Config::SimpleType(SimpleType::P2wpkh as _)),
+            }),
+            keypath: vec![84 + HARDENED, 0 + HARDENED, 0 + HARDENED],
+            change: true,
+            start_index: 0,
+            count: 3,
+            silent: true,
+        };
+
+        // No ui_confirm_create mock: any dialog would panic.
+        mock(Data {
+            ..Default::default()
+        });
+        mock_unlocked();
+        let addresses = match block_on(process_addresses(&request)).unwrap() {
+            pb::btc_response::Response::Addresses(pb::BtcAddressesResponse { addresses }) => {
+                addresses
+            }
+            _ => panic!("wrong response type"),
+        };
+        assert_eq!(addresses.len(), 3);
+        for (i, address) in addresses.iter().enumerate() {
+            assert_eq!(
+                address.address,
+                derive_address_simple(
+                    BtcCoin::Btc,
+                    SimpleType::P2wpkh,
+                    &[84 + HARDENED, 0 + HARDENED, 0 + HARDENED, 1, i as u32]
+                )
+                .unwrap()
+            );
+        }
+
+        // Receive addresses cannot be derived silently.
+        assert_eq!(
+            block_on(process_addresses(&pb::BtcAddressesRequest {
+                change: false,
+                ..request.clone()
+            })),
+            Err(Error::InvalidInput)
+        );
+
+        // Unusual accounts cannot be derived silently.
+        assert_eq!(
+            block_on(process_addresses(&pb::BtcAddressesRequest {
+                keypath: vec![84 + HARDENED, 0 + HARDENED, 100 + HARDENED],
+                ..request.clone()
+            })),
+            Err(Error::InvalidInput)
+        );
+    }
+
+    #[test]
+    fn test_process_addresses_failures() {
+        let request = pb::BtcAddressesRequest {
+            coin: BtcCoin::Btc as _,
+            script_config: Some(BtcScriptConfig {
+                config: Some(Config::SimpleType(SimpleType::P2wpkh as _)),
+            }),
+            keypath: vec![84 + HARDENED, 0 + HARDENED, 0 + HARDENED],
+            change: false,
+            start_index: 0,
+            count: 2,
+            silent: false,
+        };
+
+        let mock_default = || {
+            mock(Data {
+                ui_confirm_create: Some(Box::new(|_params| true)),
+                ..Default::default()
+            });
+            mock_unlocked();
+        };
+
+        // Count of zero.
+        mock_default();
+        assert_eq!(
+            block_on(process_addresses(&pb::BtcAddressesRequest {
+                count: 0,
+                ..request.clone()
+            })),
+            Err(Error::InvalidInput)
+        );
+
+        // Count exceeding the limit.
+        mock_default();
+        assert_eq!(
+            block_on(process_addresses(&pb::BtcAddressesRequest {
+                count: ADDRESSES_MAX_COUNT + 1,
+                ..request.clone()
+            })),
+            Err(Error::InvalidInput)
+        );
+
+        // Address index exceeding the BIP-44 limit.
+        mock_default();
+        assert_eq!(
+            block_on(process_addresses(&pb::BtcAddressesRequest {
+                start_index: 9999,
+                count: 2,
+                ..request.clone()
+            })),
+            Err(Error::InvalidInput)
+        );
+
+        // Not an account-level keypath.
+        mock_default();
+        assert_eq!(
+            block_on(process_addresses(&pb::BtcAddressesRequest {
+                keypath: vec![84 + HARDENED, 0 + HARDENED, 0 + HARDENED, 0],
+                ..request.clone()
+            })),
+            Err(Error::InvalidInput)
+        );
+
+        // Only simple (single-sig) script configs are supported.
+        mock_default();
+        assert_eq!(
+            block_on(process_addresses(&pb::BtcAddressesRequest {
+                script_config: Some(BtcScriptConfig {
+                    config: Some(Config::Multisig(Multisig::default())),
+                }),
+                ..request.clone()
+            })),
+            Err(Error::InvalidInput)
+        );
+    }
+
+    #[test]
+    fn test_process_coin_params() {
+        assert_eq!(
+            process_coin_params(&pb::BtcCoinParamsRequest {
+                coin: BtcCoin::Btc as _
+            }),
+            Ok(pb::btc_response::Response::CoinParams(
+                pb::BtcCoinParamsResponse {
+                    name: "Bitcoin".into(),
+                    unit: "BTC".into(),
+                    bech32_hrp: "bc".into(),
+                    base58_version_p2pkh: 0x00,
+                    base58_version_p2sh: 0x05,
+                    slip44: 0,
+                    taproot_support: true,
+                    rbf_support: true,
+                }
+            ))
+        );
+        assert_eq!(
+            process_coin_params(&pb::BtcCoinParamsRequest {
+                coin: BtcCoin::Tbtc as _
+            }),
+            Ok(pb::btc_response::Response::CoinParams(
+                pb::BtcCoinParamsResponse {
+                    name: "BTC Testnet".into(),
+                    unit: "TBTC".into(),
+                    bech32_hrp: "tb".into(),
+                    base58_version_p2pkh: 0x6f,
+                    base58_version_p2sh: 0xc4,
+                    slip44: 1,
+                    taproot_support: true,
+                    rbf_support: true,
+                }
+            ))
+        );
+        assert_eq!(
+            process_coin_params(&pb::BtcCoinParamsRequest {
+                coin: BtcCoin::Ltc as _
+            }),
+            Ok(pb::btc_response::Response::CoinParams(
+                pb::BtcCoinParamsResponse {
+                    name: "Litecoin".into(),
+                    unit: "LTC".into(),
+                    bech32_hrp: "ltc".into(),
+                    base58_version_p2pkh: 0x30,
+                    base58_version_p2sh: 0x32,
+                    slip44: 2,
+                    taproot_support: true,
+                    rbf_support: false,
+                }
+            ))
+        );
+
+        // Invalid coin.
+        assert_eq!(
+            process_coin_params(&pb::BtcCoinParamsRequest { coin: 100 }),
+            Err(Error::InvalidInput)
+        );
+    }
+
+    #[test]
+    fn test_process_verify_address() {
+        static mut UI_COUNTER: u32 = 0;
+
+        // Each address type is shown together with the formatted amount.
+        for address in [
+            // P2PKH
+            "1AUrwD77AL5ax5zj2BhZQ1x43wA5NLsYg1",
+            // P2SH
+            "3Ecs74kCeeAc6EKWMGe7RXupUoeeXPdyj7",
+            // P2WPKH (bech32)
+            "bc1q8uxu96g59kyrnt5ujzsee2rvxmvj8k9trg5ltx",
+            // P2TR (bech32m)
+            "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr",
+        ] {
+            mock(Data {
+                ui_transaction_address_create: Some(Box::new(move |amount, recipient| {
+                    unsafe { UI_COUNTER += 1 }
+                    assert_eq!(amount, "12.34567890 BTC");
+                    assert_eq!(recipient, address);
+                    true
+                })),
+                ..Default::default()
+            });
+            assert_eq!(
+                block_on(process_verify_address(&pb::BtcVerifyAddressRequest {
+                    coin: BtcCoin::Btc as _,
+                    address: address.into(),
+                    amount: Some(1234567890),
+                })),
+                Ok(pb::btc_response::Response::Success(pb::BtcSuccess {}))
+            );
+        }
+        assert_eq!(unsafe { UI_COUNTER }, 4);
+
+        // Without an amount, the coin name takes its place.
+        mock(Data {
+            ui_transaction_address_create: Some(Box::new(|amount, recipient| {
+                assert_eq!(amount, "Bitcoin");
+                assert_eq!(recipient, "1AUrwD77AL5ax5zj2BhZQ1x43wA5NLsYg1");
+                true
+            })),
+            ..Default::default()
+        });
+        assert_eq!(
+            block_on(process_verify_address(&pb::BtcVerifyAddressRequest {
+                coin: BtcCoin::Btc as _,
+                address: "1AUrwD77AL5ax5zj2BhZQ1x43wA5NLsYg1".into(),
+                amount: None,
+            })),
+            Ok(pb::btc_response::Response::Success(pb::BtcSuccess {}))
+        );
+
+        // User aborts.
+        mock(Data {
+            ui_transaction_address_create: Some(Box::new(|_amount, _recipient| false)),
+            ..Default::default()
+        });
+        assert_eq!(
+            block_on(process_verify_address(&pb::BtcVerifyAddressRequest {
+                coin: BtcCoin::Btc as _,
+                address: "1AUrwD77AL5ax5zj2BhZQ1x43wA5NLsYg1".into(),
+                amount: None,
+            })),
+            Err(Error::UserAbort)
+        );
+
+        // Invalid addresses are rejected without any dialog.
+        for address in [
+            "",
+            // Bad base58check checksum (last char changed).
+            "1AUrwD77AL5ax5zj2BhZQ1x43wA5NLsYg2",
+            // Bad bech32 checksum (last char changed).
+            "bc1q8uxu96g59kyrnt5ujzsee2rvxmvj8k9trg5lty",
+            // bech32 checksum where bech32m is required for v1.
+            "bc1pw508d6qejxtdg4y5r3zarvary0c5xw7k8e76x7",
+            // Litecoin addresses for the Bitcoin coin.
+            "LUhpCRQwEzKeCtgtCKgrg31pG9XMZLm6qX",
+            "ltc1q8uxu96g59kyrnt5ujzsee2rvxmvj8k9t85wmnk",
+            // Testnet bech32 address on mainnet.
+            "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx",
+        ] {
+            mock(Data::default());
+            assert_eq!(
+                block_on(process_verify_address(&pb::BtcVerifyAddressRequest {
+                    coin: BtcCoin::Btc as _,
+                    address: address.into(),
+                    amount: None,
+                })),
+                Err(Error::InvalidInput)
+            );
+        }
+    }
+
+    #[test]
+    fn test_process_silent_payment_scan_key() {
+        static mut CONFIRM_COUNTER: u32 = 0;
+
+        // Public key export. Expected values are the BIP-352 scan keypair at m/352'/0'/0'/1'/0
+        // for the test mnemonic, derived with an independent BIP-32 implementation.
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|params| {
+                unsafe { CONFIRM_COUNTER += 1 }
+                assert_eq!(params.title, "Bitcoin");
+                assert_eq!(params.body, "Silent payments\nscan key\nfingerprint:\n8e4c42f4");
+                true
+            })),
+            ..Default::default()
+        });
+        mock_unlocked();
+        assert_eq!(
+            block_on(process_silent_payment_scan_key(
+                &pb::BtcSilentPaymentScanKeyRequest {
+                    coin: BtcCoin::Btc as _,
+                    keypath: vec![352 + HARDENED, 0 + HARDENED, 0 + HARDENED, 1 + HARDENED, 0],
+                    export_private_key: false,
+                }
+            )),
+            Ok(pb::btc_response::Response::SilentPaymentScanKey(
+                pb::BtcSilentPaymentScanKeyResponse {
+                    scan_public_key: hex::decode(
+                        "03a65cbf97473b97d9c79391ae5d23a744c613a58393153268ebcc853047f764fb"
+                    )
+                    .unwrap(),
+                    scan_private_key: vec![],
+                }
+            ))
+        );
+        assert_eq!(unsafe { CONFIRM_COUNTER }, 1);
+
+        // Private key export shows a warning first; the keypair is m/352'/1'/0'/1'/0 on testnet.
+        unsafe { CONFIRM_COUNTER = 0 }
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|params| {
+                match unsafe {
+                    CONFIRM_COUNTER += 1;
+                    CONFIRM_COUNTER
+                } {
+                    1 => {
+                        assert_eq!(params.title, "BTC Testnet");
+                        assert_eq!(
+                            params.body,
+                            "Silent payments\nscan key\nfingerprint:\na4941b0b"
+                        );
+                    }
+                    2 => {
+                        assert_eq!(params.title, "Warning");
+                        assert_eq!(
+                            params.body,
+                            "Export scan\nprivate key?\nIt can watch\nincoming funds\nbut cannot\nspend them"
+                        );
+                        assert!(params.longtouch);
+                    }
+                    _ => panic!("too many dialogs"),
+                }
+                true
+            })),
+            ..Default::default()
+        });
+        mock_unlocked();
+        assert_eq!(
+            block_on(process_silent_payment_scan_key(
+                &pb::BtcSilentPaymentScanKeyRequest {
+                    coin: BtcCoin::Tbtc as _,
+                    keypath: vec![352 + HARDENED, 1 + HARDENED, 0 + HARDENED, 1 + HARDENED, 0],
+                    export_private_key: true,
+                }
+            )),
+            Ok(pb::btc_response::Response::SilentPaymentScanKey(
+                pb::BtcSilentPaymentScanKeyResponse {
+                    scan_public_key: hex::decode(
+                        "02b137c5239795b4114ee1231552a94215564a7e6d954bbbd044dd77907cc9a4ca"
+                    )
+                    .unwrap(),
+                    scan_private_key: hex::decode(
+                        "8f3e1f6738e0b0758f41ab6369ba2f002a3d0751f728af6fc2a82cd3db0af5f9"
+                    )
+                    .unwrap(),
+                }
+            ))
+        );
+        assert_eq!(unsafe { CONFIRM_COUNTER }, 2);
+
+        // Aborting the fingerprint confirmation aborts before any key leaves the device.
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|_params| false)),
+            ..Default::default()
+        });
+        mock_unlocked();
+        assert_eq!(
+            block_on(process_silent_payment_scan_key(
+                &pb::BtcSilentPaymentScanKeyRequest {
+                    coin: BtcCoin::Btc as _,
+                    keypath: vec![352 + HARDENED, 0 + HARDENED, 0 + HARDENED, 1 + HARDENED, 0],
+                    export_private_key: true,
+                }
+            )),
+            Err(Error::UserAbort)
+        );
+
+        // Aborting the private key warning aborts the export.
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|params| params.title != "Warning")),
+            ..Default::default()
+        });
+        mock_unlocked();
+        assert_eq!(
+            block_on(process_silent_payment_scan_key(
+                &pb::BtcSilentPaymentScanKeyRequest {
+                    coin: BtcCoin::Btc as _,
+                    keypath: vec![352 + HARDENED, 0 + HARDENED, 0 + HARDENED, 1 + HARDENED, 0],
+                    export_private_key: true,
+                }
+            )),
+            Err(Error::UserAbort)
+        );
+
+        // Invalid keypaths are rejected without any dialog - in particular the spend keypath
+        // m/352'/coin'/account'/0'/0.
+        for keypath in [
+            vec![],
+            vec![352 + HARDENED, 0 + HARDENED, 0 + HARDENED, 0 + HARDENED, 0],
+            vec![352 + HARDENED, 1 + HARDENED, 0 + HARDENED, 1 + HARDENED, 0],
+            vec![84 + HARDENED, 0 + HARDENED, 0 + HARDENED, 1 + HARDENED, 0],
+        ] {
+            mock(Data::default());
+            mock_unlocked();
+            assert_eq!(
+                block_on(process_silent_payment_scan_key(
+                    &pb::BtcSilentPaymentScanKeyRequest {
+                        coin: BtcCoin::Btc as _,
+                        keypath,
+                        export_private_key: false,
+                    }
+                )),
+                Err(Error::InvalidInput)
+            );
+        }
     }
 
     #[test]
@@ -1041,6 +2466,7 @@ mod tests {
                 coin: test.coin as _,
                 keypath: test.keypath.to_vec(),
                 display: true,
+                allow_nonstandard: false,
                 output: Some(Output::ScriptConfig(BtcScriptConfig {
                     config: Some(Config::Multisig(multisig)),
                 })),
@@ -1049,12 +2475,275 @@ mod tests {
                 block_on(process_pub(&req)),
                 Ok(Response::Pub(pb::PubResponse {
                     r#pub: test.expected_address.into(),
+                    pub_key_script: address_pk_script(test.expected_address),
                 })),
             );
             assert_eq!(unsafe { UI_COUNTER }, 3);
         }
     }
 
+    /// The 2-of-3 multisig addresses must match the addresses derived via rust-bitcoin's
+    /// descriptor machinery.
+    #[test]
+    fn test_address_multisig_descriptor() {
+        use core::str::FromStr;
+
+        mock_unlocked_using_mnemonic(
+            "sudden tenant fault inject concert weather maid people chunk youth stumble grit",
+            "",
+        );
+        mock_memory();
+
+        let xpubs: &[&str] = &[
+            "xpub6FMWuwbCA9KhoRzAMm63ZhLspk5S2DM5sePo8J8mQhcS1xyMbAqnc7Q7UescVEVFCS6qBMQLkEJWQ9Z3aDPgBov5nFUYxsJhwumsxM4npSo",
+            // This xpub corresponds to the mocked seed above at m/48'/1'/0'/2'.
+            "xpub6EMfjyGVUvwhpc3WKN1zXhMFGKJGMaSBPqbja4tbGoYvRBSXeTBCaqrRDjcuGTcaY95JrrAnQvDG3pdQPdtnYUCugjeksHSbyZT7rq38VQF",
+            "xpub6ERxBysTYfQyY4USv6c6J1HNVv9hpZFN9LHVPu47Ac4rK8fLy6NnAeeAHyEsMvG4G66ay5aFZii2VM7wT3KxLKX8Q8keZPd67kRGmrD1WJj",
+        ];
+        let keypath_account = &[48 + HARDENED, 1 + HARDENED, 0 + HARDENED, 2 + HARDENED];
+        let multisig = Multisig {
+            threshold: 2,
+            xpubs: xpubs.iter().map(|s| parse_xpub(s).unwrap()).collect(),
+            our_xpub_index: 1,
+            script_type: MultisigScriptType::P2wsh as _,
+        };
+        bitbox02::memory::multisig_set_by_hash(
+            &multisig::get_hash(
+                BtcCoin::Tbtc,
+                &multisig,
+                multisig::SortXpubs::Yes,
+                keypath_account,
+            )
+            .unwrap(),
+            "test name",
+        )
+        .unwrap();
+
+        let secp = bitcoin::secp256k1::Secp256k1::new();
+        for (change, address_index) in [(0, 0), (0, 5), (1, 3)] {
+            let derived_address =
+                miniscript::Descriptor::<miniscript::DescriptorPublicKey>::from_str(&format!(
+                    "wsh(sortedmulti(2,{}/{}/{},{}/{}/{},{}/{}/{}))",
+                    xpubs[0],
+                    change,
+                    address_index,
+                    xpubs[1],
+                    change,
+                    address_index,
+                    xpubs[2],
+                    change,
+                    address_index,
+                ))
+                .unwrap()
+                .at_derivation_index(0)
+                .unwrap()
+                .derived_descriptor(&secp)
+                .unwrap()
+                .address(bitcoin::Network::Testnet)
+                .unwrap();
+            let expected_address = format!("{}", derived_address);
+
+            let mut keypath = keypath_account.to_vec();
+            keypath.extend_from_slice(&[change, address_index]);
+            assert_eq!(
+                block_on(process_pub(&pb::BtcPubRequest {
+                    coin: BtcCoin::Tbtc as _,
+                    keypath,
+                    display: false,
+                    allow_nonstandard: false,
+                    output: Some(Output::ScriptConfig(BtcScriptConfig {
+                        config: Some(Config::Multisig(multisig.clone())),
+                    })),
+                })),
+                Ok(Response::Pub(pb::PubResponse {
+                    r#pub: expected_address.clone(),
+                    pub_key_script: address_pk_script(&expected_address),
+                })),
+            );
+        }
+    }
+
+    /// Address verification of registered accounts referenced by their registration hash: several
+    /// indices are derived and compared against rust-bitcoin descriptor wallets, and wrong hashes,
+    /// unregistered accounts and indices outside the wildcard positions are rejected.
+    #[test]
+    fn test_process_registered_address() {
+        use core::str::FromStr;
+
+        static mut CONFIRM_COUNTER: u32 = 0;
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|_params| {
+                unsafe { CONFIRM_COUNTER += 1 }
+                true
+            })),
+            ..Default::default()
+        });
+        mock_memory();
+        mock_unlocked_using_mnemonic(
+            "sudden tenant fault inject concert weather maid people chunk youth stumble grit",
+            "",
+        );
+
+        let xpubs: &[&str] = &[
+            "xpub6FMWuwbCA9KhoRzAMm63ZhLspk5S2DM5sePo8J8mQhcS1xyMbAqnc7Q7UescVEVFCS6qBMQLkEJWQ9Z3aDPgBov5nFUYxsJhwumsxM4npSo",
+            // This xpub corresponds to the mocked seed above at m/48'/1'/0'/2'.
+            "xpub6EMfjyGVUvwhpc3WKN1zXhMFGKJGMaSBPqbja4tbGoYvRBSXeTBCaqrRDjcuGTcaY95JrrAnQvDG3pdQPdtnYUCugjeksHSbyZT7rq38VQF",
+            "xpub6ERxBysTYfQyY4USv6c6J1HNVv9hpZFN9LHVPu47Ac4rK8fLy6NnAeeAHyEsMvG4G66ay5aFZii2VM7wT3KxLKX8Q8keZPd67kRGmrD1WJj",
+        ];
+        let keypath_account = &[48 + HARDENED, 1 + HARDENED, 0 + HARDENED, 2 + HARDENED];
+        let multisig = Multisig {
+            threshold: 2,
+            xpubs: xpubs.iter().map(|s| parse_xpub(s).unwrap()).collect(),
+            our_xpub_index: 1,
+            script_type: MultisigScriptType::P2wsh as _,
+        };
+        let registration_hash = multisig::get_hash(
+            BtcCoin::Tbtc,
+            &multisig,
+            multisig::SortXpubs::Yes,
+            keypath_account,
+        )
+        .unwrap();
+        bitbox02::memory::multisig_set_by_hash(&registration_hash, "test name").unwrap();
+
+        let make_request = |change: u32, address_index: u32| pb::BtcRegisteredAddressRequest {
+            registration: Some(pb::BtcScriptConfigRegistration {
+                coin: BtcCoin::Tbtc as _,
+                script_config: Some(BtcScriptConfig {
+                    config: Some(Config::Multisig(multisig.clone())),
+                }),
+                keypath: keypath_account.to_vec(),
+            }),
+            registration_hash: registration_hash.clone(),
+            change,
+            address_index,
+        };
+
+        let secp = bitcoin::secp256k1::Secp256k1::new();
+        for (change, address_index) in [(0, 0), (0, 7), (1, 2)] {
+            let derived_address =
+                miniscript::Descriptor::<miniscript::DescriptorPublicKey>::from_str(&format!(
+                    "wsh(sortedmulti(2,{}/{}/{},{}/{}/{},{}/{}/{}))",
+                    xpubs[0],
+                    change,
+                    address_index,
+                    xpubs[1],
+                    change,
+                    address_index,
+                    xpubs[2],
+                    change,
+                    address_index,
+                ))
+                .unwrap()
+                .at_derivation_index(0)
+                .unwrap()
+                .derived_descriptor(&secp)
+                .unwrap()
+                .address(bitcoin::Network::Testnet)
+                .unwrap();
+            assert_eq!(
+                block_on(process_registered_address(&make_request(
+                    change,
+                    address_index
+                ))),
+                Ok(pb::btc_response::Response::RegisteredAddress(
+                    pb::BtcRegisteredAddressResponse {
+                        address: format!("{}", derived_address),
+                        name: "test name".into(),
+                    }
+                )),
+            );
+        }
+        // Three dialogs per address: multisig type, account name, address.
+        assert_eq!(unsafe { CONFIRM_COUNTER }, 9);
+
+        // Registration details that do not hash to the referenced registration are rejected.
+        let mut request = make_request(0, 0);
+        request.registration_hash[0] ^= 1;
+        assert_eq!(
+            block_on(process_registered_address(&request)),
+            Err(Error::InvalidInput)
+        );
+
+        // Keypath components outside the wildcard positions are rejected.
+        assert_eq!(
+            block_on(process_registered_address(&make_request(2, 0))),
+            Err(Error::InvalidInput)
+        );
+        assert_eq!(
+            block_on(process_registered_address(&make_request(0, 10000))),
+            Err(Error::InvalidInput)
+        );
+        assert_eq!(unsafe { CONFIRM_COUNTER }, 9);
+
+        // Deleting the registration makes the call fail.
+        bitbox02::memory::multisig_erase_by_hash(&registration_hash).unwrap();
+        assert_eq!(
+            block_on(process_registered_address(&make_request(0, 0))),
+            Err(Error::InvalidInput)
+        );
+
+        // Policy registration: the same flow works for wallet policies, which carry the account
+        // keypaths in their key infos.
+        const KEYPATH_ACCOUNT: &[u32] = &[48 + HARDENED, 1 + HARDENED, 0 + HARDENED, 3 + HARDENED];
+        const SOME_XPUB: &str = "tpubDFj9SBQssRHA5EB1ox58mcgF9sB61br9RGz6UrBukcNKmFe4fPgskZ4wigxQ1jSUzLdjnvvDHL8Z6L3ey5Ev5FNNqrDrePxwXsNHiLZhBTc";
+        let our_xpub = crate::keystore::get_xpub(KEYPATH_ACCOUNT)
+            .unwrap()
+            .serialize_str(XPubType::Xpub)
+            .unwrap();
+        let policy = pb::btc_script_config::Policy {
+            policy: "wsh(multi(2,@0/**,@1/**))".into(),
+            keys: vec![
+                pb::KeyOriginInfo {
+                    root_fingerprint: keystore::root_fingerprint().unwrap(),
+                    keypath: KEYPATH_ACCOUNT.to_vec(),
+                    xpub: Some(crate::keystore::get_xpub(KEYPATH_ACCOUNT).unwrap().into()),
+                },
+                pb::KeyOriginInfo {
+                    root_fingerprint: vec![],
+                    keypath: vec![],
+                    xpub: Some(parse_xpub(SOME_XPUB).unwrap()),
+                },
+            ],
+        };
+        let policy_hash = policies::get_hash(BtcCoin::Tbtc, &policy).unwrap();
+        bitbox02::memory::multisig_set_by_hash(&policy_hash, "policy name").unwrap();
+        for (change, address_index) in [(0, 0), (1, 5)] {
+            let derived_address =
+                miniscript::Descriptor::<miniscript::DescriptorPublicKey>::from_str(&format!(
+                    "wsh(multi(2,{}/{}/{},{}/{}/{}))",
+                    our_xpub, change, address_index, SOME_XPUB, change, address_index,
+                ))
+                .unwrap()
+                .at_derivation_index(0)
+                .unwrap()
+                .derived_descriptor(&secp)
+                .unwrap()
+                .address(bitcoin::Network::Testnet)
+                .unwrap();
+            assert_eq!(
+                block_on(process_registered_address(&pb::BtcRegisteredAddressRequest {
+                    registration: Some(pb::BtcScriptConfigRegistration {
+                        coin: BtcCoin::Tbtc as _,
+                        script_config: Some(BtcScriptConfig {
+                            config: Some(Config::Policy(policy.clone())),
+                        }),
+                        keypath: vec![],
+                    }),
+                    registration_hash: policy_hash.clone(),
+                    change,
+                    address_index,
+                })),
+                Ok(pb::btc_response::Response::RegisteredAddress(
+                    pb::BtcRegisteredAddressResponse {
+                        address: format!("{}", derived_address),
+                        name: "policy name".into(),
+                    }
+                )),
+            );
+        }
+    }
+
     #[test]
     fn test_address_policy() {
         mock_unlocked_using_mnemonic(
@@ -1190,6 +2879,7 @@ mod tests {
                 coin: test.coin as _,
                 keypath: test.keypath.to_vec(),
                 display: false,
+                allow_nonstandard: false,
                 output: Some(Output::ScriptConfig(BtcScriptConfig {
                     config: Some(Config::Policy(policy)),
                 })),
@@ -1198,6 +2888,7 @@ mod tests {
                 block_on(process_pub(&req)),
                 Ok(Response::Pub(pb::PubResponse {
                     r#pub: test.expected_address.into(),
+                    pub_key_script: address_pk_script(test.expected_address),
                 })),
             );
         }
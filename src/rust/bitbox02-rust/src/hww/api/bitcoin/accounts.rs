@@ -0,0 +1,89 @@
+// Copyright 2025 Shift Crypto AG
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Names for singlesig accounts, registered like multisig accounts and stored in the same
+//! registry. An account is identified by its coin and account-level keypath, which includes the
+//! BIP-44 purpose and account index.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use super::pb::BtcCoin;
+
+use sha2::{Digest, Sha256};
+
+/// Creates a hash identifying a singlesig account, used for name registration and lookup. The
+/// keypath is the account-level keypath and must be pre-validated.
+pub fn get_hash(coin: BtcCoin, keypath: &[u32]) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    {
+        // 1. coin
+        let byte: u8 = match coin {
+            BtcCoin::Btc => 0x00,
+            BtcCoin::Tbtc => 0x01,
+            BtcCoin::Ltc => 0x02,
+            BtcCoin::Tltc => 0x03,
+            BtcCoin::Rbtc => 0x04,
+            BtcCoin::Sbtc => 0x05,
+        };
+        hasher.update(byte.to_le_bytes());
+    }
+    {
+        // 2. script config type (0x00-0x02 are used by the multisig/MuSig2 script types)
+        let byte: u8 = 0x03;
+        hasher.update(byte.to_le_bytes());
+    }
+    {
+        // 3. keypath len
+        let num: u32 = keypath.len() as _;
+        hasher.update(num.to_le_bytes());
+    }
+    {
+        // 4. keypath
+        for el in keypath.iter() {
+            hasher.update(el.to_le_bytes());
+        }
+    }
+    hasher.finalize().as_slice().into()
+}
+
+/// Get the name of a registered singlesig account at the given account-level keypath, or `None` if
+/// no name was registered for it.
+pub fn get_name(coin: BtcCoin, keypath: &[u32]) -> Option<String> {
+    bitbox02::memory::multisig_get_by_hash(&get_hash(coin, keypath))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use bitbox02::testing::mock_memory;
+    use util::bip32::HARDENED;
+
+    #[test]
+    fn test_get_name() {
+        mock_memory();
+
+        let keypath = &[84 + HARDENED, HARDENED, HARDENED];
+        assert_eq!(get_name(BtcCoin::Btc, keypath), None);
+
+        bitbox02::memory::multisig_set_by_hash(&get_hash(BtcCoin::Btc, keypath), "savings")
+            .unwrap();
+        assert_eq!(get_name(BtcCoin::Btc, keypath), Some("savings".into()));
+        // The name is tied to the exact coin and keypath.
+        assert_eq!(get_name(BtcCoin::Ltc, keypath), None);
+        assert_eq!(get_name(BtcCoin::Btc, &[84 + HARDENED, HARDENED, 1 + HARDENED]), None);
+        assert_eq!(get_name(BtcCoin::Btc, &[49 + HARDENED, HARDENED, HARDENED]), None);
+    }
+}
@@ -0,0 +1,198 @@
+// Copyright 2025 Shift Crypto AG
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Bech32/bech32m encoding of segwit addresses, as specified by BIP-173 and BIP-350.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// The bech32 character set, mapping 5-bit values to characters.
+const CHARSET: &[u8] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+const GENERATOR: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+
+/// Checksum constant for bech32, used for witness version 0 (BIP-173).
+const BECH32_CONST: u32 = 1;
+/// Checksum constant for bech32m, used for witness versions 1-16 (BIP-350).
+const BECH32M_CONST: u32 = 0x2bc830a3;
+
+/// A bech32 string may not be longer than this, limiting the damage misread characters can do
+/// (BIP-173).
+const MAX_LENGTH: usize = 90;
+
+fn polymod(values: &[u8]) -> u32 {
+    let mut chk: u32 = 1;
+    for &value in values {
+        let top = chk >> 25;
+        chk = ((chk & 0x1ffffff) << 5) ^ (value as u32);
+        for (i, generator) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 != 0 {
+                chk ^= generator;
+            }
+        }
+    }
+    chk
+}
+
+/// The checksum covers the hrp expanded to its character high bits, a zero separator, and the
+/// character low bits.
+fn hrp_expand(hrp: &str) -> Vec<u8> {
+    let mut result: Vec<u8> = hrp.bytes().map(|b| b >> 5).collect();
+    result.push(0);
+    result.extend(hrp.bytes().map(|b| b & 31));
+    result
+}
+
+/// Regroups the witness program bits into 5-bit symbols, zero-padding the last symbol.
+fn to_5bit(data: &[u8]) -> Vec<u8> {
+    let mut result = Vec::with_capacity(data.len() * 8 / 5 + 1);
+    let mut acc: u32 = 0;
+    let mut bits: u32 = 0;
+    for &b in data {
+        acc = (acc << 8) | (b as u32);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            result.push(((acc >> bits) & 31) as u8);
+        }
+    }
+    if bits > 0 {
+        result.push(((acc << (5 - bits)) & 31) as u8);
+    }
+    result
+}
+
+/// Encodes a segwit address: the hrp, a `1` separator, the witness version symbol, the witness
+/// program regrouped into 5-bit symbols and the six symbol checksum. Witness version 0 uses the
+/// bech32 checksum, versions 1-16 use bech32m. The program length limits of BIP-141 apply: 2 to 40
+/// bytes, and exactly 20 or 32 bytes for version 0.
+pub fn encode(hrp: &str, witness_version: u8, witness_program: &[u8]) -> Result<String, ()> {
+    if witness_version > 16 {
+        return Err(());
+    }
+    match (witness_version, witness_program.len()) {
+        (0, 20) | (0, 32) => (),
+        (0, _) => return Err(()),
+        (_, 2..=40) => (),
+        _ => return Err(()),
+    }
+    let mut values: Vec<u8> = Vec::new();
+    values.push(witness_version);
+    values.extend(to_5bit(witness_program));
+
+    let checksum_const = if witness_version == 0 {
+        BECH32_CONST
+    } else {
+        BECH32M_CONST
+    };
+    let mut checksummed = hrp_expand(hrp);
+    checksummed.extend(&values);
+    checksummed.extend(&[0; 6]);
+    let polymod = polymod(&checksummed) ^ checksum_const;
+
+    let mut result = String::with_capacity(hrp.len() + 1 + values.len() + 6);
+    result.push_str(hrp);
+    result.push('1');
+    for &value in values.iter() {
+        result.push(CHARSET[value as usize] as char);
+    }
+    for i in 0..6 {
+        result.push(CHARSET[((polymod >> (5 * (5 - i))) & 31) as usize] as char);
+    }
+    if result.len() > MAX_LENGTH {
+        return Err(());
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode() {
+        // Valid segwit address test vectors from BIP-173 and BIP-350 (the bech32m vectors replace
+        // the BIP-173 vectors with witness version 1+).
+        // https://github.com/bitcoin/bips/blob/master/bip-0173.mediawiki#user-content-Test_vectors
+        // https://github.com/bitcoin/bips/blob/master/bip-0350.mediawiki#user-content-Test_vectors_for_v0v16_native_segwit_addresses
+        assert_eq!(
+            encode(
+                "bc",
+                0,
+                b"\x75\x1e\x76\xe8\x19\x91\x96\xd4\x54\x94\x1c\x45\xd1\xb3\xa3\x23\xf1\x43\x3b\xd6",
+            ),
+            Ok("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4".into())
+        );
+        assert_eq!(
+            encode(
+                "tb",
+                0,
+                b"\x18\x63\x14\x3c\x14\xc5\x16\x68\x04\xbd\x19\x20\x33\x56\xda\x13\x6c\x98\x56\x78\xcd\x4d\x27\xa1\xb8\xc6\x32\x96\x04\x90\x32\x62",
+            ),
+            Ok("tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7".into())
+        );
+        assert_eq!(
+            encode(
+                "bc",
+                1,
+                b"\x75\x1e\x76\xe8\x19\x91\x96\xd4\x54\x94\x1c\x45\xd1\xb3\xa3\x23\xf1\x43\x3b\xd6\x75\x1e\x76\xe8\x19\x91\x96\xd4\x54\x94\x1c\x45\xd1\xb3\xa3\x23\xf1\x43\x3b\xd6",
+            ),
+            Ok("bc1pw508d6qejxtdg4y5r3zarvary0c5xw7kw508d6qejxtdg4y5r3zarvary0c5xw7kt5nd6y".into())
+        );
+        assert_eq!(encode("bc", 16, b"\x75\x1e"), Ok("bc1sw50qgdz25j".into()));
+        assert_eq!(
+            encode(
+                "bc",
+                2,
+                b"\x75\x1e\x76\xe8\x19\x91\x96\xd4\x54\x94\x1c\x45\xd1\xb3\xa3\x23",
+            ),
+            Ok("bc1zw508d6qejxtdg4y5r3zarvaryvaxxpcs".into())
+        );
+        assert_eq!(
+            encode(
+                "tb",
+                0,
+                b"\x00\x00\x00\xc4\xa5\xca\xd4\x62\x21\xb2\xa1\x87\x90\x5e\x52\x66\x36\x2b\x99\xd5\xe9\x1c\x6c\xe2\x4d\x16\x5d\xab\x93\xe8\x64\x33",
+            ),
+            Ok("tb1qqqqqp399et2xygdj5xreqhjjvcmzhxw4aywxecjdzew6hylgvsesrxh6hy".into())
+        );
+        assert_eq!(
+            encode(
+                "tb",
+                1,
+                b"\x00\x00\x00\xc4\xa5\xca\xd4\x62\x21\xb2\xa1\x87\x90\x5e\x52\x66\x36\x2b\x99\xd5\xe9\x1c\x6c\xe2\x4d\x16\x5d\xab\x93\xe8\x64\x33",
+            ),
+            Ok("tb1pqqqqp399et2xygdj5xreqhjjvcmzhxw4aywxecjdzew6hylgvsesf3hn0c".into())
+        );
+        assert_eq!(
+            encode(
+                "bc",
+                1,
+                b"\x79\xbe\x66\x7e\xf9\xdc\xbb\xac\x55\xa0\x62\x95\xce\x87\x0b\x07\x02\x9b\xfc\xdb\x2d\xce\x28\xd9\x59\xf2\x81\x5b\x16\xf8\x17\x98",
+            ),
+            Ok("bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0".into())
+        );
+
+        // Invalid witness version.
+        assert!(encode("bc", 17, &[0; 20]).is_err());
+        // Invalid program lengths.
+        assert!(encode("bc", 0, &[0; 19]).is_err());
+        assert!(encode("bc", 0, &[0; 25]).is_err());
+        assert!(encode("bc", 0, &[0; 33]).is_err());
+        assert!(encode("bc", 1, &[0; 1]).is_err());
+        assert!(encode("bc", 1, &[0; 41]).is_err());
+        // Result would exceed 90 characters.
+        assert!(encode("anhrplongenoughtopushtheresultoverthelimit", 1, &[0; 40]).is_err());
+    }
+}
@@ -32,7 +32,20 @@ use sha2::{Digest, Sha256};
 const HASH160_LEN: usize = 20;
 const SHA256_LEN: usize = 32;
 
+/// Inserts an apostrophe as thousands separator every three digits, e.g. "1234567" -> "1'234'567".
+/// `v` must consist only of '0'-'9' digits.
+fn with_thousands_separators(v: &str) -> String {
+    let groups: Vec<&str> = v
+        .as_bytes()
+        .rchunks(3)
+        .rev()
+        .map(|group| core::str::from_utf8(group).unwrap())
+        .collect();
+    groups.join("'")
+}
+
 /// Converts a satoshi value to a string, suffixed with `unit`, e.g. 1234567890 -> "12.3456789 BTC".
+/// Sat amounts are formatted with thousands separators, e.g. "1'234'567'890 sat".
 pub fn format_amount(
     params: &Params,
     format_unit: FormatUnit,
@@ -47,6 +60,14 @@ pub fn format_amount(
             FormatUnit::Default => (8, "TBTC"),
             FormatUnit::Sat => (0, "tsat"),
         },
+        BtcCoin::Rbtc => match format_unit {
+            FormatUnit::Default => (8, "RBTC"),
+            FormatUnit::Sat => (0, "rsat"),
+        },
+        BtcCoin::Sbtc => match format_unit {
+            FormatUnit::Default => (8, "SBTC"),
+            FormatUnit::Sat => (0, "ssat"),
+        },
         BtcCoin::Ltc => match format_unit {
             FormatUnit::Default => (8, "LTC"),
             _ => return Err(Error::InvalidInput),
@@ -58,17 +79,55 @@ pub fn format_amount(
     };
     let mut s = match format_unit {
         FormatUnit::Default => util::decimal::format_no_trim(satoshi, decimals),
-        _ => util::decimal::format(satoshi, decimals),
+        _ => with_thousands_separators(&util::decimal::format(satoshi, decimals)),
     };
     s.push(' ');
     s.push_str(unit);
     Ok(s)
 }
 
+/// Maximum accepted fiat rate: 100 billion fiat units per coin. Rates above this are rejected so
+/// the fixed-point conversion below cannot produce nonsensically huge values.
+pub const MAX_FIAT_CENTS_PER_COIN: u64 = 10_000_000_000_000;
+
+/// Renders the approximate fiat value of a satoshi amount using the host-supplied rate, e.g.
+/// "~ 512.30 USD (rate from app, unverified)". The rate is untrusted host data: the result is only
+/// ever displayed in addition to the coin amount, never instead of it.
+pub fn format_fiat_amount(
+    fiat_rate: &pb::btc_sign_init_request::FiatRate,
+    satoshi: u64,
+) -> Result<String, Error> {
+    if fiat_rate.currency.is_empty()
+        || fiat_rate.currency.len() > 8
+        || !fiat_rate
+            .currency
+            .bytes()
+            .all(|b| b.is_ascii_uppercase())
+    {
+        return Err(Error::InvalidInput);
+    }
+    if fiat_rate.cents_per_coin == 0 || fiat_rate.cents_per_coin > MAX_FIAT_CENTS_PER_COIN {
+        return Err(Error::InvalidInput);
+    }
+    // Fixed point: fiat cents = satoshi * rate / 1e8, rounding half up. The intermediate product
+    // of two u64 cannot overflow an u128.
+    let cents: u128 =
+        (satoshi as u128 * fiat_rate.cents_per_coin as u128 + 50_000_000) / 100_000_000;
+    let cents: u64 = cents.try_into().or(Err(Error::InvalidInput))?;
+    Ok(format!(
+        "~ {} {} (rate from app, unverified)",
+        util::decimal::format_no_trim(cents, 2),
+        fiat_rate.currency,
+    ))
+}
+
 /// Payload contains the data needed to construct output pkScripts and addresses.
 pub struct Payload {
     pub data: Vec<u8>,
     pub output_type: BtcOutputType,
+    /// The segwit witness version for `FutureWitness` outputs, where `data` is the witness
+    /// program. `None` for all other output types, which imply their version.
+    pub witness_version: Option<u8>,
 }
 
 impl Payload {
@@ -79,9 +138,15 @@ impl Payload {
         keypath: &[u32],
     ) -> Result<Self, Error> {
         match simple_type {
+            SimpleType::P2pkh => Ok(Payload {
+                data: xpub_cache.get_xpub(keypath)?.pubkey_hash160(),
+                output_type: BtcOutputType::P2pkh,
+                witness_version: None,
+            }),
             SimpleType::P2wpkh => Ok(Payload {
                 data: xpub_cache.get_xpub(keypath)?.pubkey_hash160(),
                 output_type: BtcOutputType::P2wpkh,
+                witness_version: None,
             }),
             SimpleType::P2wpkhP2sh => {
                 let payload_p2wpkh =
@@ -90,6 +155,7 @@ impl Payload {
                 Ok(Payload {
                     data: bitbox02::hash160(&pkscript_p2wpkh).to_vec(),
                     output_type: BtcOutputType::P2sh,
+                    witness_version: None,
                 })
             }
             SimpleType::P2tr => {
@@ -100,6 +166,7 @@ impl Payload {
                             .schnorr_bip86_pubkey()?
                             .to_vec(),
                         output_type: BtcOutputType::P2tr,
+                        witness_version: None,
                     })
                 } else {
                     Err(Error::InvalidInput)
@@ -130,6 +197,7 @@ impl Payload {
         let payload_p2wsh = Payload {
             data: Sha256::digest(script).to_vec(),
             output_type: BtcOutputType::P2wsh,
+            witness_version: None,
         };
         match script_type {
             pb::btc_script_config::multisig::ScriptType::P2wsh => Ok(payload_p2wsh),
@@ -138,6 +206,7 @@ impl Payload {
                 Ok(Payload {
                     data: bitbox02::hash160(&pkscript_p2wsh).to_vec(),
                     output_type: BtcOutputType::P2sh,
+                    witness_version: None,
                 })
             }
         }
@@ -160,10 +229,45 @@ impl Payload {
             super::policies::Descriptor::Wsh(wsh) => Ok(Payload {
                 data: Sha256::digest(wsh.witness_script()).to_vec(),
                 output_type: BtcOutputType::P2wsh,
+                witness_version: None,
+            }),
+        }
+    }
+
+    /// Constructs the payload of a policy derived directly at a change/address index, without
+    /// going through a full keypath.
+    pub fn from_policy_derived(
+        policy: &super::policies::ParsedPolicy,
+        is_change: bool,
+        address_index: u32,
+    ) -> Result<Self, Error> {
+        let derived_descriptor = policy.derive(is_change, address_index)?;
+        match derived_descriptor {
+            super::policies::Descriptor::Wsh(wsh) => Ok(Payload {
+                data: Sha256::digest(wsh.witness_script()).to_vec(),
+                output_type: BtcOutputType::P2wsh,
+                witness_version: None,
             }),
         }
     }
 
+    /// Constructs the taproot output key payload of the MuSig2 aggregate key.
+    /// Note that the MuSig2 config is *not* validated, this must be done before calling.
+    /// The xpubs are account-level xpubs.
+    /// keypath_change: 0 for receive addresses, 1 for change addresses.
+    /// keypath_address: receive address index.
+    pub fn from_musig2(
+        musig2: &pb::btc_script_config::Musig2,
+        keypath_change: u32,
+        keypath_address: u32,
+    ) -> Result<Self, Error> {
+        Ok(Payload {
+            data: super::musig2::payload(musig2, keypath_change, keypath_address)?,
+            output_type: BtcOutputType::P2tr,
+            witness_version: None,
+        })
+    }
+
     /// Computes the payload data from a script config. The payload can then be used generate a
     /// pkScript or an address.
     pub fn from(
@@ -183,7 +287,41 @@ impl Payload {
                 keypath[keypath.len() - 1],
             ),
             ValidatedScriptConfig::Policy(policy) => Self::from_policy(policy, keypath),
+            ValidatedScriptConfig::Musig2(musig2) => Self::from_musig2(
+                musig2,
+                keypath[keypath.len() - 2],
+                keypath[keypath.len() - 1],
+            ),
+        }
+    }
+
+    /// Validates that the payload length exactly matches the expected length for the output type:
+    /// 20 bytes for the hash160-based outputs (P2PKH/P2SH/P2WPKH), 32 bytes for the witness script
+    /// hash (P2WSH) resp. the x-only pubkey (P2TR).
+    pub fn validate(&self, params: &Params) -> Result<(), Error> {
+        let expected_len = match self.output_type {
+            BtcOutputType::Unknown => return Err(Error::InvalidInput),
+            BtcOutputType::P2pkh | BtcOutputType::P2sh | BtcOutputType::P2wpkh => HASH160_LEN,
+            BtcOutputType::P2wsh => SHA256_LEN,
+            BtcOutputType::P2tr => {
+                if !params.taproot_support {
+                    return Err(Error::InvalidInput);
+                }
+                32
+            }
+            BtcOutputType::FutureWitness => {
+                // BIP-141 limits witness programs to 2-40 bytes. Versions 0 and 1 have their
+                // dedicated output types with exact length rules.
+                return match self.witness_version {
+                    Some(2..=16) if (2..=40).contains(&self.data.len()) => Ok(()),
+                    _ => Err(Error::InvalidInput),
+                };
+            }
+        };
+        if self.data.len() != expected_len {
+            return Err(Error::InvalidInput);
         }
+        Ok(())
     }
 
     /// Converts a payload to an address.
@@ -213,20 +351,24 @@ impl Payload {
                 if payload.len() != HASH160_LEN {
                     return Err(());
                 }
-                encode_segwit_addr(params.bech32_hrp, 0, payload)
+                super::bech32::encode(params.bech32_hrp, 0, payload)
             }
             BtcOutputType::P2wsh => {
                 if payload.len() != SHA256_LEN {
                     return Err(());
                 }
-                encode_segwit_addr(params.bech32_hrp, 0, payload)
+                super::bech32::encode(params.bech32_hrp, 0, payload)
             }
             BtcOutputType::P2tr => {
                 if !params.taproot_support || payload.len() != 32 {
                     return Err(());
                 }
-                encode_segwit_addr(params.bech32_hrp, 1, payload)
+                super::bech32::encode(params.bech32_hrp, 1, payload)
             }
+            BtcOutputType::FutureWitness => match self.witness_version {
+                Some(version @ 2..=16) => super::bech32::encode(params.bech32_hrp, version, payload),
+                _ => Err(()),
+            },
         }
     }
 
@@ -274,23 +416,23 @@ impl Payload {
                 script::push_data(&mut result, payload);
                 Ok(result)
             }
+            BtcOutputType::FutureWitness => {
+                let version = match self.witness_version {
+                    Some(version @ 2..=16) => version,
+                    _ => return Err(Error::InvalidInput),
+                };
+                if !(2..=40).contains(&payload.len()) {
+                    return Err(Error::Generic);
+                }
+                // OP_2 through OP_16.
+                let mut result = vec![script::OP_1 + version - 1];
+                script::push_data(&mut result, payload);
+                Ok(result)
+            }
         }
     }
 }
 
-fn encode_segwit_addr(
-    hrp: &str,
-    witness_version: u8,
-    witness_program: &[u8],
-) -> Result<String, ()> {
-    let version = match witness_version {
-        0 => bech32::segwit::VERSION_0,
-        1 => bech32::segwit::VERSION_1,
-        _ => return Err(()),
-    };
-    bech32::segwit::encode(bech32::Hrp::parse_unchecked(hrp), version, witness_program).or(Err(()))
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -308,6 +450,7 @@ mod tests {
             let payload = Payload {
                 data: b"\x67\xfe\x0b\xdd\xe7\x98\x46\x71\xf2\xed\x59\xbb\x68\xa9\x7d\x9c\xc6\x8a\x02\xe0".to_vec(),
                 output_type: BtcOutputType::P2pkh,
+                witness_version: None,
             };
 
             assert_eq!(
@@ -325,6 +468,7 @@ mod tests {
             let payload = Payload {
                 data: b"\x3f\x0d\xc2\xe9\x14\x2d\x88\x39\xae\x9c\x90\xa1\x9c\xa8\x6c\x36\xd9\x23\xd8\xab".to_vec(),
                 output_type: BtcOutputType::P2wpkh,
+                witness_version: None,
             };
             assert_eq!(
                 payload.address(params_btc),
@@ -342,6 +486,7 @@ mod tests {
             let payload = Payload {
                 data: b"\x8d\xd0\x9c\x25\xc9\x28\xbe\x67\x66\xf4\x50\x73\x87\x0c\xe3\xbb\x93\x1f\x2f\x55".to_vec(),
                 output_type: BtcOutputType::P2sh,
+                witness_version: None,
             };
             assert_eq!(
                 payload.address(params_btc),
@@ -359,6 +504,7 @@ mod tests {
             let payload = Payload {
                 data: b"aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa".to_vec(),
                 output_type: BtcOutputType::P2wsh,
+                witness_version: None,
             };
             assert_eq!(
                 payload.address(params_btc),
@@ -378,6 +524,7 @@ mod tests {
             let payload = Payload {
                 data: b"\xa6\x08\x69\xf0\xdb\xcf\x1d\xc6\x59\xc9\xce\xcb\xaf\x80\x50\x13\x5e\xa9\xe8\xcd\xc4\x87\x05\x3f\x1d\xc6\x88\x09\x49\xdc\x68\x4c".to_vec(),
                 output_type: BtcOutputType::P2tr,
+                witness_version: None,
             };
             assert!(payload.address(params_ltc).is_err());
             assert_eq!(
@@ -391,6 +538,7 @@ mod tests {
             let payload = Payload {
                 data: b"\xa8\x2f\x29\x94\x4d\x65\xb8\x6a\xe6\xb5\xe5\xcc\x75\xe2\x94\xea\xd6\xc5\x93\x91\xa1\xed\xc5\xe0\x16\xe3\x49\x8c\x67\xfc\x7b\xbb".to_vec(),
                 output_type: BtcOutputType::P2tr,
+                witness_version: None,
             };
             assert!(payload.address(params_ltc).is_err());
             assert_eq!(
@@ -404,6 +552,7 @@ mod tests {
             let payload = Payload {
                 data: b"\x88\x2d\x74\xe5\xd0\x57\x2d\x5a\x81\x6c\xef\x00\x41\xa9\x6b\x6c\x1d\xe8\x32\xf6\xf9\x67\x6d\x96\x05\xc4\x4d\x5e\x9a\x97\xd3\xdc".to_vec(),
                 output_type: BtcOutputType::P2tr,
+                witness_version: None,
             };
             assert!(payload.address(params_ltc).is_err());
             assert_eq!(
@@ -411,6 +560,33 @@ mod tests {
                 Ok("bc1p3qkhfews2uk44qtvauqyr2ttdsw7svhkl9nkm9s9c3x4ax5h60wqwruhk7".into())
             );
         }
+
+        {
+            // Future witness version, rendered as bech32m.
+
+            let payload = Payload {
+                data: vec![0x11; 32],
+                output_type: BtcOutputType::FutureWitness,
+                witness_version: Some(2),
+            };
+            assert_eq!(
+                payload.address(params_btc),
+                Ok("bc1zzyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygsjg4mh7".into())
+            );
+            assert_eq!(
+                payload.address(params_ltc),
+                Ok("ltc1zzyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygs3vmtdm".into())
+            );
+            // Versions 0/1 have their dedicated output types, and the version cannot be missing.
+            for witness_version in [None, Some(0), Some(1), Some(17)] {
+                let payload = Payload {
+                    data: vec![0x11; 32],
+                    output_type: BtcOutputType::FutureWitness,
+                    witness_version,
+                };
+                assert!(payload.address(params_btc).is_err());
+            }
+        }
     }
 
     #[test]
@@ -446,7 +622,7 @@ mod tests {
                 40000000000,
                 Ok("400.00000000 BTC"),
             ),
-            (params, FormatUnit::Sat, 40000000000, Ok("40000000000 sat")),
+            (params, FormatUnit::Sat, 40000000000, Ok("40'000'000'000 sat")),
             (
                 params,
                 FormatUnit::Default,
@@ -465,7 +641,7 @@ mod tests {
             (params, FormatUnit::Default, 5432345, Ok("0.05432345 BTC")),
             (params, FormatUnit::Default, 54323452, Ok("0.54323452 BTC")),
             (params, FormatUnit::Default, 543234527, Ok("5.43234527 BTC")),
-            (params, FormatUnit::Sat, 543234527, Ok("543234527 sat")),
+            (params, FormatUnit::Sat, 543234527, Ok("543'234'527 sat")),
             (
                 params,
                 FormatUnit::Default,
@@ -489,7 +665,20 @@ mod tests {
                 params,
                 FormatUnit::Sat,
                 1234567800000001,
-                Ok("1234567800000001 sat"),
+                Ok("1'234'567'800'000'001 sat"),
+            ),
+            // Maximum supply.
+            (
+                params,
+                FormatUnit::Default,
+                2100000000000000,
+                Ok("21000000.00000000 BTC"),
+            ),
+            (
+                params,
+                FormatUnit::Sat,
+                2100000000000000,
+                Ok("2'100'000'000'000'000 sat"),
             ),
             (
                 params,
@@ -548,6 +737,48 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_format_fiat_amount() {
+        let rate = |currency: &str, cents_per_coin: u64| pb::btc_sign_init_request::FiatRate {
+            currency: currency.into(),
+            cents_per_coin,
+        };
+        // 1 BTC at 60123.45 per BTC.
+        assert_eq!(
+            format_fiat_amount(&rate("USD", 6012345), 100000000),
+            Ok("~ 60123.45 USD (rate from app, unverified)".into())
+        );
+        // Small rate: 1 sat at 1.00 per BTC is far below one cent.
+        assert_eq!(
+            format_fiat_amount(&rate("EUR", 100), 1),
+            Ok("~ 0.00 EUR (rate from app, unverified)".into())
+        );
+        // Rounding is half up: 0.5 cents round to one cent...
+        assert_eq!(
+            format_fiat_amount(&rate("EUR", 1), 50000000),
+            Ok("~ 0.01 EUR (rate from app, unverified)".into())
+        );
+        // ...just below, they round down.
+        assert_eq!(
+            format_fiat_amount(&rate("EUR", 1), 49999999),
+            Ok("~ 0.00 EUR (rate from app, unverified)".into())
+        );
+        // The maximum rate is accepted.
+        assert_eq!(
+            format_fiat_amount(&rate("USD", MAX_FIAT_CENTS_PER_COIN), 100000000),
+            Ok("~ 100000000000.00 USD (rate from app, unverified)".into())
+        );
+        // Rate above the cap, zero rate.
+        assert!(format_fiat_amount(&rate("USD", MAX_FIAT_CENTS_PER_COIN + 1), 1).is_err());
+        assert!(format_fiat_amount(&rate("USD", 0), 1).is_err());
+        // Invalid currency codes.
+        assert!(format_fiat_amount(&rate("", 100), 1).is_err());
+        assert!(format_fiat_amount(&rate("usd", 100), 1).is_err());
+        assert!(format_fiat_amount(&rate("US DOLLAR", 100), 1).is_err());
+        // Result overflows the fixed-point range.
+        assert!(format_fiat_amount(&rate("USD", MAX_FIAT_CENTS_PER_COIN), u64::MAX).is_err());
+    }
+
     #[test]
     fn test_payload_simple() {
         mock_unlocked_using_mnemonic(
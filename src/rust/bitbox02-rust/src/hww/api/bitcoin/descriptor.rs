@@ -0,0 +1,412 @@
+// Copyright 2025 Shift Crypto AG
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use super::pb;
+use super::Error;
+
+use super::keypath;
+use super::multisig;
+use super::params;
+
+use crate::bip32;
+use crate::workflow::confirm;
+
+use pb::btc_script_config::multisig::ScriptType as MultisigScriptType;
+use pb::btc_script_config::{Config, SimpleType};
+use pb::BtcCoin;
+
+use util::bip32::HARDENED;
+
+/// The characters a descriptor may contain, in the order assigning their checksum symbol values.
+/// See
+/// https://github.com/bitcoin/bips/blob/master/bip-0380.mediawiki#user-content-Checksum.
+const INPUT_CHARSET: &[u8] =
+    b"0123456789()[],'/*abcdefgh@:$%{}IJKLMNOPQRSTUVWXYZ&+-.;<=>?!^_|~ijklmnopqrstuvwxyzABCDEFGH`#\"\\ ";
+/// The bech32 character set, used to render the checksum.
+const CHECKSUM_CHARSET: &[u8] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+const GENERATOR: [u64; 5] = [
+    0xf5dee51989,
+    0xa9fdca3312,
+    0x1bab10e32d,
+    0x3706b1677a,
+    0x644d626ffd,
+];
+
+fn polymod(symbols: &[u64]) -> u64 {
+    let mut chk: u64 = 1;
+    for &value in symbols {
+        let top = chk >> 35;
+        chk = ((chk & 0x7ffffffff) << 5) ^ value;
+        for (i, generator) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 != 0 {
+                chk ^= generator;
+            }
+        }
+    }
+    chk
+}
+
+/// Computes the BIP-380 descriptor checksum of `desc`, which must not already contain a `#`
+/// checksum suffix. Fails if the descriptor contains a character outside the descriptor charset.
+pub fn checksum(desc: &str) -> Result<String, Error> {
+    let mut symbols: Vec<u64> = Vec::new();
+    let mut groups: Vec<u64> = Vec::new();
+    for c in desc.bytes() {
+        let value = INPUT_CHARSET
+            .iter()
+            .position(|&ch| ch == c)
+            .ok_or(Error::InvalidInput)? as u64;
+        symbols.push(value & 31);
+        groups.push(value >> 5);
+        if groups.len() == 3 {
+            symbols.push(groups[0] * 9 + groups[1] * 3 + groups[2]);
+            groups.clear();
+        }
+    }
+    match groups.as_slice() {
+        [g0] => symbols.push(*g0),
+        [g0, g1] => symbols.push(g0 * 3 + g1),
+        _ => (),
+    }
+    symbols.extend([0u64; 8]);
+    let chk = polymod(&symbols) ^ 1;
+    Ok((0..8)
+        .map(|i| CHECKSUM_CHARSET[((chk >> (5 * (7 - i))) & 31) as usize] as char)
+        .collect())
+}
+
+/// Returns the xpub version to render account keys in: xpub for mainnets, tpub for testnets, as is
+/// customary for descriptor wallets.
+fn account_xpub_type(coin: BtcCoin) -> bip32::XPubType {
+    match coin {
+        BtcCoin::Btc | BtcCoin::Ltc => bip32::XPubType::Xpub,
+        BtcCoin::Tbtc | BtcCoin::Tltc | BtcCoin::Rbtc | BtcCoin::Sbtc => bip32::XPubType::Tpub,
+    }
+}
+
+/// Renders our account key with its key origin and the standard receive/change multipath, e.g.
+/// `[aabbccdd/84'/0'/0']xpub.../<0;1>/*`.
+fn our_key(coin: BtcCoin, keypath: &[u32]) -> Result<String, Error> {
+    let xpub_str = crate::keystore::get_xpub(keypath)?.serialize_str(account_xpub_type(coin))?;
+    Ok(format!(
+        "[{}/{}]{}/<0;1>/*",
+        hex::encode(crate::keystore::root_fingerprint()?),
+        util::bip32::to_string_no_prefix(keypath),
+        xpub_str
+    ))
+}
+
+/// Handles a descriptor export api call: the full output descriptor of an account, including key
+/// origin, fingerprint and the BIP-380 checksum, is returned after the user confirmed the account,
+/// so hosts can set up watch-only wallets without hand-assembling the descriptor from separately
+/// exported pieces. Multisig configs must be registered on the device before they can be exported.
+pub async fn process(
+    request: &pb::BtcDescriptorRequest,
+) -> Result<pb::btc_response::Response, Error> {
+    let coin = BtcCoin::try_from(request.coin)?;
+    super::coin_enabled(coin)?;
+    let coin_params = params::get(coin);
+    let title = "Export descriptor";
+    let descriptor = match request.script_config {
+        Some(pb::BtcScriptConfig {
+            config: Some(Config::SimpleType(simple_type)),
+        }) => {
+            let simple_type = SimpleType::try_from(simple_type)?;
+            keypath::validate_account_simple(
+                &request.keypath,
+                coin_params.bip44_coin,
+                simple_type,
+                coin_params.taproot_support,
+            )
+            .or(Err(Error::InvalidInput))?;
+            confirm::confirm(&confirm::Params {
+                title,
+                body: &format!(
+                    "{}\naccount #{}",
+                    coin_params.name,
+                    request.keypath[2] - HARDENED + 1
+                ),
+                ..Default::default()
+            })
+            .await?;
+            let key = our_key(coin, &request.keypath)?;
+            match simple_type {
+                SimpleType::P2pkh => format!("pkh({})", key),
+                SimpleType::P2wpkhP2sh => format!("sh(wpkh({}))", key),
+                SimpleType::P2wpkh => format!("wpkh({})", key),
+                SimpleType::P2tr => format!("tr({})", key),
+            }
+        }
+        Some(pb::BtcScriptConfig {
+            config: Some(Config::Multisig(ref multisig)),
+        }) => {
+            multisig::validate(multisig, &request.keypath)?;
+            let name = multisig::get_name(coin, multisig, &request.keypath)?
+                .ok_or(Error::InvalidInput)?;
+            multisig::confirm(title, coin_params, &name, multisig).await?;
+            let xpub_type = account_xpub_type(coin);
+            let keys = multisig
+                .xpubs
+                .iter()
+                .enumerate()
+                .map(|(i, xpub)| {
+                    let xpub_str = bip32::Xpub::from(xpub)
+                        .serialize_str(xpub_type)
+                        .or(Err(Error::InvalidInput))?;
+                    // Only our own key origin is known to the device.
+                    if i == multisig.our_xpub_index as usize {
+                        Ok(format!(
+                            "[{}/{}]{}/<0;1>/*",
+                            hex::encode(crate::keystore::root_fingerprint()?),
+                            util::bip32::to_string_no_prefix(&request.keypath),
+                            xpub_str
+                        ))
+                    } else {
+                        Ok(format!("{}/<0;1>/*", xpub_str))
+                    }
+                })
+                .collect::<Result<Vec<String>, Error>>()?;
+            let script = format!("wsh(sortedmulti({},{}))", multisig.threshold, keys.join(","));
+            match MultisigScriptType::try_from(multisig.script_type)
+                .or(Err(Error::InvalidInput))?
+            {
+                MultisigScriptType::P2wsh => script,
+                MultisigScriptType::P2wshP2sh => format!("sh({})", script),
+            }
+        }
+        _ => return Err(Error::InvalidInput),
+    };
+    let descriptor = format!("{}#{}", descriptor, checksum(&descriptor)?);
+    Ok(pb::btc_response::Response::Descriptor(
+        pb::BtcDescriptorResponse { descriptor },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::bb02_async::block_on;
+    use alloc::boxed::Box;
+    use bitbox02::testing::{mock, mock_memory, mock_unlocked, mock_unlocked_using_mnemonic, Data};
+    use core::str::FromStr;
+
+    use pb::btc_script_config::Multisig;
+    use pb::BtcScriptConfig;
+
+    /// Test vectors from Bitcoin Core's `descriptor_tests.cpp` / BIP-380.
+    #[test]
+    fn test_checksum() {
+        assert_eq!(checksum("raw(deadbeef)").unwrap().as_str(), "89f8spxm");
+        assert_eq!(
+            checksum("addr(mkmZxiEcEd8ZqjQWVZuC6so5dFMKEFpN2j)")
+                .unwrap()
+                .as_str(),
+            "02wpgw69"
+        );
+        // Characters outside the descriptor charset are rejected.
+        assert!(checksum("raw(деадбееф)").is_err());
+    }
+
+    /// Round trip: the exported singlesig descriptors parse in rust-miniscript (which verifies the
+    /// checksum) and derive the same addresses as the device.
+    #[test]
+    fn test_process_simple() {
+        static mut CONFIRM_COUNTER: u32 = 0;
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|params| {
+                assert_eq!(params.title, "Export descriptor");
+                assert_eq!(params.body, "Bitcoin\naccount #1");
+                unsafe { CONFIRM_COUNTER += 1 }
+                true
+            })),
+            ..Default::default()
+        });
+        mock_unlocked();
+
+        for (simple_type, purpose, expected_prefix) in [
+            (SimpleType::P2wpkh, 84u32, "wpkh(["),
+            (SimpleType::P2wpkhP2sh, 49, "sh(wpkh(["),
+            (SimpleType::P2tr, 86, "tr(["),
+            (SimpleType::P2pkh, 44, "pkh(["),
+        ] {
+            let keypath = &[purpose + HARDENED, HARDENED, HARDENED];
+            let descriptor = match block_on(process(&pb::BtcDescriptorRequest {
+                coin: BtcCoin::Btc as _,
+                script_config: Some(BtcScriptConfig {
+                    config: Some(Config::SimpleType(simple_type as _)),
+                }),
+                keypath: keypath.to_vec(),
+            }))
+            .unwrap()
+            {
+                pb::btc_response::Response::Descriptor(pb::BtcDescriptorResponse {
+                    descriptor,
+                }) => descriptor,
+                _ => panic!("wrong response type"),
+            };
+            assert!(descriptor.starts_with(&format!(
+                "{}{}/{}'/0'/0']",
+                expected_prefix,
+                hex::encode(crate::keystore::root_fingerprint().unwrap()),
+                purpose,
+            )));
+
+            // Parsing verifies the checksum; splitting the multipath and deriving must match the
+            // device's own addresses.
+            let parsed = miniscript::Descriptor::<miniscript::DescriptorPublicKey>::from_str(
+                &descriptor,
+            )
+            .unwrap();
+            let secp = bitcoin::secp256k1::Secp256k1::new();
+            let single_descriptors = parsed.into_single_descriptors().unwrap();
+            for (change, single) in single_descriptors.iter().enumerate() {
+                for address_index in [0u32, 5] {
+                    let derived_address = format!(
+                        "{}",
+                        single
+                            .at_derivation_index(address_index)
+                            .unwrap()
+                            .derived_descriptor(&secp)
+                            .unwrap()
+                            .address(bitcoin::Network::Bitcoin)
+                            .unwrap()
+                    );
+                    let device_address = super::super::derive_address_simple(
+                        BtcCoin::Btc,
+                        simple_type,
+                        &[
+                            purpose + HARDENED,
+                            HARDENED,
+                            HARDENED,
+                            change as u32,
+                            address_index,
+                        ],
+                    )
+                    .unwrap();
+                    assert_eq!(derived_address, device_address);
+                }
+            }
+        }
+        assert_eq!(unsafe { CONFIRM_COUNTER }, 4);
+    }
+
+    /// Round trip: an exported multisig descriptor parses in rust-miniscript and derives the same
+    /// addresses as the device. Unregistered multisig configs cannot be exported.
+    #[test]
+    fn test_process_multisig() {
+        static mut CONFIRM_COUNTER: u32 = 0;
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|params| {
+                match unsafe {
+                    CONFIRM_COUNTER += 1;
+                    CONFIRM_COUNTER
+                } {
+                    1 => assert_eq!(params.body, "2-of-3\nBTC Testnet multisig"),
+                    2 => assert_eq!(params.body, "test name"),
+                    _ => panic!("too many dialogs"),
+                }
+                true
+            })),
+            ..Default::default()
+        });
+        mock_memory();
+        mock_unlocked_using_mnemonic(
+            "sudden tenant fault inject concert weather maid people chunk youth stumble grit",
+            "",
+        );
+
+        let xpubs: &[&str] = &[
+            "xpub6FMWuwbCA9KhoRzAMm63ZhLspk5S2DM5sePo8J8mQhcS1xyMbAqnc7Q7UescVEVFCS6qBMQLkEJWQ9Z3aDPgBov5nFUYxsJhwumsxM4npSo",
+            // This xpub corresponds to the mocked seed above at m/48'/1'/0'/2'.
+            "xpub6EMfjyGVUvwhpc3WKN1zXhMFGKJGMaSBPqbja4tbGoYvRBSXeTBCaqrRDjcuGTcaY95JrrAnQvDG3pdQPdtnYUCugjeksHSbyZT7rq38VQF",
+            "xpub6ERxBysTYfQyY4USv6c6J1HNVv9hpZFN9LHVPu47Ac4rK8fLy6NnAeeAHyEsMvG4G66ay5aFZii2VM7wT3KxLKX8Q8keZPd67kRGmrD1WJj",
+        ];
+        let keypath = &[48 + HARDENED, 1 + HARDENED, HARDENED, 2 + HARDENED];
+        let multisig = Multisig {
+            threshold: 2,
+            xpubs: xpubs
+                .iter()
+                .map(|s| crate::bip32::parse_xpub(s).unwrap())
+                .collect(),
+            our_xpub_index: 1,
+            script_type: MultisigScriptType::P2wsh as _,
+        };
+        let request = pb::BtcDescriptorRequest {
+            coin: BtcCoin::Tbtc as _,
+            script_config: Some(BtcScriptConfig {
+                config: Some(Config::Multisig(multisig.clone())),
+            }),
+            keypath: keypath.to_vec(),
+        };
+
+        // Not registered yet.
+        assert_eq!(block_on(process(&request)), Err(Error::InvalidInput));
+
+        bitbox02::memory::multisig_set_by_hash(
+            &multisig::get_hash(
+                BtcCoin::Tbtc,
+                &multisig,
+                multisig::SortXpubs::Yes,
+                keypath,
+            )
+            .unwrap(),
+            "test name",
+        )
+        .unwrap();
+
+        let descriptor = match block_on(process(&request)).unwrap() {
+            pb::btc_response::Response::Descriptor(pb::BtcDescriptorResponse { descriptor }) => {
+                descriptor
+            }
+            _ => panic!("wrong response type"),
+        };
+        assert_eq!(unsafe { CONFIRM_COUNTER }, 2);
+        assert!(descriptor.starts_with("wsh(sortedmulti(2,tpub"));
+
+        let parsed =
+            miniscript::Descriptor::<miniscript::DescriptorPublicKey>::from_str(&descriptor)
+                .unwrap();
+        let secp = bitcoin::secp256k1::Secp256k1::new();
+        let single_descriptors = parsed.into_single_descriptors().unwrap();
+        for (change, single) in single_descriptors.iter().enumerate() {
+            for address_index in [0u32, 7] {
+                let derived_address = format!(
+                    "{}",
+                    single
+                        .at_derivation_index(address_index)
+                        .unwrap()
+                        .derived_descriptor(&secp)
+                        .unwrap()
+                        .address(bitcoin::Network::Testnet)
+                        .unwrap()
+                );
+                let device_address = super::super::common::Payload::from_multisig(
+                    params::get(BtcCoin::Tbtc),
+                    &multisig,
+                    change as u32,
+                    address_index,
+                )
+                .unwrap()
+                .address(params::get(BtcCoin::Tbtc))
+                .unwrap();
+                assert_eq!(derived_address, device_address);
+            }
+        }
+    }
+}
@@ -19,40 +19,67 @@ pub use pb::btc_script_config::SimpleType;
 const ALL_MULTISCRIPT_SCRIPT_TYPES: [MultisigScriptType; 2] =
     [MultisigScriptType::P2wsh, MultisigScriptType::P2wshP2sh];
 
-const ALL_SIMPLE_SCRIPT_TYPES: [SimpleType; 3] =
-    [SimpleType::P2wpkhP2sh, SimpleType::P2wpkh, SimpleType::P2tr];
+const ALL_SIMPLE_SCRIPT_TYPES: [SimpleType; 4] = [
+    SimpleType::P2wpkhP2sh,
+    SimpleType::P2wpkh,
+    SimpleType::P2tr,
+    SimpleType::P2pkh,
+];
 
 use util::bip32::HARDENED;
 
 const BIP44_ACCOUNT_MIN: u32 = HARDENED;
-const BIP44_ACCOUNT_MAX: u32 = HARDENED + 99; // 100 accounts
+// Accounts above this index are unusual - wallets normally create accounts sequentially and stay
+// well below it. They are still valid (other wallets allow creating them and the funds must remain
+// accessible), but are only processed after the user confirms a warning. See
+// `is_unusual_account`.
+const BIP44_ACCOUNT_USUAL_MAX: u32 = HARDENED + 99; // 100 accounts
 const BIP44_ADDRESS_MAX: u32 = 9999; // 10k addresses
 
+const PURPOSE_P2PKH: u32 = 44 + HARDENED;
 const PURPOSE_P2WPKH_P2SH: u32 = 49 + HARDENED;
 const PURPOSE_P2WPKH: u32 = 84 + HARDENED;
 const PURPOSE_P2TR: u32 = 86 + HARDENED;
 const PURPOSE_MULTISIG: u32 = 48 + HARDENED;
+const PURPOSE_SILENT_PAYMENTS: u32 = 352 + HARDENED;
 const MULTISIG_SCRIPT_TYPE_P2WSH: u32 = 2 + HARDENED;
 const MULTISIG_SCRIPT_TYPE_P2WSH_P2SH: u32 = 1 + HARDENED;
 
 /// Validates a keypath to be
-/// m/expected_purpose/expected_coin/account, where account between 0' and 99'.
+/// m/expected_purpose/expected_coin/account, where account is any hardened index.
 pub fn validate_account(
     keypath: &[u32],
     expected_purpose: u32,
     expected_coin: u32,
 ) -> Result<(), ()> {
     if let [purpose, coin, account] = *keypath {
-        if purpose == expected_purpose
-            && coin == expected_coin
-            && (BIP44_ACCOUNT_MIN..=BIP44_ACCOUNT_MAX).contains(&account)
-        {
+        if purpose == expected_purpose && coin == expected_coin && account >= BIP44_ACCOUNT_MIN {
             return Ok(());
         }
     }
     Err(())
 }
 
+/// Validates a BIP-352 silent payments scan keypath:
+/// m/352'/coin'/account'/1'/0, where account is any hardened index.
+pub fn validate_scan_key(keypath: &[u32], expected_coin: u32) -> Result<(), ()> {
+    if let [keypath_account @ .., key_type, address] = keypath {
+        validate_account(keypath_account, PURPOSE_SILENT_PAYMENTS, expected_coin)?;
+        if *key_type == 1 + HARDENED && *address == 0 {
+            return Ok(());
+        }
+    }
+    Err(())
+}
+
+/// Returns true if the account index of an account keypath (m/purpose'/coin'/account'/...) is
+/// above the range wallets normally create (account > 99'). Callers must warn the user and get an
+/// explicit confirmation before processing such a keypath. The keypath must already have been
+/// validated.
+pub fn is_unusual_account(keypath: &[u32]) -> bool {
+    keypath.len() >= 3 && keypath[2] > BIP44_ACCOUNT_USUAL_MAX
+}
+
 /// Validates a multisig keypath.
 /// Supported:
 /// - Electrum-style: m/48'/coin'/account'/script_type', where script_type is 1 for p2wsh-p2sh and 2
@@ -87,7 +114,7 @@ pub enum ReceiveSpend {
 }
 
 /// Validates that change is 0 or 1 and address is less than 10000.
-fn validate_change_address(change: u32, address: u32, mode: ReceiveSpend) -> Result<(), ()> {
+pub fn validate_change_address(change: u32, address: u32, mode: ReceiveSpend) -> Result<(), ()> {
     if change <= 1 && (mode == ReceiveSpend::Spend || address <= BIP44_ADDRESS_MAX) {
         Ok(())
     } else {
@@ -121,6 +148,7 @@ pub fn validate_address_policy(keypath: &[u32], mode: ReceiveSpend) -> Result<()
 
 /// Validates a singlesig keypath.
 /// Supported:
+/// - P2PKH: m/44'/coin'/account'
 /// - P2WPKH-P2SH: m/49'/coin'/account'
 /// - P2WPKH: m/84'/coin'/account'
 /// - P2TR: m/86'/coin'/account' (only if `taproot_support` is true)
@@ -134,6 +162,7 @@ pub fn validate_account_simple(
         return Err(());
     }
     let bip44_purpose = match script_type {
+        SimpleType::P2pkh => PURPOSE_P2PKH,
         SimpleType::P2wpkhP2sh => PURPOSE_P2WPKH_P2SH,
         SimpleType::P2wpkh => PURPOSE_P2WPKH,
         SimpleType::P2tr => PURPOSE_P2TR,
@@ -179,6 +208,30 @@ pub fn validate_xpub(keypath: &[u32], expected_coin: u32, taproot_support: bool)
     Err(())
 }
 
+/// Maximum depth of a nonstandard xpub export keypath.
+const XPUB_NONSTANDARD_MAX_DEPTH: usize = 8;
+
+/// Validates a keypath for a nonstandard xpub export, which is not restricted to the known account
+/// schemes. The keypath must start with a hardened element and all hardened elements must precede
+/// the unhardened ones, e.g. m/44'/0'/0'/0, and the depth is capped at 8.
+pub fn validate_xpub_nonstandard(keypath: &[u32]) -> Result<(), ()> {
+    if keypath.is_empty()
+        || keypath.len() > XPUB_NONSTANDARD_MAX_DEPTH
+        || keypath[0] < HARDENED
+    {
+        return Err(());
+    }
+    let mut unhardened_seen = false;
+    for &el in keypath {
+        if el < HARDENED {
+            unhardened_seen = true;
+        } else if unhardened_seen {
+            return Err(());
+        }
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -194,7 +247,11 @@ mod tests {
         for account in 0..100 {
             assert!(validate_account(&[0, 0, account + HARDENED], 0, 0).is_ok());
         }
-        assert!(validate_account(&[0, 0, 100 + HARDENED], 0, 0).is_err());
+        // High accounts are valid (callers warn before processing them).
+        assert!(validate_account(&[0, 0, 100 + HARDENED], 0, 0).is_ok());
+        assert!(validate_account(&[0, 0, u32::MAX], 0, 0).is_ok());
+        // Unhardened accounts are not.
+        assert!(validate_account(&[0, 0, HARDENED - 1], 0, 0).is_err());
 
         assert!(validate_account(
             &[84 + HARDENED, 1 + HARDENED, 1 + HARDENED],
@@ -212,6 +269,96 @@ mod tests {
         .is_err());
     }
 
+    #[test]
+    fn test_validate_scan_key() {
+        let expected_coin = 1 + HARDENED;
+        assert!(validate_scan_key(
+            &[352 + HARDENED, expected_coin, HARDENED, 1 + HARDENED, 0],
+            expected_coin
+        )
+        .is_ok());
+        assert!(validate_scan_key(
+            &[352 + HARDENED, expected_coin, 99 + HARDENED, 1 + HARDENED, 0],
+            expected_coin
+        )
+        .is_ok());
+
+        // Wrong purpose.
+        assert!(validate_scan_key(
+            &[353 + HARDENED, expected_coin, HARDENED, 1 + HARDENED, 0],
+            expected_coin
+        )
+        .is_err());
+        // Wrong coin.
+        assert!(validate_scan_key(
+            &[352 + HARDENED, expected_coin + 1, HARDENED, 1 + HARDENED, 0],
+            expected_coin
+        )
+        .is_err());
+        // Unhardened account.
+        assert!(validate_scan_key(
+            &[352 + HARDENED, expected_coin, 0, 1 + HARDENED, 0],
+            expected_coin
+        )
+        .is_err());
+        // Spend keypath, not scan keypath.
+        assert!(validate_scan_key(
+            &[352 + HARDENED, expected_coin, HARDENED, HARDENED, 0],
+            expected_coin
+        )
+        .is_err());
+        // Non-zero address.
+        assert!(validate_scan_key(
+            &[352 + HARDENED, expected_coin, HARDENED, 1 + HARDENED, 1],
+            expected_coin
+        )
+        .is_err());
+        // Wrong number of elements.
+        assert!(validate_scan_key(
+            &[352 + HARDENED, expected_coin, HARDENED, 1 + HARDENED],
+            expected_coin
+        )
+        .is_err());
+        assert!(validate_scan_key(
+            &[352 + HARDENED, expected_coin, HARDENED, 1 + HARDENED, 0, 0],
+            expected_coin
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_is_unusual_account() {
+        for account in 0..100 {
+            assert!(!is_unusual_account(&[
+                84 + HARDENED,
+                0 + HARDENED,
+                account + HARDENED
+            ]));
+            assert!(!is_unusual_account(&[
+                84 + HARDENED,
+                0 + HARDENED,
+                account + HARDENED,
+                0,
+                0
+            ]));
+        }
+        assert!(is_unusual_account(&[
+            84 + HARDENED,
+            0 + HARDENED,
+            100 + HARDENED
+        ]));
+        assert!(is_unusual_account(&[
+            84 + HARDENED,
+            0 + HARDENED,
+            100 + HARDENED,
+            0,
+            0
+        ]));
+        assert!(is_unusual_account(&[84 + HARDENED, 0 + HARDENED, u32::MAX]));
+        // Account-level part missing.
+        assert!(!is_unusual_account(&[45 + HARDENED]));
+    }
+
     #[test]
     fn test_validate_account_multisig() {
         let coin = 1 + HARDENED;
@@ -348,7 +495,7 @@ mod tests {
             )
             .is_err());
 
-            // invalid p2wpkh-p2sh; account too high
+            // valid p2wpkh-p2sh; account above the usual range (callers warn)
             assert!(validate_address_simple(
                 &[49 + HARDENED, bip44_coin, 100 + HARDENED, 0, 0],
                 bip44_coin,
@@ -356,7 +503,7 @@ mod tests {
                 taproot_support,
                 mode,
             )
-            .is_err());
+            .is_ok());
 
             // invalid p2wpkh-p2sh; account too low
             assert!(validate_address_simple(
@@ -398,6 +545,26 @@ mod tests {
             )
             .is_err());
 
+            // valid p2pkh
+            assert!(validate_address_simple(
+                &[44 + HARDENED, bip44_coin, bip44_account, 0, 0],
+                bip44_coin,
+                SimpleType::P2pkh,
+                taproot_support,
+                mode,
+            )
+            .is_ok());
+
+            // invalid p2pkh; wrong purpose
+            assert!(validate_address_simple(
+                &[84 + HARDENED, bip44_coin, bip44_account, 0, 0],
+                bip44_coin,
+                SimpleType::P2pkh,
+                taproot_support,
+                mode,
+            )
+            .is_err());
+
             // valid p2tr
             assert!(validate_address_simple(
                 &[86 + HARDENED, bip44_coin, bip44_account, 0, 0],
@@ -513,19 +680,20 @@ mod tests {
         )
         .is_err());
 
-        // Invalid account.
+        // Invalid account (not hardened).
         assert!(validate_xpub(
             &[48 + HARDENED, bip44_coin, HARDENED - 1, 2 + HARDENED],
             bip44_coin,
             taproot_support
         )
         .is_err());
+        // Valid account above the usual range (callers warn).
         assert!(validate_xpub(
             &[48 + HARDENED, bip44_coin, HARDENED + 100, 2 + HARDENED],
             bip44_coin,
             taproot_support
         )
-        .is_err());
+        .is_ok());
 
         // Invalid purpose.
         assert!(validate_xpub(
@@ -541,4 +709,26 @@ mod tests {
         )
         .is_err());
     }
+
+    #[test]
+    fn test_validate_xpub_nonstandard() {
+        // Hardened prefix followed by unhardened elements.
+        assert!(validate_xpub_nonstandard(&[44 + HARDENED, 0 + HARDENED, 0 + HARDENED, 0]).is_ok());
+        assert!(validate_xpub_nonstandard(&[1 + HARDENED]).is_ok());
+        assert!(validate_xpub_nonstandard(&[1 + HARDENED, 2, 3, 4]).is_ok());
+        // Max depth.
+        assert!(
+            validate_xpub_nonstandard(&[1 + HARDENED, 2 + HARDENED, 3, 4, 5, 6, 7, 8]).is_ok()
+        );
+        assert!(
+            validate_xpub_nonstandard(&[1 + HARDENED, 2 + HARDENED, 3, 4, 5, 6, 7, 8, 9]).is_err()
+        );
+        // Empty.
+        assert!(validate_xpub_nonstandard(&[]).is_err());
+        // Must start hardened.
+        assert!(validate_xpub_nonstandard(&[0, 1 + HARDENED]).is_err());
+        assert!(validate_xpub_nonstandard(&[0]).is_err());
+        // Hardened after unhardened.
+        assert!(validate_xpub_nonstandard(&[1 + HARDENED, 2, 3 + HARDENED]).is_err());
+    }
 }
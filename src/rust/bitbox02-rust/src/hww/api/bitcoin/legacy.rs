@@ -0,0 +1,110 @@
+// Copyright 2024 Shift Crypto AG
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use sha2::Digest;
+use sha2::Sha256;
+
+use super::script::serialize_varint;
+
+/// Outpoint and sequence number of one transaction input, as needed by the legacy (pre-segwit)
+/// signature hash, which serializes all inputs of the transaction for each signature.
+pub struct Input {
+    pub outpoint_hash: [u8; 32],
+    pub outpoint_index: u32,
+    pub sequence: u32,
+}
+
+pub struct Args<'a> {
+    pub version: u32,
+    /// All transaction inputs, in order.
+    pub inputs: &'a [Input],
+    /// Index of the input being signed.
+    pub input_index: u32,
+    /// The script used in the script code, without the VarInt length prefix. For P2PKH inputs this
+    /// is the pkScript of the output being spent.
+    pub sighash_script: &'a [u8],
+    /// All transaction outputs, each serialized as value (8 bytes little endian) followed by the
+    /// VarInt-prefixed pkScript.
+    pub serialized_outputs: &'a [u8],
+    pub num_outputs: u32,
+    pub locktime: u32,
+    pub sighash_flags: u32,
+}
+
+/// Computes the legacy (pre-segwit) signature hash: the double SHA256 of the serialized
+/// transaction, where the script of the input being signed is replaced by the script code and the
+/// scripts of all other inputs are empty. Only SIGHASH_ALL is supported.
+pub fn sighash(args: &Args) -> [u8; 32] {
+    let mut ctx = Sha256::new();
+    ctx.update(args.version.to_le_bytes());
+    ctx.update(serialize_varint(args.inputs.len() as u64).as_slice());
+    for (input_index, input) in args.inputs.iter().enumerate() {
+        ctx.update(input.outpoint_hash);
+        ctx.update(input.outpoint_index.to_le_bytes());
+        if input_index == args.input_index as usize {
+            ctx.update(serialize_varint(args.sighash_script.len() as u64).as_slice());
+            ctx.update(args.sighash_script);
+        } else {
+            ctx.update(serialize_varint(0).as_slice());
+        }
+        ctx.update(input.sequence.to_le_bytes());
+    }
+    ctx.update(serialize_varint(args.num_outputs as u64).as_slice());
+    ctx.update(args.serialized_outputs);
+    ctx.update(args.locktime.to_le_bytes());
+    ctx.update(args.sighash_flags.to_le_bytes());
+    Sha256::digest(ctx.finalize()).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_sighash() {
+        // Signature hash of the first Bitcoin transaction
+        // (f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16, block 170), spending
+        // the block 9 coinbase P2PK output. The signature in its scriptSig verifies against this
+        // digest, so the expected value is consensus-verified by Bitcoin Core.
+        let mut serialized_outputs: Vec<u8> = Vec::new();
+        // Output 1: 10 BTC to P2PK.
+        serialized_outputs.extend_from_slice(&1000000000u64.to_le_bytes());
+        serialized_outputs.extend_from_slice(b"\x43\x41\x04\xae\x1a\x62\xfe\x09\xc5\xf5\x1b\x13\x90\x5f\x07\xf0\x6b\x99\xa2\xf7\x15\x9b\x22\x25\xf3\x74\xcd\x37\x8d\x71\x30\x2f\xa2\x84\x14\xe7\xaa\xb3\x73\x97\xf5\x54\xa7\xdf\x5f\x14\x2c\x21\xc1\xb7\x30\x3b\x8a\x06\x26\xf1\xba\xde\xd5\xc7\x2a\x70\x4f\x7e\x6c\xd8\x4c\xac");
+        // Output 2: 40 BTC change back to the sender's P2PK.
+        serialized_outputs.extend_from_slice(&4000000000u64.to_le_bytes());
+        serialized_outputs.extend_from_slice(b"\x43\x41\x04\x11\xdb\x93\xe1\xdc\xdb\x8a\x01\x6b\x49\x84\x0f\x8c\x53\xbc\x1e\xb6\x8a\x38\x2e\x97\xb1\x48\x2e\xca\xd7\xb1\x48\xa6\x90\x9a\x5c\xb2\xe0\xea\xdd\xfb\x84\xcc\xf9\x74\x44\x64\xf8\x2e\x16\x0b\xfa\x9b\x8b\x64\xf9\xd4\xc0\x3f\x99\x9b\x86\x43\xf6\x56\xb4\x12\xa3\xac");
+        assert_eq!(
+            sighash(&Args {
+                version: 1,
+                inputs: &[Input {
+                    // 0437cd7f8525ceed2324359c2d0ba26006d92d856a9c20fa0241106ee5a597c9,
+                    // in reversed (serialized) byte order.
+                    outpoint_hash: *b"\xc9\x97\xa5\xe5\x6e\x10\x41\x02\xfa\x20\x9c\x6a\x85\x2d\xd9\x06\x60\xa2\x0b\x2d\x9c\x35\x24\x23\xed\xce\x25\x85\x7f\xcd\x37\x04",
+                    outpoint_index: 0,
+                    sequence: 0xffffffff,
+                }],
+                input_index: 0,
+                // The spent output's P2PK pkScript.
+                sighash_script: b"\x41\x04\x11\xdb\x93\xe1\xdc\xdb\x8a\x01\x6b\x49\x84\x0f\x8c\x53\xbc\x1e\xb6\x8a\x38\x2e\x97\xb1\x48\x2e\xca\xd7\xb1\x48\xa6\x90\x9a\x5c\xb2\xe0\xea\xdd\xfb\x84\xcc\xf9\x74\x44\x64\xf8\x2e\x16\x0b\xfa\x9b\x8b\x64\xf9\xd4\xc0\x3f\x99\x9b\x86\x43\xf6\x56\xb4\x12\xa3\xac",
+                serialized_outputs: &serialized_outputs,
+                num_outputs: 2,
+                locktime: 0,
+                sighash_flags: 1,
+            }),
+            *b"\x7a\x05\xc6\x14\x5f\x10\x10\x1e\x9d\x63\x25\x49\x42\x45\xad\xf1\x29\x7d\x80\xf8\xf3\x8d\x4d\x57\x6d\x57\xcd\xba\x22\x0b\xcb\x19"
+        );
+    }
+}
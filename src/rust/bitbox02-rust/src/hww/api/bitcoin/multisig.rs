@@ -23,6 +23,7 @@ use pb::BtcCoin;
 use crate::bip32;
 
 use crate::workflow::confirm;
+use crate::workflow::trinary_choice::{choose, TrinaryChoice};
 
 use alloc::string::String;
 use alloc::vec::Vec;
@@ -31,6 +32,24 @@ use sha2::{Digest, Sha256};
 
 pub const MAX_SIGNERS: usize = 15;
 
+/// P2WSH multisigs support up to 20 cosigners, the OP_CHECKMULTISIG limit. The P2WSH standardness
+/// limits on the witness script are checked explicitly in `pkscript()`. P2WSH-P2SH keeps the
+/// legacy limit of 15 for compatibility with coordinators that cap wrapped segwit there.
+pub const MAX_SIGNERS_P2WSH: usize = 20;
+
+/// Standardness limit on the size of a P2WSH witness script (see Bitcoin Core's policy.h).
+const MAX_STANDARD_P2WSH_SCRIPT_SIZE: usize = 3600;
+/// Consensus limit on the number of non-push opcodes per script. For OP_CHECKMULTISIG, each
+/// participating key counts towards this limit when the script is executed.
+const MAX_OPS_PER_SCRIPT: usize = 201;
+
+fn max_signers(script_type: ScriptType) -> usize {
+    match script_type {
+        ScriptType::P2wsh => MAX_SIGNERS_P2WSH,
+        ScriptType::P2wshP2sh => MAX_SIGNERS,
+    }
+}
+
 pub enum SortXpubs {
     No,
     Yes,
@@ -55,6 +74,8 @@ pub fn get_hash(
             BtcCoin::Tbtc => 0x01,
             BtcCoin::Ltc => 0x02,
             BtcCoin::Tltc => 0x03,
+            BtcCoin::Rbtc => 0x04,
+            BtcCoin::Sbtc => 0x05,
         };
         hasher.update(byte.to_le_bytes());
     }
@@ -166,7 +187,7 @@ pub async fn confirm(
 /// - name given by the user
 /// - script type (e.g. p2wsh, p2wsh-p2sh)
 /// - account keypath
-/// - all xpubs (formatted according to `xpub_type`).
+/// - all xpubs (formatted according to `xpub_type`), if the user chooses to see the full details.
 ///
 /// xpub_type: if AUTO_ELECTRUM, will automatically format xpubs as `Zpub/Vpub`,
 /// `Ypub/UPub` depending on the script type, to match Electrum's formatting. If AUTO_XPUB_TPUB,
@@ -197,6 +218,51 @@ pub async fn confirm_extended(
     })
     .await?;
 
+    // Warn if any cosigner xpub does not sit at our own account depth: the receive and change
+    // child keys are derived by appending the two non-hardened components to whatever level the
+    // cosigner supplied, so a wrong level silently shifts that cosigner's keys.
+    if multisig
+        .xpubs
+        .iter()
+        .any(|xpub| xpub.depth.as_slice() != [keypath.len() as u8])
+    {
+        let depths = multisig
+            .xpubs
+            .iter()
+            .enumerate()
+            .map(|(i, xpub)| {
+                format!(
+                    "Cosigner {}: depth {}",
+                    i + 1,
+                    xpub.depth.first().copied().unwrap_or(0)
+                )
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+        confirm::confirm(&confirm::Params {
+            title: "Warning",
+            body: &format!("Nonstandard\ncosigner depths:\n{}", depths),
+            scrollable: true,
+            accept_is_nextarrow: true,
+            ..Default::default()
+        })
+        .await?;
+    }
+
+    // Paging through all cosigner xpubs takes a while, so the user can skip them. Reading them on
+    // the device rules out that the host substituted a cosigner.
+    if choose("Show full details?", "Skip", "", "Show").await != TrinaryChoice::TRINARY_CHOICE_RIGHT
+    {
+        confirm::confirm(&confirm::Params {
+            title,
+            body: "Register account?",
+            longtouch: true,
+            ..Default::default()
+        })
+        .await?;
+        return Ok(());
+    }
+
     // Confirm cosigners.
     let output_xpub_type: bip32::XPubType = match xpub_type {
         XPubType::AutoElectrum => match params.coin {
@@ -204,14 +270,14 @@ pub async fn confirm_extended(
                 ScriptType::P2wsh => bip32::XPubType::CapitalZpub,
                 ScriptType::P2wshP2sh => bip32::XPubType::CapitalYpub,
             },
-            BtcCoin::Tbtc | BtcCoin::Tltc => match script_type {
+            BtcCoin::Tbtc | BtcCoin::Tltc | BtcCoin::Rbtc | BtcCoin::Sbtc => match script_type {
                 ScriptType::P2wsh => bip32::XPubType::CapitalVpub,
                 ScriptType::P2wshP2sh => bip32::XPubType::CapitalUpub,
             },
         },
         XPubType::AutoXpubTpub => match params.coin {
             BtcCoin::Btc | BtcCoin::Ltc => bip32::XPubType::Xpub,
-            BtcCoin::Tbtc | BtcCoin::Tltc => bip32::XPubType::Tpub,
+            BtcCoin::Tbtc | BtcCoin::Tltc | BtcCoin::Rbtc | BtcCoin::Sbtc => bip32::XPubType::Tpub,
         },
     };
     let num_cosigners = multisig.xpubs.len();
@@ -244,7 +310,7 @@ pub async fn confirm_extended(
 }
 
 /// Validate a m-of-n multisig account. This includes checking that:
-/// - 0 < m <= n <= 15
+/// - 0 < m <= n <= 20 (p2wsh) or 15 (p2wsh-p2sh)
 /// - the keypath conforms to bip48 for p2wsh: m/48'/coin'/account'/script_type'
 /// - our designated xpub is actually ours (corresponds to the xpub of the currenty unlocked
 ///   keystore).
@@ -252,7 +318,8 @@ pub async fn confirm_extended(
 /// keypath: account-level keypath, e.g. m/48'/0'/10'/2'
 /// expected:_coin expected bip44 coin in the keypath.
 pub fn validate(multisig: &Multisig, keypath: &[u32]) -> Result<(), Error> {
-    if multisig.xpubs.len() < 2 || multisig.xpubs.len() > MAX_SIGNERS {
+    let script_type = ScriptType::try_from(multisig.script_type)?;
+    if multisig.xpubs.len() < 2 || multisig.xpubs.len() > max_signers(script_type) {
         return Err(Error::InvalidInput);
     }
     if multisig.threshold == 0 || multisig.threshold > multisig.xpubs.len() as _ {
@@ -273,10 +340,29 @@ pub fn validate(multisig: &Multisig, keypath: &[u32]) -> Result<(), Error> {
     if (1..multisig.xpubs.len()).any(|i| multisig.xpubs[i..].contains(&multisig.xpubs[i - 1])) {
         return Err(Error::InvalidInput);
     }
+
+    // Cosigner xpubs usually sit at our own account depth, but some coordinators provide them at
+    // depth 3 (missing the script-type level) or 5 (one level deeper). These are accepted behind
+    // a warning during registration; see `confirm_extended()`. The receive and change child keys
+    // are derived by appending the two non-hardened components to whatever level the cosigner
+    // supplied. Our own xpub is bound to our keypath by the comparison above.
+    for (i, xpub) in multisig.xpubs.iter().enumerate() {
+        let depth = match xpub.depth.as_slice() {
+            &[depth] => depth,
+            _ => return Err(Error::InvalidInput),
+        };
+        if i != multisig.our_xpub_index as usize
+            && depth as usize != keypath.len()
+            && !(3..=5).contains(&depth)
+        {
+            return Err(Error::InvalidInput);
+        }
+    }
     Ok(())
 }
 
-/// Creates a n-of-m multisig script based on OP_CHECKMULTISIG. 0<n<=m<=15.
+/// Creates a n-of-m multisig script based on OP_CHECKMULTISIG. 0<n<=m<=20 (p2wsh) or 15
+/// (p2wsh-p2sh).
 /// Note that the multisig config and keypaths are *not* validated, this must be done before calling.
 /// keypath_change is 0 for receive addresses, 1 for change addresses.
 /// keypath_address is the receive address index.
@@ -285,7 +371,8 @@ pub fn pkscript(
     keypath_change: u32,
     keypath_address: u32,
 ) -> Result<Vec<u8>, Error> {
-    if multisig.xpubs.len() < 2 || multisig.xpubs.len() > MAX_SIGNERS {
+    let script_type = ScriptType::try_from(multisig.script_type)?;
+    if multisig.xpubs.len() < 2 || multisig.xpubs.len() > max_signers(script_type) {
         return Err(Error::InvalidInput);
     }
     if multisig.threshold == 0 || multisig.threshold > multisig.xpubs.len() as _ {
@@ -313,7 +400,13 @@ pub fn pkscript(
         .push_int(pubkeys.len() as _)
         .push_opcode(bitcoin::opcodes::all::OP_CHECKMULTISIG);
 
-    Ok(script_builder.into_bytes())
+    let script = script_builder.into_bytes();
+    // Explicitly enforce the standardness rules so a config that could never be spent from is
+    // rejected instead of producing an unspendable address.
+    if script.len() > MAX_STANDARD_P2WSH_SCRIPT_SIZE || pubkeys.len() + 1 > MAX_OPS_PER_SCRIPT {
+        return Err(Error::InvalidInput);
+    }
+    Ok(script)
 }
 
 #[cfg(test)]
@@ -624,6 +717,13 @@ mod tests {
                 "xpub6ECHc4kmTC2tQg2ZoAoazwyag9C4V6yFsZEhjwMJixdVNsUibot6uEvsZY38ZLVqWCtyc9gbzFEwHQLHCT8EiDDKSNNsFAB8NQYRgkiAQwu",
                 "xpub6F7CaxXzBCtvXwpRi61KYyhBRkgT1856ujHV5AbJK6ySCUYoDruBH6Pnsi6eHkDiuKuAJ2tSc9x3emP7aax9Dc3u7nP7RCQXEjLKihQu6w1",
             ].iter().map(|s| parse_xpub(s).unwrap()).collect();
+            // 16 cosigners are fine for p2wsh since the limit is 20.
+            assert!(validate(&invalid, keypath).is_ok());
+            // 21 cosigners are too many.
+            let base = bip32::Xpub::from(&invalid.xpubs[0]);
+            invalid
+                .xpubs
+                .extend((0..5).map(|i| pb::XPub::from(base.derive(&[i]).unwrap())));
             assert!(validate(&invalid, keypath).is_err());
         }
 
@@ -672,6 +772,25 @@ mod tests {
             invalid.xpubs[0] = invalid.xpubs[1].clone();
             assert!(validate(&invalid, keypath).is_err());
         }
+
+        {
+            // Cosigner xpubs at depths 3-5 are accepted; other depths are rejected. Our own xpub
+            // is bound to the keypath and unaffected.
+            let mut mixed = multisig.clone();
+            for depth in [3u8, 4, 5] {
+                mixed.xpubs[0].depth = vec![depth];
+                assert!(validate(&mixed, keypath).is_ok());
+            }
+            for depth in [0u8, 1, 2, 6] {
+                mixed.xpubs[0].depth = vec![depth];
+                assert!(validate(&mixed, keypath).is_err());
+            }
+            // Malformed depth encoding.
+            mixed.xpubs[0].depth = vec![];
+            assert!(validate(&mixed, keypath).is_err());
+            mixed.xpubs[0].depth = vec![4, 4];
+            assert!(validate(&mixed, keypath).is_err());
+        }
     }
 
     #[test]
@@ -763,7 +882,7 @@ mod tests {
             },
         ];
 
-        for test in tests {
+        for test in tests.iter() {
             assert_eq!(
                 hex::encode(
                     pkscript(
@@ -786,6 +905,34 @@ mod tests {
                 test.expected_script_hex
             );
         }
+
+        // The depth metadata of a cosigner xpub does not influence derivation: the receive and
+        // change components are appended to whatever level the cosigner supplied, so a
+        // mixed-depth wallet derives the same scripts as the account-level equivalent.
+        let mut mixed_depth = Multisig {
+            threshold: tests[0].threshold,
+            xpubs: tests[0]
+                .xpubs
+                .iter()
+                .map(|xpub| parse_xpub(xpub).unwrap())
+                .collect(),
+            our_xpub_index: 0,
+            script_type: ScriptType::P2wsh as _,
+        };
+        mixed_depth.xpubs[0].depth = vec![3];
+        mixed_depth.xpubs[1].depth = vec![5];
+        assert_eq!(
+            hex::encode(
+                pkscript(
+                    &mixed_depth,
+                    tests[0].keypath_change,
+                    tests[0].keypath_address
+                )
+                .unwrap()
+            )
+            .as_str(),
+            tests[0].expected_script_hex
+        );
     }
 
     #[test]
@@ -834,4 +981,34 @@ mod tests {
             .is_err());
         }
     }
+
+    #[test]
+    fn test_pkscript_max_signers() {
+        let base = bip32::Xpub::from(
+            &parse_xpub("xpub6FEZ9Bv73h1vnE4TJG4QFj2RPXJhhsPbnXgFyH3ErLvpcZrDcynY65bhWga8PazWHLSLi23PoBhGcLcYW6JRiJ12zXZ9Aop4LbAqsS3gtcy").unwrap(),
+        );
+        // Derive distinct cosigner xpubs from a common base.
+        let multisig = |num_signers: u32, script_type: ScriptType| Multisig {
+            threshold: 1,
+            xpubs: (0..num_signers)
+                .map(|i| base.derive(&[i]).unwrap().into())
+                .collect(),
+            our_xpub_index: 0,
+            script_type: script_type as _,
+        };
+
+        // 15 cosigners are okay for both script types.
+        assert!(pkscript(&multisig(15, ScriptType::P2wsh), 1, 2).is_ok());
+        assert!(pkscript(&multisig(15, ScriptType::P2wshP2sh), 1, 2).is_ok());
+        // 16-20 cosigners are only okay for p2wsh.
+        assert!(pkscript(&multisig(16, ScriptType::P2wsh), 1, 2).is_ok());
+        assert!(pkscript(&multisig(16, ScriptType::P2wshP2sh), 1, 2).is_err());
+        let script = pkscript(&multisig(20, ScriptType::P2wsh), 1, 2).unwrap();
+        // OP_1, 20 pushes of 33 byte pubkeys, push of 20, OP_CHECKMULTISIG.
+        assert_eq!(script.len(), 1 + 20 * 34 + 2 + 1);
+        assert!(pkscript(&multisig(20, ScriptType::P2wshP2sh), 1, 2).is_err());
+        // 21 cosigners exceed the OP_CHECKMULTISIG limit.
+        assert!(pkscript(&multisig(21, ScriptType::P2wsh), 1, 2).is_err());
+        assert!(pkscript(&multisig(21, ScriptType::P2wshP2sh), 1, 2).is_err());
+    }
 }
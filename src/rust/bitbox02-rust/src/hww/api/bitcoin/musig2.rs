@@ -0,0 +1,431 @@
+// Copyright 2024 Shift Crypto AG
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::params::Params;
+use super::pb;
+use super::Error;
+
+use pb::btc_script_config::Musig2;
+use pb::BtcCoin;
+
+use crate::bip32;
+
+use crate::workflow::confirm;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use bitcoin::secp256k1::{PublicKey, Scalar, Secp256k1};
+use sha2::{Digest, Sha256};
+
+pub const MAX_SIGNERS: usize = 15;
+
+/// Aggregates the cosigner pubkeys into a single public key according to the KeyAgg algorithm of
+/// BIP-327. The aggregate key depends on the order of the pubkeys, which is fixed by the script
+/// config. Returns the 33 byte compressed aggregate pubkey.
+pub fn key_agg(pubkeys: &[[u8; 33]]) -> Result<[u8; 33], ()> {
+    if pubkeys.is_empty() {
+        return Err(());
+    }
+    let secp = Secp256k1::new();
+    // The second distinct pubkey (and all its duplicates) gets the key aggregation coefficient 1.
+    let second_pubkey: Option<&[u8; 33]> = pubkeys.iter().find(|pubkey| *pubkey != &pubkeys[0]);
+    let keyagg_list_hash: [u8; 32] = {
+        let mut hasher = super::bip341::tagged_hasher(b"KeyAgg list");
+        for pubkey in pubkeys {
+            hasher.update(pubkey);
+        }
+        hasher.finalize().into()
+    };
+    let summands: Vec<PublicKey> = pubkeys
+        .iter()
+        .map(|pubkey| {
+            let parsed = PublicKey::from_slice(pubkey).or(Err(()))?;
+            if Some(pubkey) == second_pubkey {
+                Ok(parsed)
+            } else {
+                let mut hasher = super::bip341::tagged_hasher(b"KeyAgg coefficient");
+                hasher.update(keyagg_list_hash);
+                hasher.update(pubkey);
+                // The coefficient is reduced mod the curve order; a hash outside the scalar range
+                // has negligible probability.
+                let coefficient = Scalar::from_be_bytes(hasher.finalize().into()).or(Err(()))?;
+                parsed.mul_tweak(&secp, &coefficient).or(Err(()))
+            }
+        })
+        .collect::<Result<_, _>>()?;
+    let summands: Vec<&PublicKey> = summands.iter().collect();
+    Ok(PublicKey::combine_keys(&summands).or(Err(()))?.serialize())
+}
+
+/// Derives the 33 byte compressed pubkeys of all cosigners at `m/<change>/<address>`, in the order
+/// of the script config xpubs (the key aggregation order).
+pub fn derive_pubkeys(
+    musig2: &Musig2,
+    keypath_change: u32,
+    keypath_address: u32,
+) -> Result<Vec<[u8; 33]>, Error> {
+    musig2
+        .xpubs
+        .iter()
+        .map(|xpub| {
+            bip32::Xpub::from(xpub)
+                .derive(&[keypath_change, keypath_address])?
+                .public_key()
+                .try_into()
+                .or(Err(()))
+        })
+        .collect::<Result<Vec<[u8; 33]>, ()>>()
+        .or(Err(Error::InvalidInput))
+}
+
+/// Computes the payload (32 byte x-only taproot output key) of a MuSig2 script config at
+/// `m/<change>/<address>`. The aggregate key is tweaked like a regular taproot key without a
+/// script tree (BIP-86).
+pub fn payload(
+    musig2: &Musig2,
+    keypath_change: u32,
+    keypath_address: u32,
+) -> Result<Vec<u8>, Error> {
+    let pubkeys = derive_pubkeys(musig2, keypath_change, keypath_address)?;
+    let aggregate_pubkey = key_agg(&pubkeys).or(Err(Error::InvalidInput))?;
+    Ok(bitbox02::keystore::secp256k1_schnorr_bip86_pubkey(&aggregate_pubkey)?.to_vec())
+}
+
+/// Creates a hash of this MuSig2 config, useful for account registration and identification. The
+/// individual params are not validated, they must be pre-validated!
+///
+/// Unlike multisig configs, the xpubs are never sorted before hashing, as the key aggregation
+/// depends on their order.
+/// The keypath is the account-level keypath.
+pub fn get_hash(coin: BtcCoin, musig2: &Musig2, keypath: &[u32]) -> Result<Vec<u8>, ()> {
+    let mut hasher = Sha256::new();
+    {
+        // 1. coin
+        let byte: u8 = match coin {
+            BtcCoin::Btc => 0x00,
+            BtcCoin::Tbtc => 0x01,
+            BtcCoin::Ltc => 0x02,
+            BtcCoin::Tltc => 0x03,
+            BtcCoin::Rbtc => 0x04,
+            BtcCoin::Sbtc => 0x05,
+        };
+        hasher.update(byte.to_le_bytes());
+    }
+    {
+        // 2. script config type (0x00/0x01 are used by the multisig script types)
+        let byte: u8 = 0x02;
+        hasher.update(byte.to_le_bytes());
+    }
+    {
+        // 3. num xpubs
+        let num: u32 = musig2.xpubs.len() as _;
+        hasher.update(num.to_le_bytes());
+    }
+    {
+        // 4. xpubs
+        for xpub in musig2.xpubs.iter() {
+            hasher.update(bip32::Xpub::from(xpub).serialize(None)?);
+        }
+    }
+    {
+        // 5. keypath len
+        let num: u32 = keypath.len() as _;
+        hasher.update(num.to_le_bytes());
+    }
+    {
+        // 6. keypath
+        for el in keypath.iter() {
+            hasher.update(el.to_le_bytes());
+        }
+    }
+    Ok(hasher.finalize().as_slice().into())
+}
+
+/// Get the name of a registered MuSig2 account. The individual params are not validated, they must
+/// be pre-validated!
+///
+/// The keypath is the account-level keypath.
+///
+/// Returns the name of the registered MuSig2 account if it exists or None otherwise.
+pub fn get_name(coin: BtcCoin, musig2: &Musig2, keypath: &[u32]) -> Result<Option<String>, ()> {
+    Ok(bitbox02::memory::multisig_get_by_hash(&get_hash(
+        coin, musig2, keypath,
+    )?))
+}
+
+/// Confirms a MuSig2 setup with the user during send/receive.
+/// Verified are:
+/// - coin
+/// - MuSig2 type (n-of-n)
+/// - name given by the user
+pub async fn confirm(
+    title: &str,
+    params: &Params,
+    name: &str,
+    musig2: &Musig2,
+) -> Result<(), Error> {
+    let num_cosigners = musig2.xpubs.len();
+    confirm::confirm(&confirm::Params {
+        title,
+        body: &format!("{}-of-{}\n{} MuSig2", num_cosigners, num_cosigners, params.name),
+        accept_is_nextarrow: true,
+        ..Default::default()
+    })
+    .await?;
+    confirm::confirm(&confirm::Params {
+        title,
+        body: name,
+        scrollable: true,
+        accept_is_nextarrow: true,
+        ..Default::default()
+    })
+    .await?;
+    Ok(())
+}
+
+/// Confirms a MuSig2 setup with the user during account registration.
+/// Verified are:
+/// - coin
+/// - MuSig2 type (n-of-n)
+/// - name given by the user
+/// - account keypath
+/// - all xpubs
+///
+/// The xpubs are formatted as xpub (mainnets) or tpub (testnets); there is no Electrum-style
+/// format for taproot keys.
+pub async fn confirm_extended(
+    title: &str,
+    params: &Params,
+    name: &str,
+    musig2: &Musig2,
+    keypath: &[u32],
+) -> Result<(), Error> {
+    confirm(title, params, name, musig2).await?;
+    confirm::confirm(&confirm::Params {
+        title,
+        body: &format!("p2tr-musig2\nat\n{}", util::bip32::to_string(keypath)),
+        accept_is_nextarrow: true,
+        ..Default::default()
+    })
+    .await?;
+
+    // Confirm cosigners.
+    let output_xpub_type: bip32::XPubType = match params.coin {
+        BtcCoin::Btc | BtcCoin::Ltc => bip32::XPubType::Xpub,
+        BtcCoin::Tbtc | BtcCoin::Tltc | BtcCoin::Rbtc | BtcCoin::Sbtc => bip32::XPubType::Tpub,
+    };
+    let num_cosigners = musig2.xpubs.len();
+    for (i, xpub) in musig2.xpubs.iter().enumerate() {
+        let xpub_str = bip32::Xpub::from(xpub)
+            .serialize_str(output_xpub_type)
+            .or(Err(Error::InvalidInput))?;
+        confirm::confirm(&confirm::Params {
+            title,
+            body: (if i == musig2.our_xpub_index as usize {
+                format!(
+                    "Cosigner {}/{} (this device): {}",
+                    i + 1,
+                    num_cosigners,
+                    xpub_str
+                )
+            } else {
+                format!("Cosigner {}/{}: {}", i + 1, num_cosigners, xpub_str)
+            })
+            .as_str(),
+            scrollable: true,
+            longtouch: i == num_cosigners - 1,
+            accept_is_nextarrow: true,
+            ..Default::default()
+        })
+        .await?;
+    }
+    Ok(())
+}
+
+/// Validate a MuSig2 n-of-n account. This includes checking that:
+/// - 2 <= n <= 15
+/// - our designated xpub is actually ours (corresponds to the xpub of the currently unlocked
+///   keystore).
+/// - no two xpubs are the same.
+///
+/// keypath: account-level keypath.
+pub fn validate(musig2: &Musig2, keypath: &[u32]) -> Result<(), Error> {
+    if musig2.xpubs.len() < 2 || musig2.xpubs.len() > MAX_SIGNERS {
+        return Err(Error::InvalidInput);
+    }
+    if musig2.our_xpub_index >= musig2.xpubs.len() as _ {
+        return Err(Error::InvalidInput);
+    }
+
+    let our_xpub = crate::keystore::get_xpub(keypath)?.serialize(None)?;
+    let maybe_our_xpub =
+        bip32::Xpub::from(&musig2.xpubs[musig2.our_xpub_index as usize]).serialize(None)?;
+    if our_xpub != maybe_our_xpub {
+        return Err(Error::InvalidInput);
+    }
+
+    // Check for duplicates. Duplicate keys are not a problem for the key aggregation itself, but
+    // they are an indication of a misconfigured wallet.
+    if (1..musig2.xpubs.len()).any(|i| musig2.xpubs[i..].contains(&musig2.xpubs[i - 1])) {
+        return Err(Error::InvalidInput);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use bip32::parse_xpub;
+    use bitbox02::testing::mock_unlocked_using_mnemonic;
+    use util::bip32::HARDENED;
+
+    #[test]
+    fn test_key_agg() {
+        // Test vectors from:
+        // https://github.com/bitcoin/bips/blob/master/bip-0327/vectors/key_agg_vectors.json
+        let pubkey1: [u8; 33] =
+            *b"\x02\xf9\x30\x8a\x01\x92\x58\xc3\x10\x49\x34\x4f\x85\xf8\x9d\x52\x29\xb5\x31\xc8\x45\x83\x6f\x99\xb0\x86\x01\xf1\x13\xbc\xe0\x36\xf9";
+        let pubkey2: [u8; 33] =
+            *b"\x03\xdf\xf1\xd7\x7f\x2a\x67\x1c\x5f\x36\x18\x37\x26\xdb\x23\x41\xbe\x58\xfe\xae\x1d\xa2\xde\xce\xd8\x43\x24\x0f\x7b\x50\x2b\xa6\x59";
+        let pubkey3: [u8; 33] =
+            *b"\x02\x35\x90\xa9\x4e\x76\x8f\x8e\x18\x15\xc2\xf2\x4b\x4d\x80\xa8\xe3\x14\x93\x16\xc3\x51\x8c\xe7\xb7\xad\x33\x83\x68\xd0\x38\xca\x66";
+
+        assert_eq!(
+            hex::encode(key_agg(&[pubkey1, pubkey2, pubkey3]).unwrap()),
+            "0290539eede565f5d054f32cc0c220126889ed1e5d193baf15aef344fe59d4610c",
+        );
+        // The aggregate key depends on the order of the pubkeys.
+        assert_eq!(
+            hex::encode(key_agg(&[pubkey3, pubkey2, pubkey1]).unwrap()),
+            "036204de8b083426dc6eaf9502d27024d53fc826bf7d2012148a0575435df54b2b",
+        );
+        // Duplicate pubkeys are aggregated per BIP-327 without error.
+        assert_eq!(
+            hex::encode(key_agg(&[pubkey1, pubkey1, pubkey1]).unwrap()),
+            "02b436e3bad62b8cd409969a224731c193d051162d8c5ae8b109306127da3aa935",
+        );
+
+        assert!(key_agg(&[]).is_err());
+        // Invalid pubkey.
+        assert!(key_agg(&[[0xff; 33]]).is_err());
+    }
+
+    #[test]
+    fn test_get_hash() {
+        /* Fixture below verified with:
+        import hashlib
+        import base58
+
+        xpubs = [
+            "xpub6FMWuwbCA9KhoRzAMm63ZhLspk5S2DM5sePo8J8mQhcS1xyMbAqnc7Q7UescVEVFCS6qBMQLkEJWQ9Z3aDPgBov5nFUYxsJhwumsxM4npSo",
+            "xpub6EMfjyGVUvwhpc3WKN1zXhMFGKJGMaSBPqbja4tbGoYvRBSXeTBCaqrRDjcuGTcaY95JrrAnQvDG3pdQPdtnYUCugjeksHSbyZT7rq38VQF",
+        ]
+
+        keypath = [48 + 0x80000000, 0 + 0x80000000, 10 + 0x80000000, 3 + 0x80000000]
+
+        i32 = lambda i: i.to_bytes(4, 'little')
+
+        msg = []
+        msg.append(b'\x00') # coin
+        msg.append(b'\x02') # script config type
+        msg.append(i32(len(xpubs)))
+        msg.extend(base58.b58decode_check(xpub)[4:] for xpub in xpubs)
+        msg.append(i32(len(keypath)))
+        msg.extend(i32(k) for k in keypath)
+        print(hashlib.sha256(b''.join(msg)).hexdigest())
+        */
+
+        let keypath: &[u32] = &[48 + HARDENED, HARDENED, 10 + HARDENED, 3 + HARDENED];
+        let musig2 = Musig2 {
+            xpubs: vec![
+                parse_xpub("xpub6FMWuwbCA9KhoRzAMm63ZhLspk5S2DM5sePo8J8mQhcS1xyMbAqnc7Q7UescVEVFCS6qBMQLkEJWQ9Z3aDPgBov5nFUYxsJhwumsxM4npSo").unwrap(),
+                parse_xpub("xpub6EMfjyGVUvwhpc3WKN1zXhMFGKJGMaSBPqbja4tbGoYvRBSXeTBCaqrRDjcuGTcaY95JrrAnQvDG3pdQPdtnYUCugjeksHSbyZT7rq38VQF").unwrap(),
+            ],
+            our_xpub_index: 0,
+        };
+
+        assert_eq!(
+            get_hash(BtcCoin::Btc, &musig2, keypath).unwrap(),
+            hex::decode("18eaea923f33bb63820869ae3f6f88a72767a6fd01c2816d4cf6e2b4fb066c87")
+                .unwrap(),
+        );
+        assert_eq!(
+            get_hash(BtcCoin::Tbtc, &musig2, keypath).unwrap(),
+            hex::decode("4571f403fed559c0ec8ee14374593779465afeaba834da8bdd7246748ccaeef2")
+                .unwrap(),
+        );
+    }
+
+    #[test]
+    fn test_validate() {
+        let keypath = &[48 + HARDENED, 1 + HARDENED, HARDENED, 2 + HARDENED];
+        let our_xpub_str = "xpub6EMfjyGVUvwhpc3WKN1zXhMFGKJGMaSBPqbja4tbGoYvRBSXeTBCaqrRDjcuGTcaY95JrrAnQvDG3pdQPdtnYUCugjeksHSbyZT7rq38VQF";
+        let musig2 = Musig2 {
+            xpubs: vec![
+                parse_xpub("xpub6FMWuwbCA9KhoRzAMm63ZhLspk5S2DM5sePo8J8mQhcS1xyMbAqnc7Q7UescVEVFCS6qBMQLkEJWQ9Z3aDPgBov5nFUYxsJhwumsxM4npSo").unwrap(),
+                // this xpub corresponds to the mocked seed above at m/48'/1'/0'/2.
+                parse_xpub(our_xpub_str).unwrap(),
+            ],
+            our_xpub_index: 1,
+        };
+
+        // Keystore locked.
+        bitbox02::keystore::lock();
+        assert!(validate(&musig2, keypath).is_err());
+
+        // Ok.
+        mock_unlocked_using_mnemonic(
+            "sudden tenant fault inject concert weather maid people chunk youth stumble grit",
+            "",
+        );
+        assert!(validate(&musig2, keypath).is_ok());
+
+        {
+            // number of cosigners too small
+
+            let mut invalid = musig2.clone();
+            invalid.xpubs = vec![];
+            assert!(validate(&invalid, keypath).is_err());
+            invalid.our_xpub_index = 0;
+            invalid.xpubs = vec![parse_xpub(our_xpub_str).unwrap()];
+            assert!(validate(&invalid, keypath).is_err());
+        }
+
+        {
+            // our xpub index larger than number of cosigners (xpubs[our_xpub_index] would be out
+            // of bounds).
+            let mut invalid = musig2.clone();
+            invalid.our_xpub_index = 2;
+            assert!(validate(&invalid, keypath).is_err());
+        }
+
+        {
+            // our xpub is not part of the config (overwrite our xpub with an arbitrary other one).
+
+            let mut invalid = musig2.clone();
+            invalid.xpubs[1] = parse_xpub("xpub6FNT7x2ZEBMhs4jvZJSEBV2qBCBnRidNsyqe7inT9V2wmEn4sqidTEudB4dVSvEjXz2NytcymwWJb8PPYExRycNf9SH8fAHzPWUsQJAmbR3").unwrap();
+            assert!(validate(&invalid, keypath).is_err());
+        }
+
+        {
+            // duplicate
+
+            let mut invalid = musig2.clone();
+            invalid.xpubs[0] = invalid.xpubs[1].clone();
+            assert!(validate(&invalid, keypath).is_err());
+        }
+    }
+}
@@ -61,6 +61,28 @@ const PARAMS_TBTC: Params = Params {
     taproot_support: true,
 };
 
+const PARAMS_RBTC: Params = Params {
+    coin: BtcCoin::Rbtc,
+    bip44_coin: 1 + HARDENED,
+    base58_version_p2pkh: 0x6f, // starts with m or n
+    base58_version_p2sh: 0xc4,  // starts with 2
+    bech32_hrp: "bcrt",
+    name: "BTC Regtest",
+    rbf_support: true,
+    taproot_support: true,
+};
+
+const PARAMS_SBTC: Params = Params {
+    coin: BtcCoin::Sbtc,
+    bip44_coin: 1 + HARDENED,
+    base58_version_p2pkh: 0x6f, // starts with m or n
+    base58_version_p2sh: 0xc4,  // starts with 2
+    bech32_hrp: "tb",
+    name: "Signet",
+    rbf_support: true,
+    taproot_support: true,
+};
+
 const PARAMS_LTC: Params = Params {
     coin: BtcCoin::Ltc,
     bip44_coin: 2 + HARDENED,
@@ -69,7 +91,8 @@ const PARAMS_LTC: Params = Params {
     bech32_hrp: "ltc",
     name: "Litecoin",
     rbf_support: false,
-    taproot_support: false,
+    // Activated on Litecoin together with MWEB in May 2022.
+    taproot_support: true,
 };
 
 const PARAMS_TLTC: Params = Params {
@@ -80,7 +103,7 @@ const PARAMS_TLTC: Params = Params {
     bech32_hrp: "tltc",
     name: "LTC Testnet",
     rbf_support: false,
-    taproot_support: false,
+    taproot_support: true,
 };
 
 pub fn get(coin: BtcCoin) -> &'static Params {
@@ -88,6 +111,8 @@ pub fn get(coin: BtcCoin) -> &'static Params {
     match coin {
         Btc => &PARAMS_BTC,
         Tbtc => &PARAMS_TBTC,
+        Rbtc => &PARAMS_RBTC,
+        Sbtc => &PARAMS_SBTC,
         Ltc => &PARAMS_LTC,
         Tltc => &PARAMS_TLTC,
     }
@@ -178,6 +178,9 @@ impl<'a> miniscript::Translator<String, bitcoin::PublicKey, Error>
 #[derive(Debug)]
 pub struct Wsh<T: miniscript::MiniscriptKey> {
     miniscript_expr: miniscript::Miniscript<T, miniscript::Segwitv0>,
+    // If true, the policy contained `sortedmulti()`, which was desugared into `multi()`. The
+    // pubkeys are sorted lexicographically (BIP-67) when deriving the concrete descriptor.
+    sorted_multi: bool,
 }
 
 impl Wsh<bitcoin::PublicKey> {
@@ -187,6 +190,16 @@ impl Wsh<bitcoin::PublicKey> {
     }
 }
 
+impl<T: miniscript::MiniscriptKey> Wsh<T> {
+    /// Maximum size, in bytes, of a satisfying witness stack (excluding the witness script),
+    /// assuming 73 byte signatures. Used for conservative transaction weight estimation.
+    pub fn max_satisfaction_size(&self) -> Result<usize, Error> {
+        self.miniscript_expr
+            .max_satisfaction_size()
+            .or(Err(Error::InvalidInput))
+    }
+}
+
 /// See `ParsedPolicy`.
 #[derive(Debug)]
 pub enum Descriptor<T: miniscript::MiniscriptKey> {
@@ -219,7 +232,9 @@ impl<'a> ParsedPolicy<'a> {
     /// must be present.
     fn validate_keys(&self) -> Result<(), Error> {
         match &self.descriptor {
-            Descriptor::Wsh(Wsh { miniscript_expr }) => {
+            Descriptor::Wsh(Wsh {
+                miniscript_expr, ..
+            }) => {
                 // in "@key_index/<left;right>", keeps track of (key_index,left) and
                 // (key_index,right) to check for duplicates.
                 let mut derivations_seen: Vec<(usize, u32)> = Vec::new();
@@ -337,7 +352,7 @@ impl<'a> ParsedPolicy<'a> {
 
         let output_xpub_type = match params.coin {
             BtcCoin::Btc | BtcCoin::Ltc => bip32::XPubType::Xpub,
-            BtcCoin::Tbtc | BtcCoin::Tltc => bip32::XPubType::Tpub,
+            BtcCoin::Tbtc | BtcCoin::Tltc | BtcCoin::Rbtc | BtcCoin::Sbtc => bip32::XPubType::Tpub,
         };
         let num_keys = policy.keys.len();
         for (i, key) in policy.keys.iter().enumerate() {
@@ -401,13 +416,33 @@ impl<'a> ParsedPolicy<'a> {
             address_index,
         };
         match &self.descriptor {
-            Descriptor::Wsh(Wsh { miniscript_expr }) => {
+            Descriptor::Wsh(Wsh {
+                miniscript_expr,
+                sorted_multi,
+            }) => {
                 let miniscript_expr = match miniscript_expr.translate_pk(&mut translator) {
                     Ok(m) => m,
                     Err(miniscript::TranslateErr::TranslatorErr(e)) => return Err(e),
                     Err(miniscript::TranslateErr::OuterError(_)) => return Err(Error::Generic),
                 };
-                Ok(Descriptor::Wsh(Wsh { miniscript_expr }))
+                let miniscript_expr = if *sorted_multi {
+                    // Sort the pubkeys lexicographically (BIP-67). The sort order of
+                    // `bitcoin::PublicKey` is the order of the serialized keys.
+                    match miniscript_expr.node {
+                        miniscript::Terminal::Multi(mut thresh) => {
+                            thresh.data_mut().sort();
+                            miniscript::Miniscript::from_ast(miniscript::Terminal::Multi(thresh))
+                                .or(Err(Error::Generic))?
+                        }
+                        _ => return Err(Error::Generic),
+                    }
+                } else {
+                    miniscript_expr
+                };
+                Ok(Descriptor::Wsh(Wsh {
+                    miniscript_expr,
+                    sorted_multi: *sorted_multi,
+                }))
             }
         }
     }
@@ -422,7 +457,9 @@ impl<'a> ParsedPolicy<'a> {
         keypath: &[u32],
     ) -> Result<Descriptor<bitcoin::PublicKey>, Error> {
         match &self.descriptor {
-            Descriptor::Wsh(Wsh { miniscript_expr }) => {
+            Descriptor::Wsh(Wsh {
+                miniscript_expr, ..
+            }) => {
                 let (is_change, address_index) = get_change_and_address_index(
                     miniscript_expr.iter_pk(),
                     &self.policy.keys,
@@ -437,7 +474,9 @@ impl<'a> ParsedPolicy<'a> {
     /// Returns true if the address-level keypath points to a change address.
     pub fn is_change_keypath(&self, keypath: &[u32]) -> Result<bool, Error> {
         match &self.descriptor {
-            Descriptor::Wsh(Wsh { miniscript_expr }) => {
+            Descriptor::Wsh(Wsh {
+                miniscript_expr, ..
+            }) => {
                 let (is_change, _) = get_change_and_address_index(
                     miniscript_expr.iter_pk(),
                     &self.policy.keys,
@@ -451,7 +490,7 @@ impl<'a> ParsedPolicy<'a> {
 }
 
 /// Parses a policy as specified by 'Wallet policies': https://github.com/bitcoin/bips/pull/1389.
-/// Only `wsh(<miniscript expression>)` is supported for now.
+/// Only `wsh(<miniscript expression>)` and `wsh(sortedmulti(...))` are supported for now.
 /// Example: `wsh(pk(@0/**))`.
 ///
 /// The parsed output keeps the key strings as is (e.g. "@0/**"). They will be processed and
@@ -474,14 +513,23 @@ pub fn parse(policy: &Policy, coin: BtcCoin) -> Result<ParsedPolicy, Error> {
     let parsed = match desc.as_bytes() {
         // Match wsh(...).
         [b'w', b's', b'h', b'(', .., b')'] => {
+            let inner = &desc[4..desc.len() - 1];
+            // `sortedmulti()` is a descriptor fragment, not part of miniscript. We desugar it into
+            // `multi()` here and sort the pubkeys when deriving the concrete descriptor.
+            let (desugared, sorted_multi) = match inner.strip_prefix("sortedmulti(") {
+                Some(rest) => (format!("multi({}", rest), true),
+                None => (inner.into(), false),
+            };
             let miniscript_expr: miniscript::Miniscript<String, miniscript::Segwitv0> =
-                miniscript::Miniscript::from_str(&desc[4..desc.len() - 1])
-                    .or(Err(Error::InvalidInput))?;
+                miniscript::Miniscript::from_str(&desugared).or(Err(Error::InvalidInput))?;
 
             ParsedPolicy {
                 policy,
                 is_our_key,
-                descriptor: Descriptor::Wsh(Wsh { miniscript_expr }),
+                descriptor: Descriptor::Wsh(Wsh {
+                    miniscript_expr,
+                    sorted_multi,
+                }),
             }
         }
         _ => return Err(Error::InvalidInput),
@@ -490,6 +538,17 @@ pub fn parse(policy: &Policy, coin: BtcCoin) -> Result<ParsedPolicy, Error> {
     Ok(parsed)
 }
 
+/// Desugars a `Miniscript` script config into the equivalent `wsh()` wallet policy. The key
+/// placeholder syntax (`@0/**`, ...) is the same, so the script is simply wrapped in `wsh(...)`.
+/// The wrapping cannot be subverted by a crafted script string: `parse()` requires the inner
+/// string to be one complete miniscript expression, so e.g. unbalanced parentheses are rejected.
+pub fn wrap_miniscript(miniscript: &pb::btc_script_config::Miniscript) -> Policy {
+    Policy {
+        policy: format!("wsh({})", miniscript.script),
+        keys: miniscript.keys.clone(),
+    }
+}
+
 /// Confirmation mode.
 pub enum Mode {
     /// Confirm coin, number of keys and account name and optionally the advanced details.
@@ -513,6 +572,8 @@ pub fn get_hash(coin: BtcCoin, policy: &Policy) -> Result<Vec<u8>, ()> {
             BtcCoin::Tbtc => 0x01,
             BtcCoin::Ltc => 0x02,
             BtcCoin::Tltc => 0x03,
+            BtcCoin::Rbtc => 0x04,
+            BtcCoin::Sbtc => 0x05,
         };
         hasher.update(byte.to_le_bytes());
     }
@@ -1078,6 +1139,174 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_sortedmulti_witness_script() {
+        mock_unlocked_using_mnemonic(
+            "sudden tenant fault inject concert weather maid people chunk youth stumble grit",
+            "",
+        );
+
+        let our_key = make_our_key(KEYPATH_ACCOUNT);
+        let our_xpub = bip32::Xpub::from(our_key.xpub.as_ref().unwrap());
+        let some_key = make_key(SOME_XPUB_1);
+        let some_xpub = bip32::Xpub::from(some_key.xpub.as_ref().unwrap());
+        let address_index = 5;
+        let coin = BtcCoin::Tbtc;
+
+        let witness_script = |pol: &str, keys: &[pb::KeyOriginInfo]| {
+            let derived = parse(&make_policy(pol, keys), coin)
+                .unwrap()
+                .derive(false, address_index)
+                .unwrap();
+            match derived {
+                Descriptor::Wsh(wsh) => hex::encode(wsh.witness_script()),
+            }
+        };
+
+        // sortedmulti(2,key1,key2) => OP_2 <key1> <key2> OP_2 OP_CHECKMULTISIG, with the keys
+        // sorted lexicographically (BIP-67).
+        let expected_derived_pubkey1 =
+            "039d626054b8fd7e8371ee7341549846cc7703b5530d6b7ddc08dc8a3b78455924";
+        let expected_derived_pubkey2 =
+            "038995541bb031b22c58870b8b1bb4fffa40957f438b6e784a0fb0998a6b2483d7";
+        assert_eq!(
+            hex::encode(our_xpub.derive(&[0, address_index]).unwrap().public_key()).as_str(),
+            expected_derived_pubkey1
+        );
+        assert_eq!(
+            hex::encode(some_xpub.derive(&[0, address_index]).unwrap().public_key()).as_str(),
+            expected_derived_pubkey2
+        );
+        // The second key sorts before the first key.
+        let expected_witness_script = format!(
+            "5221{}21{}52ae",
+            expected_derived_pubkey2, expected_derived_pubkey1
+        );
+        assert_eq!(
+            witness_script(
+                "wsh(sortedmulti(2,@0/**,@1/**))",
+                &[our_key.clone(), some_key.clone()],
+            ),
+            expected_witness_script
+        );
+        // The key order in the policy does not matter.
+        assert_eq!(
+            witness_script(
+                "wsh(sortedmulti(2,@1/**,@0/**))",
+                &[our_key.clone(), some_key.clone()],
+            ),
+            expected_witness_script
+        );
+
+        // Unsorted multi is untouched.
+        assert_eq!(
+            witness_script(
+                "wsh(multi(2,@0/**,@1/**))",
+                &[our_key.clone(), some_key.clone()],
+            ),
+            format!(
+                "5221{}21{}52ae",
+                expected_derived_pubkey1, expected_derived_pubkey2
+            )
+        );
+
+        // `sortedmulti()` is only allowed as the top-level fragment.
+        assert_eq!(
+            parse(
+                &make_policy(
+                    "wsh(and_v(v:pk(@0/**),sortedmulti(1,@1/**)))",
+                    &[our_key.clone(), some_key.clone()]
+                ),
+                coin
+            )
+            .unwrap_err(),
+            Error::InvalidInput,
+        );
+    }
+
+    #[test]
+    fn test_wrap_miniscript() {
+        mock_unlocked_using_mnemonic(
+            "sudden tenant fault inject concert weather maid people chunk youth stumble grit",
+            "",
+        );
+
+        let our_key = make_our_key(KEYPATH_ACCOUNT);
+        let our_xpub = bip32::Xpub::from(our_key.xpub.as_ref().unwrap());
+        let some_key = make_key(SOME_XPUB_1);
+        let some_xpub = bip32::Xpub::from(some_key.xpub.as_ref().unwrap());
+        let address_index = 5;
+        let coin = BtcCoin::Tbtc;
+
+        let make_miniscript =
+            |script: &str, keys: &[pb::KeyOriginInfo]| pb::btc_script_config::Miniscript {
+                script: script.into(),
+                keys: keys.to_vec(),
+            };
+
+        // The desugared policy is the script wrapped in `wsh()`, with the keys unchanged.
+        let miniscript = make_miniscript(
+            "and_v(v:pk(@0/**),or_d(pk(@1/**),older(1000)))",
+            &[our_key.clone(), some_key.clone()],
+        );
+        let policy = wrap_miniscript(&miniscript);
+        assert_eq!(
+            policy.policy.as_str(),
+            "wsh(and_v(v:pk(@0/**),or_d(pk(@1/**),older(1000))))"
+        );
+        assert_eq!(policy.keys, miniscript.keys);
+
+        // Round-trip: the witness script of the desugared policy matches the script generated by
+        // rust-miniscript:
+        // and_v(v:pk(A),or_d(pk(B),older(1000))) =>
+        // <A> OP_CHECKSIGVERIFY <B> OP_CHECKSIG OP_IFDUP OP_NOTIF <1000> OP_CSV OP_ENDIF
+        let derived = parse(&policy, coin)
+            .unwrap()
+            .derive(false, address_index)
+            .unwrap();
+        let witness_script = match derived {
+            Descriptor::Wsh(wsh) => hex::encode(wsh.witness_script()),
+        };
+        let expected_derived_pubkey1 =
+            "039d626054b8fd7e8371ee7341549846cc7703b5530d6b7ddc08dc8a3b78455924";
+        let expected_derived_pubkey2 =
+            "038995541bb031b22c58870b8b1bb4fffa40957f438b6e784a0fb0998a6b2483d7";
+        assert_eq!(
+            hex::encode(our_xpub.derive(&[0, address_index]).unwrap().public_key()).as_str(),
+            expected_derived_pubkey1
+        );
+        assert_eq!(
+            hex::encode(some_xpub.derive(&[0, address_index]).unwrap().public_key()).as_str(),
+            expected_derived_pubkey2
+        );
+        assert_eq!(
+            witness_script,
+            format!(
+                "21{}ad21{}ac736402e803b268",
+                expected_derived_pubkey1, expected_derived_pubkey2
+            )
+        );
+
+        // Unsupported fragment inside the script.
+        assert_eq!(
+            parse(
+                &wrap_miniscript(&make_miniscript("unknown(@0/**)", &[our_key.clone()])),
+                coin
+            )
+            .unwrap_err(),
+            Error::InvalidInput,
+        );
+        // A crafted script cannot escape the `wsh()` wrapping.
+        assert_eq!(
+            parse(
+                &wrap_miniscript(&make_miniscript("pk(@0/**)),pk(@0/**", &[our_key.clone()])),
+                coin
+            )
+            .unwrap_err(),
+            Error::InvalidInput,
+        );
+    }
+
     #[test]
     fn test_get_hash() {
         // Fixture below verified with:
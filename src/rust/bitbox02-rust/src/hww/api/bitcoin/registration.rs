@@ -17,6 +17,7 @@ use super::pb;
 use super::Error;
 
 use alloc::string::String;
+use alloc::vec::Vec;
 
 use pb::btc_register_script_config_request::XPubType;
 use pb::btc_response::Response;
@@ -27,6 +28,12 @@ use super::multisig::SortXpubs;
 
 use crate::workflow::{confirm, status, trinary_input_string};
 
+use chacha20poly1305::aead::AeadInPlace;
+use chacha20poly1305::{ChaCha20Poly1305, KeyInit, Nonce, Tag};
+use hmac::{Hmac, Mac};
+use prost::Message;
+use sha2::Sha256;
+
 pub fn process_is_script_config_registered(
     request: &pb::BtcIsScriptConfigRegisteredRequest,
 ) -> Result<Response, Error> {
@@ -61,6 +68,52 @@ pub fn process_is_script_config_registered(
                 },
             ))
         }
+        Some(pb::BtcScriptConfigRegistration {
+            coin,
+            script_config:
+                Some(pb::BtcScriptConfig {
+                    config: Some(Config::SimpleType(_)),
+                }),
+            keypath,
+        }) => {
+            let coin = BtcCoin::try_from(*coin)?;
+            Ok(Response::IsScriptConfigRegistered(
+                pb::BtcIsScriptConfigRegisteredResponse {
+                    is_registered: super::accounts::get_name(coin, keypath).is_some(),
+                },
+            ))
+        }
+        Some(pb::BtcScriptConfigRegistration {
+            coin,
+            script_config:
+                Some(pb::BtcScriptConfig {
+                    config: Some(Config::Musig2(musig2)),
+                }),
+            keypath,
+        }) => {
+            let coin = BtcCoin::try_from(*coin)?;
+            Ok(Response::IsScriptConfigRegistered(
+                pb::BtcIsScriptConfigRegisteredResponse {
+                    is_registered: super::musig2::get_name(coin, musig2, keypath)?.is_some(),
+                },
+            ))
+        }
+        Some(pb::BtcScriptConfigRegistration {
+            coin,
+            script_config:
+                Some(pb::BtcScriptConfig {
+                    config: Some(Config::Miniscript(miniscript)),
+                }),
+            ..
+        }) => {
+            let coin = BtcCoin::try_from(*coin)?;
+            let policy = super::policies::wrap_miniscript(miniscript);
+            Ok(Response::IsScriptConfigRegistered(
+                pb::BtcIsScriptConfigRegisteredResponse {
+                    is_registered: super::policies::get_name(coin, &policy)?.is_some(),
+                },
+            ))
+        }
 
         _ => Err(Error::InvalidInput),
     }
@@ -165,17 +218,361 @@ pub async fn process_register_script_config(
                 Err(_) => Err(Error::Generic),
             }
         }
-        // Only multisig and policy registration supported for now.
+        Some(pb::BtcScriptConfigRegistration {
+            coin,
+            script_config:
+                Some(pb::BtcScriptConfig {
+                    config: Some(Config::Musig2(musig2)),
+                }),
+            keypath,
+        }) => {
+            let coin = BtcCoin::try_from(*coin)?;
+            let coin_params = params::get(coin);
+            if !coin_params.taproot_support {
+                return Err(Error::InvalidInput);
+            }
+            let name = get_name(request).await?;
+            super::musig2::validate(musig2, keypath)?;
+            super::musig2::confirm_extended(title, coin_params, &name, musig2, keypath).await?;
+            let hash = super::musig2::get_hash(coin, musig2, keypath)?;
+            match bitbox02::memory::multisig_set_by_hash(&hash, &name) {
+                Ok(()) => {
+                    status::status("MuSig2 account\nregistered", true).await;
+                    Ok(Response::Success(pb::BtcSuccess {}))
+                }
+                Err(bitbox02::memory::MemoryError::MEMORY_ERR_DUPLICATE_NAME) => {
+                    Err(Error::Duplicate)
+                }
+                Err(_) => Err(Error::Generic),
+            }
+        }
+        Some(pb::BtcScriptConfigRegistration {
+            coin,
+            script_config:
+                Some(pb::BtcScriptConfig {
+                    config: Some(Config::Miniscript(miniscript)),
+                }),
+            ..
+        }) => {
+            let coin = BtcCoin::try_from(*coin)?;
+            let coin_params = params::get(coin);
+            let name = get_name(request).await?;
+            let policy = super::policies::wrap_miniscript(miniscript);
+            let parsed = super::policies::parse(&policy, coin)?;
+            parsed
+                .confirm(title, coin_params, &name, super::policies::Mode::Advanced)
+                .await?;
+            let hash = super::policies::get_hash(coin, &policy)?;
+            match bitbox02::memory::multisig_set_by_hash(&hash, &name) {
+                Ok(()) => {
+                    status::status("Policy\nregistered", true).await;
+                    Ok(Response::Success(pb::BtcSuccess {}))
+                }
+                Err(bitbox02::memory::MemoryError::MEMORY_ERR_DUPLICATE_NAME) => {
+                    Err(Error::Duplicate)
+                }
+                Err(_) => Err(Error::Generic),
+            }
+        }
+        Some(pb::BtcScriptConfigRegistration {
+            coin,
+            script_config:
+                Some(pb::BtcScriptConfig {
+                    config: Some(Config::SimpleType(simple_type)),
+                }),
+            keypath,
+        }) => {
+            let coin = BtcCoin::try_from(*coin)?;
+            let coin_params = params::get(coin);
+            let simple_type = pb::btc_script_config::SimpleType::try_from(*simple_type)?;
+            super::keypath::validate_account_simple(
+                keypath,
+                coin_params.bip44_coin,
+                simple_type,
+                coin_params.taproot_support,
+            )
+            .or(Err(Error::InvalidInput))?;
+            let name = get_name(request).await?;
+            confirm::confirm(&confirm::Params {
+                title,
+                body: &format!(
+                    "{}\naccount #{}",
+                    coin_params.name,
+                    keypath[2] - util::bip32::HARDENED + 1
+                ),
+                accept_is_nextarrow: true,
+                ..Default::default()
+            })
+            .await?;
+            confirm::confirm(&confirm::Params {
+                title,
+                body: &name,
+                scrollable: true,
+                longtouch: true,
+                ..Default::default()
+            })
+            .await?;
+            let hash = super::accounts::get_hash(coin, keypath);
+            match bitbox02::memory::multisig_set_by_hash(&hash, &name) {
+                Ok(()) => {
+                    status::status("Account\nregistered", true).await;
+                    Ok(Response::Success(pb::BtcSuccess {}))
+                }
+                Err(bitbox02::memory::MemoryError::MEMORY_ERR_DUPLICATE_NAME) => {
+                    Err(Error::Duplicate)
+                }
+                Err(_) => Err(Error::Generic),
+            }
+        }
+        // Only multisig, policy, MuSig2, miniscript and singlesig registration supported for now.
         _ => Err(Error::InvalidInput),
     }
 }
 
+pub async fn process_delete_script_config(
+    request: &pb::BtcDeleteScriptConfigRequest,
+) -> Result<Response, Error> {
+    let (hash, name): (Vec<u8>, String) = match request.registration.as_ref() {
+        Some(pb::BtcScriptConfigRegistration {
+            coin,
+            script_config:
+                Some(pb::BtcScriptConfig {
+                    config: Some(config),
+                }),
+            keypath,
+        }) => {
+            let coin = BtcCoin::try_from(*coin)?;
+            // Registrations of older firmware versions may be stored under the hash computed with
+            // unsorted xpubs, so a multisig config is looked up under both hashes.
+            let hashes: Vec<Vec<u8>> = match config {
+                Config::Multisig(multisig) => vec![
+                    super::multisig::get_hash(coin, multisig, SortXpubs::Yes, keypath)?,
+                    super::multisig::get_hash(coin, multisig, SortXpubs::No, keypath)?,
+                ],
+                Config::Policy(policy) => vec![super::policies::get_hash(coin, policy)?],
+                Config::Musig2(musig2) => vec![super::musig2::get_hash(coin, musig2, keypath)?],
+                Config::Miniscript(miniscript) => {
+                    let policy = super::policies::wrap_miniscript(miniscript);
+                    vec![super::policies::get_hash(coin, &policy)?]
+                }
+                Config::SimpleType(_) => vec![super::accounts::get_hash(coin, keypath)],
+            };
+            match hashes.into_iter().find_map(|hash| {
+                bitbox02::memory::multisig_get_by_hash(&hash).map(|name| (hash, name))
+            }) {
+                Some(entry) => entry,
+                None => return Err(Error::InvalidInput),
+            }
+        }
+        _ => return Err(Error::InvalidInput),
+    };
+    confirm::confirm(&confirm::Params {
+        title: "Remove",
+        body: &format!("Remove registered\naccount\n{}?", name),
+        scrollable: true,
+        accept_is_nextarrow: true,
+        ..Default::default()
+    })
+    .await?;
+    confirm::confirm(&confirm::Params {
+        title: "Warning",
+        body: "You will not be able\nto verify receive\naddresses of this\naccount anymore",
+        longtouch: true,
+        ..Default::default()
+    })
+    .await?;
+    bitbox02::memory::multisig_erase_by_hash(&hash).or(Err(Error::Generic))?;
+    status::status("Account\nremoved", true).await;
+    Ok(Response::Success(pb::BtcSuccess {}))
+}
+
+pub async fn process_rename_script_config(
+    request: &pb::BtcRenameScriptConfigRequest,
+) -> Result<Response, Error> {
+    if !util::name::validate(&request.name, bitbox02::memory::MULTISIG_NAME_MAX_LEN) {
+        return Err(Error::InvalidInput);
+    }
+    let old_name =
+        bitbox02::memory::multisig_get_by_hash(&request.hash).ok_or(Error::InvalidInput)?;
+    confirm::confirm(&confirm::Params {
+        title: "Rename",
+        body: &format!("Rename account\n{}\nto\n{}?", old_name, request.name),
+        scrollable: true,
+        longtouch: true,
+        ..Default::default()
+    })
+    .await?;
+    // Overwrites the name of the existing entry; the hash and thus the registration itself are
+    // unchanged.
+    match bitbox02::memory::multisig_set_by_hash(&request.hash, &request.name) {
+        Ok(()) => {
+            status::status("Account\nrenamed", true).await;
+            Ok(Response::Success(pb::BtcSuccess {}))
+        }
+        Err(bitbox02::memory::MemoryError::MEMORY_ERR_DUPLICATE_NAME) => Err(Error::Duplicate),
+        Err(_) => Err(Error::Generic),
+    }
+}
+
+/// Directory on the microSD card holding the multisig registrations backups.
+const REGISTRATIONS_BACKUP_DIR: &str = "multisig-backups";
+
+/// Returns the backup filename, which is tied to the seed so that backups of different seeds on
+/// the same card do not collide, and the AEAD cipher used to encrypt/decrypt the file. The cipher
+/// key is derived from the seed, so a backup can only be restored after the same seed has been
+/// restored to the device.
+fn registrations_backup_cipher() -> Result<(String, ChaCha20Poly1305), Error> {
+    let seed = bitbox02::keystore::copy_seed()?;
+    let filename = format!("{}.bin", crate::backup::id(&seed));
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&seed).unwrap();
+    mac.update(b"multisig-registrations-backup");
+    let key = mac.finalize().into_bytes();
+    Ok((filename, ChaCha20Poly1305::new(&key)))
+}
+
+/// Backs up the given multisig registrations to an encrypted file on the microSD card. The device
+/// registration table only stores hashes and names, so the host provides the full configurations;
+/// each one must match an existing registration, and the registered name is included in the backup
+/// so a restore can re-register it unchanged. A backup thus cannot contain a config that was never
+/// confirmed on this device.
+pub async fn process_backup_registrations(
+    request: &pb::BtcBackupRegistrationsRequest,
+) -> Result<Response, Error> {
+    if !bitbox02::sd::sdcard_inserted() {
+        return Err(Error::InvalidInput);
+    }
+    if request.registrations.is_empty() {
+        return Err(Error::InvalidInput);
+    }
+    let mut entries = Vec::new();
+    for registration in request.registrations.iter() {
+        match registration {
+            pb::BtcScriptConfigRegistration {
+                coin,
+                script_config:
+                    Some(pb::BtcScriptConfig {
+                        config: Some(Config::Multisig(multisig)),
+                    }),
+                keypath,
+            } => {
+                let coin = BtcCoin::try_from(*coin)?;
+                super::multisig::validate(multisig, keypath)?;
+                let hash = super::multisig::get_hash(coin, multisig, SortXpubs::Yes, keypath)?;
+                let name =
+                    bitbox02::memory::multisig_get_by_hash(&hash).ok_or(Error::InvalidInput)?;
+                entries.push(pb::btc_registrations_backup::Entry {
+                    name,
+                    registration: Some(registration.clone()),
+                });
+            }
+            _ => return Err(Error::InvalidInput),
+        }
+    }
+    confirm::confirm(&confirm::Params {
+        title: "Multisig backup",
+        body: &format!(
+            "Back up\n{}\nregistered multisig\naccounts?",
+            entries.len()
+        ),
+        longtouch: true,
+        ..Default::default()
+    })
+    .await?;
+
+    let mut ciphertext = pb::BtcRegistrationsBackup { entries }.encode_to_vec();
+    let (filename, cipher) = registrations_backup_cipher()?;
+    let nonce = {
+        let mut random = [0u8; 32];
+        bitbox02::random::mcu_32_bytes(&mut random);
+        let mut nonce = Nonce::default();
+        let nonce_len = nonce.len();
+        nonce.copy_from_slice(&random[..nonce_len]);
+        nonce
+    };
+    let tag = cipher
+        .encrypt_in_place_detached(&nonce, b"", &mut ciphertext)
+        .or(Err(Error::Generic))?;
+    let contents = [nonce.as_slice(), tag.as_slice(), &ciphertext].concat();
+    bitbox02::sd::write_bin(&filename, REGISTRATIONS_BACKUP_DIR, &contents)
+        .or(Err(Error::Generic))?;
+    // Verify the write before reporting success, like the seed backup does.
+    if bitbox02::sd::load_bin(&filename, REGISTRATIONS_BACKUP_DIR)
+        .or(Err(Error::Generic))?
+        .as_slice()
+        != contents.as_slice()
+    {
+        return Err(Error::Generic);
+    }
+    status::status("Backup created", true).await;
+    Ok(Response::Success(pb::BtcSuccess {}))
+}
+
+/// Restores multisig registrations from the encrypted microSD card backup. Every entry goes
+/// through the same confirmation flow as a fresh registration, so a substituted backup cannot
+/// register a config silently; a tampered file additionally fails the authenticated decryption.
+/// Entries which are already registered are skipped, so a restore on a partially set up device
+/// does not fail with a duplicate error.
+pub async fn process_restore_registrations() -> Result<Response, Error> {
+    if !bitbox02::sd::sdcard_inserted() {
+        return Err(Error::InvalidInput);
+    }
+    let (filename, cipher) = registrations_backup_cipher()?;
+    let contents = bitbox02::sd::load_bin(&filename, REGISTRATIONS_BACKUP_DIR)
+        .or(Err(Error::InvalidInput))?;
+    if contents.len() < core::mem::size_of::<Nonce>() + core::mem::size_of::<Tag>() {
+        return Err(Error::InvalidInput);
+    }
+    let (nonce, rest) = contents.split_at(core::mem::size_of::<Nonce>());
+    let (tag, ciphertext) = rest.split_at(core::mem::size_of::<Tag>());
+    let mut plaintext = ciphertext.to_vec();
+    cipher
+        .decrypt_in_place_detached(
+            Nonce::from_slice(nonce),
+            b"",
+            &mut plaintext,
+            Tag::from_slice(tag),
+        )
+        .or(Err(Error::InvalidInput))?;
+    let backup =
+        pb::BtcRegistrationsBackup::decode(plaintext.as_slice()).or(Err(Error::InvalidInput))?;
+    if backup.entries.is_empty() {
+        return Err(Error::InvalidInput);
+    }
+    for entry in backup.entries.iter() {
+        match entry.registration.as_ref() {
+            Some(pb::BtcScriptConfigRegistration {
+                coin,
+                script_config:
+                    Some(pb::BtcScriptConfig {
+                        config: Some(Config::Multisig(multisig)),
+                    }),
+                keypath,
+            }) => {
+                let coin = BtcCoin::try_from(*coin)?;
+                if super::multisig::get_name(coin, multisig, keypath)?.is_some() {
+                    continue;
+                }
+            }
+            _ => return Err(Error::InvalidInput),
+        }
+        process_register_script_config(&pb::BtcRegisterScriptConfigRequest {
+            registration: entry.registration.clone(),
+            name: entry.name.clone(),
+            xpub_type: XPubType::AutoElectrum as _,
+        })
+        .await?;
+    }
+    Ok(Response::Success(pb::BtcSuccess {}))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    use crate::bb02_async::block_on;
     use crate::bip32::parse_xpub;
-    use bitbox02::testing::{mock_memory, mock_unlocked_using_mnemonic};
+    use alloc::boxed::Box;
+    use bitbox02::testing::{mock, mock_memory, mock_sd, mock_unlocked_using_mnemonic, Data};
     use util::bip32::HARDENED;
 
     use pb::btc_script_config::{multisig::ScriptType, Multisig};
@@ -233,4 +630,567 @@ mod tests {
         test(SortXpubs::No);
         test(SortXpubs::Yes);
     }
+
+    #[test]
+    fn test_process_delete_script_config() {
+        mock_memory();
+
+        let keypath = &[48 + HARDENED, 0 + HARDENED, 10 + HARDENED, 2 + HARDENED];
+        let multisig = Multisig {
+            threshold: 1,
+            xpubs: vec![
+                parse_xpub("xpub6FMWuwbCA9KhoRzAMm63ZhLspk5S2DM5sePo8J8mQhcS1xyMbAqnc7Q7UescVEVFCS6qBMQLkEJWQ9Z3aDPgBov5nFUYxsJhwumsxM4npSo").unwrap(),
+                parse_xpub("xpub6EMfjyGVUvwhpc3WKN1zXhMFGKJGMaSBPqbja4tbGoYvRBSXeTBCaqrRDjcuGTcaY95JrrAnQvDG3pdQPdtnYUCugjeksHSbyZT7rq38VQF").unwrap(),
+            ],
+            our_xpub_index: 0,
+            script_type: ScriptType::P2wsh as _,
+        };
+        let hash =
+            super::super::multisig::get_hash(BtcCoin::Btc, &multisig, SortXpubs::Yes, keypath)
+                .unwrap();
+        bitbox02::memory::multisig_set_by_hash(&hash, "test name").unwrap();
+
+        let request = pb::BtcDeleteScriptConfigRequest {
+            registration: Some(pb::BtcScriptConfigRegistration {
+                coin: BtcCoin::Btc as _,
+                script_config: Some(pb::BtcScriptConfig {
+                    config: Some(Config::Multisig(multisig)),
+                }),
+                keypath: keypath.to_vec(),
+            }),
+        };
+        let is_registered_request = pb::BtcIsScriptConfigRegisteredRequest {
+            registration: request.registration.clone(),
+        };
+        let is_registered = || {
+            matches!(
+                process_is_script_config_registered(&is_registered_request),
+                Ok(Response::IsScriptConfigRegistered(
+                    pb::BtcIsScriptConfigRegisteredResponse {
+                        is_registered: true,
+                    },
+                ))
+            )
+        };
+
+        static mut CONFIRM_COUNTER: u32 = 0;
+
+        // User abort on the name confirmation: the registration is kept.
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|params| {
+                match unsafe {
+                    CONFIRM_COUNTER += 1;
+                    CONFIRM_COUNTER
+                } {
+                    1 => {
+                        assert_eq!(params.title, "Remove");
+                        assert_eq!(params.body, "Remove registered\naccount\ntest name?");
+                        false
+                    }
+                    _ => panic!("too many dialogs"),
+                }
+            })),
+            ..Default::default()
+        });
+        assert_eq!(
+            block_on(process_delete_script_config(&request)),
+            Err(Error::UserAbort)
+        );
+        assert!(is_registered());
+
+        // Confirmed: the registration is removed.
+        unsafe { CONFIRM_COUNTER = 0 }
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|params| {
+                match unsafe {
+                    CONFIRM_COUNTER += 1;
+                    CONFIRM_COUNTER
+                } {
+                    1 => true,
+                    2 => {
+                        assert_eq!(params.title, "Warning");
+                        assert!(params.longtouch);
+                        true
+                    }
+                    _ => panic!("too many dialogs"),
+                }
+            })),
+            ..Default::default()
+        });
+        assert_eq!(
+            block_on(process_delete_script_config(&request)),
+            Ok(Response::Success(pb::BtcSuccess {}))
+        );
+        assert!(!is_registered());
+
+        // Deleting a nonexistent registration fails cleanly, before any dialog.
+        assert_eq!(
+            block_on(process_delete_script_config(&request)),
+            Err(Error::InvalidInput)
+        );
+    }
+
+    #[test]
+    fn test_process_rename_script_config() {
+        mock_memory();
+
+        let hash = &[0xab; 32];
+        bitbox02::memory::multisig_set_by_hash(hash, "old name").unwrap();
+
+        static mut CONFIRM_COUNTER: u32 = 0;
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|params| {
+                match unsafe {
+                    CONFIRM_COUNTER += 1;
+                    CONFIRM_COUNTER
+                } {
+                    1 => {
+                        assert_eq!(params.title, "Rename");
+                        assert_eq!(params.body, "Rename account\nold name\nto\nnew name?");
+                        assert!(params.longtouch);
+                        true
+                    }
+                    _ => panic!("too many dialogs"),
+                }
+            })),
+            ..Default::default()
+        });
+        assert_eq!(
+            block_on(process_rename_script_config(&pb::BtcRenameScriptConfigRequest {
+                hash: hash.to_vec(),
+                name: "new name".into(),
+            })),
+            Ok(Response::Success(pb::BtcSuccess {}))
+        );
+        // The name record changed, the hash did not.
+        assert_eq!(
+            bitbox02::memory::multisig_get_by_hash(hash).as_deref(),
+            Some("new name")
+        );
+
+        // Invalid names are rejected before any dialog.
+        for invalid_name in ["", "name\nwith newline", "with\ttab", " leading space", &"x".repeat(bitbox02::memory::MULTISIG_NAME_MAX_LEN + 1)] {
+            assert_eq!(
+                block_on(process_rename_script_config(
+                    &pb::BtcRenameScriptConfigRequest {
+                        hash: hash.to_vec(),
+                        name: invalid_name.into(),
+                    }
+                )),
+                Err(Error::InvalidInput)
+            );
+        }
+
+        // Nonexistent registration.
+        assert_eq!(
+            block_on(process_rename_script_config(&pb::BtcRenameScriptConfigRequest {
+                hash: vec![0xcd; 32],
+                name: "some name".into(),
+            })),
+            Err(Error::InvalidInput)
+        );
+
+        // Renaming to the name of another registration is rejected.
+        bitbox02::memory::multisig_set_by_hash(&[0xef; 32], "taken").unwrap();
+        unsafe { CONFIRM_COUNTER = 0 }
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|_params| true)),
+            ..Default::default()
+        });
+        assert_eq!(
+            block_on(process_rename_script_config(&pb::BtcRenameScriptConfigRequest {
+                hash: hash.to_vec(),
+                name: "taken".into(),
+            })),
+            Err(Error::Duplicate)
+        );
+    }
+
+    #[test]
+    fn test_process_register_script_config() {
+        use bitbox02::ui::TrinaryChoice;
+
+        let make_request = || pb::BtcRegisterScriptConfigRequest {
+            registration: Some(pb::BtcScriptConfigRegistration {
+                coin: BtcCoin::Tbtc as _,
+                script_config: Some(pb::BtcScriptConfig {
+                    config: Some(Config::Multisig(Multisig {
+                        threshold: 1,
+                        xpubs: vec![
+                            parse_xpub("xpub6FMWuwbCA9KhoRzAMm63ZhLspk5S2DM5sePo8J8mQhcS1xyMbAqnc7Q7UescVEVFCS6qBMQLkEJWQ9Z3aDPgBov5nFUYxsJhwumsxM4npSo").unwrap(),
+                            // This xpub corresponds to the mocked seed below at m/48'/1'/0'/2'.
+                            parse_xpub("xpub6EMfjyGVUvwhpc3WKN1zXhMFGKJGMaSBPqbja4tbGoYvRBSXeTBCaqrRDjcuGTcaY95JrrAnQvDG3pdQPdtnYUCugjeksHSbyZT7rq38VQF").unwrap(),
+                        ],
+                        our_xpub_index: 1,
+                        script_type: ScriptType::P2wsh as _,
+                    })),
+                }),
+                keypath: vec![48 + HARDENED, 1 + HARDENED, 0 + HARDENED, 2 + HARDENED],
+            }),
+            name: "test name".into(),
+            xpub_type: XPubType::AutoElectrum as _,
+        };
+
+        static mut CONFIRM_COUNTER: u32 = 0;
+
+        // The user chooses to see the full details, paging through every cosigner xpub.
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|params| {
+                assert_eq!(params.title, "Register");
+                match unsafe { CONFIRM_COUNTER } {
+                    0 => assert_eq!(params.body, "1-of-2\nBTC Testnet multisig"),
+                    1 => assert_eq!(params.body, "test name"),
+                    2 => assert_eq!(params.body, "p2wsh\nat\nm/48'/1'/0'/2'"),
+                    3 => {
+                        assert!(params.body.starts_with("Cosigner 1/2: Vpub"));
+                        assert!(!params.longtouch);
+                    }
+                    4 => {
+                        assert!(params.body.starts_with("Cosigner 2/2 (this device): Vpub"));
+                        assert!(params.longtouch);
+                    }
+                    _ => panic!("too many dialogs"),
+                }
+                unsafe { CONFIRM_COUNTER += 1 }
+                true
+            })),
+            ui_trinary_choice_create: Some(Box::new(|message, label_left, label_middle, label_right| {
+                assert_eq!(message, "Show full details?");
+                assert_eq!(label_left, "Skip");
+                assert_eq!(label_middle, "");
+                assert_eq!(label_right, "Show");
+                TrinaryChoice::TRINARY_CHOICE_RIGHT
+            })),
+            ..Default::default()
+        });
+        mock_memory();
+        mock_unlocked_using_mnemonic(
+            "sudden tenant fault inject concert weather maid people chunk youth stumble grit",
+            "",
+        );
+        assert_eq!(
+            block_on(process_register_script_config(&make_request())),
+            Ok(Response::Success(pb::BtcSuccess {}))
+        );
+        assert_eq!(unsafe { CONFIRM_COUNTER }, 5);
+
+        // The user skips the details and confirms the registration right away.
+        unsafe { CONFIRM_COUNTER = 0 }
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|params| {
+                assert_eq!(params.title, "Register");
+                match unsafe { CONFIRM_COUNTER } {
+                    0 => assert_eq!(params.body, "1-of-2\nBTC Testnet multisig"),
+                    1 => assert_eq!(params.body, "test name"),
+                    2 => assert_eq!(params.body, "p2wsh\nat\nm/48'/1'/0'/2'"),
+                    3 => {
+                        assert_eq!(params.body, "Register account?");
+                        assert!(params.longtouch);
+                    }
+                    _ => panic!("too many dialogs"),
+                }
+                unsafe { CONFIRM_COUNTER += 1 }
+                true
+            })),
+            ui_trinary_choice_create: Some(Box::new(|_, _, _, _| {
+                TrinaryChoice::TRINARY_CHOICE_LEFT
+            })),
+            ..Default::default()
+        });
+        mock_memory();
+        mock_unlocked_using_mnemonic(
+            "sudden tenant fault inject concert weather maid people chunk youth stumble grit",
+            "",
+        );
+        assert_eq!(
+            block_on(process_register_script_config(&make_request())),
+            Ok(Response::Success(pb::BtcSuccess {}))
+        );
+        assert_eq!(unsafe { CONFIRM_COUNTER }, 4);
+
+        // A cosigner xpub at a nonstandard depth is accepted behind an additional warning listing
+        // each cosigner's depth.
+        unsafe { CONFIRM_COUNTER = 0 }
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|params| {
+                match unsafe { CONFIRM_COUNTER } {
+                    0 => assert_eq!(params.body, "1-of-2\nBTC Testnet multisig"),
+                    1 => assert_eq!(params.body, "test name"),
+                    2 => assert_eq!(params.body, "p2wsh\nat\nm/48'/1'/0'/2'"),
+                    3 => {
+                        assert_eq!(params.title, "Warning");
+                        assert_eq!(
+                            params.body,
+                            "Nonstandard\ncosigner depths:\nCosigner 1: depth 3\nCosigner 2: depth 4"
+                        );
+                    }
+                    4 => {
+                        assert_eq!(params.body, "Register account?");
+                        assert!(params.longtouch);
+                    }
+                    _ => panic!("too many dialogs"),
+                }
+                unsafe { CONFIRM_COUNTER += 1 }
+                true
+            })),
+            ui_trinary_choice_create: Some(Box::new(|_, _, _, _| {
+                TrinaryChoice::TRINARY_CHOICE_LEFT
+            })),
+            ..Default::default()
+        });
+        mock_memory();
+        mock_unlocked_using_mnemonic(
+            "sudden tenant fault inject concert weather maid people chunk youth stumble grit",
+            "",
+        );
+        let mut request = make_request();
+        match request
+            .registration
+            .as_mut()
+            .unwrap()
+            .script_config
+            .as_mut()
+            .unwrap()
+            .config
+            .as_mut()
+            .unwrap()
+        {
+            Config::Multisig(multisig) => multisig.xpubs[0].depth = vec![3],
+            _ => panic!("unexpected config"),
+        }
+        assert_eq!(
+            block_on(process_register_script_config(&request)),
+            Ok(Response::Success(pb::BtcSuccess {}))
+        );
+        assert_eq!(unsafe { CONFIRM_COUNTER }, 5);
+    }
+
+    #[test]
+    fn test_process_register_script_config_singlesig() {
+        let keypath = vec![84 + HARDENED, 0 + HARDENED, 0 + HARDENED];
+        let make_registration = |keypath: &[u32]| {
+            Some(pb::BtcScriptConfigRegistration {
+                coin: BtcCoin::Btc as _,
+                script_config: Some(pb::BtcScriptConfig {
+                    config: Some(Config::SimpleType(
+                        pb::btc_script_config::SimpleType::P2wpkh as _,
+                    )),
+                }),
+                keypath: keypath.to_vec(),
+            })
+        };
+        let request = pb::BtcRegisterScriptConfigRequest {
+            registration: make_registration(&keypath),
+            name: "salary".into(),
+            xpub_type: XPubType::AutoElectrum as _,
+        };
+
+        static mut CONFIRM_COUNTER: u32 = 0;
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|params| {
+                assert_eq!(params.title, "Register");
+                match unsafe { CONFIRM_COUNTER } {
+                    0 => assert_eq!(params.body, "Bitcoin\naccount #1"),
+                    1 => {
+                        assert_eq!(params.body, "salary");
+                        assert!(params.longtouch);
+                    }
+                    _ => panic!("too many dialogs"),
+                }
+                unsafe { CONFIRM_COUNTER += 1 }
+                true
+            })),
+            ..Default::default()
+        });
+        mock_memory();
+        assert_eq!(
+            block_on(process_register_script_config(&request)),
+            Ok(Response::Success(pb::BtcSuccess {}))
+        );
+        assert_eq!(unsafe { CONFIRM_COUNTER }, 2);
+        assert_eq!(
+            process_is_script_config_registered(&pb::BtcIsScriptConfigRegisteredRequest {
+                registration: make_registration(&keypath),
+            }),
+            Ok(Response::IsScriptConfigRegistered(
+                pb::BtcIsScriptConfigRegisteredResponse {
+                    is_registered: true,
+                },
+            ))
+        );
+        assert_eq!(
+            super::super::accounts::get_name(BtcCoin::Btc, &keypath).as_deref(),
+            Some("salary")
+        );
+
+        // A keypath not matching the script type is rejected before any dialog.
+        assert_eq!(
+            block_on(process_register_script_config(
+                &pb::BtcRegisterScriptConfigRequest {
+                    registration: make_registration(&[49 + HARDENED, 0 + HARDENED, 0 + HARDENED]),
+                    name: "salary".into(),
+                    xpub_type: XPubType::AutoElectrum as _,
+                }
+            )),
+            Err(Error::InvalidInput)
+        );
+
+        // Deleting removes the name again.
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|_params| true)),
+            ..Default::default()
+        });
+        assert_eq!(
+            block_on(process_delete_script_config(
+                &pb::BtcDeleteScriptConfigRequest {
+                    registration: make_registration(&keypath),
+                }
+            )),
+            Ok(Response::Success(pb::BtcSuccess {}))
+        );
+        assert_eq!(super::super::accounts::get_name(BtcCoin::Btc, &keypath), None);
+    }
+
+    #[test]
+    fn test_process_backup_restore_registrations() {
+        use bitbox02::ui::TrinaryChoice;
+
+        let keypath = vec![48 + HARDENED, 1 + HARDENED, 0 + HARDENED, 2 + HARDENED];
+        let multisig = Multisig {
+            threshold: 1,
+            xpubs: vec![
+                parse_xpub("xpub6FMWuwbCA9KhoRzAMm63ZhLspk5S2DM5sePo8J8mQhcS1xyMbAqnc7Q7UescVEVFCS6qBMQLkEJWQ9Z3aDPgBov5nFUYxsJhwumsxM4npSo").unwrap(),
+                // This xpub corresponds to the mocked seed below at m/48'/1'/0'/2'.
+                parse_xpub("xpub6EMfjyGVUvwhpc3WKN1zXhMFGKJGMaSBPqbja4tbGoYvRBSXeTBCaqrRDjcuGTcaY95JrrAnQvDG3pdQPdtnYUCugjeksHSbyZT7rq38VQF").unwrap(),
+            ],
+            our_xpub_index: 1,
+            script_type: ScriptType::P2wsh as _,
+        };
+        let registration = pb::BtcScriptConfigRegistration {
+            coin: BtcCoin::Tbtc as _,
+            script_config: Some(pb::BtcScriptConfig {
+                config: Some(Config::Multisig(multisig.clone())),
+            }),
+            keypath: keypath.clone(),
+        };
+        let backup_request = pb::BtcBackupRegistrationsRequest {
+            registrations: vec![registration.clone()],
+        };
+
+        mock_sd();
+        mock_memory();
+        mock_unlocked_using_mnemonic(
+            "sudden tenant fault inject concert weather maid people chunk youth stumble grit",
+            "",
+        );
+        let hash =
+            super::super::multisig::get_hash(BtcCoin::Tbtc, &multisig, SortXpubs::Yes, &keypath)
+                .unwrap();
+        bitbox02::memory::multisig_set_by_hash(&hash, "test name").unwrap();
+
+        // No sd card inserted.
+        mock(Data {
+            sdcard_inserted: Some(false),
+            ..Default::default()
+        });
+        assert_eq!(
+            block_on(process_backup_registrations(&backup_request)),
+            Err(Error::InvalidInput)
+        );
+
+        // A config which is not registered on the device cannot be backed up.
+        mock(Data {
+            sdcard_inserted: Some(true),
+            ..Default::default()
+        });
+        let mut unregistered = registration.clone();
+        unregistered.coin = BtcCoin::Btc as _;
+        unregistered.keypath[1] = 0 + HARDENED;
+        assert_eq!(
+            block_on(process_backup_registrations(
+                &pb::BtcBackupRegistrationsRequest {
+                    registrations: vec![unregistered],
+                }
+            )),
+            Err(Error::InvalidInput)
+        );
+
+        // Backup created after user confirmation.
+        mock(Data {
+            sdcard_inserted: Some(true),
+            ui_confirm_create: Some(Box::new(|params| {
+                assert_eq!(params.title, "Multisig backup");
+                assert_eq!(params.body, "Back up\n1\nregistered multisig\naccounts?");
+                assert!(params.longtouch);
+                true
+            })),
+            ..Default::default()
+        });
+        assert_eq!(
+            block_on(process_backup_registrations(&backup_request)),
+            Ok(Response::Success(pb::BtcSuccess {}))
+        );
+
+        // Wipe the registration table, like after a factory reset followed by a seed restore.
+        mock_memory();
+        assert!(bitbox02::memory::multisig_get_by_hash(&hash).is_none());
+
+        // Restoring replays the full registration flow for the entry, using the backed up name.
+        static mut CONFIRM_COUNTER: u32 = 0;
+        mock(Data {
+            sdcard_inserted: Some(true),
+            ui_confirm_create: Some(Box::new(|params| {
+                assert_eq!(params.title, "Register");
+                match unsafe { CONFIRM_COUNTER } {
+                    0 => assert_eq!(params.body, "1-of-2\nBTC Testnet multisig"),
+                    1 => assert_eq!(params.body, "test name"),
+                    2 => assert_eq!(params.body, "p2wsh\nat\nm/48'/1'/0'/2'"),
+                    3 => {
+                        assert_eq!(params.body, "Register account?");
+                        assert!(params.longtouch);
+                    }
+                    _ => panic!("too many dialogs"),
+                }
+                unsafe { CONFIRM_COUNTER += 1 }
+                true
+            })),
+            ui_trinary_choice_create: Some(Box::new(|_, _, _, _| TrinaryChoice::TRINARY_CHOICE_LEFT)),
+            ..Default::default()
+        });
+        assert_eq!(
+            block_on(process_restore_registrations()),
+            Ok(Response::Success(pb::BtcSuccess {}))
+        );
+        assert_eq!(unsafe { CONFIRM_COUNTER }, 4);
+        assert_eq!(
+            bitbox02::memory::multisig_get_by_hash(&hash).as_deref(),
+            Some("test name")
+        );
+
+        // Restoring again skips the already registered entry without any dialog.
+        mock(Data {
+            sdcard_inserted: Some(true),
+            ..Default::default()
+        });
+        assert_eq!(
+            block_on(process_restore_registrations()),
+            Ok(Response::Success(pb::BtcSuccess {}))
+        );
+
+        // A tampered backup file fails the authenticated decryption.
+        mock_memory();
+        mock(Data {
+            sdcard_inserted: Some(true),
+            ..Default::default()
+        });
+        let (filename, _) = registrations_backup_cipher().unwrap();
+        let mut contents = bitbox02::sd::load_bin(&filename, REGISTRATIONS_BACKUP_DIR).unwrap();
+        contents[0] ^= 1;
+        bitbox02::sd::write_bin(&filename, REGISTRATIONS_BACKUP_DIR, &contents).unwrap();
+        assert_eq!(
+            block_on(process_restore_registrations()),
+            Err(Error::InvalidInput)
+        );
+        assert!(bitbox02::memory::multisig_get_by_hash(&hash).is_none());
+    }
 }
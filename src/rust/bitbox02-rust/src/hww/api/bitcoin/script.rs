@@ -22,6 +22,10 @@ pub const OP_DUP: u8 = 0x76;
 pub const OP_EQUALVERIFY: u8 = 0x88;
 pub const OP_CHECKSIG: u8 = 0xac;
 pub const OP_EQUAL: u8 = 0x87;
+#[allow(dead_code)] // used by the pkscript classifier below
+pub const OP_16: u8 = 0x60;
+#[allow(dead_code)] // used by the pkscript classifier below
+pub const OP_RETURN: u8 = 0x6a;
 
 /// Serialize a number in the VarInt encoding.
 /// https://en.bitcoin.it/wiki/Protocol_documentation#Variable_length_integer
@@ -51,6 +55,90 @@ pub fn push_data(v: &mut Vec<u8>, data: &[u8]) {
     v.extend_from_slice(data);
 }
 
+/// Parses a number in the VarInt encoding, the inverse of `serialize_varint`. Returns the value
+/// and the number of bytes consumed. Returns `None` if the input is truncated or the value is not
+/// canonically encoded (i.e. not using the smallest possible encoding).
+#[allow(dead_code)] // not used in production code yet
+pub fn parse_varint(data: &[u8]) -> Option<(u64, usize)> {
+    let (value, size) = match data.first()? {
+        value @ 0..=0xFC => (*value as u64, 1),
+        0xFD => (
+            u16::from_le_bytes(data.get(1..3)?.try_into().unwrap()) as u64,
+            3,
+        ),
+        0xFE => (
+            u32::from_le_bytes(data.get(1..5)?.try_into().unwrap()) as u64,
+            5,
+        ),
+        0xFF => (u64::from_le_bytes(data.get(1..9)?.try_into().unwrap()), 9),
+    };
+    let min = match size {
+        3 => 0xFD,
+        5 => 0x10000,
+        9 => 0x100000000,
+        _ => 0,
+    };
+    if value < min {
+        return None;
+    }
+    Some((value, size))
+}
+
+/// A recognized scriptPubKey type. The contained slices point into the original script.
+#[allow(dead_code)] // not used in production code yet
+#[derive(Debug, PartialEq, Eq)]
+pub enum PkScript<'a> {
+    /// Pay-to-pubkey-hash. Contains the 20 byte pubkey hash.
+    P2pkh(&'a [u8]),
+    /// Pay-to-script-hash. Contains the 20 byte script hash.
+    P2sh(&'a [u8]),
+    /// Witness v0 pay-to-witness-pubkey-hash. Contains the 20 byte pubkey hash.
+    P2wpkh(&'a [u8]),
+    /// Witness v0 pay-to-witness-script-hash. Contains the 32 byte script hash.
+    P2wsh(&'a [u8]),
+    /// Witness v1 pay-to-taproot. Contains the 32 byte x-only output pubkey.
+    P2tr(&'a [u8]),
+    /// OP_RETURN data output. Contains the raw bytes following OP_RETURN.
+    OpReturn(&'a [u8]),
+    /// Witness program of a future version (2-16). Contains the version and the witness program.
+    FutureWitness(u8, &'a [u8]),
+}
+
+/// Classifies a scriptPubKey, e.g. from an output of a previous transaction. Returns `None` if the
+/// script is not one of the recognized output types, including witness programs that are invalid
+/// per BIP-141 (v0 with a program size other than 20 or 32 bytes).
+#[allow(dead_code)] // not used in production code yet
+pub fn parse_pkscript(script: &[u8]) -> Option<PkScript<'_>> {
+    match script {
+        [OP_DUP, OP_HASH160, 0x14, payload @ .., OP_EQUALVERIFY, OP_CHECKSIG]
+            if payload.len() == 20 =>
+        {
+            Some(PkScript::P2pkh(payload))
+        }
+        [OP_HASH160, 0x14, payload @ .., OP_EQUAL] if payload.len() == 20 => {
+            Some(PkScript::P2sh(payload))
+        }
+        [OP_RETURN, payload @ ..] => Some(PkScript::OpReturn(payload)),
+        [version @ (OP_0 | OP_1..=OP_16), push_len, program @ ..]
+            if *push_len as usize == program.len() && (2..=40).contains(&program.len()) =>
+        {
+            let version = if *version == OP_0 {
+                0
+            } else {
+                version - OP_1 + 1
+            };
+            match (version, program.len()) {
+                (0, 20) => Some(PkScript::P2wpkh(program)),
+                (0, 32) => Some(PkScript::P2wsh(program)),
+                (0, _) => None,
+                (1, 32) => Some(PkScript::P2tr(program)),
+                _ => Some(PkScript::FutureWitness(version, program)),
+            }
+        }
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -112,6 +200,109 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_varint() {
+        let values = [
+            0,
+            1,
+            2,
+            0x7f,
+            0x80,
+            0xfc,
+            0xfd,
+            0xff,
+            0x100,
+            0xffff,
+            0x10000,
+            0xffffffff,
+            0x100000000,
+            u64::MAX,
+        ];
+        for value in values {
+            let serialized = serialize_varint(value);
+            // Round trip.
+            assert_eq!(parse_varint(&serialized), Some((value, serialized.len())));
+            // Trailing bytes are not consumed.
+            let mut extended = serialized.clone();
+            extended.push(0xaa);
+            assert_eq!(parse_varint(&extended), Some((value, serialized.len())));
+            // Truncated input.
+            assert_eq!(parse_varint(&serialized[..serialized.len() - 1]), None);
+        }
+        // Non-canonical encodings are rejected.
+        assert_eq!(parse_varint(b"\xfd\xfc\x00"), None);
+        assert_eq!(parse_varint(b"\xfe\xff\xff\x00\x00"), None);
+        assert_eq!(parse_varint(b"\xff\xff\xff\xff\xff\x00\x00\x00\x00"), None);
+    }
+
+    #[test]
+    fn test_parse_pkscript() {
+        let hash20 = [0x11; 20];
+        let hash32 = [0x22; 32];
+
+        let mut p2pkh = vec![OP_DUP, OP_HASH160, 0x14];
+        p2pkh.extend_from_slice(&hash20);
+        p2pkh.extend_from_slice(&[OP_EQUALVERIFY, OP_CHECKSIG]);
+        assert_eq!(parse_pkscript(&p2pkh), Some(PkScript::P2pkh(&hash20[..])));
+
+        let mut p2sh = vec![OP_HASH160, 0x14];
+        p2sh.extend_from_slice(&hash20);
+        p2sh.push(OP_EQUAL);
+        assert_eq!(parse_pkscript(&p2sh), Some(PkScript::P2sh(&hash20[..])));
+
+        let mut p2wpkh = vec![OP_0, 0x14];
+        p2wpkh.extend_from_slice(&hash20);
+        assert_eq!(parse_pkscript(&p2wpkh), Some(PkScript::P2wpkh(&hash20[..])));
+
+        let mut p2wsh = vec![OP_0, 0x20];
+        p2wsh.extend_from_slice(&hash32);
+        assert_eq!(parse_pkscript(&p2wsh), Some(PkScript::P2wsh(&hash32[..])));
+
+        let mut p2tr = vec![OP_1, 0x20];
+        p2tr.extend_from_slice(&hash32);
+        assert_eq!(parse_pkscript(&p2tr), Some(PkScript::P2tr(&hash32[..])));
+
+        // OP_RETURN, with and without data.
+        assert_eq!(parse_pkscript(&[OP_RETURN]), Some(PkScript::OpReturn(b"")));
+        assert_eq!(
+            parse_pkscript(b"\x6a\x04data"),
+            Some(PkScript::OpReturn(b"\x04data"))
+        );
+
+        // Future witness versions.
+        let mut v2 = vec![OP_1 + 1, 0x14];
+        v2.extend_from_slice(&hash20);
+        assert_eq!(
+            parse_pkscript(&v2),
+            Some(PkScript::FutureWitness(2, &hash20[..]))
+        );
+        assert_eq!(
+            parse_pkscript(b"\x60\x02\xab\xcd"),
+            Some(PkScript::FutureWitness(16, b"\xab\xcd"))
+        );
+
+        // Witness v0 with an invalid program size.
+        let mut v0_invalid = vec![OP_0, 25];
+        v0_invalid.extend_from_slice(&[0x33; 25]);
+        assert_eq!(parse_pkscript(&v0_invalid), None);
+
+        // Truncated and malformed scripts.
+        assert_eq!(parse_pkscript(b""), None);
+        assert_eq!(parse_pkscript(&p2pkh[..p2pkh.len() - 1]), None);
+        assert_eq!(parse_pkscript(&p2sh[..p2sh.len() - 1]), None);
+        assert_eq!(parse_pkscript(&p2wpkh[..p2wpkh.len() - 1]), None);
+        assert_eq!(parse_pkscript(&p2tr[..p2tr.len() - 1]), None);
+        // Push length does not match the remaining script.
+        assert_eq!(parse_pkscript(b"\x00\x15\x11\x11"), None);
+        // Witness program too short/too long.
+        assert_eq!(parse_pkscript(b"\x00\x01\xaa"), None);
+        let mut too_long = vec![OP_1, 41];
+        too_long.extend_from_slice(&[0x44; 41]);
+        assert_eq!(parse_pkscript(&too_long), None);
+        // Garbage.
+        assert_eq!(parse_pkscript(&[0xaa; 30]), None);
+    }
+
     #[test]
     fn test_push_data() {
         assert_eq!(
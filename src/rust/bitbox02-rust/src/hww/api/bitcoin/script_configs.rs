@@ -13,7 +13,7 @@
 // limitations under the License.
 
 use super::pb;
-use pb::btc_script_config::{Multisig, SimpleType};
+use pb::btc_script_config::{Multisig, Musig2, SimpleType};
 
 use super::policies::ParsedPolicy;
 
@@ -22,6 +22,7 @@ pub enum ValidatedScriptConfig<'a> {
     SimpleType(SimpleType),
     Multisig(&'a Multisig),
     Policy(ParsedPolicy<'a>),
+    Musig2(&'a Musig2),
 }
 
 /// Parsed and validated form of `pb::BtcScriptConfigWithKeypath`.
@@ -12,6 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use alloc::string::String;
 use alloc::vec::Vec;
 
 use sha2::{Digest, Sha256};
@@ -19,6 +20,9 @@ use sha2::{Digest, Sha256};
 use super::pb;
 use super::Error;
 
+use super::common;
+use super::script::serialize_varint;
+
 use pb::btc_script_config::{Config, SimpleType};
 use pb::BtcCoin;
 
@@ -27,16 +31,392 @@ use pb::btc_response::Response;
 use bitbox02::keystore;
 
 use crate::workflow::{confirm, verify_message};
+use crate::xpubcache::Bip32XpubCache;
 
 const MAX_MESSAGE_SIZE: usize = 1024;
 
+/// Maximum size of a message streamed in chunks. Larger than `MAX_MESSAGE_SIZE`, as the message
+/// is never held in RAM in full; only the incremental hashes and a short excerpt are kept.
+const STREAM_MAX_MESSAGE_SIZE: usize = 64 * 1024;
+/// Number of bytes of the start and the end of a streamed message retained for review.
+const STREAM_REVIEW_SIZE: usize = 32;
+
+/// Produces the classic 65 byte base64-ready recoverable signature used by Electrum and other
+/// verifiers: a header byte encoding the recovery id and the address type, followed by the compact
+/// signature. The header offsets follow BIP-137, so verifiers can recover the pubkey directly
+/// instead of brute-forcing the recovery id.
+fn electrum_signature(simple_type: SimpleType, sign_result: &keystore::SignResult) -> Vec<u8> {
+    // BIP-137 header bases: 27 for uncompressed and 31 for compressed P2PKH, 35 for P2WPKH-P2SH,
+    // 39 for P2WPKH. We only ever use compressed pubkeys.
+    let header_base: u8 = match simple_type {
+        SimpleType::P2pkh => 31,
+        SimpleType::P2wpkhP2sh => 35,
+        // Only ECDSA script types reach this; taproot messages use Schnorr signatures.
+        _ => 39,
+    };
+    let mut result: Vec<u8> = vec![header_base + sign_result.recid];
+    result.extend_from_slice(&sign_result.signature);
+    result
+}
+
+/// Computes the txid of the virtual `to_spend` transaction of BIP-322, which commits to the
+/// message (as a BIP-340 tagged hash in the input's scriptSig) and to the address whose ownership
+/// is being proven (as the pkScript of the single output). The txid is returned in the internal
+/// byte order, ready to be used as an outpoint hash.
+///
+/// https://github.com/bitcoin/bips/blob/master/bip-0322.mediawiki#full
+fn bip322_txid_to_spend(pk_script: &[u8], msg: &[u8]) -> [u8; 32] {
+    let msg_hash: [u8; 32] = {
+        let mut ctx = super::bip341::tagged_hasher(b"BIP0322-signed-message");
+        ctx.update(msg);
+        ctx.finalize().into()
+    };
+    let mut tx: Vec<u8> = Vec::new();
+    // nVersion = 0.
+    tx.extend(0u32.to_le_bytes());
+    // One input spending the null outpoint, scriptSig = OP_0 PUSH32 <tagged message hash>,
+    // nSequence = 0.
+    tx.extend(serialize_varint(1));
+    tx.extend([0u8; 32]);
+    tx.extend(0xffffffffu32.to_le_bytes());
+    tx.extend(serialize_varint(34));
+    tx.extend(b"\x00\x20");
+    tx.extend(msg_hash);
+    tx.extend(0u32.to_le_bytes());
+    // One output of value 0 with the pkScript of the address.
+    tx.extend(serialize_varint(1));
+    tx.extend(0u64.to_le_bytes());
+    tx.extend(serialize_varint(pk_script.len() as u64));
+    tx.extend(pk_script);
+    // nLockTime = 0.
+    tx.extend(0u32.to_le_bytes());
+    Sha256::digest(Sha256::digest(tx)).into()
+}
+
+/// Serializes a witness stack as in a segwit transaction: the number of stack items followed by
+/// the length-prefixed items.
+fn serialize_witness(stack: &[&[u8]]) -> Vec<u8> {
+    let mut result: Vec<u8> = serialize_varint(stack.len() as u64);
+    for item in stack.iter() {
+        result.extend(serialize_varint(item.len() as u64));
+        result.extend(*item);
+    }
+    result
+}
+
+/// Produces a BIP-322 "simple" proof: the witness satisfying the single input of the virtual
+/// `to_sign` transaction, which spends the `to_spend` output committing to the message and the
+/// address. `to_sign` has nVersion, nLockTime and nSequence all 0 and a single OP_RETURN output
+/// of value 0.
+///
+/// The message and address confirmations have already happened at this point.
+async fn sign_bip322(
+    coin: BtcCoin,
+    simple_type: SimpleType,
+    keypath: &[u32],
+    address: String,
+    request: &pb::BtcSignMessageRequest,
+) -> Result<Response, Error> {
+    let coin_params = super::params::get(coin);
+    let mut xpub_cache = Bip32XpubCache::new();
+    let payload = common::Payload::from_simple(&mut xpub_cache, coin_params, simple_type, keypath)?;
+    let pk_script = payload.pk_script(coin_params)?;
+    let txid_to_spend = bip322_txid_to_spend(&pk_script, &request.msg);
+
+    // Serialization of the single to_sign output: an OP_RETURN of value 0.
+    let to_sign_output: &[u8] = b"\x00\x00\x00\x00\x00\x00\x00\x00\x01\x6a";
+
+    match simple_type {
+        SimpleType::P2wpkh => {
+            let mut outpoint: Vec<u8> = txid_to_spend.to_vec();
+            outpoint.extend(0u32.to_le_bytes());
+            // See https://github.com/bitcoin/bips/blob/master/bip-0143.mediawiki#specification,
+            // item 5: for P2WPKH, the script code is the P2PKH script of the pubkey hash.
+            let mut sighash_script: Vec<u8> = Vec::new();
+            sighash_script.extend_from_slice(b"\x76\xa9\x14");
+            sighash_script.extend_from_slice(&payload.data);
+            sighash_script.extend_from_slice(b"\x88\xac");
+            let sighash = super::bip143::sighash(&super::bip143::Args {
+                version: 0,
+                hash_prevouts: Sha256::digest(Sha256::digest(&outpoint)).into(),
+                hash_sequence: Sha256::digest(Sha256::digest(0u32.to_le_bytes())).into(),
+                outpoint_hash: txid_to_spend,
+                outpoint_index: 0,
+                sighash_script: &sighash_script,
+                prevout_value: 0,
+                sequence: 0,
+                hash_outputs: Sha256::digest(Sha256::digest(to_sign_output)).into(),
+                locktime: 0,
+                sighash_flags: 1, // SIGHASH_ALL
+            });
+
+            let host_nonce = match request.host_nonce_commitment {
+                // Engage in the anti-klepto protocol if the host sends a host nonce commitment.
+                Some(pb::AntiKleptoHostNonceCommitment { ref commitment }) => {
+                    let signer_commitment = keystore::secp256k1_nonce_commit(
+                        keypath,
+                        &sighash,
+                        commitment
+                            .as_slice()
+                            .try_into()
+                            .or(Err(Error::InvalidInput))?,
+                    )?;
+                    super::antiklepto_get_host_nonce(signer_commitment).await?
+                }
+                None => [0; 32],
+            };
+
+            let sign_result = keystore::secp256k1_sign(keypath, &sighash, &host_nonce)?;
+            let mut signature: Vec<u8> =
+                bitcoin::secp256k1::ecdsa::Signature::from_compact(&sign_result.signature)
+                    .or(Err(Error::Generic))?
+                    .serialize_der()
+                    .to_vec();
+            signature.push(1); // SIGHASH_ALL
+            let pubkey = xpub_cache.get_xpub(keypath)?.public_key().to_vec();
+            Ok(Response::SignMessage(pb::BtcSignMessageResponse {
+                signature: vec![],
+                bip322_proof: serialize_witness(&[&signature, &pubkey]),
+                address,
+                electrum_signature: vec![],
+            }))
+        }
+        SimpleType::P2tr => {
+            // The anti-klepto protocol is not supported for Schnorr signatures.
+            if request.host_nonce_commitment.is_some() {
+                return Err(Error::InvalidInput);
+            }
+            let mut prefixed_pk_script: Vec<u8> = serialize_varint(pk_script.len() as u64);
+            prefixed_pk_script.extend(&pk_script);
+            let mut outpoint: Vec<u8> = txid_to_spend.to_vec();
+            outpoint.extend(0u32.to_le_bytes());
+            let sighash = super::bip341::sighash(&super::bip341::Args {
+                version: 0,
+                locktime: 0,
+                hash_prevouts: Sha256::digest(&outpoint).into(),
+                hash_amounts: Sha256::digest(0u64.to_le_bytes()).into(),
+                hash_scriptpubkeys: Sha256::digest(&prefixed_pk_script).into(),
+                hash_sequences: Sha256::digest(0u32.to_le_bytes()).into(),
+                hash_outputs: Sha256::digest(to_sign_output).into(),
+                input_index: 0,
+                tapleaf_hash: None,
+            });
+            let signature = keystore::secp256k1_schnorr_bip86_sign(keypath, &sighash)?;
+            Ok(Response::SignMessage(pb::BtcSignMessageResponse {
+                signature: vec![],
+                bip322_proof: serialize_witness(&[&signature]),
+                address,
+                electrum_signature: vec![],
+            }))
+        }
+        _ => Err(Error::InvalidInput),
+    }
+}
+
+/// Signs a message streamed in chunks, modeled on the prevtx streaming in signtx: the init
+/// request announces the total size, and the host delivers each chunk in response to a
+/// `BTCSuccess` ack. The chunks are hashed incrementally into the standard message envelope, so
+/// the full message is never held in RAM. In place of the full message, the user reviews the
+/// rolling sha256 of the message plus its first and last `STREAM_REVIEW_SIZE` bytes.
+async fn sign_streamed(
+    simple_type: SimpleType,
+    keypath: &[u32],
+    address: String,
+    request: &pb::BtcSignMessageRequest,
+) -> Result<Response, Error> {
+    let total_size = request.msg_streamed_size as usize;
+
+    // See `process()`: the envelope of the widespread legacy message format, hashed incrementally
+    // as the chunks arrive.
+    let mut envelope_hasher = Sha256::new();
+    envelope_hasher.update(b"\x18Bitcoin Signed Message:\n");
+    envelope_hasher.update(serialize_varint(total_size as u64));
+    // Rolling hash of the raw message, shown to the user for review.
+    let mut msg_hasher = Sha256::new();
+    let mut head: Vec<u8> = Vec::new();
+    let mut tail: Vec<u8> = Vec::new();
+    let mut received: usize = 0;
+    let mut sequence: u32 = 0;
+    while received < total_size {
+        let chunk = match super::next_request(Response::Success(pb::BtcSuccess {})).await? {
+            pb::btc_request::Request::SignMessageChunk(pb::BtcSignMessageChunkRequest {
+                chunk,
+                sequence: chunk_sequence,
+            }) => {
+                // Abort cleanly on out-of-order or replayed chunks.
+                if chunk_sequence != sequence {
+                    return Err(Error::InvalidInput);
+                }
+                chunk
+            }
+            _ => return Err(Error::InvalidState),
+        };
+        if chunk.is_empty() || chunk.len() > total_size - received {
+            return Err(Error::InvalidInput);
+        }
+        envelope_hasher.update(&chunk);
+        msg_hasher.update(&chunk);
+        if head.len() < STREAM_REVIEW_SIZE {
+            head.extend_from_slice(&chunk[..core::cmp::min(STREAM_REVIEW_SIZE - head.len(), chunk.len())]);
+        }
+        tail.extend_from_slice(&chunk);
+        if tail.len() > STREAM_REVIEW_SIZE {
+            tail.drain(..tail.len() - STREAM_REVIEW_SIZE);
+        }
+        received += chunk.len();
+        sequence += 1;
+    }
+    let sighash: [u8; 32] = Sha256::digest(envelope_hasher.finalize()).into();
+    let msg_hash: [u8; 32] = msg_hasher.finalize().into();
+
+    confirm::confirm(&confirm::Params {
+        title: "Message hash",
+        body: &hex::encode(msg_hash),
+        scrollable: true,
+        accept_is_nextarrow: true,
+        ..Default::default()
+    })
+    .await?;
+    // The excerpts are shown as text only if printable; the middle of the message is covered by
+    // the hash only.
+    let excerpt = |data: &[u8]| -> String {
+        if util::ascii::is_printable_ascii(data, util::ascii::Charset::AllNewline) {
+            core::str::from_utf8(data).unwrap().into()
+        } else {
+            hex::encode(data)
+        }
+    };
+    confirm::confirm(&confirm::Params {
+        title: "Starts with",
+        body: &excerpt(&head),
+        scrollable: true,
+        accept_is_nextarrow: true,
+        ..Default::default()
+    })
+    .await?;
+    confirm::confirm(&confirm::Params {
+        title: "Ends with",
+        body: &excerpt(&tail),
+        scrollable: true,
+        longtouch: true,
+        ..Default::default()
+    })
+    .await?;
+
+    let host_nonce = match request.host_nonce_commitment {
+        // Engage in the anti-klepto protocol if the host sends a host nonce commitment.
+        Some(pb::AntiKleptoHostNonceCommitment { ref commitment }) => {
+            let signer_commitment = keystore::secp256k1_nonce_commit(
+                keypath,
+                &sighash,
+                commitment
+                    .as_slice()
+                    .try_into()
+                    .or(Err(Error::InvalidInput))?,
+            )?;
+            super::antiklepto_get_host_nonce(signer_commitment).await?
+        }
+        None => [0; 32],
+    };
+
+    let sign_result = keystore::secp256k1_sign(keypath, &sighash, &host_nonce)?;
+    let mut signature: Vec<u8> = sign_result.signature.to_vec();
+    signature.push(sign_result.recid);
+    let electrum_signature = electrum_signature(simple_type, &sign_result);
+    Ok(Response::SignMessage(pb::BtcSignMessageResponse {
+        signature,
+        bip322_proof: vec![],
+        address,
+        electrum_signature,
+    }))
+}
+
+/// Signs a message with our cosigner key of a registered multisig account, used for
+/// proof-of-reserves flows. The keypath is the full keypath of our derived key
+/// (`m/48'/coin'/account'/script_type'/change/address`); the account prefix must match a multisig
+/// registration on the device. The user confirms the registered account name and the message. Our
+/// derived pubkey is shown and returned in place of an address, as a multisig address does not
+/// identify the individual cosigner key that signed.
+async fn sign_multisig(
+    coin: BtcCoin,
+    multisig: &pb::btc_script_config::Multisig,
+    keypath: &[u32],
+    request: &pb::BtcSignMessageRequest,
+) -> Result<Response, Error> {
+    // The chunked and BIP-322 flows remain singlesig-only.
+    if request.bip322 || request.msg_streamed_size != 0 || request.msg.len() > MAX_MESSAGE_SIZE {
+        return Err(Error::InvalidInput);
+    }
+    let coin_params = super::params::get(coin);
+    super::keypath::validate_address_policy(keypath, super::keypath::ReceiveSpend::Receive)
+        .or(Err(Error::InvalidInput))?;
+    let account_keypath = &keypath[..keypath.len() - 2];
+    super::multisig::validate(multisig, account_keypath)?;
+    let name = super::multisig::get_name(coin, multisig, account_keypath)?
+        .ok_or(Error::InvalidInput)?;
+
+    confirm::confirm(&confirm::Params {
+        title: "Sign message",
+        body: &format!("Coin: {}", coin_params.name),
+        accept_is_nextarrow: true,
+        ..Default::default()
+    })
+    .await?;
+    super::multisig::confirm("Sign message", coin_params, &name, multisig).await?;
+    let pubkey = hex::encode(crate::keystore::get_xpub(keypath)?.public_key());
+    confirm::confirm(&confirm::Params {
+        title: "Public key",
+        body: &pubkey,
+        scrollable: true,
+        accept_is_nextarrow: true,
+        ..Default::default()
+    })
+    .await?;
+    verify_message::verify(&request.msg).await?;
+
+    // Same legacy message envelope as in `process()`.
+    let mut msg: Vec<u8> = Vec::new();
+    msg.extend(b"\x18Bitcoin Signed Message:\n");
+    msg.extend(serialize_varint(request.msg.len() as _));
+    msg.extend(&request.msg);
+    let sighash: [u8; 32] = Sha256::digest(Sha256::digest(msg)).into();
+
+    let host_nonce = match request.host_nonce_commitment {
+        // Engage in the anti-klepto protocol if the host sends a host nonce commitment.
+        Some(pb::AntiKleptoHostNonceCommitment { ref commitment }) => {
+            let signer_commitment = keystore::secp256k1_nonce_commit(
+                keypath,
+                &sighash,
+                commitment
+                    .as_slice()
+                    .try_into()
+                    .or(Err(Error::InvalidInput))?,
+            )?;
+            super::antiklepto_get_host_nonce(signer_commitment).await?
+        }
+        None => [0; 32],
+    };
+    let sign_result = keystore::secp256k1_sign(keypath, &sighash, &host_nonce)?;
+    let mut signature: Vec<u8> = sign_result.signature.to_vec();
+    signature.push(sign_result.recid);
+    Ok(Response::SignMessage(pb::BtcSignMessageResponse {
+        signature,
+        bip322_proof: vec![],
+        address: pubkey,
+        // There is no BIP-137 header range for multisig cosigner keys.
+        electrum_signature: vec![],
+    }))
+}
+
 /// Process a sign message request.
 ///
 /// The result contains a 65 byte signature. The first 64 bytes are the secp256k1 signature in
-/// compact format (R and S values), and the last byte is the recoverable id (recid).
+/// compact format (R and S values), and the last byte is the recoverable id (recid). For P2TR
+/// script configs, the result is a 64 byte BIP-340 Schnorr signature of the BIP-322 tagged
+/// message hash instead, made with the BIP-86 tweaked key.
 pub async fn process(request: &pb::BtcSignMessageRequest) -> Result<Response, Error> {
     let coin = BtcCoin::try_from(request.coin)?;
-    if coin != BtcCoin::Btc {
+    if !matches!(coin, BtcCoin::Btc | BtcCoin::Tbtc) {
         return Err(Error::InvalidInput);
     }
     let (keypath, simple_type) = match &request.script_config {
@@ -47,12 +427,32 @@ pub async fn process(request: &pb::BtcSignMessageRequest) -> Result<Response, Er
                 }),
             keypath,
         }) => (keypath, SimpleType::try_from(*simple_type)?),
+        Some(pb::BtcScriptConfigWithKeypath {
+            script_config:
+                Some(pb::BtcScriptConfig {
+                    config: Some(Config::Multisig(ref multisig)),
+                }),
+            keypath,
+        }) => return sign_multisig(coin, multisig, keypath, request).await,
         _ => return Err(Error::InvalidInput),
     };
-    if simple_type == SimpleType::P2tr {
+    if request.bip322
+        && !matches!(simple_type, SimpleType::P2wpkh | SimpleType::P2tr)
+    {
+        // BIP-322 "simple" proofs carry the spending conditions entirely in the witness, which
+        // excludes script types needing a scriptSig (P2PKH, P2WPKH-P2SH).
         return Err(Error::InvalidInput);
     }
-    if request.msg.len() > MAX_MESSAGE_SIZE {
+    let streamed = request.msg_streamed_size != 0;
+    if streamed {
+        // The chunked flow only covers the legacy message format.
+        if !request.msg.is_empty() || request.bip322 || simple_type == SimpleType::P2tr {
+            return Err(Error::InvalidInput);
+        }
+        if request.msg_streamed_size as usize > STREAM_MAX_MESSAGE_SIZE {
+            return Err(Error::InvalidInput);
+        }
+    } else if request.msg.len() > MAX_MESSAGE_SIZE {
         return Err(Error::InvalidInput);
     }
 
@@ -77,8 +477,38 @@ pub async fn process(request: &pb::BtcSignMessageRequest) -> Result<Response, Er
     };
     confirm::confirm(&confirm_params).await?;
 
+    if streamed {
+        return sign_streamed(simple_type, keypath, address, request).await;
+    }
+
     verify_message::verify(&request.msg).await?;
 
+    if request.bip322 {
+        return sign_bip322(coin, simple_type, keypath, address, request).await;
+    }
+
+    if simple_type == SimpleType::P2tr {
+        // The anti-klepto protocol is not supported for Schnorr signatures.
+        if request.host_nonce_commitment.is_some() {
+            return Err(Error::InvalidInput);
+        }
+        // There is no established legacy message signing format for taproot addresses. We sign
+        // the BIP-322 tagged message hash with the BIP-86 tweaked key, which verifiers can check
+        // directly against the x-only pubkey committed to in the address.
+        let msg_hash: [u8; 32] = {
+            let mut ctx = super::bip341::tagged_hasher(b"BIP0322-signed-message");
+            ctx.update(&request.msg);
+            ctx.finalize().into()
+        };
+        let signature = keystore::secp256k1_schnorr_bip86_sign(keypath, &msg_hash)?;
+        return Ok(Response::SignMessage(pb::BtcSignMessageResponse {
+            signature: signature.to_vec(),
+            bip322_proof: vec![],
+            address,
+            electrum_signature: vec![],
+        }));
+    }
+
     // See
     // https://github.com/spesmilo/electrum/blob/84dc181b6e7bb20e88ef6b98fb8925c5f645a765/electrum/ecc.py#L355-L358.
     // This is the message format that is widespread for p2pkh addresses.
@@ -117,9 +547,13 @@ pub async fn process(request: &pb::BtcSignMessageRequest) -> Result<Response, Er
 
     let mut signature: Vec<u8> = sign_result.signature.to_vec();
     signature.push(sign_result.recid);
+    let electrum_signature = electrum_signature(simple_type, &sign_result);
 
     Ok(Response::SignMessage(pb::BtcSignMessageResponse {
         signature,
+        bip322_proof: vec![],
+        address,
+        electrum_signature,
     }))
 }
 
@@ -129,7 +563,7 @@ mod tests {
 
     use crate::bb02_async::block_on;
     use alloc::boxed::Box;
-    use bitbox02::testing::{mock, mock_unlocked, Data};
+    use bitbox02::testing::{mock, mock_unlocked, mock_unlocked_using_mnemonic, Data};
     use util::bip32::HARDENED;
 
     const MESSAGE: &[u8] = b"message";
@@ -146,6 +580,8 @@ mod tests {
             }),
             msg: MESSAGE.to_vec(),
             host_nonce_commitment: None,
+            bip322: false,
+            msg_streamed_size: 0,
         };
 
         static mut CONFIRM_COUNTER: u32 = 0;
@@ -181,6 +617,11 @@ mod tests {
             block_on(process(&request)),
             Ok(Response::SignMessage(pb::BtcSignMessageResponse {
                 signature: b"\x0f\x1d\x54\x2a\x9e\x2f\x37\x4e\xfe\xd4\x57\x8c\xaa\x84\x72\xd1\xc3\x12\x68\xfb\x89\x2d\x39\xa6\x15\x44\x59\x18\x5b\x2d\x35\x4d\x3b\x2b\xff\xf0\xe1\x61\x5c\x77\x25\x73\x4f\x43\x13\x4a\xb4\x51\x6b\x7e\x7c\xb3\x9d\x2d\xba\xaa\x5f\x4e\x8b\x8a\xff\x9f\x97\xd0\x00".to_vec(),
+                bip322_proof: vec![],
+                address: "bc1qk5f9em9qc8yfpks8ngfg3h8h02n2e3yeqdyhpt".into(),
+                // BIP-137 header 0x27 = 39 (P2WPKH) + recid 0, followed by R and S. Verifies in
+                // Electrum against the address above.
+                electrum_signature: b"\x27\x0f\x1d\x54\x2a\x9e\x2f\x37\x4e\xfe\xd4\x57\x8c\xaa\x84\x72\xd1\xc3\x12\x68\xfb\x89\x2d\x39\xa6\x15\x44\x59\x18\x5b\x2d\x35\x4d\x3b\x2b\xff\xf0\xe1\x61\x5c\x77\x25\x73\x4f\x43\x13\x4a\xb4\x51\x6b\x7e\x7c\xb3\x9d\x2d\xba\xaa\x5f\x4e\x8b\x8a\xff\x9f\x97\xd0".to_vec(),
             }))
         );
     }
@@ -197,6 +638,8 @@ mod tests {
             }),
             msg: MESSAGE.to_vec(),
             host_nonce_commitment: None,
+            bip322: false,
+            msg_streamed_size: 0,
         };
 
         static mut CONFIRM_COUNTER: u32 = 0;
@@ -232,12 +675,91 @@ mod tests {
             block_on(process(&request)),
             Ok(Response::SignMessage(pb::BtcSignMessageResponse {
                 signature: b"\x87\x19\x05\x3c\x29\xff\xcf\x54\x31\x40\x69\x86\x75\x8a\xc8\xed\x80\x1c\xff\x3d\x61\x46\xe4\x8c\x46\x25\x75\xb6\x47\x34\x46\xf8\x44\xf1\x38\x7d\x48\xe1\x36\x88\x42\x09\x43\xfa\x8e\x4f\x0a\x23\xaa\x2e\x49\xa8\x3a\xf8\x88\x52\x2c\xec\xa9\x05\x0b\xe6\xc3\x47\x00".to_vec(),
+                bip322_proof: vec![],
+                address: "3BaL6XecvLAidPToUDhXo1zxD99ZUrErpd".into(),
+                // BIP-137 header 0x23 = 35 (P2WPKH-P2SH) + recid 0, followed by R and S. Verifies
+                // in Electrum against the address above.
+                electrum_signature: b"\x23\x87\x19\x05\x3c\x29\xff\xcf\x54\x31\x40\x69\x86\x75\x8a\xc8\xed\x80\x1c\xff\x3d\x61\x46\xe4\x8c\x46\x25\x75\xb6\x47\x34\x46\xf8\x44\xf1\x38\x7d\x48\xe1\x36\x88\x42\x09\x43\xfa\x8e\x4f\x0a\x23\xaa\x2e\x49\xa8\x3a\xf8\x88\x52\x2c\xec\xa9\x05\x0b\xe6\xc3\x47".to_vec(),
             }))
         );
     }
 
+    /// Messages that cannot be rendered (bytes outside printable ascii) are confirmed by their
+    /// SHA-256 hash after an explicit warning.
     #[test]
-    pub fn test_process_user_aborted() {
+    pub fn test_non_printable_message() {
+        static mut CONFIRM_COUNTER: u32 = 0;
+
+        let msgs: &[(&[u8], &str)] = &[
+            // UTF-8, not ascii.
+            (
+                "Ünicode message".as_bytes(),
+                "7cb44343 43b51ee7 eb11eefc 85318c35 ceb18389 eb198fc8 646b92b0 c1aa69cb",
+            ),
+            // Control characters.
+            (
+                b"line1\x00line2\x07",
+                "016df492 a6b57cdc 3f713fdf 4ae7dbfe 4be96a41 fdc7cbed 0416fd97 1d5db86f",
+            ),
+            // Pure binary.
+            (
+                b"\xde\xad\xbe\xef\x00\xff\xfe",
+                "17ba38ea 0f6cb185 d5b5b9b7 08c3ce47 42253501 ebb0f090 39a482cd 7efaa30c",
+            ),
+        ];
+        for &(msg, expected_hash) in msgs {
+            let request = pb::BtcSignMessageRequest {
+                coin: BtcCoin::Btc as _,
+                script_config: Some(pb::BtcScriptConfigWithKeypath {
+                    script_config: Some(pb::BtcScriptConfig {
+                        config: Some(Config::SimpleType(SimpleType::P2wpkh as _)),
+                    }),
+                    keypath: vec![84 + HARDENED, 0 + HARDENED, 0 + HARDENED, 0, 0],
+                }),
+                msg: msg.to_vec(),
+                host_nonce_commitment: None,
+                bip322: false,
+                msg_streamed_size: 0,
+            };
+
+            unsafe { CONFIRM_COUNTER = 0 };
+            mock(Data {
+                ui_confirm_create: Some(Box::new(move |params| {
+                    match unsafe {
+                        CONFIRM_COUNTER += 1;
+                        CONFIRM_COUNTER
+                    } {
+                        1 | 2 => true,
+                        3 => {
+                            assert_eq!(params.title, "Warning");
+                            assert_eq!(params.body, "Binary message\ncannot be shown.\nVerify its hash");
+                            true
+                        }
+                        4 => {
+                            assert_eq!(params.title, "Binary message");
+                            assert_eq!(params.body, format!("SHA-256:\n{}", expected_hash));
+                            assert!(params.longtouch);
+                            true
+                        }
+                        _ => panic!("too many user confirmations"),
+                    }
+                })),
+                ..Default::default()
+            });
+            mock_unlocked();
+            let result = block_on(process(&request)).unwrap();
+            match result {
+                Response::SignMessage(response) => assert_eq!(response.signature.len(), 65),
+                _ => panic!("wrong response type"),
+            }
+            assert_eq!(unsafe { CONFIRM_COUNTER }, 4);
+        }
+    }
+
+    /// Tabs are printable (rendered as spaces), so a message containing them keeps the regular
+    /// rendering instead of falling back to the hash display.
+    #[test]
+    pub fn test_tab_message() {
         let request = pb::BtcSignMessageRequest {
             coin: BtcCoin::Btc as _,
             script_config: Some(pb::BtcScriptConfigWithKeypath {
@@ -246,23 +768,25 @@ mod tests {
                 }),
                 keypath: vec![84 + HARDENED, 0 + HARDENED, 0 + HARDENED, 0, 0],
             }),
-            msg: MESSAGE.to_vec(),
+            msg: b"column1\tcolumn2".to_vec(),
             host_nonce_commitment: None,
+            bip322: false,
+            msg_streamed_size: 0,
         };
 
         static mut CONFIRM_COUNTER: u32 = 0;
 
-        // Basic info dialog aborted.
         mock(Data {
             ui_confirm_create: Some(Box::new(|params| {
                 match unsafe {
                     CONFIRM_COUNTER += 1;
                     CONFIRM_COUNTER
                 } {
-                    1 => {
+                    1 | 2 => true,
+                    3 => {
                         assert_eq!(params.title, "Sign message");
-                        assert_eq!(params.body, "Coin: Bitcoin");
-                        false
+                        assert_eq!(params.body, "column1 column2");
+                        true
                     }
                     _ => panic!("too many user confirmations"),
                 }
@@ -270,100 +794,440 @@ mod tests {
             ..Default::default()
         });
         mock_unlocked();
-        assert_eq!(block_on(process(&request)), Err(Error::UserAbort));
+        assert!(block_on(process(&request)).is_ok());
+    }
 
-        // Address verification aborted.
-        unsafe {
-            CONFIRM_COUNTER = 0;
+    /// A message containing something that looks like an address is a phishing vector ("send to
+    /// this address") and triggers an extra warning before the message is shown. Signing is
+    /// unaffected. Messages with Unicode bidi override characters can never be rendered, as they
+    /// are outside printable ascii; they fall back to the hash display.
+    #[test]
+    pub fn test_address_like_message() {
+        static mut CONFIRM_COUNTER: u32 = 0;
+
+        let msgs: &[&[u8]] = &[
+            // base58.
+            b"Please send 1 BTC to 1AUrwD77AL5ax5zj2BhZQ1x43wA5NLsYg1, thanks",
+            // bech32.
+            b"refund: bc1qk5f9em9qc8yfpks8ngfg3h8h02n2e3yeqdyhpt",
+        ];
+        for &msg in msgs {
+            let request = pb::BtcSignMessageRequest {
+                coin: BtcCoin::Btc as _,
+                script_config: Some(pb::BtcScriptConfigWithKeypath {
+                    script_config: Some(pb::BtcScriptConfig {
+                        config: Some(Config::SimpleType(SimpleType::P2wpkh as _)),
+                    }),
+                    keypath: vec![84 + HARDENED, 0 + HARDENED, 0 + HARDENED, 0, 0],
+                }),
+                msg: msg.to_vec(),
+                host_nonce_commitment: None,
+                bip322: false,
+                msg_streamed_size: 0,
+            };
+
+            unsafe { CONFIRM_COUNTER = 0 };
+            mock(Data {
+                ui_confirm_create: Some(Box::new(move |params| {
+                    match unsafe {
+                        CONFIRM_COUNTER += 1;
+                        CONFIRM_COUNTER
+                    } {
+                        1 | 2 => true,
+                        3 => {
+                            assert_eq!(params.title, "Warning");
+                            assert_eq!(
+                                params.body,
+                                "This message\ncontains what\nlooks like an\naddress. Signing\ndoes NOT\nsend funds"
+                            );
+                            true
+                        }
+                        4 => {
+                            assert_eq!(params.title, "Sign message");
+                            assert_eq!(params.body.as_bytes(), msg);
+                            true
+                        }
+                        _ => panic!("too many user confirmations"),
+                    }
+                })),
+                ..Default::default()
+            });
+            mock_unlocked();
+            assert!(block_on(process(&request)).is_ok());
+            assert_eq!(unsafe { CONFIRM_COUNTER }, 4);
         }
+
+        // A message with an RTL override character (U+202E) is not printable ascii and is
+        // confirmed by its hash, so the override can never disguise rendered content.
+        let request = pb::BtcSignMessageRequest {
+            coin: BtcCoin::Btc as _,
+            script_config: Some(pb::BtcScriptConfigWithKeypath {
+                script_config: Some(pb::BtcScriptConfig {
+                    config: Some(Config::SimpleType(SimpleType::P2wpkh as _)),
+                }),
+                keypath: vec![84 + HARDENED, 0 + HARDENED, 0 + HARDENED, 0, 0],
+            }),
+            msg: "pay 1AUrwD77AL5ax5zj2BhZQ1x43wA5NLsYg1 \u{202e}niaga sknaht".as_bytes().to_vec(),
+            host_nonce_commitment: None,
+            bip322: false,
+            msg_streamed_size: 0,
+        };
+        unsafe { CONFIRM_COUNTER = 0 };
         mock(Data {
-            ui_confirm_create: Some(Box::new(|_params| {
+            ui_confirm_create: Some(Box::new(|params| {
                 match unsafe {
                     CONFIRM_COUNTER += 1;
                     CONFIRM_COUNTER
                 } {
-                    1 => true,
-                    2 => false,
+                    1 | 2 => true,
+                    3 => {
+                        assert_eq!(params.title, "Warning");
+                        assert_eq!(
+                            params.body,
+                            "Binary message\ncannot be shown.\nVerify its hash"
+                        );
+                        true
+                    }
+                    4 => {
+                        assert_eq!(params.title, "Binary message");
+                        true
+                    }
                     _ => panic!("too many user confirmations"),
                 }
             })),
             ..Default::default()
         });
         mock_unlocked();
-        assert_eq!(block_on(process(&request)), Err(Error::UserAbort));
+        assert!(block_on(process(&request)).is_ok());
+        assert_eq!(unsafe { CONFIRM_COUNTER }, 4);
+    }
+
+    /// Taproot message signing: a 64 byte Schnorr signature of the BIP-322 tagged message hash,
+    /// made with the BIP-86 tweaked key.
+    #[test]
+    pub fn test_p2tr() {
+        // Mainnet. Mnemonic and address from the BIP-86 test vectors:
+        // https://github.com/bitcoin/bips/blob/edffe529056f6dfd33d8f716fb871467c3c09263/bip-0086.mediawiki#test-vectors
+        let request = pb::BtcSignMessageRequest {
+            coin: BtcCoin::Btc as _,
+            script_config: Some(pb::BtcScriptConfigWithKeypath {
+                script_config: Some(pb::BtcScriptConfig {
+                    config: Some(Config::SimpleType(SimpleType::P2tr as _)),
+                }),
+                keypath: vec![86 + HARDENED, 0 + HARDENED, 0 + HARDENED, 0, 0],
+            }),
+            msg: MESSAGE.to_vec(),
+            host_nonce_commitment: None,
+            bip322: false,
+            msg_streamed_size: 0,
+        };
+
+        static mut CONFIRM_COUNTER: u32 = 0;
 
-        // Message verification aborted.
-        unsafe {
-            CONFIRM_COUNTER = 0;
-        }
         mock(Data {
             ui_confirm_create: Some(Box::new(|params| {
                 match unsafe {
                     CONFIRM_COUNTER += 1;
                     CONFIRM_COUNTER
                 } {
-                    1 | 2 => true,
+                    1 => {
+                        assert_eq!(params.title, "Sign message");
+                        assert_eq!(params.body, "Coin: Bitcoin");
+                        true
+                    }
+                    2 => {
+                        assert_eq!(params.title, "Address");
+                        assert_eq!(
+                            params.body,
+                            "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr"
+                        );
+                        true
+                    }
                     3 => {
                         assert_eq!(params.title, "Sign message");
                         assert_eq!(params.body.as_bytes(), MESSAGE);
-                        false
+                        true
+                    }
+                    _ => panic!("too many user confirmations"),
+                }
+            })),
+            ..Default::default()
+        });
+        mock_unlocked_using_mnemonic(
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+            "",
+        );
+        match block_on(process(&request)) {
+            Ok(Response::SignMessage(pb::BtcSignMessageResponse {
+                ref signature,
+                ref bip322_proof,
+                ref address,
+                ref electrum_signature,
+            })) => {
+                assert_eq!(signature.len(), 64);
+                assert!(bip322_proof.is_empty());
+                assert!(electrum_signature.is_empty());
+                assert_eq!(
+                    address,
+                    "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr"
+                );
+            }
+            _ => panic!("wrong response type"),
+        }
+
+        // Testnet.
+        unsafe { CONFIRM_COUNTER = 0 }
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|params| {
+                match unsafe {
+                    CONFIRM_COUNTER += 1;
+                    CONFIRM_COUNTER
+                } {
+                    1 => {
+                        assert_eq!(params.title, "Sign message");
+                        assert_eq!(params.body, "Coin: BTC Testnet");
+                        true
+                    }
+                    2 => {
+                        assert_eq!(params.title, "Address");
+                        assert!(params.body.starts_with("tb1p"));
+                        true
                     }
+                    3 => true,
                     _ => panic!("too many user confirmations"),
                 }
             })),
             ..Default::default()
         });
         mock_unlocked();
-        assert_eq!(block_on(process(&request)), Err(Error::UserAbort));
-    }
+        match block_on(process(&pb::BtcSignMessageRequest {
+            coin: BtcCoin::Tbtc as _,
+            script_config: Some(pb::BtcScriptConfigWithKeypath {
+                script_config: Some(pb::BtcScriptConfig {
+                    config: Some(Config::SimpleType(SimpleType::P2tr as _)),
+                }),
+                keypath: vec![86 + HARDENED, 1 + HARDENED, 0 + HARDENED, 0, 0],
+            }),
+            msg: MESSAGE.to_vec(),
+            host_nonce_commitment: None,
+            bip322: false,
+            msg_streamed_size: 0,
+        })) {
+            Ok(Response::SignMessage(pb::BtcSignMessageResponse {
+                ref signature,
+                ref bip322_proof,
+                ref address,
+                ref electrum_signature,
+            })) => {
+                assert_eq!(signature.len(), 64);
+                assert!(bip322_proof.is_empty());
+                assert!(electrum_signature.is_empty());
+                assert!(address.starts_with("tb1p"));
+            }
+            _ => panic!("wrong response type"),
+        }
 
-    #[test]
-    pub fn test_process_failures() {
-        const KEYPATH: &[u32] = &[84 + HARDENED, 0 + HARDENED, 0 + HARDENED, 0, 0];
-        // Invalid coin
+        // The anti-klepto protocol is not available for Schnorr signatures.
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|_params| true)),
+            ..Default::default()
+        });
+        mock_unlocked();
         assert_eq!(
             block_on(process(&pb::BtcSignMessageRequest {
-                coin: -1,
-                script_config: Some(pb::BtcScriptConfigWithKeypath {
-                    script_config: Some(pb::BtcScriptConfig {
-                        config: Some(Config::SimpleType(SimpleType::P2wpkh as _))
-                    }),
-                    keypath: KEYPATH.to_vec(),
+                host_nonce_commitment: Some(pb::AntiKleptoHostNonceCommitment {
+                    commitment: vec![0; 32],
                 }),
-                msg: MESSAGE.to_vec(),
-                host_nonce_commitment: None,
+                ..request
             })),
             Err(Error::InvalidInput)
         );
+    }
 
-        // Invalid script type (invalid simple type)
+    /// Exercise the anti-klepto protocol: the host commits to a nonce, receives the signer
+    /// commitment and answers with the host nonce, which is mixed into the final signature.
+    #[test]
+    fn test_antiklepto() {
+        let host_nonce = b"\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab";
+        let request = pb::BtcSignMessageRequest {
+            coin: BtcCoin::Btc as _,
+            script_config: Some(pb::BtcScriptConfigWithKeypath {
+                script_config: Some(pb::BtcScriptConfig {
+                    config: Some(Config::SimpleType(SimpleType::P2wpkh as _)),
+                }),
+                keypath: vec![84 + HARDENED, 0 + HARDENED, 0 + HARDENED, 0, 0],
+            }),
+            msg: MESSAGE.to_vec(),
+            host_nonce_commitment: Some(pb::AntiKleptoHostNonceCommitment {
+                commitment: bitbox02::secp256k1::ecdsa_anti_exfil_host_commit(host_nonce).unwrap(),
+            }),
+            bip322: false,
+            msg_streamed_size: 0,
+        };
+
+        // Host side of the protocol: check the signer commitment and deliver the host nonce. The
+        // full host verification of the commitment against the final signature lives in the
+        // secp256k1 lib and is exercised in test_keystore_antiklepto.c.
+        *crate::hww::MOCK_NEXT_REQUEST.0.borrow_mut() = Some(Box::new(
+            move |response: crate::pb::response::Response| {
+                match response {
+                    crate::pb::response::Response::Btc(pb::BtcResponse {
+                        response:
+                            Some(pb::btc_response::Response::AntikleptoSignerCommitment(
+                                pb::AntiKleptoSignerCommitment { commitment },
+                            )),
+                    }) => {
+                        // The signer commitment is a valid compressed public key.
+                        assert!(bitcoin::secp256k1::PublicKey::from_slice(&commitment).is_ok());
+                    }
+                    _ => panic!("expected signer commitment"),
+                }
+                Ok(crate::pb::request::Request::Btc(pb::BtcRequest {
+                    request: Some(pb::btc_request::Request::AntikleptoSignature(
+                        pb::AntiKleptoSignatureRequest {
+                            host_nonce: host_nonce.to_vec(),
+                        },
+                    )),
+                }))
+            },
+        ));
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|_params| true)),
+            ..Default::default()
+        });
+        mock_unlocked();
+        match block_on(process(&request)).unwrap() {
+            Response::SignMessage(response) => {
+                assert_eq!(response.signature.len(), 65);
+                // The host nonce was included: the signature differs from the plain signature over
+                // the same message (see the fixture in `test_p2wpkh`).
+                assert_ne!(
+                    response.signature,
+                    b"\x0f\x1d\x54\x2a\x9e\x2f\x37\x4e\xfe\xd4\x57\x8c\xaa\x84\x72\xd1\xc3\x12\x68\xfb\x89\x2d\x39\xa6\x15\x44\x59\x18\x5b\x2d\x35\x4d\x3b\x2b\xff\xf0\xe1\x61\x5c\x77\x25\x73\x4f\x43\x13\x4a\xb4\x51\x6b\x7e\x7c\xb3\x9d\x2d\xba\xaa\x5f\x4e\x8b\x8a\xff\x9f\x97\xd0\x00".to_vec()
+                );
+            }
+            _ => panic!("wrong response type"),
+        }
+    }
+
+    #[test]
+    pub fn test_process_user_aborted() {
+        let request = pb::BtcSignMessageRequest {
+            coin: BtcCoin::Btc as _,
+            script_config: Some(pb::BtcScriptConfigWithKeypath {
+                script_config: Some(pb::BtcScriptConfig {
+                    config: Some(Config::SimpleType(SimpleType::P2wpkh as _)),
+                }),
+                keypath: vec![84 + HARDENED, 0 + HARDENED, 0 + HARDENED, 0, 0],
+            }),
+            msg: MESSAGE.to_vec(),
+            host_nonce_commitment: None,
+            bip322: false,
+            msg_streamed_size: 0,
+        };
+
+        static mut CONFIRM_COUNTER: u32 = 0;
+
+        // Basic info dialog aborted.
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|params| {
+                match unsafe {
+                    CONFIRM_COUNTER += 1;
+                    CONFIRM_COUNTER
+                } {
+                    1 => {
+                        assert_eq!(params.title, "Sign message");
+                        assert_eq!(params.body, "Coin: Bitcoin");
+                        false
+                    }
+                    _ => panic!("too many user confirmations"),
+                }
+            })),
+            ..Default::default()
+        });
+        mock_unlocked();
+        assert_eq!(block_on(process(&request)), Err(Error::UserAbort));
+
+        // Address verification aborted.
+        unsafe {
+            CONFIRM_COUNTER = 0;
+        }
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|_params| {
+                match unsafe {
+                    CONFIRM_COUNTER += 1;
+                    CONFIRM_COUNTER
+                } {
+                    1 => true,
+                    2 => false,
+                    _ => panic!("too many user confirmations"),
+                }
+            })),
+            ..Default::default()
+        });
+        mock_unlocked();
+        assert_eq!(block_on(process(&request)), Err(Error::UserAbort));
+
+        // Message verification aborted.
+        unsafe {
+            CONFIRM_COUNTER = 0;
+        }
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|params| {
+                match unsafe {
+                    CONFIRM_COUNTER += 1;
+                    CONFIRM_COUNTER
+                } {
+                    1 | 2 => true,
+                    3 => {
+                        assert_eq!(params.title, "Sign message");
+                        assert_eq!(params.body.as_bytes(), MESSAGE);
+                        false
+                    }
+                    _ => panic!("too many user confirmations"),
+                }
+            })),
+            ..Default::default()
+        });
+        mock_unlocked();
+        assert_eq!(block_on(process(&request)), Err(Error::UserAbort));
+    }
+
+    #[test]
+    pub fn test_process_failures() {
+        const KEYPATH: &[u32] = &[84 + HARDENED, 0 + HARDENED, 0 + HARDENED, 0, 0];
+        // Invalid coin
         assert_eq!(
             block_on(process(&pb::BtcSignMessageRequest {
-                coin: BtcCoin::Btc as _,
+                coin: -1,
                 script_config: Some(pb::BtcScriptConfigWithKeypath {
                     script_config: Some(pb::BtcScriptConfig {
-                        config: Some(Config::SimpleType(-1))
+                        config: Some(Config::SimpleType(SimpleType::P2wpkh as _))
                     }),
                     keypath: KEYPATH.to_vec(),
                 }),
                 msg: MESSAGE.to_vec(),
                 host_nonce_commitment: None,
+                bip322: false,
+                msg_streamed_size: 0,
             })),
             Err(Error::InvalidInput)
         );
 
-        // Invalid script type (taproot not supported)
+        // Invalid script type (invalid simple type)
         assert_eq!(
             block_on(process(&pb::BtcSignMessageRequest {
                 coin: BtcCoin::Btc as _,
                 script_config: Some(pb::BtcScriptConfigWithKeypath {
                     script_config: Some(pb::BtcScriptConfig {
-                        config: Some(Config::SimpleType(SimpleType::P2tr as _)),
+                        config: Some(Config::SimpleType(-1))
                     }),
-                    keypath: vec![86 + HARDENED, 0 + HARDENED, 0 + HARDENED, 0, 0],
+                    keypath: KEYPATH.to_vec(),
                 }),
                 msg: MESSAGE.to_vec(),
                 host_nonce_commitment: None,
+                bip322: false,
+                msg_streamed_size: 0,
             })),
             Err(Error::InvalidInput)
         );
@@ -382,6 +1246,8 @@ mod tests {
                 }),
                 msg: MESSAGE.to_vec(),
                 host_nonce_commitment: None,
+                bip322: false,
+                msg_streamed_size: 0,
             })),
             Err(Error::InvalidInput)
         );
@@ -398,6 +1264,8 @@ mod tests {
                 }),
                 msg: [0; 1025].to_vec(),
                 host_nonce_commitment: None,
+                bip322: false,
+                msg_streamed_size: 0,
             })),
             Err(Error::InvalidInput)
         );
@@ -418,8 +1286,530 @@ mod tests {
                 }),
                 msg: MESSAGE.to_vec(),
                 host_nonce_commitment: None,
+                bip322: false,
+                msg_streamed_size: 0,
+            })),
+            Err(Error::InvalidInput)
+        );
+    }
+
+    /// Signing with our cosigner key of a registered multisig account (proof of reserves). The
+    /// signature is verified against our derived pubkey returned in the response, and
+    /// unregistered configs are rejected.
+    #[test]
+    fn test_multisig() {
+        let keypath_account = &[48 + HARDENED, 1 + HARDENED, 0 + HARDENED, 2 + HARDENED];
+        let multisig = pb::btc_script_config::Multisig {
+            threshold: 2,
+            xpubs: vec![
+                crate::bip32::parse_xpub("xpub6FMWuwbCA9KhoRzAMm63ZhLspk5S2DM5sePo8J8mQhcS1xyMbAqnc7Q7UescVEVFCS6qBMQLkEJWQ9Z3aDPgBov5nFUYxsJhwumsxM4npSo").unwrap(),
+                // This xpub corresponds to the mocked seed below at m/48'/1'/0'/2'.
+                crate::bip32::parse_xpub("xpub6EMfjyGVUvwhpc3WKN1zXhMFGKJGMaSBPqbja4tbGoYvRBSXeTBCaqrRDjcuGTcaY95JrrAnQvDG3pdQPdtnYUCugjeksHSbyZT7rq38VQF").unwrap(),
+                crate::bip32::parse_xpub("xpub6ERxBysTYfQyY4USv6c6J1HNVv9hpZFN9LHVPu47Ac4rK8fLy6NnAeeAHyEsMvG4G66ay5aFZii2VM7wT3KxLKX8Q8keZPd67kRGmrD1WJj").unwrap(),
+            ],
+            our_xpub_index: 1,
+            script_type: pb::btc_script_config::multisig::ScriptType::P2wsh as _,
+        };
+        let request = pb::BtcSignMessageRequest {
+            coin: BtcCoin::Tbtc as _,
+            script_config: Some(pb::BtcScriptConfigWithKeypath {
+                script_config: Some(pb::BtcScriptConfig {
+                    config: Some(Config::Multisig(multisig.clone())),
+                }),
+                keypath: vec![
+                    48 + HARDENED,
+                    1 + HARDENED,
+                    0 + HARDENED,
+                    2 + HARDENED,
+                    0,
+                    0,
+                ],
+            }),
+            msg: MESSAGE.to_vec(),
+            host_nonce_commitment: None,
+            bip322: false,
+            msg_streamed_size: 0,
+        };
+
+        static mut CONFIRM_COUNTER: u32 = 0;
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|params| {
+                match unsafe {
+                    CONFIRM_COUNTER += 1;
+                    CONFIRM_COUNTER
+                } {
+                    1 => {
+                        assert_eq!(params.title, "Sign message");
+                        assert_eq!(params.body, "Coin: BTC Testnet");
+                    }
+                    2 => assert_eq!(params.body, "2-of-3\nBTC Testnet multisig"),
+                    3 => assert_eq!(params.body, "proof of reserves"),
+                    4 => {
+                        assert_eq!(params.title, "Public key");
+                        assert!(params.scrollable);
+                    }
+                    5 => {
+                        assert_eq!(params.title, "Sign message");
+                        assert_eq!(params.body.as_bytes(), MESSAGE);
+                    }
+                    _ => panic!("too many user confirmations"),
+                }
+                true
+            })),
+            ..Default::default()
+        });
+        bitbox02::testing::mock_memory();
+        mock_unlocked_using_mnemonic(
+            "sudden tenant fault inject concert weather maid people chunk youth stumble grit",
+            "",
+        );
+
+        // Not registered yet.
+        assert_eq!(block_on(process(&request)), Err(Error::InvalidInput));
+        assert_eq!(unsafe { CONFIRM_COUNTER }, 0);
+
+        bitbox02::memory::multisig_set_by_hash(
+            &super::super::multisig::get_hash(
+                BtcCoin::Tbtc,
+                &multisig,
+                super::super::multisig::SortXpubs::Yes,
+                keypath_account,
+            )
+            .unwrap(),
+            "proof of reserves",
+        )
+        .unwrap();
+
+        let our_pubkey =
+            hex::encode(crate::keystore::get_xpub(&[48 + HARDENED, 1 + HARDENED, 0 + HARDENED, 2 + HARDENED, 0, 0]).unwrap().public_key());
+        let response = block_on(process(&request)).unwrap();
+        assert_eq!(unsafe { CONFIRM_COUNTER }, 5);
+        let (signature, address) = match response {
+            Response::SignMessage(pb::BtcSignMessageResponse {
+                ref signature,
+                ref bip322_proof,
+                ref address,
+                ref electrum_signature,
+            }) => {
+                assert!(bip322_proof.is_empty());
+                assert!(electrum_signature.is_empty());
+                (signature.clone(), address.clone())
+            }
+            _ => panic!("wrong response type"),
+        };
+        assert_eq!(address, our_pubkey);
+        assert_eq!(signature.len(), 65);
+
+        // Verify the signature against our derived pubkey.
+        let mut msg: Vec<u8> = Vec::new();
+        msg.extend(b"\x18Bitcoin Signed Message:\n");
+        msg.extend(serialize_varint(MESSAGE.len() as _));
+        msg.extend(MESSAGE);
+        let sighash: [u8; 32] = Sha256::digest(Sha256::digest(msg)).into();
+        let secp = bitcoin::secp256k1::Secp256k1::new();
+        secp.verify_ecdsa(
+            &bitcoin::secp256k1::Message::from_digest(sighash),
+            &bitcoin::secp256k1::ecdsa::Signature::from_compact(&signature[..64]).unwrap(),
+            &bitcoin::secp256k1::PublicKey::from_slice(&hex::decode(&address).unwrap()).unwrap(),
+        )
+        .unwrap();
+
+        // BIP-322 and streamed flows remain singlesig-only.
+        let mut invalid = request.clone();
+        invalid.bip322 = true;
+        assert_eq!(block_on(process(&invalid)), Err(Error::InvalidInput));
+        let mut invalid = request.clone();
+        invalid.msg_streamed_size = 100;
+        assert_eq!(block_on(process(&invalid)), Err(Error::InvalidInput));
+    }
+
+    /// Test vectors from
+    /// https://github.com/bitcoin/bips/blob/master/bip-0322.mediawiki#test-vectors, proving
+    /// ownership of bc1q9vza2e8x573nczrlzms0wvx3gsqjx7vavgkx0l. The to_spend txid only depends on
+    /// the message and the pkScript, not on the private key.
+    #[test]
+    fn test_bip322_txid_to_spend() {
+        let message_hash = |msg: &[u8]| -> Vec<u8> {
+            let mut ctx = super::super::bip341::tagged_hasher(b"BIP0322-signed-message");
+            ctx.update(msg);
+            ctx.finalize().to_vec()
+        };
+        assert_eq!(
+            hex::encode(message_hash(b"")),
+            "c90c269c4f8fcbe6880f72a721ddfbf1914268a794cbb21cfafee13770ae19f1"
+        );
+        assert_eq!(
+            hex::encode(message_hash(b"Hello World")),
+            "f0eb03b1a75ac6d9847f55c624a99169b5dccba2a31f5b23bea77ba270de0a7a"
+        );
+
+        let pk_script =
+            b"\x00\x14\x2b\x05\xd5\x64\xe6\xa7\xa3\x3c\x08\x7f\x16\xe0\xf7\x30\xd1\x44\x01\x23\x79\x9d";
+        // Reverse to the display byte order of the txids in the BIP.
+        let mut txid: Vec<u8> = bip322_txid_to_spend(pk_script, b"").to_vec();
+        txid.reverse();
+        assert_eq!(
+            hex::encode(&txid),
+            "c5680aa69bb8d860bf82d4e9cd3504b55dde018de765a91bb566283c545a99a7"
+        );
+        let mut txid: Vec<u8> = bip322_txid_to_spend(pk_script, b"Hello World").to_vec();
+        txid.reverse();
+        assert_eq!(
+            hex::encode(&txid),
+            "b79d196740ad5217771c1098fc4a4b51e0535c32236c71f1ea4d61a2d603352b"
+        );
+    }
+
+    #[test]
+    pub fn test_bip322_p2wpkh() {
+        const KEYPATH: &[u32] = &[84 + HARDENED, 0 + HARDENED, 0 + HARDENED, 0, 0];
+        let request = pb::BtcSignMessageRequest {
+            coin: BtcCoin::Btc as _,
+            script_config: Some(pb::BtcScriptConfigWithKeypath {
+                script_config: Some(pb::BtcScriptConfig {
+                    config: Some(Config::SimpleType(SimpleType::P2wpkh as _)),
+                }),
+                keypath: KEYPATH.to_vec(),
+            }),
+            msg: MESSAGE.to_vec(),
+            host_nonce_commitment: None,
+            bip322: true,
+            msg_streamed_size: 0,
+        };
+
+        static mut CONFIRM_COUNTER: u32 = 0;
+
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|params| {
+                match unsafe {
+                    CONFIRM_COUNTER += 1;
+                    CONFIRM_COUNTER
+                } {
+                    1 => {
+                        assert_eq!(params.title, "Sign message");
+                        assert_eq!(params.body, "Coin: Bitcoin");
+                        true
+                    }
+                    2 => {
+                        assert_eq!(params.title, "Address");
+                        assert_eq!(params.body, "bc1qk5f9em9qc8yfpks8ngfg3h8h02n2e3yeqdyhpt");
+                        true
+                    }
+                    3 => {
+                        assert_eq!(params.title, "Sign message");
+                        assert_eq!(params.body.as_bytes(), MESSAGE);
+                        true
+                    }
+                    _ => panic!("too many user confirmations"),
+                }
+            })),
+            ..Default::default()
+        });
+        mock_unlocked();
+        let response = block_on(process(&request)).unwrap();
+        let proof = match response {
+            Response::SignMessage(pb::BtcSignMessageResponse {
+                ref signature,
+                ref bip322_proof,
+                ref address,
+                ref electrum_signature,
+            }) => {
+                assert!(signature.is_empty());
+                assert!(electrum_signature.is_empty());
+                assert_eq!(address, "bc1qk5f9em9qc8yfpks8ngfg3h8h02n2e3yeqdyhpt");
+                bip322_proof.clone()
+            }
+            _ => panic!("wrong response type"),
+        };
+        // Witness stack of two items: <DER signature + sighash byte> <compressed pubkey>.
+        assert_eq!(proof[0], 2);
+        let sig_len = proof[1] as usize;
+        let signature = &proof[2..2 + sig_len];
+        // DER sequence tag and trailing SIGHASH_ALL byte.
+        assert_eq!(signature[0], 0x30);
+        assert_eq!(signature[sig_len - 1], 0x01);
+        bitcoin::secp256k1::ecdsa::Signature::from_der(&signature[..sig_len - 1]).unwrap();
+        assert_eq!(proof[2 + sig_len] as usize, 33);
+        assert_eq!(
+            &proof[3 + sig_len..],
+            crate::keystore::get_xpub(KEYPATH).unwrap().public_key()
+        );
+    }
+
+    #[test]
+    pub fn test_bip322_p2tr() {
+        const KEYPATH: &[u32] = &[86 + HARDENED, 0 + HARDENED, 0 + HARDENED, 0, 0];
+        let request = pb::BtcSignMessageRequest {
+            coin: BtcCoin::Btc as _,
+            script_config: Some(pb::BtcScriptConfigWithKeypath {
+                script_config: Some(pb::BtcScriptConfig {
+                    config: Some(Config::SimpleType(SimpleType::P2tr as _)),
+                }),
+                keypath: KEYPATH.to_vec(),
+            }),
+            msg: MESSAGE.to_vec(),
+            host_nonce_commitment: None,
+            bip322: true,
+            msg_streamed_size: 0,
+        };
+
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|_params| true)),
+            ..Default::default()
+        });
+        mock_unlocked();
+        let response = block_on(process(&request)).unwrap();
+        match response {
+            Response::SignMessage(pb::BtcSignMessageResponse {
+                ref signature,
+                ref bip322_proof,
+                ref address,
+                ref electrum_signature,
+            }) => {
+                assert!(signature.is_empty());
+                assert!(electrum_signature.is_empty());
+                // Witness stack of one item: the 64 byte Schnorr signature (SIGHASH_DEFAULT).
+                assert_eq!(bip322_proof.len(), 66);
+                assert_eq!(bip322_proof[0], 1);
+                assert_eq!(bip322_proof[1], 64);
+                assert!(address.starts_with("bc1p"));
+            }
+            _ => panic!("wrong response type"),
+        }
+
+        // The anti-klepto protocol is not available for Schnorr signatures.
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|_params| true)),
+            ..Default::default()
+        });
+        mock_unlocked();
+        assert_eq!(
+            block_on(process(&pb::BtcSignMessageRequest {
+                host_nonce_commitment: Some(pb::AntiKleptoHostNonceCommitment {
+                    commitment: vec![0; 32],
+                }),
+                ..request
+            })),
+            Err(Error::InvalidInput)
+        );
+    }
+
+    /// BIP-322 "simple" proofs are rejected for script types that need a scriptSig.
+    #[test]
+    pub fn test_bip322_unsupported_script_type() {
+        for simple_type in [SimpleType::P2pkh, SimpleType::P2wpkhP2sh] {
+            assert_eq!(
+                block_on(process(&pb::BtcSignMessageRequest {
+                    coin: BtcCoin::Btc as _,
+                    script_config: Some(pb::BtcScriptConfigWithKeypath {
+                        script_config: Some(pb::BtcScriptConfig {
+                            config: Some(Config::SimpleType(simple_type as _)),
+                        }),
+                        keypath: vec![49 + HARDENED, 0 + HARDENED, 0 + HARDENED, 0, 0],
+                    }),
+                    msg: MESSAGE.to_vec(),
+                    host_nonce_commitment: None,
+                    bip322: true,
+                    msg_streamed_size: 0,
+                })),
+                Err(Error::InvalidInput)
+            );
+        }
+    }
+
+    /// A multi-chunk streamed message: the chunks are hashed incrementally, and the user reviews
+    /// the rolling hash plus the start and the end of the message.
+    #[test]
+    pub fn test_streamed() {
+        let msg: Vec<u8> = (0..5000u32).map(|i| b'a' + (i % 26) as u8).collect();
+        let request = pb::BtcSignMessageRequest {
+            coin: BtcCoin::Btc as _,
+            script_config: Some(pb::BtcScriptConfigWithKeypath {
+                script_config: Some(pb::BtcScriptConfig {
+                    config: Some(Config::SimpleType(SimpleType::P2wpkh as _)),
+                }),
+                keypath: vec![84 + HARDENED, 0 + HARDENED, 0 + HARDENED, 0, 0],
+            }),
+            msg: vec![],
+            host_nonce_commitment: None,
+            bip322: false,
+            msg_streamed_size: msg.len() as _,
+        };
+
+        static mut CHUNK_COUNTER: u32 = 0;
+        let msg_clone = msg.clone();
+        *crate::hww::MOCK_NEXT_REQUEST.0.borrow_mut() =
+            Some(Box::new(move |_response: crate::pb::response::Response| {
+                let sequence = unsafe {
+                    let counter = CHUNK_COUNTER;
+                    CHUNK_COUNTER += 1;
+                    counter
+                };
+                let chunk = msg_clone
+                    .chunks(1000)
+                    .nth(sequence as usize)
+                    .unwrap()
+                    .to_vec();
+                Ok(crate::pb::request::Request::Btc(pb::BtcRequest {
+                    request: Some(pb::btc_request::Request::SignMessageChunk(
+                        pb::BtcSignMessageChunkRequest { chunk, sequence },
+                    )),
+                }))
+            }));
+
+        static mut CONFIRM_COUNTER: u32 = 0;
+        let expected_hash = hex::encode(Sha256::digest(&msg));
+        let expected_head: String = core::str::from_utf8(&msg[..32]).unwrap().into();
+        let expected_tail: String = core::str::from_utf8(&msg[msg.len() - 32..]).unwrap().into();
+        mock(Data {
+            ui_confirm_create: Some(Box::new(move |params| {
+                match unsafe {
+                    CONFIRM_COUNTER += 1;
+                    CONFIRM_COUNTER
+                } {
+                    1 => {
+                        assert_eq!(params.title, "Sign message");
+                        assert_eq!(params.body, "Coin: Bitcoin");
+                    }
+                    2 => {
+                        assert_eq!(params.title, "Address");
+                        assert_eq!(params.body, "bc1qk5f9em9qc8yfpks8ngfg3h8h02n2e3yeqdyhpt");
+                    }
+                    3 => {
+                        assert_eq!(params.title, "Message hash");
+                        assert_eq!(params.body, expected_hash);
+                    }
+                    4 => {
+                        assert_eq!(params.title, "Starts with");
+                        assert_eq!(params.body, expected_head);
+                    }
+                    5 => {
+                        assert_eq!(params.title, "Ends with");
+                        assert_eq!(params.body, expected_tail);
+                        assert!(params.longtouch);
+                    }
+                    _ => panic!("too many user confirmations"),
+                }
+                true
+            })),
+            ..Default::default()
+        });
+        mock_unlocked();
+        match block_on(process(&request)) {
+            Ok(Response::SignMessage(pb::BtcSignMessageResponse {
+                ref signature,
+                ref bip322_proof,
+                ref address,
+                ref electrum_signature,
+            })) => {
+                assert_eq!(signature.len(), 65);
+                assert!(bip322_proof.is_empty());
+                // Same compact signature with the BIP-137 header in front instead of the trailing
+                // recid.
+                assert_eq!(electrum_signature.len(), 65);
+                assert_eq!(electrum_signature[0], 39 + signature[64]);
+                assert_eq!(&electrum_signature[1..], &signature[..64]);
+                assert_eq!(address, "bc1qk5f9em9qc8yfpks8ngfg3h8h02n2e3yeqdyhpt");
+            }
+            _ => panic!("wrong response type"),
+        }
+        assert_eq!(unsafe { CHUNK_COUNTER }, 5);
+    }
+
+    /// A truncated stream (host stops delivering chunks) and out-of-order chunks abort cleanly.
+    #[test]
+    pub fn test_streamed_failures() {
+        let request = pb::BtcSignMessageRequest {
+            coin: BtcCoin::Btc as _,
+            script_config: Some(pb::BtcScriptConfigWithKeypath {
+                script_config: Some(pb::BtcScriptConfig {
+                    config: Some(Config::SimpleType(SimpleType::P2wpkh as _)),
+                }),
+                keypath: vec![84 + HARDENED, 0 + HARDENED, 0 + HARDENED, 0, 0],
+            }),
+            msg: vec![],
+            host_nonce_commitment: None,
+            bip322: false,
+            msg_streamed_size: 5000,
+        };
+
+        // Announced size exceeds the bound.
+        mock(Data {
+            ..Default::default()
+        });
+        mock_unlocked();
+        assert_eq!(
+            block_on(process(&pb::BtcSignMessageRequest {
+                msg_streamed_size: (STREAM_MAX_MESSAGE_SIZE + 1) as _,
+                ..request.clone()
             })),
             Err(Error::InvalidInput)
         );
+
+        // Streaming cannot be combined with an inline message.
+        assert_eq!(
+            block_on(process(&pb::BtcSignMessageRequest {
+                msg: MESSAGE.to_vec(),
+                ..request.clone()
+            })),
+            Err(Error::InvalidInput)
+        );
+
+        // Truncated stream: the host answers the third chunk request with an unrelated message.
+        static mut CHUNK_COUNTER: u32 = 0;
+        *crate::hww::MOCK_NEXT_REQUEST.0.borrow_mut() =
+            Some(Box::new(move |_response: crate::pb::response::Response| {
+                let sequence = unsafe {
+                    let counter = CHUNK_COUNTER;
+                    CHUNK_COUNTER += 1;
+                    counter
+                };
+                if sequence == 2 {
+                    return Ok(crate::pb::request::Request::Btc(pb::BtcRequest {
+                        request: Some(pb::btc_request::Request::AntikleptoSignature(
+                            pb::AntiKleptoSignatureRequest { host_nonce: vec![] },
+                        )),
+                    }));
+                }
+                Ok(crate::pb::request::Request::Btc(pb::BtcRequest {
+                    request: Some(pb::btc_request::Request::SignMessageChunk(
+                        pb::BtcSignMessageChunkRequest {
+                            chunk: vec![b'x'; 1000],
+                            sequence,
+                        },
+                    )),
+                }))
+            }));
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|_params| true)),
+            ..Default::default()
+        });
+        mock_unlocked();
+        assert_eq!(block_on(process(&request)), Err(Error::InvalidState));
+
+        // Out-of-order chunk: the sequence number of the second chunk is repeated.
+        unsafe { CHUNK_COUNTER = 0 }
+        *crate::hww::MOCK_NEXT_REQUEST.0.borrow_mut() =
+            Some(Box::new(move |_response: crate::pb::response::Response| {
+                let sequence = unsafe {
+                    let counter = CHUNK_COUNTER;
+                    CHUNK_COUNTER += 1;
+                    counter
+                };
+                Ok(crate::pb::request::Request::Btc(pb::BtcRequest {
+                    request: Some(pb::btc_request::Request::SignMessageChunk(
+                        pb::BtcSignMessageChunkRequest {
+                            chunk: vec![b'x'; 1000],
+                            sequence: if sequence >= 2 { 1 } else { sequence },
+                        },
+                    )),
+                }))
+            }));
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|_params| true)),
+            ..Default::default()
+        });
+        mock_unlocked();
+        assert_eq!(block_on(process(&request)), Err(Error::InvalidInput));
     }
 }
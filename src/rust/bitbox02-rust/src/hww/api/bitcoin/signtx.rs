@@ -19,11 +19,12 @@ use super::common::format_amount;
 use super::payment_request;
 use super::script::serialize_varint;
 use super::script_configs::{ValidatedScriptConfig, ValidatedScriptConfigWithKeypath};
-use super::{bip143, bip341, common, keypath};
+use super::{bip143, bip341, common, keypath, legacy};
 
 use crate::workflow::{confirm, status, transaction};
 use crate::xpubcache::Bip32XpubCache;
 
+use alloc::string::String;
 use alloc::vec::Vec;
 
 use pb::request::Request;
@@ -87,6 +88,12 @@ async fn get_request(
         signature: vec![],
         prev_index: 0,
         anti_klepto_signer_commitment: None,
+        musig2_pub_nonce: vec![],
+        generated_output_pkscript: vec![],
+        total_out: 0,
+        fee: 0,
+        change_total: 0,
+        signatures: vec![],
     };
     Ok(request)
 }
@@ -103,6 +110,31 @@ async fn get_tx_input(
     }
 }
 
+/// Requests the second-pass input(s) starting at `index`. The host may answer with a single input
+/// as usual, or with a window of up to `SIGN_INPUTS_MAX_WINDOW` consecutive inputs wrapped in a
+/// `BTCSignInputsRequest`, saving one round trip per input. Returns the received inputs and
+/// whether they arrived as a wrapped window.
+async fn get_tx_inputs_windowed(
+    next_type: NextType,
+    index: u32,
+    response: &mut NextResponse,
+) -> Result<(Vec<pb::BtcSignInputRequest>, bool), Error> {
+    let request = get_request(next_type, index, None, response).await?;
+    match request {
+        Request::BtcSignInput(request) => {
+            response.wrap = false;
+            Ok((vec![request], false))
+        }
+        Request::Btc(pb::BtcRequest {
+            request: Some(pb::btc_request::Request::SignInputs(request)),
+        }) => {
+            response.wrap = true;
+            Ok((request.inputs, true))
+        }
+        _ => Err(Error::InvalidState),
+    }
+}
+
 async fn get_prevtx_init(
     index: u32,
     response: &mut NextResponse,
@@ -161,6 +193,52 @@ async fn get_prevtx_output(
     }
 }
 
+/// Requests a batch of previous transaction inputs starting at `prevtx_input_index`, for hosts
+/// that declared `supports_batched_entries` in the prevtx init.
+async fn get_prevtx_inputs(
+    input_index: u32,
+    prevtx_input_index: u32,
+    response: &mut NextResponse,
+) -> Result<Vec<pb::BtcPrevTxInputRequest>, Error> {
+    let request = get_request(
+        NextType::PrevtxInput,
+        input_index,
+        Some(prevtx_input_index),
+        response,
+    )
+    .await?;
+    response.wrap = true;
+    match request {
+        Request::Btc(pb::BtcRequest {
+            request: Some(pb::btc_request::Request::PrevtxInputs(request)),
+        }) => Ok(request.inputs),
+        _ => Err(Error::InvalidState),
+    }
+}
+
+/// Requests a batch of previous transaction outputs starting at `prevtx_output_index`, for hosts
+/// that declared `supports_batched_entries` in the prevtx init.
+async fn get_prevtx_outputs(
+    output_index: u32,
+    prevtx_output_index: u32,
+    response: &mut NextResponse,
+) -> Result<Vec<pb::BtcPrevTxOutputRequest>, Error> {
+    let request = get_request(
+        NextType::PrevtxOutput,
+        output_index,
+        Some(prevtx_output_index),
+        response,
+    )
+    .await?;
+    response.wrap = true;
+    match request {
+        Request::Btc(pb::BtcRequest {
+            request: Some(pb::btc_request::Request::PrevtxOutputs(request)),
+        }) => Ok(request.outputs),
+        _ => Err(Error::InvalidState),
+    }
+}
+
 async fn get_tx_output(
     index: u32,
     response: &mut NextResponse,
@@ -201,6 +279,32 @@ async fn get_antiklepto_host_nonce(
     }
 }
 
+/// Sends our MuSig2 public nonce for the input at `index` to the host and waits for the host to
+/// deliver the public nonces of the other cosigners.
+async fn get_musig2_nonces(
+    index: u32,
+    our_pub_nonce: &[u8; 66],
+    response: &mut NextResponse,
+) -> Result<pb::BtcMusig2NoncesRequest, Error> {
+    response.next.musig2_pub_nonce = our_pub_nonce.to_vec();
+    let request = get_request(NextType::Musig2Nonces, index, None, response).await?;
+    response.wrap = true;
+    match request {
+        Request::Btc(pb::BtcRequest {
+            request: Some(pb::btc_request::Request::Musig2Nonces(request)),
+        }) => Ok(request),
+        _ => Err(Error::InvalidState),
+    }
+}
+
+// Maximum length of a host-provided output label (`display_name`).
+const MAX_DISPLAY_NAME_LEN: usize = 50;
+
+// Account and address indices in input and change keypaths above this bound trigger a warning
+// confirmation: a wallet restoring from the seed will never scan that far past the gap limit, so
+// funds on such keypaths are easily lost.
+const ADDRESS_INDEX_WARNING_BOUND: u32 = 10_000;
+
 fn validate_keypath(
     params: &super::params::Params,
     script_config_account: &ValidatedScriptConfigWithKeypath,
@@ -216,29 +320,73 @@ fn validate_keypath(
                 params.taproot_support,
                 mode,
             )
-            .or(Err(Error::InvalidInput))?;
+            .or(Err(Error::InvalidInputDetail("invalid keypath")))?;
         }
-        ValidatedScriptConfig::Multisig(_) | ValidatedScriptConfig::Policy(_) => {
-            keypath::validate_address_policy(keypath, mode).or(Err(Error::InvalidInput))?;
+        ValidatedScriptConfig::Multisig(_)
+        | ValidatedScriptConfig::Policy(_)
+        | ValidatedScriptConfig::Musig2(_) => {
+            keypath::validate_address_policy(keypath, mode)
+                .or(Err(Error::InvalidInputDetail("invalid keypath")))?;
         }
     }
     // Check that keypath_account is a prefix to keypath with two elements left (change, address).
     if script_config_account.keypath.len() + 2 != keypath.len() {
-        return Err(Error::InvalidInput);
+        return Err(Error::InvalidInputDetail("invalid keypath"));
     }
     if &keypath[..script_config_account.keypath.len()] != script_config_account.keypath {
-        return Err(Error::InvalidInput);
+        return Err(Error::InvalidInputDetail("invalid keypath"));
     }
     Ok(())
 }
 
+/// Returns true if the account or final address index of `keypath` exceeds
+/// `ADDRESS_INDEX_WARNING_BOUND`. The keypath must have passed `validate_keypath()`, which
+/// guarantees it ends in `<account>/<change>/<address>`.
+fn has_unusual_address_index(keypath: &[u32]) -> bool {
+    let account = keypath[keypath.len() - 3] & !util::bip32::HARDENED;
+    let address = keypath[keypath.len() - 1];
+    account > ADDRESS_INDEX_WARNING_BOUND || address > ADDRESS_INDEX_WARNING_BOUND
+}
+
+/// Warns about an unusually high account or address index in an input or change keypath. A
+/// compromised host could otherwise move change to an index no wallet will ever rediscover,
+/// effectively burning the funds. Rejecting the warning aborts the signing process.
+async fn confirm_unusual_address_index(keypath: &[u32]) -> Result<(), Error> {
+    confirm::confirm(&confirm::Params {
+        title: "Warning",
+        body: &format!(
+            "Unusually high\naddress index:\n{}",
+            util::bip32::to_string(keypath)
+        ),
+        scrollable: true,
+        accept_is_nextarrow: true,
+        ..Default::default()
+    })
+    .await?;
+    Ok(())
+}
+
 fn validate_input(
     input: &pb::BtcSignInputRequest,
     params: &super::params::Params,
     script_config_account: &ValidatedScriptConfigWithKeypath,
 ) -> Result<(), Error> {
     if input.prev_out_value == 0 {
-        return Err(Error::InvalidInput);
+        return Err(Error::InvalidInputDetail("zero input value"));
+    }
+    // Taproot script path spends must provide both the leaf script and the control block, and are
+    // only valid for single-sig taproot script configs (MuSig2 inputs are always key path spends
+    // of the aggregate key).
+    if !input.script_path_leaf_script.is_empty() || !input.script_path_control_block.is_empty() {
+        if input.script_path_leaf_script.is_empty() || input.script_path_control_block.is_empty() {
+            return Err(Error::InvalidInput);
+        }
+        if !matches!(
+            script_config_account.config,
+            ValidatedScriptConfig::SimpleType(SimpleType::P2tr)
+        ) {
+            return Err(Error::InvalidInput);
+        }
     }
     validate_keypath(
         params,
@@ -248,10 +396,179 @@ fn validate_input(
     )
 }
 
+/// Renders the recipient line of an output confirmation. A host-provided label (`display_name`)
+/// is shown above the address, clearly marked as unverified app data; it never replaces the
+/// address. Invalid labels are rejected instead of sanitized: control characters (e.g. newlines)
+/// could be used to spoof the amount line or push the address off-screen.
+fn format_recipient(address: &str, display_name: &str) -> Result<String, Error> {
+    if display_name.is_empty() {
+        return Ok(address.into());
+    }
+    if !util::name::validate(display_name, MAX_DISPLAY_NAME_LEN) {
+        return Err(Error::InvalidInput);
+    }
+    Ok(format!("Note from app:\n{}\n{}", display_name, address))
+}
+
+/// Defense in depth against a faulty keystore or secure chip and against fault injection
+/// (glitching) attacks: every produced ECDSA signature is verified against the sighash and the
+/// public key derived at the keypath before it leaves the device. This also covers signatures
+/// produced with the anti-klepto protocol, where the nonce is adapted after signing. A signature
+/// failing verification aborts the whole signing flow; it is never emitted.
+fn verify_ecdsa_signature(
+    xpub_cache: &mut Bip32XpubCache,
+    keypath: &[u32],
+    sighash: &[u8; 32],
+    signature: &[u8; 64],
+) -> Result<(), Error> {
+    let secp = bitcoin::secp256k1::Secp256k1::new();
+    let pubkey =
+        bitcoin::secp256k1::PublicKey::from_slice(xpub_cache.get_xpub(keypath)?.public_key())
+            .or(Err(Error::SignatureCheck))?;
+    let msg = bitcoin::secp256k1::Message::from_digest_slice(sighash).unwrap();
+    let sig = bitcoin::secp256k1::ecdsa::Signature::from_compact(signature)
+        .or(Err(Error::SignatureCheck))?;
+    secp.verify_ecdsa(&msg, &sig, &pubkey)
+        .or(Err(Error::SignatureCheck))
+}
+
+/// Schnorr counterpart of `verify_ecdsa_signature()`. Key path spends are verified against the
+/// BIP-86 tweaked output key, script path spends against the untweaked key at the keypath.
+fn verify_schnorr_signature(
+    xpub_cache: &mut Bip32XpubCache,
+    keypath: &[u32],
+    script_path_spend: bool,
+    sighash: &[u8; 32],
+    signature: &[u8; 64],
+) -> Result<(), Error> {
+    let pubkey: [u8; 33] = xpub_cache
+        .get_xpub(keypath)?
+        .public_key()
+        .try_into()
+        .or(Err(Error::SignatureCheck))?;
+    let pubkey_xonly: [u8; 32] = if script_path_spend {
+        pubkey[1..].try_into().unwrap()
+    } else {
+        bip341::output_key_no_script(&pubkey).or(Err(Error::SignatureCheck))?
+    };
+    let secp = bitcoin::secp256k1::Secp256k1::new();
+    let pubkey = bitcoin::secp256k1::XOnlyPublicKey::from_slice(&pubkey_xonly)
+        .or(Err(Error::SignatureCheck))?;
+    let msg = bitcoin::secp256k1::Message::from_digest_slice(sighash).unwrap();
+    let sig = bitcoin::secp256k1::schnorr::Signature::from_slice(signature)
+        .or(Err(Error::SignatureCheck))?;
+    secp.verify_schnorr(&sig, &msg, &pubkey)
+        .or(Err(Error::SignatureCheck))
+}
+
+/// Accumulates the identifying fields of an input into `hasher`. The same hash is computed over
+/// both input passes and compared, verifying that the host streamed byte-identical inputs in the
+/// same order; see `_process()`.
+fn hash_input(hasher: &mut Sha256, input: &pb::BtcSignInputRequest) {
+    hasher.update(input.prev_out_hash.as_slice());
+    hasher.update(input.prev_out_index.to_le_bytes());
+    hasher.update(input.prev_out_value.to_le_bytes());
+    hasher.update(input.sequence.to_le_bytes());
+    // The keypath is length-prefixed so that field boundaries are unambiguous.
+    hasher.update(serialize_varint(input.keypath.len() as u64).as_slice());
+    for el in input.keypath.iter() {
+        hasher.update(el.to_le_bytes());
+    }
+    hasher.update(input.script_config_index.to_le_bytes());
+}
+
+/// Rough human readable duration of `minutes`, used to display BIP-68 relative locktimes.
+fn format_duration(minutes: u64) -> String {
+    if minutes < 60 {
+        return format!("{} min", minutes);
+    }
+    let (value, unit) = if minutes < 1440 {
+        (minutes / 60, "hour")
+    } else {
+        (minutes / 1440, "day")
+    };
+    if value == 1 {
+        format!("1 {}", unit)
+    } else {
+        format!("{} {}s", value, unit)
+    }
+}
+
+/// Validates an input that is marked foreign (not belonging to this device, e.g. the receiver's
+/// input in a payjoin transaction). Such inputs have no keypath and are never signed.
+fn validate_input_foreign(input: &pb::BtcSignInputRequest) -> Result<(), Error> {
+    if input.prev_out_value == 0
+        || !input.keypath.is_empty()
+        || input.host_nonce_commitment.is_some()
+        || !input.script_path_leaf_script.is_empty()
+        || !input.script_path_control_block.is_empty()
+    {
+        return Err(Error::InvalidInput);
+    }
+    Ok(())
+}
+
+/// Verifies the control block of a taproot script path input against our internal key (the
+/// untweaked pubkey at the input keypath) and returns the resulting x-only taproot output key.
+fn validate_script_path_spend(
+    xpub_cache: &mut Bip32XpubCache,
+    input: &pb::BtcSignInputRequest,
+) -> Result<[u8; 32], Error> {
+    let internal_pubkey: [u8; 33] = xpub_cache
+        .get_xpub(&input.keypath)?
+        .public_key()
+        .try_into()
+        .or(Err(Error::Generic))?;
+    bip341::verify_control_block(
+        &input.script_path_control_block,
+        &internal_pubkey,
+        &input.script_path_leaf_script,
+    )
+    .or(Err(Error::InvalidInput))
+}
+
 fn is_taproot(script_config_account: &ValidatedScriptConfigWithKeypath) -> bool {
     matches!(
         script_config_account.config,
-        ValidatedScriptConfig::SimpleType(SimpleType::P2tr)
+        ValidatedScriptConfig::SimpleType(SimpleType::P2tr) | ValidatedScriptConfig::Musig2(_)
+    )
+}
+
+/// Dust threshold in satoshis per output type, following Bitcoin Core's default dust relay fee of
+/// 3000 sat/kvB: an output is dust if spending it costs more than a third of its value.
+fn dust_threshold(output_type: pb::BtcOutputType) -> u64 {
+    match output_type {
+        pb::BtcOutputType::P2pkh => 546,
+        pb::BtcOutputType::P2sh => 540,
+        pb::BtcOutputType::P2wpkh => 294,
+        pb::BtcOutputType::P2wsh | pb::BtcOutputType::P2tr => 330,
+        // Future witness outputs get the generic segwit threshold, matching Bitcoin Core's dust
+        // rule for unknown witness versions.
+        pb::BtcOutputType::FutureWitness => 330,
+        pb::BtcOutputType::Unknown => 0,
+    }
+}
+
+/// Formats a satoshi amount for display, appending the approximate fiat value on a second line if
+/// the host supplied a fiat rate. The fiat line is marked as unverified host data.
+fn format_display_amount(
+    coin_params: &super::params::Params,
+    format_unit: FormatUnit,
+    fiat_rate: Option<&pb::btc_sign_init_request::FiatRate>,
+    satoshi: u64,
+) -> Result<String, Error> {
+    let mut amount = format_amount(coin_params, format_unit, satoshi)?;
+    if let Some(fiat_rate) = fiat_rate {
+        amount.push('\n');
+        amount.push_str(&common::format_fiat_amount(fiat_rate, satoshi)?);
+    }
+    Ok(amount)
+}
+
+fn is_legacy(script_config_account: &ValidatedScriptConfigWithKeypath) -> bool {
+    matches!(
+        script_config_account.config,
+        ValidatedScriptConfig::SimpleType(SimpleType::P2pkh)
     )
 }
 
@@ -269,9 +586,11 @@ fn sighash_script(
             ..
         } => {
             match simple_type {
-                SimpleType::P2wpkhP2sh | SimpleType::P2wpkh => {
+                SimpleType::P2pkh | SimpleType::P2wpkhP2sh | SimpleType::P2wpkh => {
                     // See https://github.com/bitcoin/bips/blob/master/bip-0143.mediawiki#specification, item 5:
                     // > For P2WPKH witness program, the scriptCode is 0x1976a914{20-byte-pubkey-hash}88ac.
+                    // For legacy P2PKH inputs, the script code is the pkScript of the output being
+                    // spent, which has the same shape.
                     let pubkey_hash160 = xpub_cache.get_xpub(keypath)?.pubkey_hash160();
                     let mut result = Vec::<u8>::new();
                     result.extend_from_slice(b"\x76\xa9\x14");
@@ -296,90 +615,380 @@ fn sighash_script(
         } => match policy.derive_at_keypath(keypath)? {
             super::policies::Descriptor::Wsh(wsh) => Ok(wsh.witness_script()),
         },
+        // MuSig2 inputs are taproot inputs and do not use the bip143 sighash.
+        ValidatedScriptConfigWithKeypath {
+            config: ValidatedScriptConfig::Musig2(_),
+            ..
+        } => Err(Error::Generic),
+    }
+}
+
+// A standard transaction must not exceed this weight (Bitcoin Core's MAX_STANDARD_TX_WEIGHT). A
+// heavier transaction signs fine but is then rejected by every relay node, so the user is warned
+// before any signing happens.
+const MAX_STANDARD_TX_WEIGHT: u64 = 400_000;
+
+// Conservative weight estimate for one foreign input. Its witness is produced by another wallet
+// and its type is unknown to us; 600 weight units cover all common singlesig input types (legacy
+// P2PKH is the largest at 592).
+const FOREIGN_INPUT_WEIGHT_ESTIMATE: u64 = 600;
+
+// If the host opts in via `summarize_outputs` and the transaction pays more external recipients
+// than this (withdrawal batching), they are confirmed in one summarized dialog instead of one
+// dialog per recipient. At or below the threshold the flag is ignored.
+const SUMMARIZE_OUTPUTS_THRESHOLD: usize = 20;
+
+// With `summarize_outputs`, the rendered recipients are retained for the optional individual
+// inspection only up to this count. Beyond it, only the summary (recipient count and total) is
+// shown and the inspection step is not offered, so memory stays bounded for large withdrawal
+// batches.
+const SUMMARIZE_OUTPUTS_INSPECT_MAX: usize = 200;
+
+// Hard caps on the number of inputs and outputs of the transaction being signed. Both passes
+// stream with constant memory - the prevout/sequence/amount/output hashes are running
+// accumulators - so the caps only bound the small per-entry bookkeeping that remains (such as
+// the first-pass values retained for `confirm_outputs_first`, 8 bytes per input) and keep a
+// misbehaving host from occupying the device indefinitely.
+const SIGN_MAX_INPUTS: u32 = 2_500;
+const SIGN_MAX_OUTPUTS: u32 = 2_500;
+
+// Lower caps if any script config is legacy (P2PKH): the legacy sighash re-serializes the whole
+// transaction per signed input, so every outpoint and every serialized output is retained in
+// memory for the duration of the signing.
+const SIGN_MAX_INPUTS_LEGACY: u32 = 400;
+const SIGN_MAX_OUTPUTS_LEGACY: u32 = 400;
+
+// Upper bound on the entries of each deferred per-input warning list (relative locktimes, unusual
+// address indexes, advanced input verification, self outputs in a mixed transaction). Every entry
+// costs the user one confirmation dialog, so more than this many is not meaningfully reviewable,
+// and the bound keeps the lists from growing with the input/output count.
+const SIGN_MAX_DEFERRED_CONFIRMS: usize = 25;
+
+/// Conservative estimate (overestimate) of the weight contributed by one signed input: its
+/// serialization in the base transaction, counted four times per BIP-141, plus its witness.
+fn estimate_input_weight(
+    xpub_cache: &mut Bip32XpubCache,
+    script_config_account: &ValidatedScriptConfigWithKeypath,
+    tx_input: &pb::BtcSignInputRequest,
+) -> Result<u64, Error> {
+    // Outpoint (36 bytes), scriptSig length prefix (1 byte) and sequence (4 bytes).
+    const INPUT_BASE_SIZE: u64 = 32 + 4 + 1 + 4;
+    // Witness of a P2WPKH spend: the item count, a 72 byte DER signature (incl. sighash byte) and
+    // a 33 byte public key, each with a length prefix.
+    const P2WPKH_WITNESS_SIZE: u64 = 1 + 1 + 72 + 1 + 33;
+    Ok(match &script_config_account.config {
+        ValidatedScriptConfig::SimpleType(SimpleType::P2wpkh) => {
+            4 * INPUT_BASE_SIZE + P2WPKH_WITNESS_SIZE
+        }
+        ValidatedScriptConfig::SimpleType(SimpleType::P2wpkhP2sh) => {
+            // The scriptSig contains the 22 byte segwit redeem script with a push prefix.
+            4 * (INPUT_BASE_SIZE + 23) + P2WPKH_WITNESS_SIZE
+        }
+        ValidatedScriptConfig::SimpleType(SimpleType::P2pkh) => {
+            // No witness; the scriptSig contains a 72 byte DER signature and a 33 byte public
+            // key, each with a push prefix.
+            4 * (INPUT_BASE_SIZE + 73 + 34)
+        }
+        ValidatedScriptConfig::SimpleType(SimpleType::P2tr) | ValidatedScriptConfig::Musig2(_) => {
+            if tx_input.script_path_leaf_script.is_empty() {
+                // Key path spend: the witness is a single 64 byte Schnorr signature.
+                4 * INPUT_BASE_SIZE + 1 + 1 + 64
+            } else {
+                // Script path spend: the signature, the leaf script and the control block, each
+                // with a length prefix.
+                4 * INPUT_BASE_SIZE
+                    + 1
+                    + 1
+                    + 64
+                    + 3
+                    + tx_input.script_path_leaf_script.len() as u64
+                    + 3
+                    + tx_input.script_path_control_block.len() as u64
+            }
+        }
+        ValidatedScriptConfig::Multisig(multisig) => {
+            let witness_script_len =
+                sighash_script(xpub_cache, script_config_account, &tx_input.keypath)?.len() as u64;
+            // The item count, the OP_0 dummy element, one 72 byte DER signature per cosigner of
+            // the threshold and the witness script, each with a length prefix.
+            4 * INPUT_BASE_SIZE + 1 + 1 + 73 * multisig.threshold as u64 + 3 + witness_script_len
+        }
+        ValidatedScriptConfig::Policy(policy) => {
+            let witness_script_len =
+                sighash_script(xpub_cache, script_config_account, &tx_input.keypath)?.len() as u64;
+            let super::policies::Descriptor::Wsh(wsh) = &policy.descriptor;
+            // The item count, the maximum satisfaction stack (which includes per-item length
+            // prefixes) and the witness script with a length prefix.
+            4 * INPUT_BASE_SIZE + 3 + wsh.max_satisfaction_size()? as u64 + 3 + witness_script_len
+        }
+    })
+}
+
+// Bounds on previous transactions streamed by the host. A malicious host could otherwise claim
+// billions of inputs and keep the device grinding in `handle_prevtx` forever. The limits are far
+// above anything occurring in a valid transaction.
+const PREVTX_MAX_INPUTS: u32 = 10_000;
+const PREVTX_MAX_OUTPUTS: u32 = 10_000;
+const PREVTX_MAX_SCRIPT_SIZE: usize = 10_000;
+// Maximum cumulative size of the variable-length fields hashed per previous transaction, larger
+// than the maximum size of a Bitcoin transaction.
+const PREVTX_MAX_SIZE: u64 = 1_000_000;
+
+// Maximum number of prevtx entries accepted per batched host message, keeping the decoded message
+// small enough for the device's RAM. Hosts declaring `supports_batched_entries` must not exceed
+// it.
+const PREVTX_MAX_BATCH_ENTRIES: u32 = 25;
+
+// Maximum number of second-pass inputs accepted per `BTCSignInputsRequest` window, keeping the
+// decoded message small enough for the device's RAM.
+const SIGN_INPUTS_MAX_WINDOW: u32 = 25;
+
+// Bounds of the previous transaction cache: number of cached transactions (the oldest entry is
+// evicted beyond that) and number of outputs stored per cached transaction (bigger transactions
+// are simply streamed again).
+const PREVTX_CACHE_MAX_TXS: usize = 4;
+const PREVTX_CACHE_MAX_OUTPUTS: usize = 64;
+
+/// Throttles progress bar updates during prevtx streaming: on big previous transactions,
+/// redrawing the screen for every streamed entry becomes a measurable fraction of the loading
+/// time (a 500-entry prevtx used to trigger 500 redraws; with the ~1% threshold, at most ~100
+/// remain). The final 100% update at the end of the first inputs pass is drawn unconditionally.
+struct ProgressThrottle {
+    last_drawn: f32,
+}
+
+impl ProgressThrottle {
+    fn new() -> Self {
+        ProgressThrottle { last_drawn: -1. }
+    }
+
+    /// Returns true if `fraction` moved at least 1% since the last drawn value and should be
+    /// drawn. The first update is always drawn.
+    fn should_draw(&mut self, fraction: f32) -> bool {
+        if (fraction - self.last_drawn).abs() >= 0.01 {
+            self.last_drawn = fraction;
+            return true;
+        }
+        false
     }
 }
 
+/// Outputs of already streamed and verified previous transactions, keyed by the transaction hash.
+/// Spending several outputs of the same previous transaction then only streams it once.
+type PrevtxCache = Vec<([u8; 32], Vec<pb::BtcPrevTxOutputRequest>)>;
+
 /// Stream an input's previous transaction and verify that the prev_out_hash in the input matches
 /// the hash of the previous transaction, as well as that the amount provided in the input is correct.
+///
+/// If the previous transaction was already streamed and verified for an earlier input, it is
+/// served from `cache` and not streamed again.
+///
+/// Returns the pubkey script of the previous transaction output referenced by the input, which for
+/// foreign inputs is needed to compute the BIP-341 `sha_scriptpubkeys`.
 async fn handle_prevtx(
     input_index: u32,
     input: &pb::BtcSignInputRequest,
     num_inputs: u32,
     progress_component: &mut bitbox02::ui::Component<'_>,
+    cache: &mut PrevtxCache,
     next_response: &mut NextResponse,
-) -> Result<(), Error> {
+) -> Result<Vec<u8>, Error> {
+    if let Some((_, cached_outputs)) = cache
+        .iter()
+        .find(|(hash, _)| hash.as_slice() == input.prev_out_hash.as_slice())
+    {
+        // Only the amount needs re-verification; the transaction itself was already verified
+        // against its hash.
+        let prevtx_output = cached_outputs
+            .get(input.prev_out_index as usize)
+            .ok_or(Error::InvalidInputDetail("prevout index out of range"))?;
+        if input.prev_out_value != prevtx_output.value {
+            return Err(Error::InvalidInputDetail("prevout value mismatch"));
+        }
+        return Ok(prevtx_output.pubkey_script.clone());
+    }
+
     let prevtx_init = get_prevtx_init(input_index, next_response).await?;
 
-    if prevtx_init.num_inputs < 1
-        || prevtx_init.num_outputs < 1
-        || input.prev_out_index >= prevtx_init.num_outputs
-    {
+    if prevtx_init.num_inputs < 1 || prevtx_init.num_outputs < 1 {
+        return Err(Error::InvalidInputDetail("empty prevtx"));
+    }
+    if input.prev_out_index >= prevtx_init.num_outputs {
+        return Err(Error::InvalidInputDetail("prevout index out of range"));
+    }
+    if prevtx_init.num_inputs > PREVTX_MAX_INPUTS || prevtx_init.num_outputs > PREVTX_MAX_OUTPUTS {
         return Err(Error::InvalidInput);
     }
 
+    // Cumulative size of the variable-length fields hashed for this prevtx.
+    let mut prevtx_size: u64 = 0;
+
+    let mut progress_throttle = ProgressThrottle::new();
+
     let mut hasher = Sha256::new();
     hasher.update(prevtx_init.version.to_le_bytes());
 
     hasher.update(serialize_varint(prevtx_init.num_inputs as u64).as_slice());
-    for prevtx_input_index in 0..prevtx_init.num_inputs {
-        // Update progress.
-        bitbox02::ui::progress_set(progress_component, {
-            let step = 1f32 / (num_inputs as f32);
-            let subprogress: f32 = (prevtx_input_index as f32)
-                / (prevtx_init.num_inputs + prevtx_init.num_outputs) as f32;
-            (input_index as f32 + subprogress) * step
-        });
+    let mut prevtx_input_index: u32 = 0;
+    while prevtx_input_index < prevtx_init.num_inputs {
+        // A batching host sends a chunk of consecutive inputs per request; legacy hosts send them
+        // one by one.
+        let prevtx_inputs: Vec<pb::BtcPrevTxInputRequest> = if prevtx_init.supports_batched_entries
+        {
+            let prevtx_inputs =
+                get_prevtx_inputs(input_index, prevtx_input_index, next_response).await?;
+            let remaining = prevtx_init.num_inputs - prevtx_input_index;
+            if prevtx_inputs.is_empty()
+                || prevtx_inputs.len() as u32 > remaining.min(PREVTX_MAX_BATCH_ENTRIES)
+            {
+                return Err(Error::InvalidInput);
+            }
+            prevtx_inputs
+        } else {
+            vec![get_prevtx_input(input_index, prevtx_input_index, next_response).await?]
+        };
+        for prevtx_input in prevtx_inputs {
+            // Update progress. Dividing by the input count directly, instead of multiplying by a
+            // precomputed reciprocal, avoids compounding two roundings with large input counts.
+            let progress = {
+                let subprogress: f32 = (prevtx_input_index as f32)
+                    / (prevtx_init.num_inputs + prevtx_init.num_outputs) as f32;
+                (input_index as f32 + subprogress) / (num_inputs as f32)
+            };
+            if progress_throttle.should_draw(progress) {
+                bitbox02::ui::progress_set(progress_component, progress);
+            }
 
-        let prevtx_input = get_prevtx_input(input_index, prevtx_input_index, next_response).await?;
-        hasher.update(prevtx_input.prev_out_hash.as_slice());
-        hasher.update(prevtx_input.prev_out_index.to_le_bytes());
-        hasher.update(serialize_varint(prevtx_input.signature_script.len() as u64).as_slice());
-        hasher.update(prevtx_input.signature_script.as_slice());
-        hasher.update(prevtx_input.sequence.to_le_bytes());
+            if prevtx_input.signature_script.len() > PREVTX_MAX_SCRIPT_SIZE {
+                return Err(Error::InvalidInput);
+            }
+            prevtx_size +=
+                (prevtx_input.prev_out_hash.len() + prevtx_input.signature_script.len()) as u64;
+            if prevtx_size > PREVTX_MAX_SIZE {
+                return Err(Error::InvalidInput);
+            }
+            hasher.update(prevtx_input.prev_out_hash.as_slice());
+            hasher.update(prevtx_input.prev_out_index.to_le_bytes());
+            hasher.update(serialize_varint(prevtx_input.signature_script.len() as u64).as_slice());
+            hasher.update(prevtx_input.signature_script.as_slice());
+            hasher.update(prevtx_input.sequence.to_le_bytes());
+            prevtx_input_index += 1;
+        }
     }
 
+    let mut referenced_pubkey_script: Option<Vec<u8>> = None;
+    let mut outputs_to_cache: Vec<pb::BtcPrevTxOutputRequest> = Vec::new();
     hasher.update(serialize_varint(prevtx_init.num_outputs as u64).as_slice());
-    for prevtx_output_index in 0..prevtx_init.num_outputs {
-        // Update progress.
-        bitbox02::ui::progress_set(progress_component, {
-            let step = 1f32 / (num_inputs as f32);
-            let subprogress: f32 = (prevtx_init.num_inputs + prevtx_output_index) as f32
-                / (prevtx_init.num_inputs + prevtx_init.num_outputs) as f32;
-            (input_index as f32 + subprogress) * step
-        });
+    let mut prevtx_output_index: u32 = 0;
+    while prevtx_output_index < prevtx_init.num_outputs {
+        let prevtx_outputs: Vec<pb::BtcPrevTxOutputRequest> =
+            if prevtx_init.supports_batched_entries {
+                let prevtx_outputs =
+                    get_prevtx_outputs(input_index, prevtx_output_index, next_response).await?;
+                let remaining = prevtx_init.num_outputs - prevtx_output_index;
+                if prevtx_outputs.is_empty()
+                    || prevtx_outputs.len() as u32 > remaining.min(PREVTX_MAX_BATCH_ENTRIES)
+                {
+                    return Err(Error::InvalidInput);
+                }
+                prevtx_outputs
+            } else {
+                vec![get_prevtx_output(input_index, prevtx_output_index, next_response).await?]
+            };
+        for prevtx_output in prevtx_outputs {
+            // Update progress; see above regarding the division.
+            let progress = {
+                let subprogress: f32 = (prevtx_init.num_inputs + prevtx_output_index) as f32
+                    / (prevtx_init.num_inputs + prevtx_init.num_outputs) as f32;
+                (input_index as f32 + subprogress) / (num_inputs as f32)
+            };
+            if progress_throttle.should_draw(progress) {
+                bitbox02::ui::progress_set(progress_component, progress);
+            }
 
-        let prevtx_output =
-            get_prevtx_output(input_index, prevtx_output_index, next_response).await?;
-        if prevtx_output_index == input.prev_out_index
-            && input.prev_out_value != prevtx_output.value
-        {
-            return Err(Error::InvalidInput);
+            if prevtx_output.pubkey_script.len() > PREVTX_MAX_SCRIPT_SIZE {
+                return Err(Error::InvalidInput);
+            }
+            prevtx_size += prevtx_output.pubkey_script.len() as u64;
+            if prevtx_size > PREVTX_MAX_SIZE {
+                return Err(Error::InvalidInput);
+            }
+            if prevtx_output_index == input.prev_out_index {
+                if input.prev_out_value != prevtx_output.value {
+                    return Err(Error::InvalidInputDetail("prevout value mismatch"));
+                }
+                referenced_pubkey_script = Some(prevtx_output.pubkey_script.clone());
+            }
+            hasher.update(prevtx_output.value.to_le_bytes());
+            hasher.update(serialize_varint(prevtx_output.pubkey_script.len() as u64).as_slice());
+            hasher.update(prevtx_output.pubkey_script.as_slice());
+            if prevtx_init.num_outputs as usize <= PREVTX_CACHE_MAX_OUTPUTS {
+                outputs_to_cache.push(prevtx_output);
+            }
+            prevtx_output_index += 1;
         }
-        hasher.update(prevtx_output.value.to_le_bytes());
-        hasher.update(serialize_varint(prevtx_output.pubkey_script.len() as u64).as_slice());
-        hasher.update(prevtx_output.pubkey_script.as_slice());
     }
 
     hasher.update(prevtx_init.locktime.to_le_bytes());
     // Hash again to produce the final double-hash.
     let hash = Sha256::digest(hasher.finalize());
     if hash.as_slice() != input.prev_out_hash.as_slice() {
-        return Err(Error::InvalidInput);
+        return Err(Error::InvalidInputDetail("prevtx hash mismatch"));
     }
-    Ok(())
+    // Only fully verified transactions enter the cache.
+    if !outputs_to_cache.is_empty() {
+        if cache.len() == PREVTX_CACHE_MAX_TXS {
+            // Evict the oldest entry to keep memory bounded.
+            cache.remove(0);
+        }
+        cache.push((hash.into(), outputs_to_cache));
+    }
+    // Defense in depth: this is only set where the claimed `prev_out_value` was actually compared
+    // against the referenced output, so the bounds check on `prev_out_index` above is not the only
+    // thing standing between an unverified claim and the fee math.
+    referenced_pubkey_script.ok_or(Error::InvalidInput)
+}
+
+/// Desugars `Miniscript` script configs into the equivalent `wsh()` wallet policies, so that the
+/// rest of the signing flow only deals with the `Policy` variant. Other configs are passed through
+/// unchanged.
+fn desugar_script_configs(
+    script_configs: &[pb::BtcScriptConfigWithKeypath],
+) -> Vec<pb::BtcScriptConfigWithKeypath> {
+    script_configs
+        .iter()
+        .map(|script_config| match script_config {
+            pb::BtcScriptConfigWithKeypath {
+                script_config:
+                    Some(pb::BtcScriptConfig {
+                        config: Some(pb::btc_script_config::Config::Miniscript(miniscript)),
+                    }),
+                keypath,
+            } => pb::BtcScriptConfigWithKeypath {
+                script_config: Some(pb::BtcScriptConfig {
+                    config: Some(pb::btc_script_config::Config::Policy(
+                        super::policies::wrap_miniscript(miniscript),
+                    )),
+                }),
+                keypath: keypath.clone(),
+            },
+            script_config => script_config.clone(),
+        })
+        .collect()
 }
 
 async fn validate_script_configs<'a>(
     coin_params: &super::params::Params,
     script_configs: &'a [pb::BtcScriptConfigWithKeypath],
+    dry_run: bool,
 ) -> Result<Vec<ValidatedScriptConfigWithKeypath<'a>>, Error> {
     if script_configs.is_empty() {
         return Err(Error::InvalidInput);
     }
 
     // If there are multiple script configs, only SimpleType (single sig, no additional inputs)
-    // configs are allowed, so e.g. mixing p2wpkh and pw2wpkh-p2sh is okay, but mixing p2wpkh with
-    // multisig-pw2sh is not.
+    // and Multisig configs are allowed, so e.g. mixing p2wpkh and pw2wpkh-p2sh is okay, and so is
+    // mixing p2wpkh with a registered multisig, but policies and MuSig2 cannot be mixed.
 
     // We get multisig out of the way first.
 
@@ -394,13 +1003,40 @@ async fn validate_script_configs<'a>(
         super::multisig::validate(multisig, keypath)?;
         let name = super::multisig::get_name(coin_params.coin, multisig, keypath)?
             .ok_or(Error::InvalidInput)?;
-        super::multisig::confirm("Spend from", coin_params, &name, multisig).await?;
+        if !dry_run {
+            super::multisig::confirm("Spend from", coin_params, &name, multisig).await?;
+        }
         return Ok(vec![ValidatedScriptConfigWithKeypath {
             keypath,
             config: ValidatedScriptConfig::Multisig(multisig),
         }]);
     }
 
+    // Then we get MuSig2 out of the way.
+
+    if let [pb::BtcScriptConfigWithKeypath {
+        script_config:
+            Some(pb::BtcScriptConfig {
+                config: Some(pb::btc_script_config::Config::Musig2(musig2)),
+            }),
+        keypath,
+    }] = script_configs
+    {
+        if !coin_params.taproot_support {
+            return Err(Error::InvalidInput);
+        }
+        super::musig2::validate(musig2, keypath)?;
+        let name = super::musig2::get_name(coin_params.coin, musig2, keypath)?
+            .ok_or(Error::InvalidInput)?;
+        if !dry_run {
+            super::musig2::confirm("Spend from", coin_params, &name, musig2).await?;
+        }
+        return Ok(vec![ValidatedScriptConfigWithKeypath {
+            keypath,
+            config: ValidatedScriptConfig::Musig2(musig2),
+        }]);
+    }
+
     // Then we get policies out of the way.
 
     if let [pb::BtcScriptConfigWithKeypath {
@@ -421,14 +1057,16 @@ async fn validate_script_configs<'a>(
         // the input keypath, and the computation of the pk_script checks that full keypath is
         // valid.
 
-        parsed_policy
-            .confirm(
-                "Spend from",
-                coin_params,
-                &name,
-                super::policies::Mode::Basic,
-            )
-            .await?;
+        if !dry_run {
+            parsed_policy
+                .confirm(
+                    "Spend from",
+                    coin_params,
+                    &name,
+                    super::policies::Mode::Basic,
+                )
+                .await?;
+        }
 
         return Ok(vec![ValidatedScriptConfigWithKeypath {
             keypath,
@@ -436,62 +1074,120 @@ async fn validate_script_configs<'a>(
         }]);
     }
 
-    let validated: Vec<ValidatedScriptConfigWithKeypath> = script_configs
-        .iter()
-        .map(|script_config| {
-            // Only allow simple single sig configs here.
-            match script_config {
-                pb::BtcScriptConfigWithKeypath {
-                    script_config:
-                        Some(pb::BtcScriptConfig {
-                            config: Some(pb::btc_script_config::Config::SimpleType(simple_type)),
-                        }),
-                    keypath,
-                } => {
-                    let simple_type = SimpleType::try_from(*simple_type)?;
-                    keypath::validate_account_simple(
-                        keypath,
-                        coin_params.bip44_coin,
-                        simple_type,
-                        coin_params.taproot_support,
-                    )
-                    .or(Err(Error::InvalidInput))?;
-
-                    // Check that the bip44 account is the same for all. While we allow mixing input
-                    // types (bip44 purpose), we do not allow mixing accounts.
-
-                    if keypath[2] != script_configs[0].keypath[2] {
-                        return Err(Error::InvalidInput);
-                    }
-                    Ok(ValidatedScriptConfigWithKeypath {
-                        keypath,
-                        config: ValidatedScriptConfig::SimpleType(simple_type),
-                    })
-                }
-                _ => Err(Error::InvalidInput),
-            }
-        })
-        .collect::<Result<Vec<ValidatedScriptConfigWithKeypath>, Error>>()?;
-    Ok(validated)
-}
-
-// We configure the xpub cache to cache up to change/receive level. If e.g. there is an input/change
-// with keypath `m/84'/0'/0'/0/0`, we want the xpub cache to return the xpub at `m/84'/0'/0'/0`, so
-// only one child derivation needs to be done for the input/change.
-//
-// Only xpubs for simple configs (single-sig) are cached - multisig already provides all necessary
-// xpubs in the script config itself.
-fn setup_xpub_cache(cache: &mut Bip32XpubCache, script_configs: &[pb::BtcScriptConfigWithKeypath]) {
+    let mut validated: Vec<ValidatedScriptConfigWithKeypath> = Vec::new();
+    let mut has_simple_config = false;
+    let mut multisig_names: Vec<String> = Vec::new();
     for script_config in script_configs.iter() {
+        // Only allow simple single sig and registered multisig configs here.
         match script_config {
             pb::BtcScriptConfigWithKeypath {
                 script_config:
                     Some(pb::BtcScriptConfig {
-                        config: Some(pb::btc_script_config::Config::SimpleType(_)),
+                        config: Some(pb::btc_script_config::Config::SimpleType(simple_type)),
                     }),
                 keypath,
             } => {
-                let mut receive = keypath.to_vec();
+                let simple_type = SimpleType::try_from(*simple_type)?;
+                keypath::validate_account_simple(
+                    keypath,
+                    coin_params.bip44_coin,
+                    simple_type,
+                    coin_params.taproot_support,
+                )
+                .or(Err(Error::InvalidInput))?;
+                if !dry_run && keypath::is_unusual_account(keypath) {
+                    super::confirm_unusual_account(keypath).await?;
+                }
+
+                has_simple_config = true;
+                validated.push(ValidatedScriptConfigWithKeypath {
+                    keypath,
+                    config: ValidatedScriptConfig::SimpleType(simple_type),
+                });
+            }
+            pb::BtcScriptConfigWithKeypath {
+                script_config:
+                    Some(pb::BtcScriptConfig {
+                        config: Some(pb::btc_script_config::Config::Multisig(multisig)),
+                    }),
+                keypath,
+            } => {
+                super::multisig::validate(multisig, keypath)?;
+                let name = super::multisig::get_name(coin_params.coin, multisig, keypath)?
+                    .ok_or(Error::InvalidInput)?;
+                if !dry_run {
+                    super::multisig::confirm("Spend from", coin_params, &name, multisig).await?;
+                }
+
+                multisig_names.push(name);
+                validated.push(ValidatedScriptConfigWithKeypath {
+                    keypath,
+                    config: ValidatedScriptConfig::Multisig(multisig),
+                });
+            }
+            _ => return Err(Error::InvalidInput),
+        }
+    }
+
+    // Mixing multisig and singlesig configs in one transaction (e.g. when migrating funds from a
+    // singlesig account into a multisig setup) is unusual and needs an explicit confirmation.
+    if !dry_run && has_simple_config && !multisig_names.is_empty() {
+        confirm::confirm(&confirm::Params {
+            title: "Warning",
+            body: &format!(
+                "Spending from\nboth '{}' and\nsinglesig account",
+                multisig_names.join("', '")
+            ),
+            accept_is_nextarrow: true,
+            ..Default::default()
+        })
+        .await?;
+    }
+
+    // While we allow mixing input types (bip44 purpose), spending from multiple bip44 accounts in
+    // one transaction is unusual (e.g. when consolidating UTXOs) and needs an explicit
+    // confirmation.
+    let mut accounts: Vec<u32> = Vec::new();
+    for script_config in validated.iter() {
+        let account = script_config.keypath[2] - util::bip32::HARDENED;
+        if !accounts.contains(&account) {
+            accounts.push(account);
+        }
+    }
+    if !dry_run && accounts.len() > 1 {
+        let accounts_list = accounts
+            .iter()
+            .map(|account| format!("#{}", account))
+            .collect::<Vec<_>>()
+            .join(" and ");
+        confirm::confirm(&confirm::Params {
+            title: "Warning",
+            body: &format!("Spending from\naccounts {}", accounts_list),
+            accept_is_nextarrow: true,
+            ..Default::default()
+        })
+        .await?;
+    }
+    Ok(validated)
+}
+
+// We configure the xpub cache to cache up to change/receive level. If e.g. there is an input/change
+// with keypath `m/84'/0'/0'/0/0`, we want the xpub cache to return the xpub at `m/84'/0'/0'/0`, so
+// only one child derivation needs to be done for the input/change.
+//
+// Only xpubs for simple configs (single-sig) are cached - multisig already provides all necessary
+// xpubs in the script config itself.
+fn setup_xpub_cache(cache: &mut Bip32XpubCache, script_configs: &[pb::BtcScriptConfigWithKeypath]) {
+    for script_config in script_configs.iter() {
+        match script_config {
+            pb::BtcScriptConfigWithKeypath {
+                script_config:
+                    Some(pb::BtcScriptConfig {
+                        config: Some(pb::btc_script_config::Config::SimpleType(_)),
+                    }),
+                keypath,
+            } => {
+                let mut receive = keypath.to_vec();
                 receive.push(0);
                 let mut change = keypath.to_vec();
                 change.push(1);
@@ -511,6 +1207,74 @@ fn setup_xpub_cache(cache: &mut Bip32XpubCache, script_configs: &[pb::BtcScriptC
     }
 }
 
+/// Validates everything about a sign init request that is checked before any transaction data is
+/// streamed: keystore state, coin, format unit, version, input/output counts and the script
+/// configs themselves. Shared between the signing flow and the dry-run validation api call so the
+/// two cannot diverge. With `dry_run`, all user confirmations are skipped.
+async fn validate_init<'a>(
+    request: &pb::BtcSignInitRequest,
+    script_configs: &'a [pb::BtcScriptConfigWithKeypath],
+    dry_run: bool,
+) -> Result<(FormatUnit, Vec<ValidatedScriptConfigWithKeypath<'a>>), Error> {
+    if bitbox02::keystore::is_locked() {
+        return Err(Error::InvalidState);
+    }
+    // Validate the coin.
+    let coin = pb::BtcCoin::try_from(request.coin)?;
+    let coin_params = super::params::get(coin);
+    // Validate the format_unit.
+    let format_unit = FormatUnit::try_from(request.format_unit)?;
+    // Currently only support version 1, 2 or 3 tx.
+    // Version 2: https://github.com/bitcoin/bips/blob/master/bip-0068.mediawiki
+    // Version 3: https://github.com/bitcoin/bips/blob/master/bip-0431.mediawiki
+    if request.version != 1 && request.version != 2 && request.version != 3 {
+        return Err(Error::InvalidInputDetail("unsupported version"));
+    }
+    if request.num_inputs < 1 || request.num_outputs < 1 {
+        return Err(Error::InvalidInput);
+    }
+    if request.num_inputs > SIGN_MAX_INPUTS || request.num_outputs > SIGN_MAX_OUTPUTS {
+        return Err(Error::InvalidInputDetail("too many inputs or outputs"));
+    }
+    if let Some(ref replacement) = request.replacement {
+        // A fee bump keeps the recipients; the batched confirmation modes replace the
+        // per-recipient dialogs the comparison is meant to shorten, so combining them is
+        // ambiguous.
+        if request.coinjoin || request.summarize_outputs {
+            return Err(Error::InvalidInput);
+        }
+        // Replacement only exists for coins with RBF; a claimed original without external
+        // outputs could not have been fee-bumped through this mode either.
+        if !coin_params.rbf_support || replacement.original_outputs.is_empty() {
+            return Err(Error::InvalidInput);
+        }
+    }
+    let validated_script_configs =
+        validate_script_configs(coin_params, script_configs, dry_run).await?;
+    // Lower caps if any script config is legacy (P2PKH): the legacy sighash re-serializes the
+    // whole transaction per input, so per-entry data is retained for the whole signing process.
+    if validated_script_configs.iter().any(is_legacy)
+        && (request.num_inputs > SIGN_MAX_INPUTS_LEGACY
+            || request.num_outputs > SIGN_MAX_OUTPUTS_LEGACY)
+    {
+        return Err(Error::InvalidInputDetail("too many inputs or outputs"));
+    }
+    Ok((format_unit, validated_script_configs))
+}
+
+/// Handles a dry-run validation of a prospective sign init request: runs exactly the same checks
+/// as the start of the signing flow, with no user interaction and no state change, so hosts can
+/// detect rejected script configs before attempting to sign. Returns success or the error the
+/// signing flow would return.
+pub async fn process_is_script_config_valid(
+    request: &pb::BtcIsScriptConfigValidRequest,
+) -> Result<pb::btc_response::Response, Error> {
+    let init_request = request.init.as_ref().ok_or(Error::InvalidInput)?;
+    let script_configs = desugar_script_configs(&init_request.script_configs);
+    validate_init(init_request, &script_configs, true).await?;
+    Ok(pb::btc_response::Response::Success(pb::BtcSuccess {}))
+}
+
 /// Singing flow:
 ///
 /// init
@@ -543,43 +1307,35 @@ fn setup_xpub_cache(cache: &mut Bip32XpubCache, script_configs: &[pb::BtcScriptC
 /// For each output, the recipient is confirmed. At the last output, the total out, fee, locktime/RBF
 /// are confirmed.
 ///
+/// If the host sets `confirm_outputs_first`, streaming the previous transactions is deferred from
+/// the first pass to the signing pass: the outputs, total and fee are confirmed based on the
+/// host-claimed input values, so the user is not kept waiting through the prevtx loading before
+/// learning where the coins go. Each input's previous transaction is then streamed and verified
+/// right before that input is signed, and the claimed value must match the first pass exactly, so
+/// no signature is produced if the confirmed fee was based on a wrong claim. Foreign inputs are
+/// rejected in this mode, as their pubkey script is needed in the first pass, and the deferred
+/// prevtx round trips cannot be part of a second-pass input window.
+///
 /// The inputs are signed in inputs_pass2.
 ///
 /// IMPORTANT assumptions:
 ///
-/// - In the 2nd pass, if the inputs provided by the host are not the same as in the 1st pass,
-///   nothing bad will happen because the sighash uses the prevout and sequence hashes from the first
-///   pass, and the value from the 2nd pass. The BTC consensus rules will reject the tx if there is a
-///   mismatch.
+/// - The inputs of the 2nd pass must be identical to the inputs of the 1st pass. This is enforced
+///   by accumulating a hash over all inputs in each pass and comparing the two hashes; a mismatch
+///   fails the signing process. We deliberately do not rely on the BTC consensus rules rejecting a
+///   transaction assembled from mismatching passes, as that reasoning depends on the sighash mode.
 ///
 /// - Only SIGHASH_ALL (SIGHASH_DEFAULT in taproot inputs). Other sighash types must be carefully
 ///   studied and might not be secure with the above flow or the above assumption.
 async fn _process(request: &pb::BtcSignInitRequest) -> Result<Response, Error> {
-    if bitbox02::keystore::is_locked() {
-        return Err(Error::InvalidState);
-    }
-    // Validate the coin.
     let coin = pb::BtcCoin::try_from(request.coin)?;
     let coin_params = super::params::get(coin);
-    // Validate the format_unit.
-    let format_unit = FormatUnit::try_from(request.format_unit)?;
-    // Currently we do not support time-based nlocktime
-    if request.locktime >= 500000000 {
-        return Err(Error::InvalidInput);
-    }
-    // Currently only support version 1 or version 2 tx.
-    // Version 2: https://github.com/bitcoin/bips/blob/master/bip-0068.mediawiki
-    if request.version != 1 && request.version != 2 {
-        return Err(Error::InvalidInput);
-    }
-    if request.num_inputs < 1 || request.num_outputs < 1 {
-        return Err(Error::InvalidInput);
-    }
-    let validated_script_configs =
-        validate_script_configs(coin_params, &request.script_configs).await?;
+    let script_configs = desugar_script_configs(&request.script_configs);
+    let (format_unit, validated_script_configs) =
+        validate_init(request, &script_configs, false).await?;
 
     let mut xpub_cache = Bip32XpubCache::new();
-    setup_xpub_cache(&mut xpub_cache, &request.script_configs);
+    setup_xpub_cache(&mut xpub_cache, &script_configs);
 
     // For now we only allow one payment request with one output per transaction.  In the future,
     // this could be extended to allow multiple outputs per payment request (payment request
@@ -601,6 +1357,12 @@ async fn _process(request: &pb::BtcSignInitRequest) -> Result<Response, Error> {
             signature: vec![],
             prev_index: 0,
             anti_klepto_signer_commitment: None,
+            musig2_pub_nonce: vec![],
+            generated_output_pkscript: vec![],
+            total_out: 0,
+            fee: 0,
+            change_total: 0,
+            signatures: vec![],
         },
         wrap: false,
     };
@@ -608,17 +1370,67 @@ async fn _process(request: &pb::BtcSignInitRequest) -> Result<Response, Error> {
     // Will contain the sum of all spent output values in the first inputs pass.
     let mut inputs_sum_pass1: u64 = 0;
 
+    // Host-claimed input values of the first pass, retained if the previous transaction
+    // verification is deferred to the signing pass (`confirm_outputs_first`). Each value is
+    // re-checked there before its input is signed: the final cross-pass checks below come too
+    // late to protect the fee display, as earlier signatures are already released to the host.
+    let mut pass1_input_values: Vec<u64> = Vec::new();
+
     let mut locktime_applies: bool = false;
-    let mut rbf: bool = false;
+    // Number of inputs signaling replaceability (BIP-125). Inputs can mix signaling and
+    // non-signaling sequences, so all of them are counted to render an accurate RBF summary.
+    let mut num_rbf_inputs: u32 = 0;
+    // Inputs with a BIP-68 relative locktime encoded in their sequence number: (input index,
+    // sequence).
+    let mut relative_locktimes: Vec<(u32, u32)> = Vec::new();
+    // Input keypaths with an unusually high account or address index. The user is warned below,
+    // after all inputs are processed, so the progress bar is not interrupted.
+    let mut unusual_index_keypaths: Vec<Vec<u32>> = Vec::new();
+    // Rendered confirmation bodies for the advanced input verification. The user is asked below,
+    // after all inputs are processed, so the progress bar is not interrupted.
+    let mut advanced_verify_input_bodies: Vec<String> = Vec::new();
 
     let mut hasher_prevouts = Sha256::new();
     let mut hasher_sequence = Sha256::new();
     let mut hasher_amounts = Sha256::new();
     let mut hasher_scriptpubkeys = Sha256::new();
+    // Commits to all inputs of the first pass; the second pass must stream identical inputs.
+    let mut hasher_inputs_pass1 = Sha256::new();
 
     // Are all inputs taproot?
     let taproot_only = validated_script_configs.iter().all(is_taproot);
 
+    // Is any script config legacy (pre-segwit) P2PKH? The legacy sighash serializes the full
+    // transaction per input, so the outpoints, sequences and serialized outputs are retained
+    // below.
+    let has_legacy = validated_script_configs.iter().any(is_legacy);
+    let mut legacy_inputs: Vec<legacy::Input> = Vec::new();
+
+    // BIP-69 sort key (txid in displayed big-endian order, output index) of the previous input,
+    // if the host requested ordering verification.
+    let mut last_bip69_input: Option<([u8; 32], u32)> = None;
+
+    // Number of foreign (not ours, e.g. payjoin) inputs seen in the first pass.
+    let mut num_foreign_inputs: u32 = 0;
+
+    // Number of taproot script path (BIP-342) inputs seen in the first pass.
+    let mut num_script_path_inputs: u32 = 0;
+
+    // Previous transactions already streamed and verified, so that inputs spending several
+    // outputs of the same previous transaction do not stream it repeatedly.
+    let mut prevtx_cache: PrevtxCache = Vec::new();
+
+    // Conservative estimate of the fully signed transaction's weight (BIP-141), starting with the
+    // version, locktime, input/output counts and the segwit marker and flag. Inputs and outputs
+    // are added as they are streamed; the user is warned below if the total exceeds the
+    // standardness limit.
+    let mut estimated_weight: u64 = 4
+        * (4
+            + serialize_varint(request.num_inputs as u64).len() as u64
+            + serialize_varint(request.num_outputs as u64).len() as u64
+            + 4)
+        + 2;
+
     for input_index in 0..request.num_inputs {
         // Update progress.
         bitbox02::ui::progress_set(
@@ -627,19 +1439,116 @@ async fn _process(request: &pb::BtcSignInitRequest) -> Result<Response, Error> {
         );
 
         let tx_input = get_tx_input(input_index, &mut next_response).await?;
-        let script_config_account = validated_script_configs
-            .get(tx_input.script_config_index as usize)
-            .ok_or(Error::InvalidInput)?;
-        validate_input(&tx_input, coin_params, script_config_account)?;
+        hash_input(&mut hasher_inputs_pass1, &tx_input);
+        let script_config_account = if tx_input.foreign {
+            // A foreign input's pubkey script is only known from its previous transaction, which
+            // is needed already in this pass for `sha_scriptpubkeys`, so it cannot be combined
+            // with the deferred prevtx verification.
+            if request.confirm_outputs_first {
+                return Err(Error::InvalidInputDetail("foreign input not supported"));
+            }
+            validate_input_foreign(&tx_input)?;
+            num_foreign_inputs = num_foreign_inputs.checked_add(1).ok_or(Error::InvalidInput)?;
+            None
+        } else {
+            let script_config_account = validated_script_configs
+                .get(tx_input.script_config_index as usize)
+                .ok_or(Error::InvalidInputDetail("invalid script config index"))?;
+            validate_input(&tx_input, coin_params, script_config_account)?;
+            if has_unusual_address_index(&tx_input.keypath) {
+                if unusual_index_keypaths.len() == SIGN_MAX_DEFERRED_CONFIRMS {
+                    return Err(Error::InvalidInput);
+                }
+                unusual_index_keypaths.push(tx_input.keypath.clone());
+            }
+            Some(script_config_account)
+        };
+        if request.advanced_verify_inputs {
+            if advanced_verify_input_bodies.len() == SIGN_MAX_DEFERRED_CONFIRMS {
+                return Err(Error::InvalidInput);
+            }
+            // Transaction IDs are conventionally displayed in reversed byte order.
+            let mut txid: Vec<u8> = tx_input.prev_out_hash.clone();
+            txid.reverse();
+            let origin = match script_config_account {
+                Some(_) => {
+                    util::bip32::to_string(&tx_input.keypath[..tx_input.keypath.len() - 2])
+                }
+                None => "other wallet".into(),
+            };
+            advanced_verify_input_bodies.push(format!(
+                "Input {}/{}\n{}\nFrom {}...:{}\n{}",
+                input_index + 1,
+                request.num_inputs,
+                format_amount(coin_params, format_unit, tx_input.prev_out_value)?,
+                hex::encode(txid.get(..4).ok_or(Error::InvalidInput)?),
+                tx_input.prev_out_index,
+                origin,
+            ));
+        }
         if tx_input.sequence < 0xffffffff - 1 {
-            rbf = true;
+            num_rbf_inputs = num_rbf_inputs.checked_add(1).ok_or(Error::InvalidInput)?;
         }
         if tx_input.sequence < 0xffffffff {
             locktime_applies = true;
         }
+        // https://github.com/bitcoin/bips/blob/master/bip-0068.mediawiki
+        // For transaction version >= 2, an input with the disable bit (bit 31) cleared encodes a
+        // relative locktime in its sequence number. The user is informed below that the spend is
+        // time-constrained, after all inputs are processed, so the progress bar is not
+        // interrupted.
+        if request.version >= 2 && tx_input.sequence & (1 << 31) == 0 && tx_input.sequence & 0xffff != 0
+        {
+            if relative_locktimes.len() == SIGN_MAX_DEFERRED_CONFIRMS {
+                return Err(Error::InvalidInput);
+            }
+            relative_locktimes.push((input_index, tx_input.sequence));
+        }
         inputs_sum_pass1 = inputs_sum_pass1
             .checked_add(tx_input.prev_out_value)
             .ok_or(Error::InvalidInput)?;
+        if request.confirm_outputs_first {
+            pass1_input_values.push(tx_input.prev_out_value);
+        }
+
+        estimated_weight = estimated_weight
+            .checked_add(match script_config_account {
+                Some(script_config_account) => {
+                    estimate_input_weight(&mut xpub_cache, script_config_account, &tx_input)?
+                }
+                None => FOREIGN_INPUT_WEIGHT_ESTIMATE,
+            })
+            .ok_or(Error::InvalidInput)?;
+
+        if request.verify_bip69_order {
+            // BIP-69: inputs must be sorted by txid (in displayed, big-endian order), ties broken
+            // by the output index.
+            let mut txid: [u8; 32] = tx_input
+                .prev_out_hash
+                .as_slice()
+                .try_into()
+                .or(Err(Error::InvalidInput))?;
+            txid.reverse();
+            let key = (txid, tx_input.prev_out_index);
+            if let Some(ref last) = last_bip69_input {
+                if key < *last {
+                    return Err(Error::InvalidInput);
+                }
+            }
+            last_bip69_input = Some(key);
+        }
+
+        if has_legacy {
+            legacy_inputs.push(legacy::Input {
+                outpoint_hash: tx_input
+                    .prev_out_hash
+                    .as_slice()
+                    .try_into()
+                    .or(Err(Error::InvalidInput))?,
+                outpoint_index: tx_input.prev_out_index,
+                sequence: tx_input.sequence,
+            });
+        }
 
         // https://github.com/bitcoin/bips/blob/master/bip-0143.mediawiki
         // point 2: accumulate hashPrevouts
@@ -656,37 +1565,156 @@ async fn _process(request: &pb::BtcSignInitRequest) -> Result<Response, Error> {
         // accumulate `sha_amounts`
         hasher_amounts.update(tx_input.prev_out_value.to_le_bytes());
 
+        // The previous transaction is streamed if any script config is non-taproot (the amounts
+        // are not committed to in the bip143 sighash), and always for foreign inputs, as their
+        // amount and pubkey script cannot be verified against the keystore. With
+        // `confirm_outputs_first`, the streaming is deferred to the signing pass (foreign inputs
+        // were rejected above).
+        let prevtx_pubkey_script: Option<Vec<u8>> = if !request.confirm_outputs_first
+            && (tx_input.foreign || !taproot_only)
+        {
+            Some(
+                handle_prevtx(
+                    input_index,
+                    &tx_input,
+                    request.num_inputs,
+                    progress_component.as_mut().unwrap(),
+                    &mut prevtx_cache,
+                    &mut next_response,
+                )
+                .await?,
+            )
+        } else {
+            None
+        };
+
         // https://github.com/bitcoin/bips/blob/bb8dc57da9b3c6539b88378348728a2ff43f7e9c/bip-0341.mediawiki#common-signature-message
         // accumulate `sha_scriptpubkeys`
-        let pk_script = common::Payload::from(
-            &mut xpub_cache,
-            coin_params,
-            &tx_input.keypath,
-            script_config_account,
-        )?
-        .pk_script(coin_params)?;
+        let pk_script = match script_config_account {
+            Some(script_config_account) => {
+                if !tx_input.script_path_leaf_script.is_empty() {
+                    // Taproot script path spend: the output key is derived from our internal key
+                    // and the provided leaf script/control block.
+                    num_script_path_inputs = num_script_path_inputs
+                        .checked_add(1)
+                        .ok_or(Error::InvalidInput)?;
+                    let output_key = validate_script_path_spend(&mut xpub_cache, &tx_input)?;
+                    common::Payload {
+                        data: output_key.to_vec(),
+                        output_type: pb::BtcOutputType::P2tr,
+                        witness_version: None,
+                    }
+                    .pk_script(coin_params)?
+                } else {
+                    common::Payload::from(
+                        &mut xpub_cache,
+                        coin_params,
+                        &tx_input.keypath,
+                        script_config_account,
+                    )?
+                    .pk_script(coin_params)?
+                }
+            }
+            // Foreign input: the pubkey script was verified as part of the previous transaction.
+            None => prevtx_pubkey_script.unwrap(),
+        };
         hasher_scriptpubkeys.update(serialize_varint(pk_script.len() as u64).as_slice());
         hasher_scriptpubkeys.update(pk_script.as_slice());
-
-        if !taproot_only {
-            handle_prevtx(
-                input_index,
-                &tx_input,
-                request.num_inputs,
-                progress_component.as_mut().unwrap(),
-                &mut next_response,
-            )
-            .await?;
-        }
     }
 
     // The progress for loading the inputs is 100%.
     bitbox02::ui::progress_set(progress_component.as_mut().unwrap(), 1.);
 
+    // There must be something for us to sign.
+    if num_foreign_inputs == request.num_inputs {
+        return Err(Error::InvalidInput);
+    }
+
+    for body in advanced_verify_input_bodies.iter() {
+        // Stop rendering inputs progress update.
+        drop(progress_component.take());
+        confirm::confirm(&confirm::Params {
+            body,
+            scrollable: true,
+            accept_is_nextarrow: true,
+            ..Default::default()
+        })
+        .await?;
+    }
+
+    if num_foreign_inputs > 0 {
+        // Stop rendering inputs progress update.
+        drop(progress_component.take());
+        confirm::confirm(&confirm::Params {
+            body: &format!(
+                "This transaction\nincludes {}\ninputs from\nother wallets",
+                num_foreign_inputs
+            ),
+            accept_is_nextarrow: true,
+            ..Default::default()
+        })
+        .await?;
+    }
+
+    if num_script_path_inputs > 0 {
+        // Stop rendering inputs progress update.
+        drop(progress_component.take());
+        confirm::confirm(&confirm::Params {
+            body: &format!(
+                "This transaction\nincludes {}\nscript path\nspend inputs",
+                num_script_path_inputs
+            ),
+            accept_is_nextarrow: true,
+            ..Default::default()
+        })
+        .await?;
+    }
+
+    for &(input_index, sequence) in relative_locktimes.iter() {
+        // Stop rendering inputs progress update.
+        drop(progress_component.take());
+        let value = sequence & 0xffff;
+        let body = if sequence & (1 << 22) != 0 {
+            // Time-based relative locktime, in units of 512 seconds.
+            format!(
+                "Input {} requires\n{}\nsince confirmation",
+                input_index + 1,
+                format_duration(value as u64 * 512 / 60),
+            )
+        } else {
+            // Block-based relative locktime, assuming 10 minutes per block.
+            format!(
+                "Input {} requires\n{} blocks (~{})\nsince confirmation",
+                input_index + 1,
+                value,
+                format_duration(value as u64 * 10),
+            )
+        };
+        confirm::confirm(&confirm::Params {
+            body: &body,
+            accept_is_nextarrow: true,
+            ..Default::default()
+        })
+        .await?;
+    }
+
+    for keypath in unusual_index_keypaths.iter() {
+        // Stop rendering inputs progress update.
+        drop(progress_component.take());
+        confirm_unusual_address_index(keypath).await?;
+    }
+
+    // Enforce BIP-125 replaceability if the host requires it: at least one input must have a
+    // sequence number of 0xFFFFFFFD or below, otherwise the transaction cannot be fee-bumped.
+    if request.rbf_required && (!coin_params.rbf_support || num_rbf_inputs == 0) {
+        return Err(Error::InvalidInputDetail("rbf not signaled"));
+    }
+
     let hash_prevouts = hasher_prevouts.finalize();
     let hash_sequence = hasher_sequence.finalize();
     let hash_amounts = hasher_amounts.finalize();
     let hash_scriptpubkeys = hasher_scriptpubkeys.finalize();
+    let hash_inputs_pass1 = hasher_inputs_pass1.finalize();
 
     // Base component on the screen stack during signing, which is shown while the device is waiting
     // for the next signing api call. Without this, the 'See the BitBoxApp' waiting screen would
@@ -704,7 +1732,41 @@ async fn _process(request: &pb::BtcSignInitRequest) -> Result<Response, Error> {
 
     let mut num_changes: u32 = 0;
 
+    // Outputs that are ours but not change (receive to self): (rendered recipient, value). Their
+    // confirmation is deferred until all outputs are seen: if the whole transaction stays within
+    // this wallet (UTXO consolidation), a single summary replaces the per-output dialogs. At most
+    // `SIGN_MAX_DEFERRED_CONFIRMS` are retained; a mixed transaction with more self outputs than
+    // that is rejected below, as each would need its own dialog.
+    let mut self_outputs: Vec<(String, u64)> = Vec::new();
+    let mut num_self_outputs: usize = 0;
+    // Fail closed: any output that is not ours disables the consolidation summary.
+    let mut has_external_output = false;
+
+    // External outputs (rendered recipient, value), deferred if the host requested summarized
+    // confirmation: whether the threshold is exceeded is only known once all outputs are seen. At
+    // most `SUMMARIZE_OUTPUTS_INSPECT_MAX` are retained; the count and total for the summary
+    // itself are accumulated separately.
+    let mut external_outputs: Vec<(String, u64)> = Vec::new();
+    let mut num_external_outputs: usize = 0;
+    let mut external_outputs_total: u64 = 0;
+
+    // In replacement (fee bump) mode, the (pkScript, value) pairs of the external outputs, to be
+    // compared against the host-supplied original outputs once all outputs are seen. The rendered
+    // recipients are retained in `external_outputs` for the fallback review on a mismatch.
+    let mut replacement_new_outputs: Vec<(Vec<u8>, u64)> = Vec::new();
+
+    // In coinjoin mode, the number of outputs that are not ours and their common value. They are
+    // confirmed in one batched dialog after all outputs are processed.
+    let mut num_coinjoin_outputs: u32 = 0;
+    let mut coinjoin_output_value: Option<u64> = None;
+
     let mut hasher_outputs = Sha256::new();
+    // Serialized outputs, retained if any script config is legacy P2PKH: the legacy sighash
+    // serializes them again for every signed input.
+    let mut serialized_outputs: Vec<u8> = Vec::new();
+    // BIP-69 sort key (value, pkScript) of the previous output, if the host requested ordering
+    // verification.
+    let mut last_bip69_output: Option<(u64, Vec<u8>)> = None;
     for output_index in 0..request.num_outputs {
         let tx_output = get_tx_output(output_index, &mut next_response).await?;
         if output_index == 0 {
@@ -719,7 +1781,7 @@ async fn _process(request: &pb::BtcSignInitRequest) -> Result<Response, Error> {
         }
 
         if tx_output.value == 0 {
-            return Err(Error::InvalidInput);
+            return Err(Error::InvalidInputDetail("zero output value"));
         }
 
         // Get payload. If the output is marked ours, we compute the payload from the keystore,
@@ -728,33 +1790,88 @@ async fn _process(request: &pb::BtcSignInitRequest) -> Result<Response, Error> {
             // Compute the payload from the keystore.
             let script_config_account = validated_script_configs
                 .get(tx_output.script_config_index as usize)
-                .ok_or(Error::InvalidInput)?;
+                .ok_or(Error::InvalidInputDetail("invalid script config index"))?;
 
+            // Spend mode: the address index is not hard-bounded, but an unusually high account or
+            // address index needs an explicit warning confirmation below.
             validate_keypath(
                 coin_params,
                 script_config_account,
                 &tx_output.keypath,
-                keypath::ReceiveSpend::Receive,
+                keypath::ReceiveSpend::Spend,
             )?;
+            if has_unusual_address_index(&tx_output.keypath) {
+                confirm_unusual_address_index(&tx_output.keypath).await?;
+            }
 
-            common::Payload::from(
+            let payload = common::Payload::from(
                 &mut xpub_cache,
                 coin_params,
                 &tx_output.keypath,
                 script_config_account,
-            )?
+            )?;
+            // For taproot outputs, cross-check the BIP-86 output key against an independent
+            // derivation (taproot tweak of the pubkey at the keypath, empty merkle root), so a
+            // refactor of the payload construction cannot silently break taproot change.
+            if matches!(
+                script_config_account.config,
+                ValidatedScriptConfig::SimpleType(SimpleType::P2tr)
+            ) {
+                let internal_pubkey: [u8; 33] = xpub_cache
+                    .get_xpub(&tx_output.keypath)?
+                    .public_key()
+                    .try_into()
+                    .or(Err(Error::Generic))?;
+                let output_key =
+                    bip341::output_key_no_script(&internal_pubkey).or(Err(Error::InvalidInput))?;
+                if payload.data.as_slice() != output_key {
+                    return Err(Error::InvalidInput);
+                }
+            }
+            // A host that assembled the output before marking it as ours may provide the payload
+            // as well; if it does, it must match our own derivation exactly.
+            if !tx_output.payload.is_empty() && tx_output.payload != payload.data {
+                return Err(Error::InvalidInputDetail("output payload mismatch"));
+            }
+            payload
         } else {
-            // Take payload from provided output.
-            common::Payload {
+            // Take payload from provided output. The payload length is strictly validated before
+            // anything is rendered, so a malformed payload can't produce a misleading address.
+            let output_type = pb::BtcOutputType::try_from(tx_output.r#type)?;
+            let payload = common::Payload {
                 data: tx_output.payload.clone(),
-                output_type: pb::BtcOutputType::try_from(tx_output.r#type)?,
+                output_type,
+                witness_version: match output_type {
+                    pb::BtcOutputType::FutureWitness => Some(
+                        tx_output
+                            .witness_version
+                            .try_into()
+                            .or(Err(Error::InvalidInput))?,
+                    ),
+                    _ => None,
+                },
+            };
+            payload
+                .validate(coin_params)
+                .or(Err(Error::InvalidInputDetail("invalid output payload")))?;
+            // The device can only render the address of a witness version it does not know yet;
+            // nothing ties it to a known script format, so be explicit about that.
+            if output_type == pb::BtcOutputType::FutureWitness {
+                confirm::confirm(&confirm::Params {
+                    title: "Warning",
+                    body: "Unrecognized\naddress format.\nProceed only if\nyou expect this",
+                    accept_is_nextarrow: true,
+                    ..Default::default()
+                })
+                .await?;
             }
+            payload
         };
 
         let is_change = if tx_output.ours {
             let script_config_account = validated_script_configs
                 .get(tx_output.script_config_index as usize)
-                .ok_or(Error::InvalidInput)?;
+                .ok_or(Error::InvalidInputDetail("invalid script config index"))?;
 
             match &script_config_account.config {
                 // Policy.
@@ -776,12 +1893,48 @@ async fn _process(request: &pb::BtcSignInitRequest) -> Result<Response, Error> {
             return Err(Error::InvalidInput);
         }
 
-        if !is_change {
+        // A label cannot be displayed for change outputs, which are not confirmed individually.
+        if is_change && !tx_output.display_name.is_empty() {
+            return Err(Error::InvalidInput);
+        }
+
+        if !is_change && request.coinjoin && !tx_output.ours {
+            has_external_output = true;
+            // Batched confirmation of equal-valued external outputs; the dialog is shown once
+            // after all outputs are processed.
+            if tx_output.payment_request_index.is_some() {
+                return Err(Error::InvalidInput);
+            }
+            // There is no per-output dialog which could show a label.
+            if !tx_output.display_name.is_empty() {
+                return Err(Error::InvalidInput);
+            }
+            match coinjoin_output_value {
+                None => coinjoin_output_value = Some(tx_output.value),
+                // All external outputs must be of identical value, without exception.
+                Some(value) if value != tx_output.value => return Err(Error::InvalidInput),
+                Some(_) => {}
+            }
+            num_coinjoin_outputs = num_coinjoin_outputs
+                .checked_add(1)
+                .ok_or(Error::InvalidInput)?;
+        } else if !is_change {
             // Verify output if it is not a change output.
             // Assemble address to display, get user confirmation.
             let address = payload.address(coin_params)?;
 
             if let Some(output_payment_request_index) = tx_output.payment_request_index {
+                has_external_output = true;
+                // A fee bump does not change the recipients; re-verifying a payment request in
+                // this flow is not supported.
+                if request.replacement.is_some() {
+                    return Err(Error::InvalidInput);
+                }
+                // A payment request carries its own verified recipient name; a competing
+                // host-provided label would only be confusing.
+                if !tx_output.display_name.is_empty() {
+                    return Err(Error::InvalidInput);
+                }
                 if output_payment_request_index != 0 {
                     return Err(Error::InvalidInput);
                 }
@@ -804,20 +1957,88 @@ async fn _process(request: &pb::BtcSignInitRequest) -> Result<Response, Error> {
                 }
 
                 payment_request_seen = true;
+            } else if tx_output.ours {
+                let script_config_account = validated_script_configs
+                    .get(tx_output.script_config_index as usize)
+                    .ok_or(Error::InvalidInputDetail("invalid script config index"))?;
+                let recipient = match &script_config_account.config {
+                    // Receive into one of our own registered multisig accounts (e.g. moving funds
+                    // from a singlesig account into a vault): show the account name the user
+                    // chose at registration instead of the raw address.
+                    ValidatedScriptConfig::Multisig(multisig) => {
+                        let name = super::multisig::get_name(
+                            coin_params.coin,
+                            multisig,
+                            script_config_account.keypath,
+                        )?
+                        .ok_or(Error::InvalidInput)?;
+                        format!("To your multisig account: {}", name)
+                    }
+                    _ => format!("This BitBox02: {}", address),
+                };
+                // Deferred; see the consolidation summary below.
+                num_self_outputs += 1;
+                if self_outputs.len() < SIGN_MAX_DEFERRED_CONFIRMS {
+                    self_outputs.push((
+                        format_recipient(&recipient, &tx_output.display_name)?,
+                        tx_output.value,
+                    ));
+                }
+            } else if request.summarize_outputs {
+                has_external_output = true;
+                // Deferred; see the batching summary below.
+                num_external_outputs += 1;
+                external_outputs_total = external_outputs_total
+                    .checked_add(tx_output.value)
+                    .ok_or(Error::InvalidInput)?;
+                if external_outputs.len() < SUMMARIZE_OUTPUTS_INSPECT_MAX {
+                    external_outputs.push((
+                        format_recipient(&address, &tx_output.display_name)?,
+                        tx_output.value,
+                    ));
+                }
+            } else if request.replacement.is_some() {
+                has_external_output = true;
+                // Deferred; see the fee bump comparison below. The (pkScript, value) pair is
+                // what is compared against the host-supplied original outputs.
+                num_external_outputs += 1;
+                replacement_new_outputs.push((payload.pk_script(coin_params)?, tx_output.value));
+                if external_outputs.len() < SUMMARIZE_OUTPUTS_INSPECT_MAX {
+                    external_outputs.push((
+                        format_recipient(&address, &tx_output.display_name)?,
+                        tx_output.value,
+                    ));
+                }
             } else {
+                has_external_output = true;
                 transaction::verify_recipient(
-                    &(if tx_output.ours {
-                        format!("This BitBox02: {}", address)
-                    } else {
-                        address
-                    }),
-                    &format_amount(coin_params, format_unit, tx_output.value)?,
+                    &format_recipient(&address, &tx_output.display_name)?,
+                    &format_display_amount(
+                        coin_params,
+                        format_unit,
+                        request.fiat_rate.as_ref(),
+                        tx_output.value,
+                    )?,
                 )
                 .await?;
             }
         }
 
         if is_change {
+            // A change output below the dust limit costs more to spend than it is worth. Give
+            // the user a chance to reject, so the host can fold it into the fee instead.
+            if tx_output.value < dust_threshold(payload.output_type) {
+                confirm::confirm(&confirm::Params {
+                    title: "Warning",
+                    body: &format!(
+                        "Dust change:\n{}\nReject to add it\nto the fee instead",
+                        format_amount(coin_params, format_unit, tx_output.value)?
+                    ),
+                    accept_is_nextarrow: true,
+                    ..Default::default()
+                })
+                .await?;
+            }
             num_changes += 1;
             outputs_sum_ours = outputs_sum_ours
                 .checked_add(tx_output.value)
@@ -833,42 +2054,288 @@ async fn _process(request: &pb::BtcSignInitRequest) -> Result<Response, Error> {
         // only SIGHASH_ALL supported.
         hasher_outputs.update(tx_output.value.to_le_bytes());
         let pk_script = payload.pk_script(coin_params)?;
+        if request.verify_bip69_order {
+            // BIP-69: outputs must be sorted by value, ties broken by the pkScript.
+            let key = (tx_output.value, pk_script.clone());
+            if let Some(ref last) = last_bip69_output {
+                if key < *last {
+                    return Err(Error::InvalidInput);
+                }
+            }
+            last_bip69_output = Some(key);
+        }
         hasher_outputs.update(serialize_varint(pk_script.len() as u64).as_slice());
         hasher_outputs.update(pk_script.as_slice());
+        // The output's serialization: value (8 bytes) and the pkScript with a length prefix, all
+        // part of the base transaction.
+        estimated_weight = estimated_weight
+            .checked_add(
+                4 * (8
+                    + serialize_varint(pk_script.len() as u64).len() as u64
+                    + pk_script.len() as u64),
+            )
+            .ok_or(Error::InvalidInput)?;
+        if has_legacy {
+            serialized_outputs.extend_from_slice(&tx_output.value.to_le_bytes());
+            serialized_outputs.extend_from_slice(serialize_varint(pk_script.len() as u64).as_slice());
+            serialized_outputs.extend_from_slice(pk_script.as_slice());
+        }
+
+        if tx_output.ours {
+            // Attach the derived pkScript to the next response, so the host can cross-check its
+            // own change derivation before broadcasting.
+            next_response.next.generated_output_pkscript = pk_script;
+        }
     }
 
-    if num_changes > 1 {
+    if !has_external_output && !request.coinjoin && num_foreign_inputs == 0 {
+        // Pure consolidation: every output is ours, nothing leaves the wallet. A summary replaces
+        // the per-recipient confirmations; the total/fee confirmation below remains mandatory.
         confirm::confirm(&confirm::Params {
-            title: "Warning",
-            body: &format!("There are {}\nchange outputs.\nProceed?", num_changes),
+            title: "Consolidation",
+            body: &format!(
+                "Consolidating\n{} inputs into\n{} outputs\nwithin this wallet",
+                request.num_inputs, request.num_outputs
+            ),
             accept_is_nextarrow: true,
             ..Default::default()
         })
         .await?;
+    } else {
+        // Mixed transaction: the outputs we receive back to ourselves are confirmed like any other
+        // recipient. More self outputs than were retained cannot all be confirmed.
+        if num_self_outputs > self_outputs.len() {
+            return Err(Error::InvalidInput);
+        }
+        for (recipient, value) in self_outputs.iter() {
+            transaction::verify_recipient(
+                recipient,
+                &format_display_amount(coin_params, format_unit, request.fiat_rate.as_ref(), *value)?,
+            )
+            .await?;
+        }
     }
 
-    // Verify locktime/rbf.
-    // A locktime of 0 will also not be verified, as it's certainly in the past and can't do any
-    // harm.
+    if let Some(ref replacement) = request.replacement {
+        // The mode is pointless without external outputs; refuse to activate it as a no-op.
+        if num_external_outputs == 0 {
+            return Err(Error::InvalidInput);
+        }
+        let mut original_outputs: Vec<(&[u8], u64)> = replacement
+            .original_outputs
+            .iter()
+            .map(|output| (output.pk_script.as_slice(), output.value))
+            .collect();
+        original_outputs.sort_unstable();
+        let mut new_outputs: Vec<(&[u8], u64)> = replacement_new_outputs
+            .iter()
+            .map(|(pk_script, value)| (pk_script.as_slice(), *value))
+            .collect();
+        new_outputs.sort_unstable();
+        if original_outputs == new_outputs {
+            // Only the fee changed: one comparison screen replaces the per-recipient dialogs.
+            // The total/fee confirmation below remains mandatory.
+            let fee: u64 = inputs_sum_pass1
+                .checked_sub(outputs_sum_ours)
+                .and_then(|total| total.checked_sub(outputs_sum_out))
+                .ok_or(Error::InvalidInputDetail("negative fee"))?;
+            confirm::confirm(&confirm::Params {
+                title: "Fee bump",
+                body: &format!(
+                    "{}\nto\n{}\nRecipients\nunchanged",
+                    format_amount(coin_params, format_unit, replacement.original_fee)?,
+                    format_amount(coin_params, format_unit, fee)?,
+                ),
+                accept_is_nextarrow: true,
+                ..Default::default()
+            })
+            .await?;
+        } else {
+            // Any discrepancy falls back to the full per-recipient review, which needs every
+            // recipient to have been retained.
+            if num_external_outputs > external_outputs.len() {
+                return Err(Error::InvalidInput);
+            }
+            for (recipient, value) in external_outputs.iter() {
+                transaction::verify_recipient(
+                    recipient,
+                    &format_display_amount(
+                        coin_params,
+                        format_unit,
+                        request.fiat_rate.as_ref(),
+                        *value,
+                    )?,
+                )
+                .await?;
+            }
+        }
+    } else if num_external_outputs > SUMMARIZE_OUTPUTS_THRESHOLD {
+        // Withdrawal batching: a per-recipient review of hundreds of outputs invites blind
+        // clicking. One summary covers them all; rejecting it aborts the transaction.
+        confirm::confirm(&confirm::Params {
+            title: "Summary",
+            body: &format!(
+                "{} recipients\nTotal: {}",
+                num_external_outputs,
+                format_display_amount(
+                    coin_params,
+                    format_unit,
+                    request.fiat_rate.as_ref(),
+                    external_outputs_total,
+                )?,
+            ),
+            accept_is_nextarrow: true,
+            ..Default::default()
+        })
+        .await?;
+        // The summary is already accepted at this point; rejecting here merely declines the
+        // optional full review and is not an abort. Aborting remains possible in any of the
+        // per-recipient dialogs and in the total/fee confirmation. The inspection is only offered
+        // if every recipient was retained.
+        let inspect = num_external_outputs == external_outputs.len()
+            && confirm::confirm(&confirm::Params {
+                title: "Summary",
+                body: &format!(
+                    "Inspect all {}\nrecipients\nindividually?",
+                    num_external_outputs
+                ),
+                accept_is_nextarrow: true,
+                ..Default::default()
+            })
+            .await
+            .is_ok();
+        if inspect {
+            for (recipient, value) in external_outputs.iter() {
+                transaction::verify_recipient(
+                    recipient,
+                    &format_display_amount(
+                        coin_params,
+                        format_unit,
+                        request.fiat_rate.as_ref(),
+                        *value,
+                    )?,
+                )
+                .await?;
+            }
+        }
+    } else {
+        // At or below the threshold, `summarize_outputs` is ignored: every external recipient is
+        // confirmed individually, as without the flag.
+        for (recipient, value) in external_outputs.iter() {
+            transaction::verify_recipient(
+                recipient,
+                &format_display_amount(coin_params, format_unit, request.fiat_rate.as_ref(), *value)?,
+            )
+            .await?;
+        }
+    }
+
+    if request.coinjoin {
+        // The mode is pointless without external outputs; refuse to activate it as a no-op.
+        let coinjoin_output_value = coinjoin_output_value.ok_or(Error::InvalidInput)?;
+        confirm::confirm(&confirm::Params {
+            title: "CoinJoin",
+            body: &format!(
+                "{} outputs of\n{} each to\nexternal addresses",
+                num_coinjoin_outputs,
+                format_amount(coin_params, format_unit, coinjoin_output_value)?,
+            ),
+            accept_is_nextarrow: true,
+            ..Default::default()
+        })
+        .await?;
+    }
+
+    if num_changes > 1 {
+        confirm::confirm(&confirm::Params {
+            title: "Warning",
+            body: &format!("There are {}\nchange outputs.\nProceed?", num_changes),
+            accept_is_nextarrow: true,
+            ..Default::default()
+        })
+        .await?;
+    }
+
+    // A transaction without any change outputs sweeps the whole selected amount out of the
+    // wallet, so make sure the user is aware nothing comes back.
+    if num_changes == 0 {
+        confirm::confirm(&confirm::Params {
+            title: "Warning",
+            body: "This transaction\nempties the\nselected account\n(no change)",
+            accept_is_nextarrow: true,
+            ..Default::default()
+        })
+        .await?;
+    }
+
+    // A transaction above the standardness weight limit signs fine but is rejected by relay
+    // nodes; let the user abort before going through the signing ceremony for nothing. The
+    // estimate is conservative, so the warning can only trigger early, never too late.
+    if estimated_weight > MAX_STANDARD_TX_WEIGHT {
+        confirm::confirm(&confirm::Params {
+            title: "Warning",
+            body: &format!(
+                "Estimated size\nof {} weight\nunits exceeds\nthe relay limit",
+                estimated_weight
+            ),
+            accept_is_nextarrow: true,
+            ..Default::default()
+        })
+        .await?;
+    }
+
+    // Inform about version 3 (TRUC) transactions, which come with special relay and replacement
+    // rules (BIP-431).
+    if request.version == 3 {
+        confirm::confirm(&confirm::Params {
+            body: "This is a\nversion 3 (TRUC)\ntransaction",
+            accept_is_nextarrow: true,
+            ..Default::default()
+        })
+        .await?;
+    }
+
+    // Verify locktime/rbf.
+    // A locktime of 0 will also not be verified, as it's certainly in the past and can't do any
+    // harm.
     //
     // This is not a security feature, the extra locktime/RBF user confirmation is skipped if the tx
     // is not rbf or has a locktime of 0.
     if request.locktime > 0 && locktime_applies {
+        // Locktimes below 500000000 are block heights, all other values are Unix timestamps
+        // (BIP-65).
+        let locktime_formatted = if request.locktime < 500000000 {
+            format!("Locktime on block:\n{}", request.locktime)
+        } else {
+            format!(
+                "Locktime:\n{}",
+                util::datetime::format_timestamp(request.locktime)
+            )
+        };
         // The RBF nsequence bytes are often set in conjunction with a locktime,
         // so verify both simultaneously.
         confirm::confirm(&confirm::Params {
             body: &format!(
-                "Locktime on block:\n{}\n{}",
-                request.locktime,
+                "{}\n{}",
+                locktime_formatted,
                 if coin_params.rbf_support {
-                    if rbf {
-                        "Transaction is RBF"
+                    if request.rbf_required {
+                        "RBF enforced".into()
+                    } else if num_rbf_inputs == request.num_inputs {
+                        "Transaction is RBF".into()
+                    } else if num_rbf_inputs > 0 {
+                        // Mixed sequences: only some inputs signal replaceability, but one is
+                        // enough for the transaction to be replaceable.
+                        format!(
+                            "RBF enabled ({} of {}\ninputs signal\nreplaceability)",
+                            num_rbf_inputs, request.num_inputs
+                        )
                     } else {
-                        "Transaction is not RBF"
+                        "Transaction is not RBF".into()
                     }
                 } else {
                     // There is no RBF in Litecoin.
-                    ""
+                    String::new()
                 }
             ),
             accept_is_nextarrow: true,
@@ -877,20 +2344,88 @@ async fn _process(request: &pb::BtcSignInitRequest) -> Result<Response, Error> {
         .await?;
     }
 
-    // Total out, including fee.
+    // Total out, including fee. Outputs exceeding the inputs would make the transaction invalid
+    // by consensus; catch it here with a specific error instead of displaying a nonsensical fee.
     let total_out: u64 = inputs_sum_pass1
         .checked_sub(outputs_sum_ours)
-        .ok_or(Error::InvalidInput)?;
+        .ok_or(Error::InvalidInputDetail("negative fee"))?;
     let fee: u64 = total_out
         .checked_sub(outputs_sum_out)
-        .ok_or(Error::InvalidInput)?;
+        .ok_or(Error::InvalidInputDetail("negative fee"))?;
     let fee_percentage: Option<f64> = if outputs_sum_out == 0 {
         None
     } else {
         Some(100. * (fee as f64) / (outputs_sum_out as f64))
     };
+    // Repeat which account is being spent from on the total screen, so the user catches a host
+    // silently switching which account it drains. Registered accounts (multisig, MuSig2,
+    // policies) are shown by their user-chosen name, which was already confirmed in
+    // `validate_script_configs()`; otherwise the account is described by its number.
+    let spend_from: String = match script_configs.as_slice() {
+        [pb::BtcScriptConfigWithKeypath {
+            script_config:
+                Some(pb::BtcScriptConfig {
+                    config: Some(pb::btc_script_config::Config::Multisig(multisig)),
+                }),
+            keypath,
+        }] => super::multisig::get_name(coin_params.coin, multisig, keypath)?
+            .ok_or(Error::InvalidInput)?,
+        [pb::BtcScriptConfigWithKeypath {
+            script_config:
+                Some(pb::BtcScriptConfig {
+                    config: Some(pb::btc_script_config::Config::Musig2(musig2)),
+                }),
+            keypath,
+        }] => super::musig2::get_name(coin_params.coin, musig2, keypath)?
+            .ok_or(Error::InvalidInput)?,
+        [pb::BtcScriptConfigWithKeypath {
+            script_config:
+                Some(pb::BtcScriptConfig {
+                    config: Some(pb::btc_script_config::Config::Policy(policy)),
+                }),
+            ..
+        }] => super::policies::get_name(coin_params.coin, policy)?.ok_or(Error::InvalidInput)?,
+        // A single singlesig config spends from a named account if one was registered.
+        [pb::BtcScriptConfigWithKeypath {
+            script_config:
+                Some(pb::BtcScriptConfig {
+                    config: Some(pb::btc_script_config::Config::SimpleType(_)),
+                }),
+            keypath,
+        }] if super::accounts::get_name(coin_params.coin, keypath).is_some() => {
+            super::accounts::get_name(coin_params.coin, keypath).unwrap()
+        }
+        _ => {
+            let mut accounts: Vec<u32> = Vec::new();
+            for script_config in validated_script_configs.iter() {
+                let account = script_config.keypath[2] - util::bip32::HARDENED;
+                if !accounts.contains(&account) {
+                    accounts.push(account);
+                }
+            }
+            let accounts_list = accounts
+                .iter()
+                .map(|account| format!("#{}", account))
+                .collect::<Vec<_>>()
+                .join(" and ");
+            if accounts.len() > 1 {
+                format!("accounts {}", accounts_list)
+            } else {
+                format!("account {}", accounts_list)
+            }
+        }
+    };
     transaction::verify_total_fee(
-        &format_amount(coin_params, format_unit, total_out)?,
+        &format!(
+            "{}\nFrom: {}",
+            format_display_amount(
+                coin_params,
+                format_unit,
+                request.fiat_rate.as_ref(),
+                total_out,
+            )?,
+            spend_from
+        ),
         &format_amount(coin_params, format_unit, fee)?,
         fee_percentage,
     )
@@ -903,8 +2438,11 @@ async fn _process(request: &pb::BtcSignInitRequest) -> Result<Response, Error> {
     drop(empty_component);
 
     // Show progress of signing inputs if there are more than 2 inputs. This is an arbitrary cutoff;
-    // less or equal to 2 inputs is fast enough so it does not need a progress bar.
-    let mut progress_component = if request.num_inputs > 2 {
+    // less or equal to 2 inputs is fast enough so it does not need a progress bar. If the previous
+    // transaction verification was deferred to this pass, the progress bar is always shown, as
+    // prevtx streaming can be slow.
+    let defer_prevtx = request.confirm_outputs_first && !taproot_only;
+    let mut progress_component = if request.num_inputs > 2 || defer_prevtx {
         let mut c = bitbox02::ui::progress_create("Signing transaction...");
         c.screen_stack_push();
         Some(c)
@@ -914,109 +2452,311 @@ async fn _process(request: &pb::BtcSignInitRequest) -> Result<Response, Error> {
 
     // Will contain the sum of all spent output values in the second inputs pass.
     let mut inputs_sum_pass2: u64 = 0;
-    for input_index in 0..request.num_inputs {
-        let tx_input = get_tx_input(input_index, &mut next_response).await?;
-        let script_config_account = validated_script_configs
-            .get(tx_input.script_config_index as usize)
-            .ok_or(Error::InvalidInput)?;
-
-        validate_input(&tx_input, coin_params, script_config_account)?;
-
-        inputs_sum_pass2 = inputs_sum_pass2
-            .checked_add(tx_input.prev_out_value)
-            .ok_or(Error::InvalidInput)?;
-        if inputs_sum_pass2 > inputs_sum_pass1 {
+    let mut hasher_inputs_pass2 = Sha256::new();
+    // Start of the input window being requested. A windowing host sends several consecutive
+    // second-pass inputs per message; legacy hosts send them one by one.
+    let mut window_start: u32 = 0;
+    // Whether the host streams the second-pass inputs in wrapped windows. Entered as soon as
+    // the host answers an input request with a `BTCSignInputsRequest`; subsequent windows are
+    // then requested with `INPUT_BATCH`.
+    let mut prev_windowed = false;
+    while window_start < request.num_inputs {
+        let next_type = if prev_windowed {
+            NextType::InputBatch
+        } else {
+            NextType::Input
+        };
+        let (window, windowed) =
+            get_tx_inputs_windowed(next_type, window_start, &mut next_response).await?;
+        let remaining = request.num_inputs - window_start;
+        if window.is_empty() || window.len() as u32 > remaining.min(SIGN_INPUTS_MAX_WINDOW) {
             return Err(Error::InvalidInput);
         }
+        prev_windowed = windowed;
+        let window_len = window.len() as u32;
+        for (window_offset, tx_input) in window.into_iter().enumerate() {
+            let input_index = window_start + window_offset as u32;
+            hash_input(&mut hasher_inputs_pass2, &tx_input);
+
+            if tx_input.foreign {
+                // Foreign inputs are not signed; `has_signature` stays false for this index.
+                validate_input_foreign(&tx_input)?;
+                inputs_sum_pass2 = inputs_sum_pass2
+                    .checked_add(tx_input.prev_out_value)
+                    .ok_or(Error::InvalidInput)?;
+                if inputs_sum_pass2 > inputs_sum_pass1 {
+                    return Err(Error::InvalidInput);
+                }
+                if windowed {
+                    // Keep the batched signatures aligned with the window's inputs.
+                    next_response.next.signatures.push(vec![]);
+                }
+                // Update progress.
+                if let Some(ref mut c) = progress_component {
+                    bitbox02::ui::progress_set(
+                        c,
+                        (input_index + 1) as f32 / (request.num_inputs as f32),
+                    );
+                }
+                continue;
+            }
 
-        if is_taproot(script_config_account) {
-            // This is a taproot (P2TR) input.
+            let script_config_account = validated_script_configs
+                .get(tx_input.script_config_index as usize)
+                .ok_or(Error::InvalidInputDetail("invalid script config index"))?;
+
+            validate_input(&tx_input, coin_params, script_config_account)?;
 
-            // Anti-Klepto protocol not supported yet for Schnorr signatures.
-            if tx_input.host_nonce_commitment.is_some() {
+            inputs_sum_pass2 = inputs_sum_pass2
+                .checked_add(tx_input.prev_out_value)
+                .ok_or(Error::InvalidInput)?;
+            if inputs_sum_pass2 > inputs_sum_pass1 {
                 return Err(Error::InvalidInput);
             }
 
-            let sighash = bip341::sighash(&bip341::Args {
-                version: request.version,
-                locktime: request.locktime,
-                hash_prevouts: hash_prevouts.into(),
-                hash_amounts: hash_amounts.into(),
-                hash_scriptpubkeys: hash_scriptpubkeys.into(),
-                hash_sequences: hash_sequence.into(),
-                hash_outputs: hash_outputs.into(),
-                input_index,
-            });
-            next_response.next.has_signature = true;
-            next_response.next.signature =
-                bitbox02::keystore::secp256k1_schnorr_bip86_sign(&tx_input.keypath, &sighash)?
-                    .to_vec();
-        } else {
-            // Sign all other supported inputs.
-
-            const SIGHASH_ALL: u32 = 0x01;
-            let sighash = bip143::sighash(&bip143::Args {
-                version: request.version,
-                hash_prevouts: Sha256::digest(hash_prevouts).into(),
-                hash_sequence: Sha256::digest(hash_sequence).into(),
-                outpoint_hash: tx_input.prev_out_hash.as_slice().try_into().unwrap(),
-                outpoint_index: tx_input.prev_out_index,
-                sighash_script: &sighash_script(
-                    &mut xpub_cache,
-                    script_config_account,
-                    &tx_input.keypath,
-                )?,
-                prevout_value: tx_input.prev_out_value,
-                sequence: tx_input.sequence,
-                hash_outputs: Sha256::digest(hash_outputs).into(),
-                locktime: request.locktime,
-                sighash_flags: SIGHASH_ALL,
-            });
+            if request.confirm_outputs_first {
+                // The outputs, total and fee were confirmed based on the values claimed in the
+                // first pass; before producing a signature, the claim must match this pass
+                // exactly. The cross-pass checks after this loop come too late: signatures of
+                // earlier inputs are already released to the host.
+                if tx_input.prev_out_value != pass1_input_values[input_index as usize] {
+                    return Err(Error::InvalidInput);
+                }
+                if defer_prevtx {
+                    // The deferred prevtx streaming needs round trips of its own per input and
+                    // cannot be part of a window.
+                    if windowed {
+                        return Err(Error::InvalidInput);
+                    }
+                    // Verify the claimed value and prevout hash against the input's previous
+                    // transaction, deferred from the first pass; a mismatch aborts before this
+                    // input is signed. The pubkey script is not needed: the input is ours, so it
+                    // is derived from the keystore.
+                    handle_prevtx(
+                        input_index,
+                        &tx_input,
+                        request.num_inputs,
+                        progress_component.as_mut().unwrap(),
+                        &mut prevtx_cache,
+                        &mut next_response,
+                    )
+                    .await?;
+                }
+            }
+
+            if is_taproot(script_config_account) {
+                // This is a taproot (P2TR) input.
+
+                // Anti-Klepto protocol not supported yet for Schnorr signatures.
+                if tx_input.host_nonce_commitment.is_some() {
+                    return Err(Error::InvalidInput);
+                }
+
+                let script_path_spend = !tx_input.script_path_leaf_script.is_empty();
+                if script_path_spend {
+                    // Re-verify the control block in the second pass; the sighash commits to the
+                    // pubkey scripts of the first pass, so a mismatch would invalidate the signature,
+                    // but we fail early instead of producing a useless signature.
+                    validate_script_path_spend(&mut xpub_cache, &tx_input)?;
+                }
 
-            // Engage in the Anti-Klepto protocol if the host sends a host nonce commitment.
-            let host_nonce: [u8; 32] = match tx_input.host_nonce_commitment {
-                Some(pb::AntiKleptoHostNonceCommitment { ref commitment }) => {
-                    let signer_commitment = bitbox02::keystore::secp256k1_nonce_commit(
+                let sighash = bip341::sighash(&bip341::Args {
+                    version: request.version,
+                    locktime: request.locktime,
+                    hash_prevouts: hash_prevouts.into(),
+                    hash_amounts: hash_amounts.into(),
+                    hash_scriptpubkeys: hash_scriptpubkeys.into(),
+                    hash_sequences: hash_sequence.into(),
+                    hash_outputs: hash_outputs.into(),
+                    input_index,
+                    tapleaf_hash: if script_path_spend {
+                        Some(bip341::tapleaf_hash(&tx_input.script_path_leaf_script))
+                    } else {
+                        None
+                    },
+                });
+                if let ValidatedScriptConfig::Musig2(musig2) = &script_config_account.config {
+                    // The nonce exchange needs a round trip of its own per input and cannot be part
+                    // of a window.
+                    if windowed {
+                        return Err(Error::InvalidInput);
+                    }
+                    // MuSig2 key path spend of the aggregate key. Two host round trips per input:
+                    // first the public nonces are exchanged, then our partial signature is produced.
+                    let pubkeys = super::musig2::derive_pubkeys(
+                        musig2,
+                        tx_input.keypath[tx_input.keypath.len() - 2],
+                        tx_input.keypath[tx_input.keypath.len() - 1],
+                    )?;
+                    // A fresh secret nonce is generated in the keystore for each input; it is consumed
+                    // by the partial signing below and never reused.
+                    let our_pub_nonce =
+                        bitbox02::keystore::secp256k1_musig_nonce_gen(&tx_input.keypath, &sighash)?;
+                    let host_nonces =
+                        get_musig2_nonces(input_index, &our_pub_nonce, &mut next_response).await?;
+                    // One nonce per cosigner, without ours.
+                    if host_nonces.pub_nonces.len() != pubkeys.len() - 1 {
+                        return Err(Error::InvalidInput);
+                    }
+                    let mut pub_nonces: Vec<[u8; 66]> = host_nonces
+                        .pub_nonces
+                        .iter()
+                        .map(|nonce| nonce.as_slice().try_into().or(Err(Error::InvalidInput)))
+                        .collect::<Result<_, _>>()?;
+                    pub_nonces.insert(musig2.our_xpub_index as usize, our_pub_nonce);
+                    // A MuSig2 partial signature cannot be self-verified like a complete
+                    // signature; it only verifies in combination with the other partial
+                    // signatures, which the host aggregates.
+                    let partial_signature = bitbox02::keystore::secp256k1_musig_partial_sign(
+                        &tx_input.keypath,
+                        &sighash,
+                        &pubkeys,
+                        &pub_nonces,
+                    )?;
+                    next_response.next.has_signature = true;
+                    next_response.next.signature = partial_signature.to_vec();
+                } else {
+                    let signature: [u8; 64] = if script_path_spend {
+                        // Script path spends are signed with the untweaked key at the keypath; the
+                        // leaf script is expected to commit to that key.
+                        bitbox02::keystore::secp256k1_schnorr_sign(&tx_input.keypath, &sighash)?
+                    } else {
+                        bitbox02::keystore::secp256k1_schnorr_bip86_sign(&tx_input.keypath, &sighash)?
+                    };
+                    verify_schnorr_signature(
+                        &mut xpub_cache,
                         &tx_input.keypath,
+                        script_path_spend,
                         &sighash,
-                        commitment
+                        &signature,
+                    )?;
+                    if windowed {
+                        next_response.next.signatures.push(signature.to_vec());
+                    } else {
+                        next_response.next.has_signature = true;
+                        next_response.next.signature = signature.to_vec();
+                    }
+                }
+            } else {
+                // Sign all other supported inputs.
+
+                const SIGHASH_ALL: u32 = 0x01;
+                let sighash = if is_legacy(script_config_account) {
+                    legacy::sighash(&legacy::Args {
+                        version: request.version,
+                        inputs: &legacy_inputs,
+                        input_index,
+                        sighash_script: &sighash_script(
+                            &mut xpub_cache,
+                            script_config_account,
+                            &tx_input.keypath,
+                        )?,
+                        serialized_outputs: &serialized_outputs,
+                        num_outputs: request.num_outputs,
+                        locktime: request.locktime,
+                        sighash_flags: SIGHASH_ALL,
+                    })
+                } else {
+                    bip143::sighash(&bip143::Args {
+                        version: request.version,
+                        hash_prevouts: Sha256::digest(hash_prevouts).into(),
+                        hash_sequence: Sha256::digest(hash_sequence).into(),
+                        outpoint_hash: tx_input.prev_out_hash.as_slice().try_into().unwrap(),
+                        outpoint_index: tx_input.prev_out_index,
+                        sighash_script: &sighash_script(
+                            &mut xpub_cache,
+                            script_config_account,
+                            &tx_input.keypath,
+                        )?,
+                        prevout_value: tx_input.prev_out_value,
+                        sequence: tx_input.sequence,
+                        hash_outputs: Sha256::digest(hash_outputs).into(),
+                        locktime: request.locktime,
+                        sighash_flags: SIGHASH_ALL,
+                    })
+                };
+
+                // The anti-klepto protocol needs a round trip of its own per input and cannot be
+                // part of a window.
+                if windowed && tx_input.host_nonce_commitment.is_some() {
+                    return Err(Error::InvalidInput);
+                }
+
+                // Engage in the Anti-Klepto protocol if the host sends a host nonce commitment.
+                // This covers every ECDSA input type, including multisig and policy inputs - a
+                // malicious device in a quorum could otherwise leak the seed through its nonces.
+                let host_nonce: [u8; 32] = match tx_input.host_nonce_commitment {
+                    Some(pb::AntiKleptoHostNonceCommitment { ref commitment }) => {
+                        let signer_commitment = bitbox02::keystore::secp256k1_nonce_commit(
+                            &tx_input.keypath,
+                            &sighash,
+                            commitment
+                                .as_slice()
+                                .try_into()
+                                .or(Err(Error::InvalidInput))?,
+                        )?;
+                        next_response.next.anti_klepto_signer_commitment =
+                            Some(pb::AntiKleptoSignerCommitment {
+                                commitment: signer_commitment.to_vec(),
+                            });
+
+                        get_antiklepto_host_nonce(input_index, &mut next_response)
+                            .await?
+                            .host_nonce
                             .as_slice()
                             .try_into()
-                            .or(Err(Error::InvalidInput))?,
-                    )?;
-                    next_response.next.anti_klepto_signer_commitment =
-                        Some(pb::AntiKleptoSignerCommitment {
-                            commitment: signer_commitment.to_vec(),
-                        });
-
-                    get_antiklepto_host_nonce(input_index, &mut next_response)
-                        .await?
-                        .host_nonce
-                        .as_slice()
-                        .try_into()
-                        .or(Err(Error::InvalidInput))?
-                }
-                // Return signature directly without the anti-klepto protocol, for backwards compatibility.
-                None => [0; 32],
-            };
+                            .or(Err(Error::InvalidInput))?
+                    }
+                    // Return signature directly without the anti-klepto protocol, for backwards
+                    // compatibility - unless the user opted into requiring the protocol on every
+                    // signature.
+                    None => {
+                        if bitbox02::memory::is_antiklepto_required() {
+                            return Err(Error::InvalidInput);
+                        }
+                        [0; 32]
+                    }
+                };
 
-            let sign_result =
-                bitbox02::keystore::secp256k1_sign(&tx_input.keypath, &sighash, &host_nonce)?;
-            next_response.next.has_signature = true;
-            next_response.next.signature = sign_result.signature.to_vec();
-        }
+                let sign_result =
+                    bitbox02::keystore::secp256k1_sign(&tx_input.keypath, &sighash, &host_nonce)?;
+                verify_ecdsa_signature(
+                    &mut xpub_cache,
+                    &tx_input.keypath,
+                    &sighash,
+                    &sign_result.signature,
+                )?;
+                if windowed {
+                    next_response.next.signatures.push(sign_result.signature.to_vec());
+                } else {
+                    next_response.next.has_signature = true;
+                    next_response.next.signature = sign_result.signature.to_vec();
+                }
+            }
 
-        // Update progress.
-        if let Some(ref mut c) = progress_component {
-            bitbox02::ui::progress_set(c, (input_index + 1) as f32 / (request.num_inputs as f32));
+            // Update progress.
+            if let Some(ref mut c) = progress_component {
+                bitbox02::ui::progress_set(c, (input_index + 1) as f32 / (request.num_inputs as f32));
+            }
         }
+        window_start += window_len;
     }
 
     if inputs_sum_pass1 != inputs_sum_pass2 {
         return Err(Error::InvalidInput);
     }
+    // The sighash commits to the prevout and sequence hashes of the first pass, so a mismatching
+    // second pass would mostly produce useless signatures, but we do not rely on consensus rules
+    // rejecting the result: the inputs of both passes must be byte-identical.
+    if hash_inputs_pass1 != hasher_inputs_pass2.finalize() {
+        return Err(Error::InvalidInput);
+    }
 
     next_response.next.r#type = NextType::Done as _;
+    // Machine-readable summary of the amounts the user approved, from our own accumulation.
+    next_response.next.total_out = total_out;
+    next_response.next.fee = fee;
+    next_response.next.change_total = outputs_sum_ours;
     Ok(next_response.to_protobuf())
 }
 
@@ -1048,6 +2788,7 @@ mod tests {
         }
     }
 
+    #[derive(Clone)]
     struct TxInput {
         input: pb::BtcSignInputRequest,
         prevtx_version: u32,
@@ -1066,6 +2807,9 @@ mod tests {
         outputs: Vec<pb::BtcSignOutputRequest>,
         locktime: u32,
         payment_request: Option<pb::BtcPaymentRequestRequest>,
+        // If true, the host declares `supports_batched_entries` and answers prevtx entry requests
+        // with multi-entry messages.
+        prevtx_batched: bool,
     }
 
     impl Transaction {
@@ -1074,7 +2818,7 @@ mod tests {
             let bip44_coin = super::super::params::get(coin).bip44_coin;
             Transaction {
                 coin,
-                total_confirmations: 6,
+                total_confirmations: 7,
                 version: 1,
                 inputs: vec![
                     TxInput {
@@ -1090,6 +2834,9 @@ mod tests {
                             keypath: vec![84 + HARDENED, bip44_coin, 10 + HARDENED, 0, 5],
                             script_config_index: 0,
                             host_nonce_commitment: None,
+                            foreign: false,
+                            script_path_leaf_script: vec![],
+                            script_path_control_block: vec![],
                         },
                         prevtx_version: 1,
                         prevtx_inputs: vec![
@@ -1142,6 +2889,9 @@ mod tests {
                             keypath: vec![84 + HARDENED, bip44_coin, 10 + HARDENED, 0, 7],
                             script_config_index: 0,
                             host_nonce_commitment: None,
+                            foreign: false,
+                            script_path_leaf_script: vec![],
+                            script_path_control_block: vec![],
                         },
                         prevtx_version: 2,
                         prevtx_inputs: vec![pb::BtcPrevTxInputRequest {
@@ -1174,6 +2924,8 @@ mod tests {
                         keypath: vec![],
                         script_config_index: 0,
                         payment_request_index: None,
+                        display_name: String::new(),
+                        witness_version: 0,
                     },
                     pb::BtcSignOutputRequest {
                         ours: false,
@@ -1186,6 +2938,8 @@ mod tests {
                         keypath: vec![],
                         script_config_index: 0,
                         payment_request_index: None,
+                        display_name: String::new(),
+                        witness_version: 0,
                     },
                     pb::BtcSignOutputRequest {
                         ours: false,
@@ -1198,6 +2952,8 @@ mod tests {
                         keypath: vec![],
                         script_config_index: 0,
                         payment_request_index: None,
+                        display_name: String::new(),
+                        witness_version: 0,
                     },
                     pb::BtcSignOutputRequest {
                         ours: false,
@@ -1211,6 +2967,8 @@ mod tests {
                         keypath: vec![],
                         script_config_index: 0,
                         payment_request_index: None,
+                        display_name: String::new(),
+                        witness_version: 0,
                     },
                     pb::BtcSignOutputRequest {
                         // change
@@ -1221,6 +2979,8 @@ mod tests {
                         keypath: vec![84 + HARDENED, bip44_coin, 10 + HARDENED, 1, 3],
                         script_config_index: 0,
                         payment_request_index: None,
+                        display_name: String::new(),
+                        witness_version: 0,
                     },
                     pb::BtcSignOutputRequest {
                         // change #2
@@ -1231,10 +2991,13 @@ mod tests {
                         keypath: vec![84 + HARDENED, bip44_coin, 10 + HARDENED, 1, 30],
                         script_config_index: 0,
                         payment_request_index: None,
+                        display_name: String::new(),
+                        witness_version: 0,
                     },
                 ],
                 locktime: 0,
                 payment_request: None,
+                prevtx_batched: false,
             }
         }
 
@@ -1259,6 +3022,9 @@ mod tests {
                         keypath: vec![48 + HARDENED, bip44_coin, 0 + HARDENED, 2 + HARDENED, 0, 0],
                         script_config_index: 0,
                         host_nonce_commitment: None,
+                        foreign: false,
+                        script_path_leaf_script: vec![],
+                        script_path_control_block: vec![],
                     },
                     prevtx_version: 1,
                     prevtx_inputs: vec![pb::BtcPrevTxInputRequest {
@@ -1287,6 +3053,8 @@ mod tests {
                         keypath: vec![48 + HARDENED, bip44_coin, 0 + HARDENED, 2 + HARDENED, 1, 0],
                         script_config_index: 0,
                         payment_request_index: None,
+                        display_name: String::new(),
+                        witness_version: 0,
                     },
                     pb::BtcSignOutputRequest {
                         ours: false,
@@ -1300,10 +3068,13 @@ mod tests {
                         keypath: vec![],
                         script_config_index: 0,
                         payment_request_index: None,
+                        display_name: String::new(),
+                        witness_version: 0,
                     },
                 ],
                 locktime: 1663289,
                 payment_request: None,
+                prevtx_batched: false,
             }
         }
 
@@ -1339,6 +3110,14 @@ mod tests {
                 num_outputs: self.outputs.len() as _,
                 locktime: self.locktime,
                 format_unit: FormatUnit::Default as _,
+                coinjoin: false,
+                advanced_verify_inputs: false,
+                rbf_required: false,
+                fiat_rate: None,
+                verify_bip69_order: false,
+                summarize_outputs: false,
+                confirm_outputs_first: false,
+                replacement: None,
             }
         }
 
@@ -1360,6 +3139,14 @@ mod tests {
                 num_outputs: self.outputs.len() as _,
                 locktime: self.locktime,
                 format_unit: FormatUnit::Default as _,
+                coinjoin: false,
+                advanced_verify_inputs: false,
+                rbf_required: false,
+                fiat_rate: None,
+                verify_bip69_order: false,
+                summarize_outputs: false,
+                confirm_outputs_first: false,
+                replacement: None,
             }
         }
 
@@ -1385,6 +3172,19 @@ mod tests {
                             num_inputs: self.inputs[next.index as usize].prevtx_inputs.len() as _,
                             num_outputs: self.inputs[next.index as usize].prevtx_outputs.len() as _,
                             locktime: self.inputs[next.index as usize].prevtx_locktime,
+                            supports_batched_entries: self.prevtx_batched,
+                        },
+                    )),
+                }),
+                NextType::PrevtxInput if self.prevtx_batched => Request::Btc(pb::BtcRequest {
+                    request: Some(pb::btc_request::Request::PrevtxInputs(
+                        pb::BtcPrevTxInputsRequest {
+                            inputs: self.inputs[next.index as usize].prevtx_inputs
+                                [next.prev_index as usize..]
+                                .iter()
+                                .take(PREVTX_MAX_BATCH_ENTRIES as usize)
+                                .cloned()
+                                .collect(),
                         },
                     )),
                 }),
@@ -1394,6 +3194,18 @@ mod tests {
                             .clone(),
                     )),
                 }),
+                NextType::PrevtxOutput if self.prevtx_batched => Request::Btc(pb::BtcRequest {
+                    request: Some(pb::btc_request::Request::PrevtxOutputs(
+                        pb::BtcPrevTxOutputsRequest {
+                            outputs: self.inputs[next.index as usize].prevtx_outputs
+                                [next.prev_index as usize..]
+                                .iter()
+                                .take(PREVTX_MAX_BATCH_ENTRIES as usize)
+                                .cloned()
+                                .collect(),
+                        },
+                    )),
+                }),
                 NextType::PrevtxOutput => Request::Btc(pb::BtcRequest {
                     request: Some(pb::btc_request::Request::PrevtxOutput(
                         self.inputs[next.index as usize].prevtx_outputs[next.prev_index as usize]
@@ -1416,6 +3228,28 @@ mod tests {
         }
     }
 
+    /// Computes the txid of an input's previous transaction from the fixture data.
+    fn compute_prevtx_hash(input: &TxInput) -> Vec<u8> {
+        let mut hasher = Sha256::new();
+        hasher.update(input.prevtx_version.to_le_bytes());
+        hasher.update(serialize_varint(input.prevtx_inputs.len() as u64).as_slice());
+        for prevtx_input in input.prevtx_inputs.iter() {
+            hasher.update(prevtx_input.prev_out_hash.as_slice());
+            hasher.update(prevtx_input.prev_out_index.to_le_bytes());
+            hasher.update(serialize_varint(prevtx_input.signature_script.len() as u64).as_slice());
+            hasher.update(prevtx_input.signature_script.as_slice());
+            hasher.update(prevtx_input.sequence.to_le_bytes());
+        }
+        hasher.update(serialize_varint(input.prevtx_outputs.len() as u64).as_slice());
+        for prevtx_output in input.prevtx_outputs.iter() {
+            hasher.update(prevtx_output.value.to_le_bytes());
+            hasher.update(serialize_varint(prevtx_output.pubkey_script.len() as u64).as_slice());
+            hasher.update(prevtx_output.pubkey_script.as_slice());
+        }
+        hasher.update(input.prevtx_locktime.to_le_bytes());
+        Sha256::digest(hasher.finalize()).to_vec()
+    }
+
     fn mock_host_responder(tx: alloc::rc::Rc<core::cell::RefCell<Transaction>>) {
         *crate::hww::MOCK_NEXT_REQUEST.0.borrow_mut() =
             Some(Box::new(move |response: Response| {
@@ -1433,6 +3267,21 @@ mod tests {
         });
     }
 
+    /// Asserts that signing fails at the init stage with `expected`, and that the dry-run
+    /// validation request rejects the same init request for the same reason.
+    fn assert_init_fails(req: &pb::BtcSignInitRequest, expected: Error) {
+        assert_eq!(block_on(process(req)).unwrap_err(), expected);
+        assert_eq!(
+            block_on(process_is_script_config_valid(
+                &pb::BtcIsScriptConfigValidRequest {
+                    init: Some(req.clone()),
+                }
+            ))
+            .unwrap_err(),
+            expected
+        );
+    }
+
     #[test]
     pub fn test_sign_init_fail() {
         *crate::hww::MOCK_NEXT_REQUEST.0.borrow_mut() = None;
@@ -1452,12 +3301,20 @@ mod tests {
             num_outputs: 1,
             locktime: 0,
             format_unit: FormatUnit::Default as _,
+            coinjoin: false,
+            advanced_verify_inputs: false,
+            rbf_required: false,
+            fiat_rate: None,
+            verify_bip69_order: false,
+            summarize_outputs: false,
+            confirm_outputs_first: false,
+            replacement: None,
         };
 
         {
             // test keystore locked
             bitbox02::keystore::lock();
-            assert_eq!(block_on(process(&init_req_valid)), Err(Error::InvalidState));
+            assert_init_fails(&init_req_valid, Error::InvalidState);
         }
 
         mock_unlocked();
@@ -1466,147 +3323,126 @@ mod tests {
             let mut init_req_invalid = init_req_valid.clone();
             init_req_invalid.coin = pb::BtcCoin::Ltc as _;
             init_req_invalid.format_unit = FormatUnit::Sat as _;
-            assert_eq!(
-                block_on(process(&init_req_invalid)),
-                Err(Error::InvalidInput)
-            );
+            assert_init_fails(&init_req_invalid, Error::InvalidInput);
         }
         {
             // test invalid version
             let mut init_req_invalid = init_req_valid.clone();
-            for version in 3..10 {
+            for version in 4..10 {
                 init_req_invalid.version = version;
-                assert_eq!(
-                    block_on(process(&init_req_invalid)),
-                    Err(Error::InvalidInput)
+                assert_init_fails(
+                    &init_req_invalid,
+                    Error::InvalidInputDetail("unsupported version"),
                 );
             }
         }
         {
-            // test invalid locktime
+            // test invalid inputs
+            let mut init_req_invalid = init_req_valid.clone();
+            init_req_invalid.num_inputs = 0;
+            assert_init_fails(&init_req_invalid, Error::InvalidInput);
+        }
+        {
+            // test invalid outputs
             let mut init_req_invalid = init_req_valid.clone();
-            init_req_invalid.locktime = 500000000;
-            assert_eq!(
-                block_on(process(&init_req_invalid)),
-                Err(Error::InvalidInput)
+            init_req_invalid.num_outputs = 0;
+            assert_init_fails(&init_req_invalid, Error::InvalidInput);
+        }
+        {
+            // too many inputs
+            let mut init_req_invalid = init_req_valid.clone();
+            init_req_invalid.num_inputs = SIGN_MAX_INPUTS + 1;
+            assert_init_fails(
+                &init_req_invalid,
+                Error::InvalidInputDetail("too many inputs or outputs"),
             );
         }
         {
-            // test invalid inputs
+            // too many outputs
             let mut init_req_invalid = init_req_valid.clone();
-            init_req_invalid.num_inputs = 0;
-            assert_eq!(
-                block_on(process(&init_req_invalid)),
-                Err(Error::InvalidInput)
+            init_req_invalid.num_outputs = SIGN_MAX_OUTPUTS + 1;
+            assert_init_fails(
+                &init_req_invalid,
+                Error::InvalidInputDetail("too many inputs or outputs"),
             );
         }
         {
-            // test invalid outputs
+            // legacy script configs cap the counts lower, as per-entry data is retained for the
+            // legacy sighash
             let mut init_req_invalid = init_req_valid.clone();
-            init_req_invalid.num_outputs = 0;
-            assert_eq!(
-                block_on(process(&init_req_invalid)),
-                Err(Error::InvalidInput)
+            init_req_invalid.script_configs[0] = pb::BtcScriptConfigWithKeypath {
+                script_config: Some(pb::BtcScriptConfig {
+                    config: Some(pb::btc_script_config::Config::SimpleType(
+                        SimpleType::P2pkh as _,
+                    )),
+                }),
+                keypath: vec![44 + HARDENED, 0 + HARDENED, 10 + HARDENED],
+            };
+            init_req_invalid.num_inputs = SIGN_MAX_INPUTS_LEGACY + 1;
+            assert_init_fails(
+                &init_req_invalid,
+                Error::InvalidInputDetail("too many inputs or outputs"),
             );
         }
         {
             // test invalid coin
             let mut init_req_invalid = init_req_valid.clone();
             init_req_invalid.coin = 4; // BtcCoin is defined from 0 to 3.
-            assert_eq!(
-                block_on(process(&init_req_invalid)),
-                Err(Error::InvalidInput)
-            );
+            assert_init_fails(&init_req_invalid, Error::InvalidInput);
         }
         {
             // test invalid account keypath
             let mut init_req_invalid = init_req_valid.clone();
             init_req_invalid.script_configs[0].keypath[2] = HARDENED + 100;
-            assert_eq!(
-                block_on(process(&init_req_invalid)),
-                Err(Error::InvalidInput)
-            );
+            assert_init_fails(&init_req_invalid, Error::InvalidInput);
         }
         {
             // no script configs is invalid
             let mut init_req_invalid = init_req_valid.clone();
             init_req_invalid.script_configs = vec![];
-            assert_eq!(
-                block_on(process(&init_req_invalid)),
-                Err(Error::InvalidInput)
-            );
+            assert_init_fails(&init_req_invalid, Error::InvalidInput);
         }
         {
-            // can't mix script configs from different bip44 accounts
-            // (mixing input scripts is allowed, but only from the same bip44 account).
+            // mixing simple type (singlesig) and multisig configs is allowed, but the multisig
+            // config must be valid and registered
             let mut init_req_invalid = init_req_valid.clone();
             init_req_invalid.script_configs = vec![
                 pb::BtcScriptConfigWithKeypath {
                     script_config: Some(pb::BtcScriptConfig {
                         config: Some(pb::btc_script_config::Config::SimpleType(
-                            SimpleType::P2wpkh as _,
+                            pb::btc_script_config::SimpleType::P2wpkh as _,
                         )),
                     }),
                     keypath: vec![84 + HARDENED, 0 + HARDENED, 10 + HARDENED],
                 },
                 pb::BtcScriptConfigWithKeypath {
                     script_config: Some(pb::BtcScriptConfig {
-                        config: Some(pb::btc_script_config::Config::SimpleType(
-                            SimpleType::P2wpkhP2sh as _,
+                        config: Some(pb::btc_script_config::Config::Multisig(
+                            pb::btc_script_config::Multisig {
+                                threshold: 1,
+                                xpubs: vec![
+                                    pb::XPub {
+                                        ..Default::default()
+                                    },
+                                    pb::XPub {
+                                        ..Default::default()
+                                    },
+                                ],
+                                our_xpub_index: 0,
+                                script_type: pb::btc_script_config::multisig::ScriptType::P2wsh
+                                    as _,
+                            },
                         )),
                     }),
                     keypath: vec![49 + HARDENED, 0 + HARDENED, 0 + HARDENED],
                 },
             ];
-            assert_eq!(
-                block_on(process(&init_req_invalid)),
-                Err(Error::InvalidInput)
-            );
+            assert_init_fails(&init_req_invalid, Error::InvalidInput);
         }
-
         {
-            // can't mix simple type (singlesig) and multisig configs in one tx
-            let mut init_req_invalid = init_req_valid.clone();
-            init_req_invalid.script_configs = vec![
-                pb::BtcScriptConfigWithKeypath {
-                    script_config: Some(pb::BtcScriptConfig {
-                        config: Some(pb::btc_script_config::Config::SimpleType(
-                            pb::btc_script_config::SimpleType::P2wpkh as _,
-                        )),
-                    }),
-                    keypath: vec![84 + HARDENED, 0 + HARDENED, 10 + HARDENED],
-                },
-                pb::BtcScriptConfigWithKeypath {
-                    script_config: Some(pb::BtcScriptConfig {
-                        config: Some(pb::btc_script_config::Config::Multisig(
-                            pb::btc_script_config::Multisig {
-                                threshold: 1,
-                                xpubs: vec![
-                                    pb::XPub {
-                                        ..Default::default()
-                                    },
-                                    pb::XPub {
-                                        ..Default::default()
-                                    },
-                                ],
-                                our_xpub_index: 0,
-                                script_type: pb::btc_script_config::multisig::ScriptType::P2wsh
-                                    as _,
-                            },
-                        )),
-                    }),
-                    keypath: vec![49 + HARDENED, 0 + HARDENED, 0 + HARDENED],
-                },
-            ];
-            assert_eq!(
-                block_on(process(&init_req_invalid)),
-                Err(Error::InvalidInput)
-            );
-        }
-        {
-            // no taproot in Litecoin
-            assert_eq!(
-                block_on(process(&pb::BtcSignInitRequest {
+            // taproot on Litecoin follows BIP-86: purposes other than 86' are rejected
+            assert_init_fails(
+                &pb::BtcSignInitRequest {
                     coin: pb::BtcCoin::Ltc as _,
                     script_configs: vec![pb::BtcScriptConfigWithKeypath {
                         script_config: Some(pb::BtcScriptConfig {
@@ -1621,7 +3457,37 @@ mod tests {
                     num_outputs: 1,
                     locktime: 0,
                     format_unit: FormatUnit::Default as _,
-                })),
+                    coinjoin: false,
+                    advanced_verify_inputs: false,
+                    rbf_required: false,
+                    fiat_rate: None,
+                    verify_bip69_order: false,
+                    summarize_outputs: false,
+                    confirm_outputs_first: false,
+                    replacement: None,
+                },
+                Error::InvalidInput,
+            );
+        }
+        {
+            // the valid init request passes the dry-run validation without any user interaction
+            mock(Data::default());
+            mock_unlocked();
+            assert_eq!(
+                block_on(process_is_script_config_valid(
+                    &pb::BtcIsScriptConfigValidRequest {
+                        init: Some(init_req_valid.clone()),
+                    }
+                )),
+                Ok(pb::btc_response::Response::Success(pb::BtcSuccess {}))
+            );
+        }
+        {
+            // a dry-run without an init request is invalid
+            assert_eq!(
+                block_on(process_is_script_config_valid(
+                    &pb::BtcIsScriptConfigValidRequest { init: None }
+                )),
                 Err(Error::InvalidInput)
             );
         }
@@ -1635,6 +3501,7 @@ mod tests {
         for (coin, format_unit) in [
             (pb::BtcCoin::Btc, FormatUnit::Default),
             (pb::BtcCoin::Btc, FormatUnit::Sat),
+            (pb::BtcCoin::Sbtc, FormatUnit::Default),
             (pb::BtcCoin::Ltc, FormatUnit::Default),
         ] {
             unsafe {
@@ -1666,9 +3533,13 @@ mod tests {
                                     assert_eq!(address, "12ZEw5Hcv1hTb6YUQJ69y1V7uhcoDz92PH");
                                     match format_unit {
                                         FormatUnit::Default => assert_eq!(amount, "1.00000000 BTC"),
-                                        FormatUnit::Sat => assert_eq!(amount, "100000000 sat"),
+                                        FormatUnit::Sat => assert_eq!(amount, "100'000'000 sat"),
                                     }
                                 }
+                                pb::BtcCoin::Sbtc => {
+                                    assert_eq!(address, "mh5CE8Nbj38iND267s4XnvhSmhDW7yWc6Q");
+                                    assert_eq!(amount, "1.00000000 SBTC");
+                                }
                                 pb::BtcCoin::Ltc => {
                                     assert_eq!(address, "LLnCCHbSzfwWquEdaS5TF2Yt7uz5Qb1SZ1");
                                     assert_eq!(amount, "1.00000000 LTC");
@@ -1685,9 +3556,13 @@ mod tests {
                                         FormatUnit::Default => {
                                             assert_eq!(amount, "12.34567890 BTC")
                                         }
-                                        FormatUnit::Sat => assert_eq!(amount, "1234567890 sat"),
+                                        FormatUnit::Sat => assert_eq!(amount, "1'234'567'890 sat"),
                                     }
                                 }
+                                pb::BtcCoin::Sbtc => {
+                                    assert_eq!(address, "2MvMhrRzhzPDeU9QvbpTmybKSBGjUmC6TTu");
+                                    assert_eq!(amount, "12.34567890 SBTC");
+                                }
                                 pb::BtcCoin::Ltc => {
                                     assert_eq!(address, "MB1e6aUeL3Zj4s4H2ZqFBHaaHd7kvvzTco");
                                     assert_eq!(amount, "12.34567890 LTC");
@@ -1707,9 +3582,16 @@ mod tests {
                                         FormatUnit::Default => {
                                             assert_eq!(amount, "0.00006000 BTC")
                                         }
-                                        FormatUnit::Sat => assert_eq!(amount, "6000 sat"),
+                                        FormatUnit::Sat => assert_eq!(amount, "6'000 sat"),
                                     }
                                 }
+                                pb::BtcCoin::Sbtc => {
+                                    assert_eq!(
+                                        address,
+                                        "tb1qxvenxvenxvenxvenxvenxvenxvenxvenqzqps5"
+                                    );
+                                    assert_eq!(amount, "0.00006000 SBTC");
+                                }
                                 pb::BtcCoin::Ltc => {
                                     assert_eq!(
                                         address,
@@ -1732,9 +3614,16 @@ mod tests {
                                         FormatUnit::Default => {
                                             assert_eq!(amount, "0.00007000 BTC")
                                         }
-                                        FormatUnit::Sat => assert_eq!(amount, "7000 sat"),
+                                        FormatUnit::Sat => assert_eq!(amount, "7'000 sat"),
                                     }
                                 }
+                                pb::BtcCoin::Sbtc => {
+                                    assert_eq!(
+                                        address,
+                                        "tb1qg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zq60xf56"
+                                    );
+                                    assert_eq!(amount, "0.00007000 SBTC");
+                                }
                                 pb::BtcCoin::Ltc => {
                                     assert_eq!(
                                         address,
@@ -1754,20 +3643,24 @@ mod tests {
                         UI_COUNTER += 1;
                         UI_COUNTER
                     } {
-                        6 => {
+                        7 => {
                             match coin {
                                 pb::BtcCoin::Btc => match format_unit {
                                     FormatUnit::Default => {
-                                        assert_eq!(total, "13.39999900 BTC");
+                                        assert_eq!(total, "13.39999900 BTC\nFrom: account #10");
                                         assert_eq!(fee, "0.05419010 BTC");
                                     }
                                     FormatUnit::Sat => {
-                                        assert_eq!(total, "1339999900 sat");
-                                        assert_eq!(fee, "5419010 sat");
+                                        assert_eq!(total, "1'339'999'900 sat\nFrom: account #10");
+                                        assert_eq!(fee, "5'419'010 sat");
                                     }
                                 },
+                                pb::BtcCoin::Sbtc => {
+                                    assert_eq!(total, "13.39999900 SBTC\nFrom: account #10");
+                                    assert_eq!(fee, "0.05419010 SBTC");
+                                }
                                 pb::BtcCoin::Ltc => {
-                                    assert_eq!(total, "13.39999900 LTC");
+                                    assert_eq!(total, "13.39999900 LTC\nFrom: account #10");
                                     assert_eq!(fee, "0.05419010 LTC");
                                 }
                                 _ => panic!("unexpected coin"),
@@ -1778,12 +3671,30 @@ mod tests {
                         _ => panic!("unexpected UI dialog"),
                     }
                 })),
-                ui_confirm_create: Some(Box::new(|params| {
+                ui_confirm_create: Some(Box::new(move |params| {
                     match unsafe {
                         UI_COUNTER += 1;
                         UI_COUNTER
                     } {
                         5 => {
+                            assert_eq!(params.title, "Warning");
+                            let amount = match (coin, format_unit) {
+                                (pb::BtcCoin::Btc, FormatUnit::Sat) => "100 sat",
+                                (pb::BtcCoin::Btc, _) => "0.00000100 BTC",
+                                (pb::BtcCoin::Sbtc, _) => "0.00000100 SBTC",
+                                (pb::BtcCoin::Ltc, _) => "0.00000100 LTC",
+                                _ => panic!("unexpected coin"),
+                            };
+                            assert_eq!(
+                                params.body,
+                                format!(
+                                    "Dust change:\n{}\nReject to add it\nto the fee instead",
+                                    amount
+                                )
+                            );
+                            true
+                        }
+                        6 => {
                             assert_eq!(params.title, "Warning");
                             assert_eq!(params.body, "There are 2\nchange outputs.\nProceed?");
                             true
@@ -1801,6 +3712,10 @@ mod tests {
             match result {
                 Ok(Response::BtcSignNext(next)) => {
                     assert!(next.has_signature);
+                    // The machine-readable amounts match what the fee dialog above displayed.
+                    assert_eq!(next.total_out, 1339999900);
+                    assert_eq!(next.fee, 5419010);
+                    assert_eq!(next.change_total, 690000100);
                     match coin {
                         pb::BtcCoin::Btc => {
                             assert_eq!(
@@ -1817,6 +3732,239 @@ mod tests {
         }
     }
 
+    /// Amounts are additionally shown in fiat if the host supplies a rate; the fiat line is
+    /// clearly marked as unverified and never replaces the coin amount.
+    #[test]
+    fn test_fiat_rate() {
+        let transaction =
+            alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
+        mock_host_responder(transaction.clone());
+        static mut RECIPIENT_CHECKED: bool = false;
+        static mut TOTAL_CHECKED: bool = false;
+        mock(Data {
+            ui_transaction_address_create: Some(Box::new(|amount, address| {
+                if address == "12ZEw5Hcv1hTb6YUQJ69y1V7uhcoDz92PH" {
+                    assert_eq!(
+                        amount,
+                        "1.00000000 BTC\n~ 60000.00 USD (rate from app, unverified)"
+                    );
+                    unsafe { RECIPIENT_CHECKED = true };
+                }
+                true
+            })),
+            ui_transaction_fee_create: Some(Box::new(|total, fee, _longtouch| {
+                assert_eq!(
+                    total,
+                    "13.39999900 BTC\n~ 803999.94 USD (rate from app, unverified)\nFrom: account #10"
+                );
+                assert_eq!(fee, "0.05419010 BTC");
+                unsafe { TOTAL_CHECKED = true };
+                true
+            })),
+            ui_confirm_create: Some(Box::new(|_params| true)),
+            ..Default::default()
+        });
+        mock_unlocked();
+        let mut init_request = transaction.borrow().init_request();
+        init_request.fiat_rate = Some(pb::btc_sign_init_request::FiatRate {
+            currency: "USD".into(),
+            cents_per_coin: 6000000, // 60'000.00 USD per BTC.
+        });
+        assert!(block_on(process(&init_request)).is_ok());
+        assert!(unsafe { RECIPIENT_CHECKED });
+        assert!(unsafe { TOTAL_CHECKED });
+
+        // A rate above the cap aborts the signing.
+        let transaction =
+            alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
+        mock_host_responder(transaction.clone());
+        mock_default_ui();
+        mock_unlocked();
+        let mut init_request = transaction.borrow().init_request();
+        init_request.fiat_rate = Some(pb::btc_sign_init_request::FiatRate {
+            currency: "USD".into(),
+            cents_per_coin: common::MAX_FIAT_CENTS_PER_COIN + 1,
+        });
+        assert_eq!(block_on(process(&init_request)), Err(Error::InvalidInput));
+    }
+
+    /// The pkScripts derived for change outputs are returned to the host, so it can cross-check
+    /// them against its own change derivation.
+    #[test]
+    fn test_generated_output_pkscript() {
+        // P2WPKH singlesig; the two change outputs are at m/84'/0'/10'/1/3 and m/84'/0'/10'/1/30.
+        {
+            static mut PKSCRIPT_COUNTER: u32 = 0;
+            let transaction =
+                alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
+            let tx = transaction.clone();
+            *crate::hww::MOCK_NEXT_REQUEST.0.borrow_mut() =
+                Some(Box::new(move |response: Response| {
+                    let next = extract_next(&response);
+                    if !next.generated_output_pkscript.is_empty() {
+                        match unsafe {
+                            PKSCRIPT_COUNTER += 1;
+                            PKSCRIPT_COUNTER
+                        } {
+                            1 => assert_eq!(
+                                next.generated_output_pkscript,
+                                b"\x00\x14\x00\x48\xdb\xb8\x40\xc2\x93\xde\x96\xf0\x20\x6e\x8e\x81\x53\xb0\x79\x9b\x1c\x16".to_vec(),
+                            ),
+                            2 => assert_eq!(
+                                next.generated_output_pkscript,
+                                b"\x00\x14\x26\x62\x6d\x8a\x36\x96\x33\x59\xc2\x17\x6e\xda\x5e\x38\xda\x65\x1c\xcc\x69\xc2".to_vec(),
+                            ),
+                            _ => panic!("unexpected generated pkScript"),
+                        }
+                    }
+                    Ok(tx.borrow().make_host_request(response))
+                }));
+            mock_default_ui();
+            mock_unlocked();
+            assert!(block_on(process(&transaction.borrow().init_request())).is_ok());
+            assert_eq!(unsafe { PKSCRIPT_COUNTER }, 2);
+        }
+        // P2WSH multisig; the change output is at m/48'/1'/0'/2'/1/0.
+        {
+            static mut PKSCRIPT_COUNTER: u32 = 0;
+            let transaction =
+                alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new_multisig()));
+            let tx = transaction.clone();
+            *crate::hww::MOCK_NEXT_REQUEST.0.borrow_mut() =
+                Some(Box::new(move |response: Response| {
+                    let next = extract_next(&response);
+                    if !next.generated_output_pkscript.is_empty() {
+                        match unsafe {
+                            PKSCRIPT_COUNTER += 1;
+                            PKSCRIPT_COUNTER
+                        } {
+                            // sha256 of the 1-of-2 multisig witness script with the cosigner
+                            // pubkeys derived at 1/0, sorted.
+                            1 => assert_eq!(
+                                next.generated_output_pkscript,
+                                b"\x00\x20\x02\x0d\x82\x3d\x84\x71\x3f\x41\x1c\xa8\xcd\x25\xd1\x70\xad\x94\x87\xdb\x08\xe2\xc7\xeb\xe9\x5d\xd3\x50\x98\x80\x9c\xb1\xb0\x28".to_vec(),
+                            ),
+                            _ => panic!("unexpected generated pkScript"),
+                        }
+                    }
+                    Ok(tx.borrow().make_host_request(response))
+                }));
+            mock_default_ui();
+            mock_unlocked_using_mnemonic(
+                "sudden tenant fault inject concert weather maid people chunk youth stumble grit",
+                "",
+            );
+            // For the multisig registration below.
+            mock_memory();
+
+            // Hash of the multisig configuration as computed by `btc_common_multisig_hash_sorted()`.
+            let multisig_hash = b"\x89\x75\x1d\x19\xe4\xe2\x6f\xbe\xee\x2f\xd2\xc4\xf5\x6a\xb7\xae\x5b\xe6\xdc\x46\x48\x2e\x81\x24\x1f\x4a\xcc\xfb\xc0\xa1\x58\x4e";
+            bitbox02::memory::multisig_set_by_hash(multisig_hash, "test multisig account name")
+                .unwrap();
+
+            let init_request = {
+                let tx = transaction.borrow();
+                pb::BtcSignInitRequest {
+                    coin: tx.coin as _,
+                    script_configs: vec![pb::BtcScriptConfigWithKeypath {
+                        script_config: Some(pb::BtcScriptConfig {
+                            config: Some(pb::btc_script_config::Config::Multisig(
+                                pb::btc_script_config::Multisig {
+                                    threshold: 1,
+                                    xpubs: vec![
+                                        // sudden tenant fault inject concert weather maid people chunk
+                                        // youth stumble grit / 48'/1'/0'/2'
+                                        parse_xpub("xpub6EMfjyGVUvwhpc3WKN1zXhMFGKJGMaSBPqbja4tbGoYvRBSXeTBCaqrRDjcuGTcaY95JrrAnQvDG3pdQPdtnYUCugjeksHSbyZT7rq38VQF").unwrap(),
+                                        // dumb rough room report huge dry sudden hamster wait foot crew
+                                        // obvious / 48'/1'/0'/2'
+                                        parse_xpub("xpub6ERxBysTYfQyY4USv6c6J1HNVv9hpZFN9LHVPu47Ac4rK8fLy6NnAeeAHyEsMvG4G66ay5aFZii2VM7wT3KxLKX8Q8keZPd67kRGmrD1WJj").unwrap(),
+                                    ],
+                                    our_xpub_index: 0,
+                                    script_type: pb::btc_script_config::multisig::ScriptType::P2wsh
+                                        as _,
+                                },
+                            )),
+                        }),
+                        keypath: vec![
+                            48 + HARDENED,
+                            super::super::params::get(tx.coin).bip44_coin,
+                            0 + HARDENED,
+                            2 + HARDENED,
+                        ],
+                    }],
+                    version: tx.version,
+                    num_inputs: tx.inputs.len() as _,
+                    num_outputs: tx.outputs.len() as _,
+                    locktime: tx.locktime,
+                    format_unit: FormatUnit::Default as _,
+                    coinjoin: false,
+                    advanced_verify_inputs: false,
+                    rbf_required: false,
+                    fiat_rate: None,
+                    verify_bip69_order: false,
+                    summarize_outputs: false,
+                    confirm_outputs_first: false,
+                    replacement: None,
+                }
+            };
+            assert!(block_on(process(&init_request)).is_ok());
+            assert_eq!(unsafe { PKSCRIPT_COUNTER }, 1);
+        }
+    }
+
+    /// Output payload lengths are strictly validated per output type.
+    #[test]
+    fn test_output_payload_lengths() {
+        struct Test {
+            output_type: pb::BtcOutputType,
+            payload_len: usize,
+            expected: Result<(), Error>,
+        }
+        let mut tests = Vec::new();
+        for (output_type, expected_len) in [
+            (pb::BtcOutputType::P2pkh, 20),
+            (pb::BtcOutputType::P2sh, 20),
+            (pb::BtcOutputType::P2wpkh, 20),
+            (pb::BtcOutputType::P2wsh, 32),
+            (pb::BtcOutputType::P2tr, 32),
+        ] {
+            tests.push(Test {
+                output_type,
+                payload_len: expected_len - 1,
+                expected: Err(Error::InvalidInputDetail("invalid output payload")),
+            });
+            tests.push(Test {
+                output_type,
+                payload_len: expected_len,
+                expected: Ok(()),
+            });
+            tests.push(Test {
+                output_type,
+                payload_len: expected_len + 1,
+                expected: Err(Error::InvalidInputDetail("invalid output payload")),
+            });
+        }
+        tests.push(Test {
+            output_type: pb::BtcOutputType::Unknown,
+            payload_len: 20,
+            expected: Err(Error::InvalidInputDetail("invalid output payload")),
+        });
+        for test_case in tests {
+            let transaction =
+                alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
+            transaction.borrow_mut().outputs[0].r#type = test_case.output_type as _;
+            transaction.borrow_mut().outputs[0].payload = vec![0x11; test_case.payload_len];
+            mock_host_responder(transaction.clone());
+            mock_default_ui();
+            mock_unlocked();
+            let result = block_on(process(&transaction.borrow().init_request()));
+            match test_case.expected {
+                Ok(()) => assert!(result.is_ok()),
+                Err(expected_err) => assert_eq!(result, Err(expected_err)),
+            }
+        }
+    }
+
     /// Test that receiving an unexpected message from the host results in an invalid state error.
     #[test]
     pub fn test_invalid_state() {
@@ -1964,51 +4112,332 @@ mod tests {
         );
     }
 
-    /// Test signing UTXOs with high keypath address indices. Even though we don't support verifying
-    /// receive addresses at these indices (to mitigate ransom attacks), we should still be able to
-    /// spend them.
+    /// Computes the control block of a taproot script path spend where the leaf script is the only
+    /// script committed to by the internal key (no merkle path).
+    fn make_control_block(internal_pubkey: &[u8; 33], leaf_script: &[u8]) -> Vec<u8> {
+        let leaf_hash = bip341::tapleaf_hash(leaf_script);
+        let tweak: [u8; 32] = {
+            let tag = Sha256::digest(b"TapTweak");
+            let mut ctx = Sha256::new();
+            ctx.update(tag);
+            ctx.update(tag);
+            ctx.update(&internal_pubkey[1..]);
+            ctx.update(leaf_hash);
+            ctx.finalize().into()
+        };
+        let secp = bitcoin::secp256k1::Secp256k1::new();
+        let internal_key =
+            bitcoin::secp256k1::XOnlyPublicKey::from_slice(&internal_pubkey[1..]).unwrap();
+        let (_, parity) = internal_key
+            .add_tweak(
+                &secp,
+                &bitcoin::secp256k1::Scalar::from_be_bytes(tweak).unwrap(),
+            )
+            .unwrap();
+        [&[0xc0 | parity.to_u8()][..], &internal_pubkey[1..]].concat()
+    }
+
+    /// Test a taproot script path spend (BIP-342): the control block is verified against our
+    /// internal key, the user is informed about the script path spend, and the input is signed
+    /// with the untweaked key at the keypath.
     #[test]
-    pub fn test_spend_high_address_index() {
+    pub fn test_script_path_spend() {
         let transaction =
             alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
-        transaction.borrow_mut().inputs[0].input.keypath[4] = 100000;
+        {
+            let mut tx = transaction.borrow_mut();
+            for input in tx.inputs.iter_mut() {
+                input.input.keypath[0] = 86 + HARDENED;
+            }
+            for output in tx.outputs.iter_mut() {
+                if output.ours {
+                    output.keypath[0] = 86 + HARDENED;
+                }
+            }
+            // Script path spend confirmation.
+            tx.total_confirmations += 1;
+        }
 
         mock_host_responder(transaction.clone());
-        mock_default_ui();
         mock_unlocked();
+
+        let internal_pubkey: [u8; 33] = Bip32XpubCache::new()
+            .get_xpub(&transaction.borrow().inputs[0].input.keypath)
+            .unwrap()
+            .public_key()
+            .try_into()
+            .unwrap();
+        // <our x-only key> OP_CHECKSIG
+        let leaf_script = [b"\x20".as_slice(), &internal_pubkey[1..], b"\xac"].concat();
+        {
+            let mut tx = transaction.borrow_mut();
+            tx.inputs[0].input.script_path_leaf_script = leaf_script.clone();
+            tx.inputs[0].input.script_path_control_block =
+                make_control_block(&internal_pubkey, &leaf_script);
+        }
+
+        static mut UI_COUNTER: u32 = 0;
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|params| {
+                match unsafe {
+                    UI_COUNTER += 1;
+                    UI_COUNTER
+                } {
+                    1 => {
+                        assert_eq!(
+                            params.body,
+                            "This transaction\nincludes 1\nscript path\nspend inputs"
+                        );
+                        true
+                    }
+                    6 => {
+                        assert_eq!(
+                            params.body,
+                            "Dust change:\n0.00000100 BTC\nReject to add it\nto the fee instead"
+                        );
+                        true
+                    }
+                    7 => {
+                        assert_eq!(params.body, "There are 2\nchange outputs.\nProceed?");
+                        true
+                    }
+                    _ => panic!("unexpected UI dialog"),
+                }
+            })),
+            ui_transaction_address_create: Some(Box::new(|_amount, _address| {
+                unsafe { UI_COUNTER += 1 }
+                true
+            })),
+            ui_transaction_fee_create: Some(Box::new(|_total, _fee, _longtouch| {
+                unsafe { UI_COUNTER += 1 }
+                true
+            })),
+            ..Default::default()
+        });
         bitbox02::random::mock_reset();
-        let result = block_on(process(&transaction.borrow().init_request()));
-        assert!(result.is_ok());
+        let mut init_request = transaction.borrow().init_request();
+        init_request.script_configs[0] = pb::BtcScriptConfigWithKeypath {
+            script_config: Some(pb::BtcScriptConfig {
+                config: Some(pb::btc_script_config::Config::SimpleType(
+                    SimpleType::P2tr as _,
+                )),
+            }),
+            keypath: vec![86 + HARDENED, 0 + HARDENED, 10 + HARDENED],
+        };
+        let result = block_on(process(&init_request));
+        match result {
+            Ok(Response::BtcSignNext(next)) => {
+                assert!(next.has_signature);
+            }
+            _ => panic!("wrong result"),
+        }
+        assert_eq!(unsafe { UI_COUNTER }, transaction.borrow().total_confirmations);
     }
 
-    /// Test invalid input cases.
+    /// Invalid script path spends are rejected before any signature is produced.
     #[test]
-    pub fn test_invalid_input() {
+    pub fn test_script_path_spend_invalid() {
         enum TestCase {
-            // all inputs should be the same coin type.
-            WrongCoinInput,
-            // all change outputs should be the same coin type.
-            WrongCoinChange,
-            // all inputs should be from the same account.
-            WrongAccountInput,
-            // all change outputs should go the same account.
-            WrongAccountChange,
-            // change num in bip44, should be 1.
-            WrongBip44Change(u32),
-            // change address index in bip44, should be <10000
-            WrongBip44ChangeAddressTooHigh,
-            // referenced script config does not exist.
-            InvalidInputScriptConfigIndex,
-            // referenced script config does not exist.
-            InvalidChangeScriptConfigIndex,
-            // value 0 is invalid
-            WrongOutputValue,
-            // input value does not match prevtx output value
-            WrongInputValue,
-            // input's prevtx hash does not match input's prevOutHash
+            // The internal key in the control block is not our key at the input keypath.
+            WrongInternalKey,
+            // The leaf version is not 0xc0 (BIP-342 tapscript).
+            UnknownLeafVersion,
+            // The leaf script is provided without a control block.
+            MissingControlBlock,
+            // Script path spends are only valid for taproot script configs.
+            NotTaproot,
+            // Foreign inputs cannot carry script path fields.
+            ForeignInput,
+        }
+        for test_case in [
+            TestCase::WrongInternalKey,
+            TestCase::UnknownLeafVersion,
+            TestCase::MissingControlBlock,
+            TestCase::NotTaproot,
+            TestCase::ForeignInput,
+        ] {
+            let transaction =
+                alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
+            let taproot = !matches!(test_case, TestCase::NotTaproot);
+            if taproot {
+                let mut tx = transaction.borrow_mut();
+                for input in tx.inputs.iter_mut() {
+                    input.input.keypath[0] = 86 + HARDENED;
+                }
+                for output in tx.outputs.iter_mut() {
+                    if output.ours {
+                        output.keypath[0] = 86 + HARDENED;
+                    }
+                }
+            }
+
+            mock_host_responder(transaction.clone());
+            mock_default_ui();
+            mock_unlocked();
+
+            let internal_pubkey: [u8; 33] = Bip32XpubCache::new()
+                .get_xpub(&transaction.borrow().inputs[0].input.keypath)
+                .unwrap()
+                .public_key()
+                .try_into()
+                .unwrap();
+            let leaf_script = [b"\x20".as_slice(), &internal_pubkey[1..], b"\xac"].concat();
+            let mut control_block = make_control_block(&internal_pubkey, &leaf_script);
+            {
+                let mut tx = transaction.borrow_mut();
+                match test_case {
+                    TestCase::WrongInternalKey => control_block[1] ^= 1,
+                    TestCase::UnknownLeafVersion => control_block[0] ^= 2,
+                    TestCase::MissingControlBlock => control_block = vec![],
+                    TestCase::NotTaproot => {}
+                    TestCase::ForeignInput => {
+                        tx.inputs[0].input.foreign = true;
+                        tx.inputs[0].input.keypath = vec![];
+                    }
+                }
+                tx.inputs[0].input.script_path_leaf_script = leaf_script.clone();
+                tx.inputs[0].input.script_path_control_block = control_block;
+            }
+
+            let mut init_request = transaction.borrow().init_request();
+            if taproot {
+                init_request.script_configs[0] = pb::BtcScriptConfigWithKeypath {
+                    script_config: Some(pb::BtcScriptConfig {
+                        config: Some(pb::btc_script_config::Config::SimpleType(
+                            SimpleType::P2tr as _,
+                        )),
+                    }),
+                    keypath: vec![86 + HARDENED, 0 + HARDENED, 10 + HARDENED],
+                };
+            }
+            assert_eq!(block_on(process(&init_request)), Err(Error::InvalidInput));
+        }
+    }
+
+    /// Test signing UTXOs with high keypath address indices. Even though we don't support verifying
+    /// receive addresses at these indices (to mitigate ransom attacks), we should still be able to
+    /// spend them after an explicit warning naming the keypath.
+    #[test]
+    pub fn test_spend_high_address_index() {
+        let transaction =
+            alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
+        transaction.borrow_mut().inputs[0].input.keypath[4] = 100000;
+
+        mock_host_responder(transaction.clone());
+        mock_unlocked();
+        bitbox02::random::mock_reset();
+
+        // Confirming the warning proceeds with the transaction.
+        static mut WARNED: bool = false;
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|params| {
+                if params.title == "Warning"
+                    && params.body == "Unusually high\naddress index:\nm/84'/0'/10'/0/100000"
+                {
+                    unsafe { WARNED = true };
+                }
+                true
+            })),
+            ui_transaction_address_create: Some(Box::new(|_amount, _address| true)),
+            ui_transaction_fee_create: Some(Box::new(|_total, _fee, _longtouch| true)),
+            ..Default::default()
+        });
+        let result = block_on(process(&transaction.borrow().init_request()));
+        assert!(result.is_ok());
+        assert!(unsafe { WARNED });
+
+        // Aborting the warning aborts the transaction.
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|params| {
+                !(params.title == "Warning"
+                    && params.body == "Unusually high\naddress index:\nm/84'/0'/10'/0/100000")
+            })),
+            ..Default::default()
+        });
+        mock_unlocked();
+        assert_eq!(
+            block_on(process(&transaction.borrow().init_request())),
+            Err(Error::UserAbort)
+        );
+    }
+
+    /// Test that change going to an address index beyond what a restoring wallet would rediscover
+    /// requires an explicit warning confirmation, and that rejecting it aborts the signing.
+    #[test]
+    pub fn test_high_change_address_index() {
+        let transaction =
+            alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
+        assert!(transaction.borrow().outputs[4].ours);
+        transaction.borrow_mut().outputs[4].keypath[4] = 4000000000;
+
+        mock_host_responder(transaction.clone());
+        mock_unlocked();
+        bitbox02::random::mock_reset();
+
+        // Confirming the warning proceeds with the transaction.
+        static mut WARNED: bool = false;
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|params| {
+                if params.title == "Warning"
+                    && params.body == "Unusually high\naddress index:\nm/84'/0'/10'/1/4000000000"
+                {
+                    unsafe { WARNED = true };
+                }
+                true
+            })),
+            ui_transaction_address_create: Some(Box::new(|_amount, _address| true)),
+            ui_transaction_fee_create: Some(Box::new(|_total, _fee, _longtouch| true)),
+            ..Default::default()
+        });
+        let result = block_on(process(&transaction.borrow().init_request()));
+        assert!(result.is_ok());
+        assert!(unsafe { WARNED });
+
+        // Aborting the warning aborts the transaction.
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|params| {
+                !(params.title == "Warning"
+                    && params.body == "Unusually high\naddress index:\nm/84'/0'/10'/1/4000000000")
+            })),
+            ui_transaction_address_create: Some(Box::new(|_amount, _address| true)),
+            ..Default::default()
+        });
+        mock_unlocked();
+        assert_eq!(
+            block_on(process(&transaction.borrow().init_request())),
+            Err(Error::UserAbort)
+        );
+    }
+
+    /// Test invalid input cases.
+    #[test]
+    pub fn test_invalid_input() {
+        enum TestCase {
+            // all inputs should be the same coin type.
+            WrongCoinInput,
+            // all change outputs should be the same coin type.
+            WrongCoinChange,
+            // all inputs should be from the same account.
+            WrongAccountInput,
+            // all change outputs should go the same account.
+            WrongAccountChange,
+            // change num in bip44, should be 1.
+            WrongBip44Change(u32),
+            // referenced script config does not exist.
+            InvalidInputScriptConfigIndex,
+            // referenced script config does not exist.
+            InvalidChangeScriptConfigIndex,
+            // value 0 is invalid
+            WrongOutputValue,
+            // input value does not match prevtx output value
+            WrongInputValue,
+            // input's prevtx hash does not match input's prevOutHash
             WrongPrevoutHash,
             // input's prev_out_index too high
             WrongPrevoutIndex,
+            // input's prev_out_index too high and the claimed value inflated, so the value
+            // comparison would never run
+            WrongPrevoutIndexInflatedValue,
             // no inputs in prevtx
             PrevTxNoInputs,
             // no outputs in prevtx
@@ -2020,367 +4449,2732 @@ mod tests {
             TestCase::WrongAccountInput,
             TestCase::WrongAccountChange,
             TestCase::WrongBip44Change(2),
-            TestCase::WrongBip44ChangeAddressTooHigh,
             TestCase::InvalidInputScriptConfigIndex,
             TestCase::InvalidChangeScriptConfigIndex,
             TestCase::WrongOutputValue,
             TestCase::WrongInputValue,
             TestCase::WrongPrevoutHash,
             TestCase::WrongPrevoutIndex,
+            TestCase::WrongPrevoutIndexInflatedValue,
             TestCase::PrevTxNoInputs,
             TestCase::PrevTxNoOutputs,
         ] {
             let transaction =
                 alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
-            match value {
+            // Besides the rejection itself, each case is expected to report the specific failure
+            // in the `detail` field of the Error response.
+            let expected_detail: &str = match value {
                 TestCase::WrongCoinInput => {
                     transaction.borrow_mut().inputs[0].input.keypath[1] = 1 + HARDENED;
+                    "invalid keypath"
                 }
                 TestCase::WrongCoinChange => {
                     assert!(transaction.borrow().outputs[4].ours);
                     transaction.borrow_mut().outputs[4].keypath[1] = 1 + HARDENED;
+                    "invalid keypath"
                 }
                 TestCase::WrongAccountInput => {
                     transaction.borrow_mut().inputs[0].input.keypath[2] += 1;
+                    "invalid keypath"
                 }
                 TestCase::WrongAccountChange => {
                     assert!(transaction.borrow().outputs[4].ours);
                     transaction.borrow_mut().outputs[4].keypath[2] += 1;
+                    "invalid keypath"
                 }
                 TestCase::WrongBip44Change(change) => {
                     assert!(transaction.borrow().outputs[4].ours);
                     transaction.borrow_mut().outputs[4].keypath[3] = change;
-                }
-                TestCase::WrongBip44ChangeAddressTooHigh => {
-                    assert!(transaction.borrow().outputs[4].ours);
-                    transaction.borrow_mut().outputs[4].keypath[4] = 10000;
+                    "invalid keypath"
                 }
                 TestCase::InvalidInputScriptConfigIndex => {
                     transaction.borrow_mut().inputs[0].input.script_config_index = 1;
+                    "invalid script config index"
                 }
                 TestCase::InvalidChangeScriptConfigIndex => {
                     assert!(transaction.borrow().outputs[4].ours);
                     transaction.borrow_mut().outputs[4].script_config_index = 1;
+                    "invalid script config index"
                 }
                 TestCase::WrongOutputValue => {
                     transaction.borrow_mut().outputs[0].value = 0;
+                    "zero output value"
                 }
                 TestCase::WrongInputValue => {
                     transaction.borrow_mut().inputs[0].input.prev_out_value += 1;
+                    "prevout value mismatch"
                 }
                 TestCase::WrongPrevoutHash => {
                     transaction.borrow_mut().inputs[0].input.prev_out_hash[0] += 1;
+                    "prevtx hash mismatch"
                 }
                 TestCase::WrongPrevoutIndex => {
                     let mut tx = transaction.borrow_mut();
                     tx.inputs[0].input.prev_out_index = tx.inputs[0].prevtx_outputs.len() as _;
+                    "prevout index out of range"
+                }
+                TestCase::WrongPrevoutIndexInflatedValue => {
+                    // An out-of-range prevout index means no prevtx output would ever be compared
+                    // against the claimed value, so the claim (inflated here) must be rejected by
+                    // the explicit bounds check.
+                    let mut tx = transaction.borrow_mut();
+                    tx.inputs[0].input.prev_out_index = tx.inputs[0].prevtx_outputs.len() as _;
+                    tx.inputs[0].input.prev_out_value += 1_000_000;
+                    "prevout index out of range"
                 }
                 TestCase::PrevTxNoInputs => {
                     transaction.borrow_mut().inputs[0].prevtx_inputs.clear();
+                    "empty prevtx"
                 }
                 TestCase::PrevTxNoOutputs => {
                     transaction.borrow_mut().inputs[0].prevtx_outputs.clear();
+                    "empty prevtx"
                 }
-            }
+            };
             mock_host_responder(transaction.clone());
             mock_default_ui();
             mock_unlocked();
             let result = block_on(process(&transaction.borrow().init_request()));
-            assert_eq!(result, Err(Error::InvalidInput));
+            assert_eq!(result, Err(Error::InvalidInputDetail(expected_detail)));
         }
     }
 
-    /// Test signing with mixed input types.
+    /// Test that the inputs streamed in the second pass must be identical to the inputs of the
+    /// first pass. Moving value from one input to another keeps the total unchanged, so only the
+    /// input commitment hash catches the mismatch.
     #[test]
-    pub fn test_mixed_inputs() {
+    pub fn test_input_mismatch_between_passes() {
         let transaction =
             alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
-        transaction.borrow_mut().inputs[0].input.script_config_index = 1;
-        transaction.borrow_mut().inputs[0].input.keypath[0] = 49 + HARDENED;
-        mock_host_responder(transaction.clone());
+
+        let tx = transaction.clone();
+        *crate::hww::MOCK_NEXT_REQUEST.0.borrow_mut() =
+            Some(Box::new(move |response: Response| {
+                let next = extract_next(&response);
+                if NextType::try_from(next.r#type).unwrap() == NextType::Output && next.index == 0 {
+                    // The first pass is done; modify the inputs for the second pass.
+                    let mut tx_mut = tx.borrow_mut();
+                    tx_mut.inputs[0].input.prev_out_value -= 1;
+                    tx_mut.inputs[1].input.prev_out_value += 1;
+                }
+                Ok(tx.borrow().make_host_request(response))
+            }));
+
         mock_default_ui();
         mock_unlocked();
-        let mut init_request = transaction.borrow().init_request();
-        init_request
-            .script_configs
-            .push(pb::BtcScriptConfigWithKeypath {
-                script_config: Some(pb::BtcScriptConfig {
-                    config: Some(pb::btc_script_config::Config::SimpleType(
-                        SimpleType::P2wpkhP2sh as _,
-                    )),
-                }),
-                keypath: vec![49 + HARDENED, 0 + HARDENED, 10 + HARDENED],
-            });
-        assert!(block_on(process(&init_request)).is_ok());
+        bitbox02::random::mock_reset();
+        let result = block_on(process(&transaction.borrow().init_request()));
+        assert_eq!(result, Err(Error::InvalidInput));
     }
 
+    /// Test that a previous transaction shared by several inputs is streamed and verified only
+    /// once and served from the cache for the remaining inputs.
     #[test]
-    fn test_user_aborts() {
+    pub fn test_prevtx_cache() {
         let transaction =
             alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
-        mock_host_responder(transaction.clone());
-        static mut UI_COUNTER: u32 = 0;
-        static mut CURRENT_COUNTER: u32 = 0;
-        // We go through all possible user confirmations and abort one of them at a time.
-        for counter in 1..=transaction.borrow().total_confirmations {
-            unsafe {
-                UI_COUNTER = 0;
-                CURRENT_COUNTER = counter
-            }
-            mock(Data {
-                ui_transaction_address_create: Some(Box::new(|_amount, _address| unsafe {
-                    UI_COUNTER += 1;
-                    UI_COUNTER != CURRENT_COUNTER
-                })),
-                ui_transaction_fee_create: Some(Box::new(|_total, _fee, _longtouch| unsafe {
-                    UI_COUNTER += 1;
-                    UI_COUNTER != CURRENT_COUNTER
-                })),
-                ui_confirm_create: Some(Box::new(move |_params| unsafe {
-                    UI_COUNTER += 1;
-                    UI_COUNTER != CURRENT_COUNTER
-                })),
-                ..Default::default()
+        {
+            let mut tx = transaction.borrow_mut();
+            // Three inputs spending three outputs of the same previous transaction.
+            let mut input = tx.inputs[0].clone();
+            input.prevtx_outputs.push(pb::BtcPrevTxOutputRequest {
+                value: 1010000001,
+                pubkey_script: b"pubkey script 2".to_vec(),
             });
+            input.prevtx_outputs.push(pb::BtcPrevTxOutputRequest {
+                value: 1010000002,
+                pubkey_script: b"pubkey script 3".to_vec(),
+            });
+            // Recompute the prevtx hash, as adding outputs changes the txid.
+            input.input.prev_out_hash = compute_prevtx_hash(&input);
+
+            tx.inputs = (0..3)
+                .map(|i| {
+                    let mut input = input.clone();
+                    input.input.prev_out_index = i;
+                    input.input.prev_out_value = input.prevtx_outputs[i as usize].value;
+                    input.input.keypath[4] = 5 + i;
+                    input
+                })
+                .collect();
+        }
+
+        let tx = transaction.clone();
+        static mut PREVTX_REQUESTS: u32 = 0;
+        *crate::hww::MOCK_NEXT_REQUEST.0.borrow_mut() =
+            Some(Box::new(move |response: Response| {
+                let next = extract_next(&response);
+                match NextType::try_from(next.r#type).unwrap() {
+                    NextType::PrevtxInit | NextType::PrevtxInput | NextType::PrevtxOutput => {
+                        unsafe { PREVTX_REQUESTS += 1 }
+                    }
+                    _ => (),
+                }
+                Ok(tx.borrow().make_host_request(response))
+            }));
+
+        mock_default_ui();
+        mock_unlocked();
+        assert!(block_on(process(&transaction.borrow().init_request())).is_ok());
+        // One PrevtxInit, one PrevtxInput and three PrevtxOutputs - for the first input only.
+        assert_eq!(unsafe { PREVTX_REQUESTS }, 5);
+    }
+
+    /// Test the bounds on host-provided previous transactions: counts and script sizes at the
+    /// limit are accepted, above it rejected, and the cumulative size is bounded as well.
+    #[test]
+    pub fn test_prevtx_bounds() {
+        // Claimed input count above the bound is rejected before anything is streamed.
+        for (num_prevtx_inputs, expect_ok) in [(10_000usize, true), (10_001, false)] {
+            let transaction =
+                alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
+            {
+                let mut tx = transaction.borrow_mut();
+                let prevtx_input = tx.inputs[0].prevtx_inputs[0].clone();
+                tx.inputs[0].prevtx_inputs = vec![prevtx_input; num_prevtx_inputs];
+                tx.inputs[0].input.prev_out_hash = compute_prevtx_hash(&tx.inputs[0]);
+            }
+            mock_host_responder(transaction.clone());
+            mock_default_ui();
+            mock_unlocked();
+            let result = block_on(process(&transaction.borrow().init_request()));
+            if expect_ok {
+                assert!(result.is_ok());
+            } else {
+                assert_eq!(result, Err(Error::InvalidInput));
+            }
+        }
+
+        // Signature script size at/above the limit.
+        for (script_size, expect_ok) in [(10_000usize, true), (10_001, false)] {
+            let transaction =
+                alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
+            {
+                let mut tx = transaction.borrow_mut();
+                tx.inputs[0].prevtx_inputs[0].signature_script = vec![0x55; script_size];
+                tx.inputs[0].input.prev_out_hash = compute_prevtx_hash(&tx.inputs[0]);
+            }
+            mock_host_responder(transaction.clone());
+            mock_default_ui();
+            mock_unlocked();
+            let result = block_on(process(&transaction.borrow().init_request()));
+            if expect_ok {
+                assert!(result.is_ok());
+            } else {
+                assert_eq!(result, Err(Error::InvalidInput));
+            }
+        }
+
+        // Each script is within the per-message limit, but together they exceed the cumulative
+        // bound.
+        {
+            let transaction =
+                alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
+            {
+                let mut tx = transaction.borrow_mut();
+                let mut prevtx_input = tx.inputs[0].prevtx_inputs[0].clone();
+                prevtx_input.signature_script = vec![0x55; 10_000];
+                tx.inputs[0].prevtx_inputs = vec![prevtx_input; 150];
+                tx.inputs[0].input.prev_out_hash = compute_prevtx_hash(&tx.inputs[0]);
+            }
+            mock_host_responder(transaction.clone());
+            mock_default_ui();
             mock_unlocked();
             assert_eq!(
                 block_on(process(&transaction.borrow().init_request())),
-                Err(Error::UserAbort)
+                Err(Error::InvalidInput)
             );
         }
     }
 
-    /// Check workflow when a locktime applies.
+    /// Test batched prevtx streaming: a host declaring `supports_batched_entries` sends
+    /// multi-entry chunks, which must verify against the same prevtx hash as the one-by-one flow.
     #[test]
-    fn test_locktime() {
-        struct Test {
-            coin: pb::BtcCoin,
-            locktime: u32,
-            sequence: u32,
-            // If None: no user confirmation expected.
-            // If Some: confirmation body and user response.
-            confirm: Option<(&'static str, bool)>,
+    pub fn test_prevtx_batched() {
+        // The fixture streamed in batches verifies against the unchanged prevtx hashes.
+        {
+            let transaction =
+                alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
+            transaction.borrow_mut().prevtx_batched = true;
+            mock_host_responder(transaction.clone());
+            mock_default_ui();
+            mock_unlocked();
+            assert!(block_on(process(&transaction.borrow().init_request())).is_ok());
         }
-        static mut LOCKTIME_CONFIRMED: bool = false;
-        for test_case in &[
-            Test {
-                coin: pb::BtcCoin::Btc,
-                locktime: 0,
-                sequence: 0xffffffff,
-                confirm: None,
-            },
-            Test {
-                coin: pb::BtcCoin::Btc,
-                locktime: 0,
-                sequence: 0xffffffff - 1,
-                confirm: None,
-            },
-            Test {
+        // A prevtx bigger than the chunk bound is streamed in several chunks.
+        {
+            let transaction =
+                alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
+            {
+                let mut tx = transaction.borrow_mut();
+                tx.prevtx_batched = true;
+                let prevtx_input = tx.inputs[0].prevtx_inputs[0].clone();
+                tx.inputs[0].prevtx_inputs = vec![prevtx_input; 60];
+                tx.inputs[0].input.prev_out_hash = compute_prevtx_hash(&tx.inputs[0]);
+            }
+            let tx = transaction.clone();
+            static mut PREVTX_INPUT_REQUESTS: u32 = 0;
+            *crate::hww::MOCK_NEXT_REQUEST.0.borrow_mut() =
+                Some(Box::new(move |response: Response| {
+                    let next = extract_next(&response);
+                    if let Ok(NextType::PrevtxInput) = NextType::try_from(next.r#type) {
+                        unsafe { PREVTX_INPUT_REQUESTS += 1 }
+                    }
+                    Ok(tx.borrow().make_host_request(response))
+                }));
+            mock_default_ui();
+            mock_unlocked();
+            assert!(block_on(process(&transaction.borrow().init_request())).is_ok());
+            // 60 inputs in chunks of 25 for the first input, plus one chunk for the second
+            // input's single-input prevtx.
+            assert_eq!(unsafe { PREVTX_INPUT_REQUESTS }, 4);
+        }
+        // An empty chunk is rejected.
+        {
+            let transaction =
+                alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
+            transaction.borrow_mut().prevtx_batched = true;
+            let tx = transaction.clone();
+            *crate::hww::MOCK_NEXT_REQUEST.0.borrow_mut() =
+                Some(Box::new(move |response: Response| {
+                    let next = extract_next(&response);
+                    if let Ok(NextType::PrevtxInput) = NextType::try_from(next.r#type) {
+                        return Ok(Request::Btc(pb::BtcRequest {
+                            request: Some(pb::btc_request::Request::PrevtxInputs(
+                                pb::BtcPrevTxInputsRequest { inputs: vec![] },
+                            )),
+                        }));
+                    }
+                    Ok(tx.borrow().make_host_request(response))
+                }));
+            mock_default_ui();
+            mock_unlocked();
+            assert_eq!(
+                block_on(process(&transaction.borrow().init_request())),
+                Err(Error::InvalidInput)
+            );
+        }
+        // A chunk with more entries than remain in the prevtx is rejected.
+        {
+            let transaction =
+                alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
+            transaction.borrow_mut().prevtx_batched = true;
+            let tx = transaction.clone();
+            *crate::hww::MOCK_NEXT_REQUEST.0.borrow_mut() =
+                Some(Box::new(move |response: Response| {
+                    let mut request = tx.borrow().make_host_request(response);
+                    if let Request::Btc(pb::BtcRequest {
+                        request: Some(pb::btc_request::Request::PrevtxInputs(ref mut batch)),
+                    }) = request
+                    {
+                        let extra = batch.inputs[0].clone();
+                        batch.inputs.push(extra);
+                    }
+                    Ok(request)
+                }));
+            mock_default_ui();
+            mock_unlocked();
+            assert_eq!(
+                block_on(process(&transaction.borrow().init_request())),
+                Err(Error::InvalidInput)
+            );
+        }
+        // A batch message from a host that did not declare the capability is rejected.
+        {
+            let transaction =
+                alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
+            let tx = transaction.clone();
+            *crate::hww::MOCK_NEXT_REQUEST.0.borrow_mut() =
+                Some(Box::new(move |response: Response| {
+                    let next = extract_next(&response);
+                    if let Ok(NextType::PrevtxInput) = NextType::try_from(next.r#type) {
+                        return Ok(Request::Btc(pb::BtcRequest {
+                            request: Some(pb::btc_request::Request::PrevtxInputs(
+                                pb::BtcPrevTxInputsRequest {
+                                    inputs: tx.borrow().inputs[next.index as usize]
+                                        .prevtx_inputs
+                                        .clone(),
+                                },
+                            )),
+                        }));
+                    }
+                    Ok(tx.borrow().make_host_request(response))
+                }));
+            mock_default_ui();
+            mock_unlocked();
+            assert_eq!(
+                block_on(process(&transaction.borrow().init_request())),
+                Err(Error::InvalidState)
+            );
+        }
+    }
+
+    /// A large consolidation: 1,000 inputs stream, verify and sign to completion. The signing
+    /// state must not grow with the input count beyond small bounded bookkeeping.
+    #[test]
+    fn test_many_inputs() {
+        let transaction =
+            alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
+        {
+            let mut tx = transaction.borrow_mut();
+            let template = tx.inputs[0].clone();
+            tx.inputs = (0..1000u32)
+                .map(|i| {
+                    let mut input = template.clone();
+                    input.input.prev_out_index = 0;
+                    input.input.prev_out_value = 100_000;
+                    input.prevtx_outputs = vec![pb::BtcPrevTxOutputRequest {
+                        value: 100_000,
+                        pubkey_script: b"pubkey script".to_vec(),
+                    }];
+                    // A distinct previous transaction per input.
+                    input.prevtx_locktime = i;
+                    input.input.prev_out_hash = compute_prevtx_hash(&input);
+                    input
+                })
+                .collect();
+            let mut external = tx.outputs[0].clone();
+            external.value = 90_000_000;
+            let mut change = tx.outputs[4].clone();
+            change.value = 9_000_000;
+            tx.outputs = vec![external, change];
+        }
+
+        static mut PREVTX_REQUESTED: u32 = 0;
+        let tx = transaction.clone();
+        *crate::hww::MOCK_NEXT_REQUEST.0.borrow_mut() =
+            Some(Box::new(move |response: Response| {
+                let next = extract_next(&response);
+                if NextType::try_from(next.r#type).unwrap() == NextType::PrevtxInit {
+                    unsafe { PREVTX_REQUESTED += 1 }
+                }
+                Ok(tx.borrow().make_host_request(response))
+            }));
+        mock_default_ui();
+        mock_unlocked();
+        let init_request = transaction.borrow().init_request();
+        match block_on(process(&init_request)) {
+            Ok(Response::BtcSignNext(next)) => {
+                assert_eq!(next.r#type, NextType::Done as i32);
+                assert!(next.has_signature);
+            }
+            _ => panic!("wrong result"),
+        }
+        assert_eq!(unsafe { PREVTX_REQUESTED }, 1000);
+    }
+
+    /// Test windowed streaming of the second-pass inputs: the host answers an INPUT request of
+    /// the second pass with a wrapped window of inputs and receives the signatures batched in the
+    /// next response.
+    #[test]
+    fn test_sign_input_windowed() {
+        // Reference run with per-input streaming, collecting the signatures.
+        let classic_signatures: alloc::rc::Rc<core::cell::RefCell<Vec<Vec<u8>>>> =
+            Default::default();
+        {
+            let transaction =
+                alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
+            let tx = transaction.clone();
+            let signatures = classic_signatures.clone();
+            *crate::hww::MOCK_NEXT_REQUEST.0.borrow_mut() =
+                Some(Box::new(move |response: Response| {
+                    let next = extract_next(&response);
+                    if next.has_signature {
+                        signatures.borrow_mut().push(next.signature.clone());
+                    }
+                    Ok(tx.borrow().make_host_request(response))
+                }));
+            mock_default_ui();
+            mock_unlocked();
+            let result = block_on(process(&transaction.borrow().init_request())).unwrap();
+            let next = extract_next(&result);
+            assert!(next.has_signature);
+            classic_signatures.borrow_mut().push(next.signature.clone());
+            assert_eq!(classic_signatures.borrow().len(), 2);
+        }
+        // A window spanning all (and with that the last) inputs: the signatures arrive batched
+        // in the DONE response and match the reference run.
+        {
+            let transaction =
+                alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
+            let tx = transaction.clone();
+            let seen_output = alloc::rc::Rc::new(core::cell::Cell::new(false));
+            *crate::hww::MOCK_NEXT_REQUEST.0.borrow_mut() =
+                Some(Box::new(move |response: Response| {
+                    let next = extract_next(&response);
+                    match NextType::try_from(next.r#type).unwrap() {
+                        NextType::Output => {
+                            seen_output.set(true);
+                            Ok(tx.borrow().make_host_request(response))
+                        }
+                        // INPUT requests after the outputs belong to the second pass.
+                        NextType::Input if seen_output.get() => {
+                            assert_eq!(next.index, 0);
+                            Ok(Request::Btc(pb::BtcRequest {
+                                request: Some(pb::btc_request::Request::SignInputs(
+                                    pb::BtcSignInputsRequest {
+                                        inputs: tx
+                                            .borrow()
+                                            .inputs
+                                            .iter()
+                                            .map(|input| input.input.clone())
+                                            .collect(),
+                                    },
+                                )),
+                            }))
+                        }
+                        _ => Ok(tx.borrow().make_host_request(response)),
+                    }
+                }));
+            mock_default_ui();
+            mock_unlocked();
+            let result = block_on(process(&transaction.borrow().init_request())).unwrap();
+            let next = extract_next(&result);
+            assert!(!next.has_signature);
+            assert_eq!(next.signatures, *classic_signatures.borrow());
+        }
+        // Two windows of one input each: the second window is requested with INPUT_BATCH and the
+        // first window's signature is delivered with that request.
+        {
+            let transaction =
+                alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
+            let tx = transaction.clone();
+            let seen_output = alloc::rc::Rc::new(core::cell::Cell::new(false));
+            let expected = classic_signatures.clone();
+            *crate::hww::MOCK_NEXT_REQUEST.0.borrow_mut() =
+                Some(Box::new(move |response: Response| {
+                    let next = extract_next(&response);
+                    match NextType::try_from(next.r#type).unwrap() {
+                        NextType::Output => {
+                            seen_output.set(true);
+                            Ok(tx.borrow().make_host_request(response))
+                        }
+                        NextType::Input if seen_output.get() => {
+                            assert_eq!(next.index, 0);
+                            Ok(Request::Btc(pb::BtcRequest {
+                                request: Some(pb::btc_request::Request::SignInputs(
+                                    pb::BtcSignInputsRequest {
+                                        inputs: vec![tx.borrow().inputs[0].input.clone()],
+                                    },
+                                )),
+                            }))
+                        }
+                        NextType::InputBatch => {
+                            assert_eq!(next.index, 1);
+                            assert_eq!(next.signatures, expected.borrow()[..1].to_vec());
+                            Ok(Request::Btc(pb::BtcRequest {
+                                request: Some(pb::btc_request::Request::SignInputs(
+                                    pb::BtcSignInputsRequest {
+                                        inputs: vec![tx.borrow().inputs[1].input.clone()],
+                                    },
+                                )),
+                            }))
+                        }
+                        _ => Ok(tx.borrow().make_host_request(response)),
+                    }
+                }));
+            mock_default_ui();
+            mock_unlocked();
+            let result = block_on(process(&transaction.borrow().init_request())).unwrap();
+            let next = extract_next(&result);
+            assert_eq!(next.signatures, classic_signatures.borrow()[1..].to_vec());
+        }
+        // Anti-klepto inputs cannot be part of a window.
+        {
+            let transaction =
+                alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
+            transaction.borrow_mut().inputs[1].input.host_nonce_commitment =
+                Some(pb::AntiKleptoHostNonceCommitment {
+                    commitment: vec![0; 33],
+                });
+            let tx = transaction.clone();
+            let seen_output = alloc::rc::Rc::new(core::cell::Cell::new(false));
+            *crate::hww::MOCK_NEXT_REQUEST.0.borrow_mut() =
+                Some(Box::new(move |response: Response| {
+                    let next = extract_next(&response);
+                    match NextType::try_from(next.r#type).unwrap() {
+                        NextType::Output => {
+                            seen_output.set(true);
+                            Ok(tx.borrow().make_host_request(response))
+                        }
+                        NextType::Input if seen_output.get() => {
+                            Ok(Request::Btc(pb::BtcRequest {
+                                request: Some(pb::btc_request::Request::SignInputs(
+                                    pb::BtcSignInputsRequest {
+                                        inputs: tx
+                                            .borrow()
+                                            .inputs
+                                            .iter()
+                                            .map(|input| input.input.clone())
+                                            .collect(),
+                                    },
+                                )),
+                            }))
+                        }
+                        _ => Ok(tx.borrow().make_host_request(response)),
+                    }
+                }));
+            mock_default_ui();
+            mock_unlocked();
+            assert_eq!(
+                block_on(process(&transaction.borrow().init_request())),
+                Err(Error::InvalidInput)
+            );
+        }
+        // A window with more inputs than remain is rejected.
+        {
+            let transaction =
+                alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
+            let tx = transaction.clone();
+            let seen_output = alloc::rc::Rc::new(core::cell::Cell::new(false));
+            *crate::hww::MOCK_NEXT_REQUEST.0.borrow_mut() =
+                Some(Box::new(move |response: Response| {
+                    let next = extract_next(&response);
+                    match NextType::try_from(next.r#type).unwrap() {
+                        NextType::Output => {
+                            seen_output.set(true);
+                            Ok(tx.borrow().make_host_request(response))
+                        }
+                        NextType::Input if seen_output.get() => {
+                            let mut inputs: Vec<pb::BtcSignInputRequest> = tx
+                                .borrow()
+                                .inputs
+                                .iter()
+                                .map(|input| input.input.clone())
+                                .collect();
+                            let extra = inputs[1].clone();
+                            inputs.push(extra);
+                            Ok(Request::Btc(pb::BtcRequest {
+                                request: Some(pb::btc_request::Request::SignInputs(
+                                    pb::BtcSignInputsRequest { inputs },
+                                )),
+                            }))
+                        }
+                        _ => Ok(tx.borrow().make_host_request(response)),
+                    }
+                }));
+            mock_default_ui();
+            mock_unlocked();
+            assert_eq!(
+                block_on(process(&transaction.borrow().init_request())),
+                Err(Error::InvalidInput)
+            );
+        }
+    }
+
+    /// The input weight estimates must cover (and stay close to) the real serialized sizes of
+    /// maximally sized spends of the supported singlesig input types.
+    #[test]
+    fn test_estimate_input_weight() {
+        // Weight of one serialized input: the base serialization counted four times plus the
+        // witness stack, all items with their length prefixes.
+        fn real_input_weight(script_sig: &[u8], witness: &[&[u8]]) -> u64 {
+            let base: u64 = 32
+                + 4
+                + 4
+                + serialize_varint(script_sig.len() as u64).len() as u64
+                + script_sig.len() as u64;
+            let witness_size: u64 = if witness.is_empty() {
+                0
+            } else {
+                1 + witness
+                    .iter()
+                    .map(|item| serialize_varint(item.len() as u64).len() as u64 + item.len() as u64)
+                    .sum::<u64>()
+            };
+            4 * base + witness_size
+        }
+
+        let mut xpub_cache = Bip32XpubCache::new();
+        let keypath_account = &[84 + HARDENED, HARDENED, 10 + HARDENED];
+        let mut input = pb::BtcSignInputRequest {
+            prev_out_hash: vec![0; 32],
+            prev_out_index: 0,
+            prev_out_value: 0,
+            sequence: 0xffffffff,
+            keypath: vec![84 + HARDENED, HARDENED, 10 + HARDENED, 0, 0],
+            script_config_index: 0,
+            host_nonce_commitment: None,
+            foreign: false,
+            script_path_leaf_script: vec![],
+            script_path_control_block: vec![],
+        };
+        // Maximally sized signature artifacts: 72 byte DER signature (incl. sighash byte), 33
+        // byte compressed public key, 64 byte Schnorr signature.
+        let der_sig = [0; 72];
+        let pubkey = [0; 33];
+        let schnorr_sig = [0; 64];
+        for (simple_type, real_weight) in [
+            (
+                SimpleType::P2wpkh,
+                real_input_weight(&[], &[&der_sig, &pubkey]),
+            ),
+            (
+                SimpleType::P2wpkhP2sh,
+                // The scriptSig pushes the 22 byte segwit redeem script.
+                real_input_weight(&[0; 23], &[&der_sig, &pubkey]),
+            ),
+            (
+                SimpleType::P2pkh,
+                // The scriptSig pushes the signature and the public key.
+                real_input_weight(&[0; 107], &[]),
+            ),
+            (SimpleType::P2tr, real_input_weight(&[], &[&schnorr_sig])),
+        ] {
+            let estimated = estimate_input_weight(
+                &mut xpub_cache,
+                &ValidatedScriptConfigWithKeypath {
+                    keypath: keypath_account,
+                    config: ValidatedScriptConfig::SimpleType(simple_type),
+                },
+                &input,
+            )
+            .unwrap();
+            assert!(estimated >= real_weight);
+            assert!(estimated - real_weight <= 8);
+        }
+        // Taproot script path spend: the witness additionally carries the leaf script and the
+        // control block.
+        input.script_path_leaf_script = vec![0; 34];
+        input.script_path_control_block = vec![0; 33];
+        let estimated = estimate_input_weight(
+            &mut xpub_cache,
+            &ValidatedScriptConfigWithKeypath {
+                keypath: keypath_account,
+                config: ValidatedScriptConfig::SimpleType(SimpleType::P2tr),
+            },
+            &input,
+        )
+        .unwrap();
+        let real_weight = real_input_weight(&[], &[&schnorr_sig, &[0; 34], &[0; 33]]);
+        assert!(estimated >= real_weight);
+        assert!(estimated - real_weight <= 8);
+    }
+
+    /// Test the warning for transactions exceeding the standardness weight limit, which relay
+    /// nodes would reject after signing.
+    #[test]
+    fn test_standardness_weight_warning() {
+        // 1500 P2WPKH inputs at 272 weight units each put the transaction above 400000 weight
+        // units.
+        for accept in [false, true] {
+            let transaction =
+                alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
+            {
+                let mut tx = transaction.borrow_mut();
+                let input = tx.inputs[0].clone();
+                tx.inputs = vec![input; 1500];
+            }
+            mock_host_responder(transaction.clone());
+            static mut WEIGHT_WARNED: bool = false;
+            unsafe { WEIGHT_WARNED = false }
+            mock(Data {
+                ui_confirm_create: Some(Box::new(move |params| {
+                    if params.body.starts_with("Estimated size") {
+                        assert_eq!(
+                            params.body,
+                            "Estimated size\nof 408858 weight\nunits exceeds\nthe relay limit"
+                        );
+                        unsafe { WEIGHT_WARNED = true }
+                        return accept;
+                    }
+                    true
+                })),
+                ui_transaction_address_create: Some(Box::new(|_amount, _address| true)),
+                ui_transaction_fee_create: Some(Box::new(|_total, _fee, _longtouch| true)),
+                ..Default::default()
+            });
+            mock_unlocked();
+            let result = block_on(process(&transaction.borrow().init_request()));
+            if accept {
+                assert!(result.is_ok());
+            } else {
+                assert_eq!(result, Err(Error::UserAbort));
+            }
+            assert!(unsafe { WEIGHT_WARNED });
+        }
+    }
+
+    /// With `summarize_outputs` set and more than 20 external recipients (withdrawal batching),
+    /// one summarized dialog with the recipient count and total replaces the per-recipient
+    /// confirmations, with an optional step to inspect each recipient individually. At or below
+    /// the threshold the flag is ignored.
+    #[test]
+    fn test_summarize_outputs() {
+        static mut SUMMARY_CONFIRMS: u32 = 0;
+        static mut ADDRESS_CONFIRMS: u32 = 0;
+
+        // 22 external recipients of 0.1 BTC each plus one change output.
+        let make_transaction = |num_external: usize| {
+            let transaction =
+                alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
+            {
+                let mut tx = transaction.borrow_mut();
+                let mut external = tx.outputs[0].clone();
+                external.value = 10000000; // btc 0.1
+                let change = tx.outputs[4].clone();
+                tx.outputs = vec![external; num_external];
+                tx.outputs.push(change);
+            }
+            transaction
+        };
+
+        // Summary accepted, individual inspection declined: no per-recipient dialogs.
+        let transaction = make_transaction(22);
+        mock_host_responder(transaction.clone());
+        unsafe {
+            SUMMARY_CONFIRMS = 0;
+            ADDRESS_CONFIRMS = 0;
+        }
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|params| {
+                if params.title == "Summary" {
+                    match unsafe {
+                        SUMMARY_CONFIRMS += 1;
+                        SUMMARY_CONFIRMS
+                    } {
+                        1 => {
+                            assert_eq!(params.body, "22 recipients\nTotal: 2.20000000 BTC");
+                            true
+                        }
+                        2 => {
+                            assert_eq!(params.body, "Inspect all 22\nrecipients\nindividually?");
+                            // Declining the optional inspection is not an abort.
+                            false
+                        }
+                        _ => panic!("too many summary confirmations"),
+                    }
+                } else {
+                    true
+                }
+            })),
+            ui_transaction_address_create: Some(Box::new(|_amount, _address| {
+                unsafe { ADDRESS_CONFIRMS += 1 }
+                true
+            })),
+            ui_transaction_fee_create: Some(Box::new(|_total, _fee, _longtouch| true)),
+            ..Default::default()
+        });
+        mock_unlocked();
+        let mut init_request = transaction.borrow().init_request();
+        init_request.summarize_outputs = true;
+        assert!(block_on(process(&init_request)).is_ok());
+        assert_eq!(unsafe { SUMMARY_CONFIRMS }, 2);
+        assert_eq!(unsafe { ADDRESS_CONFIRMS }, 0);
+
+        // Opting into the inspection shows every recipient after the summary.
+        let transaction = make_transaction(22);
+        mock_host_responder(transaction.clone());
+        unsafe {
+            SUMMARY_CONFIRMS = 0;
+            ADDRESS_CONFIRMS = 0;
+        }
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|params| {
+                if params.title == "Summary" {
+                    unsafe { SUMMARY_CONFIRMS += 1 }
+                }
+                true
+            })),
+            ui_transaction_address_create: Some(Box::new(|amount, address| {
+                unsafe { ADDRESS_CONFIRMS += 1 }
+                assert_eq!(address, "12ZEw5Hcv1hTb6YUQJ69y1V7uhcoDz92PH");
+                assert_eq!(amount, "0.10000000 BTC");
+                true
+            })),
+            ui_transaction_fee_create: Some(Box::new(|_total, _fee, _longtouch| true)),
+            ..Default::default()
+        });
+        mock_unlocked();
+        let mut init_request = transaction.borrow().init_request();
+        init_request.summarize_outputs = true;
+        assert!(block_on(process(&init_request)).is_ok());
+        assert_eq!(unsafe { SUMMARY_CONFIRMS }, 2);
+        assert_eq!(unsafe { ADDRESS_CONFIRMS }, 22);
+
+        // Rejecting the summary itself aborts the whole flow.
+        let transaction = make_transaction(22);
+        mock_host_responder(transaction.clone());
+        mock_default_ui();
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|params| params.title != "Summary")),
+            ..Default::default()
+        });
+        mock_unlocked();
+        let mut init_request = transaction.borrow().init_request();
+        init_request.summarize_outputs = true;
+        assert_eq!(block_on(process(&init_request)), Err(Error::UserAbort));
+
+        // At the threshold (20 external recipients), the flag is ignored and every recipient is
+        // confirmed individually, as without the flag.
+        let transaction = make_transaction(20);
+        mock_host_responder(transaction.clone());
+        unsafe { ADDRESS_CONFIRMS = 0 }
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|params| {
+                assert_ne!(params.title, "Summary");
+                true
+            })),
+            ui_transaction_address_create: Some(Box::new(|_amount, _address| {
+                unsafe { ADDRESS_CONFIRMS += 1 }
+                true
+            })),
+            ui_transaction_fee_create: Some(Box::new(|_total, _fee, _longtouch| true)),
+            ..Default::default()
+        });
+        mock_unlocked();
+        let mut init_request = transaction.borrow().init_request();
+        init_request.summarize_outputs = true;
+        assert!(block_on(process(&init_request)).is_ok());
+        assert_eq!(unsafe { ADDRESS_CONFIRMS }, 20);
+    }
+
+    /// With `replacement` set and the host-supplied original outputs matching the external
+    /// outputs exactly, a single fee comparison screen replaces the per-recipient dialogs. Any
+    /// discrepancy falls back to the full per-recipient review.
+    #[test]
+    fn test_replacement() {
+        static mut FEE_BUMP_CONFIRMS: u32 = 0;
+        static mut ADDRESS_CONFIRMS: u32 = 0;
+
+        // The external outputs of the transaction, as the host would supply them for a fee bump
+        // that keeps all recipients.
+        fn original_outputs(
+            tx: &Transaction,
+        ) -> Vec<pb::btc_sign_init_request::replacement::OriginalOutput> {
+            let coin_params = super::super::params::get(tx.coin);
+            tx.outputs
+                .iter()
+                .filter(|output| !output.ours)
+                .map(|output| {
+                    pb::btc_sign_init_request::replacement::OriginalOutput {
+                        pk_script: common::Payload {
+                            data: output.payload.clone(),
+                            output_type: pb::BtcOutputType::try_from(output.r#type).unwrap(),
+                            witness_version: None,
+                        }
+                        .pk_script(coin_params)
+                        .unwrap(),
+                        value: output.value,
+                    }
+                })
+                .collect()
+        }
+
+        // Matching outputs: one fee comparison screen, no per-recipient dialogs. The order of the
+        // original outputs does not matter.
+        let transaction =
+            alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
+        mock_host_responder(transaction.clone());
+        let mut originals = original_outputs(&transaction.borrow());
+        originals.reverse();
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|params| {
+                if params.title == "Fee bump" {
+                    assert_eq!(
+                        params.body,
+                        "0.02000000 BTC\nto\n0.05419010 BTC\nRecipients\nunchanged"
+                    );
+                    unsafe { FEE_BUMP_CONFIRMS += 1 }
+                }
+                true
+            })),
+            ui_transaction_address_create: Some(Box::new(|_amount, _address| {
+                unsafe { ADDRESS_CONFIRMS += 1 }
+                true
+            })),
+            ui_transaction_fee_create: Some(Box::new(|_total, _fee, _longtouch| true)),
+            ..Default::default()
+        });
+        mock_unlocked();
+        let mut init_request = transaction.borrow().init_request();
+        init_request.replacement = Some(pb::btc_sign_init_request::Replacement {
+            original_fee: 2000000,
+            original_outputs: originals,
+        });
+        assert!(block_on(process(&init_request)).is_ok());
+        assert_eq!(unsafe { FEE_BUMP_CONFIRMS }, 1);
+        assert_eq!(unsafe { ADDRESS_CONFIRMS }, 0);
+
+        // Rejecting the fee comparison aborts the signing.
+        let transaction =
+            alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
+        mock_host_responder(transaction.clone());
+        let originals = original_outputs(&transaction.borrow());
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|params| params.title != "Fee bump")),
+            ui_transaction_fee_create: Some(Box::new(|_total, _fee, _longtouch| true)),
+            ..Default::default()
+        });
+        mock_unlocked();
+        let mut init_request = transaction.borrow().init_request();
+        init_request.replacement = Some(pb::btc_sign_init_request::Replacement {
+            original_fee: 2000000,
+            original_outputs: originals,
+        });
+        assert_eq!(block_on(process(&init_request)), Err(Error::UserAbort));
+
+        // A differing value falls back to the full per-recipient review, without a fee
+        // comparison screen.
+        let transaction =
+            alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
+        mock_host_responder(transaction.clone());
+        let mut originals = original_outputs(&transaction.borrow());
+        originals[0].value += 1;
+        unsafe { ADDRESS_CONFIRMS = 0 }
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|params| {
+                assert_ne!(params.title, "Fee bump");
+                true
+            })),
+            ui_transaction_address_create: Some(Box::new(|_amount, _address| {
+                unsafe { ADDRESS_CONFIRMS += 1 }
+                true
+            })),
+            ui_transaction_fee_create: Some(Box::new(|_total, _fee, _longtouch| true)),
+            ..Default::default()
+        });
+        mock_unlocked();
+        let mut init_request = transaction.borrow().init_request();
+        init_request.replacement = Some(pb::btc_sign_init_request::Replacement {
+            original_fee: 2000000,
+            original_outputs: originals,
+        });
+        assert!(block_on(process(&init_request)).is_ok());
+        assert_eq!(unsafe { ADDRESS_CONFIRMS }, 4);
+
+        // A missing original output is a discrepancy as well.
+        let transaction =
+            alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
+        mock_host_responder(transaction.clone());
+        let mut originals = original_outputs(&transaction.borrow());
+        originals.pop();
+        unsafe { ADDRESS_CONFIRMS = 0 }
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|params| {
+                assert_ne!(params.title, "Fee bump");
+                true
+            })),
+            ui_transaction_address_create: Some(Box::new(|_amount, _address| {
+                unsafe { ADDRESS_CONFIRMS += 1 }
+                true
+            })),
+            ui_transaction_fee_create: Some(Box::new(|_total, _fee, _longtouch| true)),
+            ..Default::default()
+        });
+        mock_unlocked();
+        let mut init_request = transaction.borrow().init_request();
+        init_request.replacement = Some(pb::btc_sign_init_request::Replacement {
+            original_fee: 2000000,
+            original_outputs: originals,
+        });
+        assert!(block_on(process(&init_request)).is_ok());
+        assert_eq!(unsafe { ADDRESS_CONFIRMS }, 4);
+
+        // Invalid init combinations are rejected, in signing and dry-run validation alike.
+        let transaction = Transaction::new(pb::BtcCoin::Btc);
+        let replacement = pb::btc_sign_init_request::Replacement {
+            original_fee: 2000000,
+            original_outputs: original_outputs(&transaction),
+        };
+        for init_request in [
+            // Without original outputs, there is nothing to compare.
+            pb::BtcSignInitRequest {
+                replacement: Some(pb::btc_sign_init_request::Replacement {
+                    original_fee: 2000000,
+                    original_outputs: vec![],
+                }),
+                ..transaction.init_request()
+            },
+            // Incompatible with the batched confirmation modes.
+            pb::BtcSignInitRequest {
+                replacement: Some(replacement.clone()),
+                coinjoin: true,
+                ..transaction.init_request()
+            },
+            pb::BtcSignInitRequest {
+                replacement: Some(replacement.clone()),
+                summarize_outputs: true,
+                ..transaction.init_request()
+            },
+        ] {
+            mock(Data::default());
+            mock_unlocked();
+            assert_init_fails(&init_request, Error::InvalidInput);
+        }
+    }
+
+    /// With `confirm_outputs_first`, the previous transactions are streamed only after the
+    /// outputs, total and fee have been confirmed, and signing aborts if the deferred
+    /// verification does not match the values the user was shown.
+    #[test]
+    fn test_confirm_outputs_first() {
+        static mut PREVTX_REQUESTED: u32 = 0;
+        static mut FEE_CONFIRMED: bool = false;
+        static mut INPUT_REQUESTS: u32 = 0;
+
+        // Happy path: every prevtx request arrives after the total/fee confirmation.
+        let transaction =
+            alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
+        let tx = transaction.clone();
+        unsafe {
+            PREVTX_REQUESTED = 0;
+            FEE_CONFIRMED = false;
+        }
+        *crate::hww::MOCK_NEXT_REQUEST.0.borrow_mut() =
+            Some(Box::new(move |response: Response| {
+                let next = extract_next(&response);
+                if NextType::try_from(next.r#type).unwrap() == NextType::PrevtxInit {
+                    assert!(unsafe { FEE_CONFIRMED });
+                    unsafe { PREVTX_REQUESTED += 1 }
+                }
+                Ok(tx.borrow().make_host_request(response))
+            }));
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|_params| true)),
+            ui_transaction_address_create: Some(Box::new(|_amount, _address| true)),
+            ui_transaction_fee_create: Some(Box::new(|_total, _fee, _longtouch| {
+                unsafe { FEE_CONFIRMED = true }
+                true
+            })),
+            ..Default::default()
+        });
+        mock_unlocked();
+        let mut init_request = transaction.borrow().init_request();
+        init_request.confirm_outputs_first = true;
+        assert!(block_on(process(&init_request)).is_ok());
+        assert_eq!(
+            unsafe { PREVTX_REQUESTED },
+            transaction.borrow().inputs.len() as _
+        );
+
+        // A claimed input value that does not match the previous transaction is only caught by
+        // the deferred verification, after the user confirmed everything - no signature is
+        // produced.
+        let transaction =
+            alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
+        transaction.borrow_mut().inputs[0].input.prev_out_value += 1;
+        mock_host_responder(transaction.clone());
+        unsafe { FEE_CONFIRMED = false }
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|_params| true)),
+            ui_transaction_address_create: Some(Box::new(|_amount, _address| true)),
+            ui_transaction_fee_create: Some(Box::new(|_total, _fee, _longtouch| {
+                unsafe { FEE_CONFIRMED = true }
+                true
+            })),
+            ..Default::default()
+        });
+        mock_unlocked();
+        let mut init_request = transaction.borrow().init_request();
+        init_request.confirm_outputs_first = true;
+        assert_eq!(
+            block_on(process(&init_request)),
+            Err(Error::InvalidInputDetail("prevout value mismatch"))
+        );
+        assert!(unsafe { FEE_CONFIRMED });
+
+        // A second-pass input value deviating from the confirmed first pass aborts before the
+        // input's previous transaction is even requested.
+        let transaction =
+            alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
+        let tx = transaction.clone();
+        unsafe { INPUT_REQUESTS = 0 }
+        *crate::hww::MOCK_NEXT_REQUEST.0.borrow_mut() =
+            Some(Box::new(move |response: Response| {
+                let is_input_request = NextType::try_from(extract_next(&response).r#type).unwrap()
+                    == NextType::Input;
+                let mut request = tx.borrow().make_host_request(response);
+                if is_input_request {
+                    unsafe { INPUT_REQUESTS += 1 }
+                    if unsafe { INPUT_REQUESTS } > tx.borrow().inputs.len() as u32 {
+                        // Second pass: lie about the first input's value.
+                        if let Request::BtcSignInput(ref mut tx_input) = request {
+                            tx_input.prev_out_value -= 1;
+                        }
+                    }
+                }
+                Ok(request)
+            }));
+        mock_default_ui();
+        mock_unlocked();
+        let mut init_request = transaction.borrow().init_request();
+        init_request.confirm_outputs_first = true;
+        assert_eq!(block_on(process(&init_request)), Err(Error::InvalidInput));
+
+        // Foreign inputs need their previous transaction already in the first pass and are
+        // rejected in this mode.
+        let transaction =
+            alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
+        {
+            let mut tx = transaction.borrow_mut();
+            tx.inputs[0].input.foreign = true;
+            tx.inputs[0].input.keypath = vec![];
+        }
+        mock_host_responder(transaction.clone());
+        mock_default_ui();
+        mock_unlocked();
+        let mut init_request = transaction.borrow().init_request();
+        init_request.confirm_outputs_first = true;
+        assert_eq!(
+            block_on(process(&init_request)),
+            Err(Error::InvalidInputDetail("foreign input not supported"))
+        );
+    }
+
+    /// Streaming a 500-entry prevtx used to redraw the progress bar once per entry; with the ~1%
+    /// threshold, only one redraw per percent step remains (100 of 500 here).
+    #[test]
+    pub fn test_progress_throttle() {
+        let mut throttle = ProgressThrottle::new();
+        assert!(throttle.should_draw(0.));
+        assert!(!throttle.should_draw(0.005));
+        assert!(throttle.should_draw(0.01));
+
+        let mut throttle = ProgressThrottle::new();
+        let num_entries = 500;
+        let num_draws = (0..num_entries)
+            .filter(|&entry| throttle.should_draw(entry as f32 / num_entries as f32))
+            .count();
+        assert_eq!(num_draws, 100);
+    }
+
+    /// The advanced input verification shows one confirmation per input with its value, truncated
+    /// previous transaction ID and origin, and is abortable at any input.
+    #[test]
+    pub fn test_advanced_verify_inputs() {
+        static mut INPUT_CONFIRMS: u32 = 0;
+        let transaction =
+            alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
+        mock_host_responder(transaction.clone());
+        mock_default_ui();
+        mock(Data {
+            ui_transaction_address_create: Some(Box::new(|_amount, _address| true)),
+            ui_transaction_fee_create: Some(Box::new(|_total, _fee, _longtouch| true)),
+            ui_confirm_create: Some(Box::new(|params| {
+                if params.body.starts_with("Input ") {
+                    match unsafe {
+                        INPUT_CONFIRMS += 1;
+                        INPUT_CONFIRMS
+                    } {
+                        1 => assert_eq!(
+                            params.body,
+                            "Input 1/2\n10.10000000 BTC\nFrom 3981435e...:1\nm/84'/0'/10'"
+                        ),
+                        2 => assert_eq!(
+                            params.body,
+                            "Input 2/2\n10.20000000 BTC\nFrom 1d6987aa...:0\nm/84'/0'/10'"
+                        ),
+                        _ => panic!("too many input confirmations"),
+                    }
+                }
+                true
+            })),
+            ..Default::default()
+        });
+        mock_unlocked();
+        let mut init_request = transaction.borrow().init_request();
+        init_request.advanced_verify_inputs = true;
+        assert!(block_on(process(&init_request)).is_ok());
+        // One extra confirmation per input.
+        assert_eq!(unsafe { INPUT_CONFIRMS }, 2);
+
+        // Rejecting any input aborts the whole flow.
+        mock_host_responder(transaction.clone());
+        mock_default_ui();
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|params| !params.body.starts_with("Input "))),
+            ..Default::default()
+        });
+        mock_unlocked();
+        let mut init_request = transaction.borrow().init_request();
+        init_request.advanced_verify_inputs = true;
+        assert_eq!(block_on(process(&init_request)), Err(Error::UserAbort));
+    }
+
+    /// Test signing with mixed input types.
+    #[test]
+    pub fn test_mixed_inputs() {
+        let transaction =
+            alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
+        transaction.borrow_mut().inputs[0].input.script_config_index = 1;
+        transaction.borrow_mut().inputs[0].input.keypath[0] = 49 + HARDENED;
+        mock_host_responder(transaction.clone());
+        mock_default_ui();
+        mock_unlocked();
+        let mut init_request = transaction.borrow().init_request();
+        init_request
+            .script_configs
+            .push(pb::BtcScriptConfigWithKeypath {
+                script_config: Some(pb::BtcScriptConfig {
+                    config: Some(pb::btc_script_config::Config::SimpleType(
+                        SimpleType::P2wpkhP2sh as _,
+                    )),
+                }),
+                keypath: vec![49 + HARDENED, 0 + HARDENED, 10 + HARDENED],
+            });
+        assert!(block_on(process(&init_request)).is_ok());
+    }
+
+    /// Test spending a legacy (pre-segwit) P2PKH input, mixed with segwit inputs. The legacy
+    /// sighash itself is covered by a Bitcoin Core-verified test vector in the legacy module.
+    #[test]
+    pub fn test_legacy_p2pkh_input() {
+        let transaction =
+            alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
+        transaction.borrow_mut().inputs[0].input.script_config_index = 1;
+        transaction.borrow_mut().inputs[0].input.keypath[0] = 44 + HARDENED;
+        mock_host_responder(transaction.clone());
+        mock_default_ui();
+        mock_unlocked();
+        let mut init_request = transaction.borrow().init_request();
+        init_request
+            .script_configs
+            .push(pb::BtcScriptConfigWithKeypath {
+                script_config: Some(pb::BtcScriptConfig {
+                    config: Some(pb::btc_script_config::Config::SimpleType(
+                        SimpleType::P2pkh as _,
+                    )),
+                }),
+                keypath: vec![44 + HARDENED, 0 + HARDENED, 10 + HARDENED],
+            });
+        assert!(block_on(process(&init_request)).is_ok());
+    }
+
+    /// Test that change can go to a different simple type than the inputs, e.g. spending wrapped
+    /// segwit (p2wpkh-p2sh) UTXOs with the change going straight to a native segwit or taproot
+    /// account. The change output references its own script config in the init request.
+    #[test]
+    pub fn test_change_different_simple_type() {
+        for (change_type, change_purpose) in [
+            (SimpleType::P2wpkh, 84 + HARDENED),
+            (SimpleType::P2tr, 86 + HARDENED),
+        ] {
+            let transaction =
+                alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
+            {
+                let mut tx = transaction.borrow_mut();
+                // Inputs are wrapped segwit.
+                for input in tx.inputs.iter_mut() {
+                    input.input.keypath[0] = 49 + HARDENED;
+                }
+                // Change outputs go to the native segwit/taproot account.
+                for output in tx.outputs.iter_mut().filter(|output| output.ours) {
+                    output.keypath[0] = change_purpose;
+                    output.script_config_index = 1;
+                }
+            }
+            mock_host_responder(transaction.clone());
+            mock_default_ui();
+            mock_unlocked();
+            let mut init_request = transaction.borrow().init_request();
+            init_request.script_configs[0] = pb::BtcScriptConfigWithKeypath {
+                script_config: Some(pb::BtcScriptConfig {
+                    config: Some(pb::btc_script_config::Config::SimpleType(
+                        SimpleType::P2wpkhP2sh as _,
+                    )),
+                }),
+                keypath: vec![49 + HARDENED, 0 + HARDENED, 10 + HARDENED],
+            };
+            init_request
+                .script_configs
+                .push(pb::BtcScriptConfigWithKeypath {
+                    script_config: Some(pb::BtcScriptConfig {
+                        config: Some(pb::btc_script_config::Config::SimpleType(change_type as _)),
+                    }),
+                    keypath: vec![change_purpose, 0 + HARDENED, 10 + HARDENED],
+                });
+            assert!(block_on(process(&init_request)).is_ok());
+        }
+    }
+
+    /// Test spending from inputs of different bip44 accounts: a warning listing the involved
+    /// accounts is shown first, and aborting it aborts the transaction.
+    #[test]
+    pub fn test_mixed_accounts() {
+        let transaction =
+            alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
+        transaction.borrow_mut().inputs[0].input.script_config_index = 1;
+        transaction.borrow_mut().inputs[0].input.keypath[2] = 11 + HARDENED;
+        mock_host_responder(transaction.clone());
+        mock_unlocked();
+        let mut init_request = transaction.borrow().init_request();
+        init_request
+            .script_configs
+            .push(pb::BtcScriptConfigWithKeypath {
+                script_config: Some(pb::BtcScriptConfig {
+                    config: Some(pb::btc_script_config::Config::SimpleType(
+                        SimpleType::P2wpkh as _,
+                    )),
+                }),
+                keypath: vec![84 + HARDENED, 0 + HARDENED, 11 + HARDENED],
+            });
+
+        // Confirming the warning proceeds with the transaction.
+        static mut CONFIRMED: bool = false;
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|params| {
+                if params.title == "Warning"
+                    && params.body == "Spending from\naccounts #10 and #11"
+                {
+                    unsafe { CONFIRMED = true };
+                }
+                true
+            })),
+            ui_transaction_address_create: Some(Box::new(|_amount, _address| true)),
+            ui_transaction_fee_create: Some(Box::new(|_total, _fee, _longtouch| true)),
+            ..Default::default()
+        });
+        assert!(block_on(process(&init_request)).is_ok());
+        assert!(unsafe { CONFIRMED });
+
+        // Aborting the warning aborts the transaction.
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|params| {
+                !(params.title == "Warning"
+                    && params.body == "Spending from\naccounts #10 and #11")
+            })),
+            ..Default::default()
+        });
+        mock_unlocked();
+        assert_eq!(block_on(process(&init_request)), Err(Error::UserAbort));
+    }
+
+    /// Test spending from a multisig and a singlesig input in one transaction. An additional
+    /// warning names the multisig account the funds leave, and aborting it aborts the transaction.
+    #[test]
+    pub fn test_mixed_multisig_singlesig() {
+        let transaction = alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new_multisig()));
+        {
+            // Add a singlesig input, referencing the singlesig script config pushed below.
+            let mut tx = transaction.borrow_mut();
+            let mut input = tx.inputs[0].clone();
+            input.input.keypath = vec![84 + HARDENED, 1 + HARDENED, 0 + HARDENED, 0, 0];
+            input.input.script_config_index = 1;
+            tx.inputs.push(input);
+        }
+        mock_host_responder(transaction.clone());
+
+        static mut WARNED: bool = false;
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|params| {
+                if params.title == "Warning"
+                    && params.body
+                        == "Spending from\nboth 'test multisig account name' and\nsinglesig account"
+                {
+                    unsafe { WARNED = true };
+                }
+                true
+            })),
+            ui_transaction_address_create: Some(Box::new(|_amount, _address| true)),
+            ui_transaction_fee_create: Some(Box::new(|_total, _fee, _longtouch| true)),
+            ..Default::default()
+        });
+        mock_unlocked_using_mnemonic(
+            "sudden tenant fault inject concert weather maid people chunk youth stumble grit",
+            "",
+        );
+        // For the multisig registration below.
+        mock_memory();
+        // Hash of the multisig configuration as computed by `btc_common_multisig_hash_sorted()`.
+        let multisig_hash = b"\x89\x75\x1d\x19\xe4\xe2\x6f\xbe\xee\x2f\xd2\xc4\xf5\x6a\xb7\xae\x5b\xe6\xdc\x46\x48\x2e\x81\x24\x1f\x4a\xcc\xfb\xc0\xa1\x58\x4e";
+        bitbox02::memory::multisig_set_by_hash(multisig_hash, "test multisig account name")
+            .unwrap();
+
+        let mut init_request = {
+            let tx = transaction.borrow();
+            pb::BtcSignInitRequest {
+                coin: tx.coin as _,
+                script_configs: vec![pb::BtcScriptConfigWithKeypath {
+                    script_config: Some(pb::BtcScriptConfig {
+                        config: Some(pb::btc_script_config::Config::Multisig(
+                            pb::btc_script_config::Multisig {
+                                threshold: 1,
+                                xpubs: vec![
+                                    // sudden tenant fault inject concert weather maid people chunk
+                                    // youth stumble grit / 48'/1'/0'/2'
+                                    parse_xpub("xpub6EMfjyGVUvwhpc3WKN1zXhMFGKJGMaSBPqbja4tbGoYvRBSXeTBCaqrRDjcuGTcaY95JrrAnQvDG3pdQPdtnYUCugjeksHSbyZT7rq38VQF").unwrap(),
+                                    // dumb rough room report huge dry sudden hamster wait foot crew
+                                    // obvious / 48'/1'/0'/2'
+                                    parse_xpub("xpub6ERxBysTYfQyY4USv6c6J1HNVv9hpZFN9LHVPu47Ac4rK8fLy6NnAeeAHyEsMvG4G66ay5aFZii2VM7wT3KxLKX8Q8keZPd67kRGmrD1WJj").unwrap(),
+                                ],
+                                our_xpub_index: 0,
+                                script_type: pb::btc_script_config::multisig::ScriptType::P2wsh
+                                    as _,
+                            },
+                        )),
+                    }),
+                    keypath: vec![
+                        48 + HARDENED,
+                        super::super::params::get(tx.coin).bip44_coin,
+                        0 + HARDENED,
+                        2 + HARDENED,
+                    ],
+                }],
+                version: tx.version,
+                num_inputs: tx.inputs.len() as _,
+                num_outputs: tx.outputs.len() as _,
+                locktime: tx.locktime,
+                format_unit: FormatUnit::Default as _,
+                coinjoin: false,
+                advanced_verify_inputs: false,
+                rbf_required: false,
+                fiat_rate: None,
+                verify_bip69_order: false,
+                summarize_outputs: false,
+                confirm_outputs_first: false,
+                replacement: None,
+            }
+        };
+        init_request
+            .script_configs
+            .push(pb::BtcScriptConfigWithKeypath {
+                script_config: Some(pb::BtcScriptConfig {
+                    config: Some(pb::btc_script_config::Config::SimpleType(
+                        SimpleType::P2wpkh as _,
+                    )),
+                }),
+                keypath: vec![84 + HARDENED, 1 + HARDENED, 0 + HARDENED],
+            });
+        assert!(block_on(process(&init_request)).is_ok());
+        assert!(unsafe { WARNED });
+
+        // Aborting the warning aborts the transaction.
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|params| params.title != "Warning")),
+            ui_transaction_address_create: Some(Box::new(|_amount, _address| true)),
+            ui_transaction_fee_create: Some(Box::new(|_total, _fee, _longtouch| true)),
+            ..Default::default()
+        });
+        mock_unlocked_using_mnemonic(
+            "sudden tenant fault inject concert weather maid people chunk youth stumble grit",
+            "",
+        );
+        assert_eq!(block_on(process(&init_request)), Err(Error::UserAbort));
+    }
+
+    /// Test signing with a mix of our own and foreign (e.g. payjoin) inputs. The foreign input
+    /// amount is included in the total_in/fee computation, the user is informed about the foreign
+    /// inputs, and no signature is emitted for them.
+    #[test]
+    pub fn test_foreign_input() {
+        let transaction =
+            alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
+        {
+            let mut tx = transaction.borrow_mut();
+            tx.inputs[0].input.foreign = true;
+            tx.inputs[0].input.keypath = vec![];
+            // Foreign inputs confirmation.
+            tx.total_confirmations += 1;
+        }
+
+        static mut UI_COUNTER: u32 = 0;
+        // Number of responses sent to the host which carried a signature.
+        static mut SIGNATURES_SEEN: u32 = 0;
+        let tx = transaction.clone();
+        *crate::hww::MOCK_NEXT_REQUEST.0.borrow_mut() =
+            Some(Box::new(move |response: Response| {
+                let next = extract_next(&response);
+                if next.has_signature {
+                    unsafe { SIGNATURES_SEEN += 1 }
+                }
+                Ok(tx.borrow().make_host_request(response))
+            }));
+
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|params| {
+                match unsafe {
+                    UI_COUNTER += 1;
+                    UI_COUNTER
+                } {
+                    1 => {
+                        assert_eq!(
+                            params.body,
+                            "This transaction\nincludes 1\ninputs from\nother wallets"
+                        );
+                        true
+                    }
+                    6 => {
+                        assert_eq!(
+                            params.body,
+                            "Dust change:\n0.00000100 BTC\nReject to add it\nto the fee instead"
+                        );
+                        true
+                    }
+                    7 => {
+                        assert_eq!(params.body, "There are 2\nchange outputs.\nProceed?");
+                        true
+                    }
+                    _ => panic!("unexpected UI dialog"),
+                }
+            })),
+            ui_transaction_address_create: Some(Box::new(|_amount, _address| {
+                unsafe { UI_COUNTER += 1 }
+                true
+            })),
+            ui_transaction_fee_create: Some(Box::new(|total, fee, _longtouch| {
+                match unsafe {
+                    UI_COUNTER += 1;
+                    UI_COUNTER
+                } {
+                    8 => {
+                        // The foreign input's value is part of total_in, so total/fee are the same
+                        // as if all inputs were ours.
+                        assert_eq!(total, "13.39999900 BTC\nFrom: account #10");
+                        assert_eq!(fee, "0.05419010 BTC");
+                        true
+                    }
+                    _ => panic!("unexpected UI dialog"),
+                }
+            })),
+            ..Default::default()
+        });
+        mock_unlocked();
+        let result = block_on(process(&transaction.borrow().init_request()));
+        match result {
+            Ok(Response::BtcSignNext(next)) => {
+                // The last input is ours and is signed.
+                assert!(next.has_signature);
+            }
+            _ => panic!("wrong result"),
+        }
+        assert_eq!(unsafe { UI_COUNTER }, transaction.borrow().total_confirmations);
+        // The only signature is the one of our own (last) input, sent in the final response - the
+        // response following the foreign input carried none.
+        assert_eq!(unsafe { SIGNATURES_SEEN }, 0);
+    }
+
+    /// With all-taproot script configs, previous transactions are normally not streamed, but they
+    /// still are for foreign inputs to verify their amount and obtain their pubkey script.
+    #[test]
+    pub fn test_foreign_input_taproot() {
+        let transaction =
+            alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
+        {
+            let mut tx = transaction.borrow_mut();
+            for input in tx.inputs.iter_mut() {
+                input.input.keypath[0] = 86 + HARDENED;
+            }
+            for output in tx.outputs.iter_mut() {
+                if output.ours {
+                    output.keypath[0] = 86 + HARDENED;
+                }
+            }
+            tx.inputs[0].input.foreign = true;
+            tx.inputs[0].input.keypath = vec![];
+        }
+
+        let tx = transaction.clone();
+        static mut PREVTX_REQUESTED: u32 = 0;
+        *crate::hww::MOCK_NEXT_REQUEST.0.borrow_mut() =
+            Some(Box::new(move |response: Response| {
+                let next = extract_next(&response);
+                if NextType::try_from(next.r#type).unwrap() == NextType::PrevtxInit {
+                    unsafe { PREVTX_REQUESTED += 1 }
+                }
+                Ok(tx.borrow().make_host_request(response))
+            }));
+
+        mock_default_ui();
+        mock_unlocked();
+        bitbox02::random::mock_reset();
+        let mut init_request = transaction.borrow().init_request();
+        init_request.script_configs[0] = pb::BtcScriptConfigWithKeypath {
+            script_config: Some(pb::BtcScriptConfig {
+                config: Some(pb::btc_script_config::Config::SimpleType(
+                    SimpleType::P2tr as _,
+                )),
+            }),
+            keypath: vec![86 + HARDENED, 0 + HARDENED, 10 + HARDENED],
+        };
+        let result = block_on(process(&init_request));
+        match result {
+            Ok(Response::BtcSignNext(next)) => {
+                assert!(next.has_signature);
+            }
+            _ => panic!("wrong result"),
+        }
+        // Only the foreign input's previous transaction was streamed.
+        assert_eq!(unsafe { PREVTX_REQUESTED }, 1);
+    }
+
+    /// A transaction where all inputs are foreign has nothing to sign and is rejected.
+    #[test]
+    pub fn test_foreign_input_all_foreign() {
+        let transaction =
+            alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
+        for input in transaction.borrow_mut().inputs.iter_mut() {
+            input.input.foreign = true;
+            input.input.keypath = vec![];
+        }
+        mock_host_responder(transaction.clone());
+        mock_default_ui();
+        mock_unlocked();
+        let result = block_on(process(&transaction.borrow().init_request()));
+        assert_eq!(result, Err(Error::InvalidInput));
+    }
+
+    /// Foreign inputs must not contain a keypath.
+    #[test]
+    pub fn test_foreign_input_with_keypath() {
+        let transaction =
+            alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
+        transaction.borrow_mut().inputs[0].input.foreign = true;
+        mock_host_responder(transaction.clone());
+        mock_default_ui();
+        mock_unlocked();
+        let result = block_on(process(&transaction.borrow().init_request()));
+        assert_eq!(result, Err(Error::InvalidInput));
+    }
+
+    #[test]
+    fn test_user_aborts() {
+        let transaction =
+            alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
+        mock_host_responder(transaction.clone());
+        static mut UI_COUNTER: u32 = 0;
+        static mut CURRENT_COUNTER: u32 = 0;
+        // We go through all possible user confirmations and abort one of them at a time.
+        for counter in 1..=transaction.borrow().total_confirmations {
+            unsafe {
+                UI_COUNTER = 0;
+                CURRENT_COUNTER = counter
+            }
+            mock(Data {
+                ui_transaction_address_create: Some(Box::new(|_amount, _address| unsafe {
+                    UI_COUNTER += 1;
+                    UI_COUNTER != CURRENT_COUNTER
+                })),
+                ui_transaction_fee_create: Some(Box::new(|_total, _fee, _longtouch| unsafe {
+                    UI_COUNTER += 1;
+                    UI_COUNTER != CURRENT_COUNTER
+                })),
+                ui_confirm_create: Some(Box::new(move |_params| unsafe {
+                    UI_COUNTER += 1;
+                    UI_COUNTER != CURRENT_COUNTER
+                })),
+                ..Default::default()
+            });
+            mock_unlocked();
+            assert_eq!(
+                block_on(process(&transaction.borrow().init_request())),
+                Err(Error::UserAbort)
+            );
+        }
+    }
+
+    /// Check workflow when a locktime applies.
+    #[test]
+    fn test_locktime() {
+        struct Test {
+            coin: pb::BtcCoin,
+            locktime: u32,
+            // Sequence number per input.
+            sequences: [u32; 2],
+            // If None: no user confirmation expected.
+            // If Some: confirmation body and user response.
+            confirm: Option<(&'static str, bool)>,
+        }
+        static mut LOCKTIME_CONFIRMED: bool = false;
+        for test_case in &[
+            Test {
+                coin: pb::BtcCoin::Btc,
+                locktime: 0,
+                sequences: [0xffffffff, 0xffffffff],
+                confirm: None,
+            },
+            Test {
                 coin: pb::BtcCoin::Btc,
                 locktime: 0,
-                sequence: 0xffffffff - 2,
+                sequences: [0xffffffff - 1, 0xffffffff - 1],
+                confirm: None,
+            },
+            Test {
+                coin: pb::BtcCoin::Btc,
+                locktime: 0,
+                sequences: [0xffffffff - 2, 0xffffffff - 2],
                 confirm: None,
             },
             Test {
                 coin: pb::BtcCoin::Btc,
                 locktime: 1,
-                sequence: 0xffffffff - 1,
+                sequences: [0xffffffff - 1, 0xffffffff - 1],
                 confirm: Some(("Locktime on block:\n1\nTransaction is not RBF", true)),
             },
             Test {
-                coin: pb::BtcCoin::Btc,
-                locktime: 1,
-                sequence: 0xffffffff - 1,
-                confirm: Some(("Locktime on block:\n1\nTransaction is not RBF", false)),
+                coin: pb::BtcCoin::Btc,
+                locktime: 1,
+                sequences: [0xffffffff - 1, 0xffffffff - 1],
+                confirm: Some(("Locktime on block:\n1\nTransaction is not RBF", false)),
+            },
+            Test {
+                coin: pb::BtcCoin::Btc,
+                locktime: 10,
+                sequences: [0xffffffff - 1, 0xffffffff - 1],
+                confirm: Some(("Locktime on block:\n10\nTransaction is not RBF", true)),
+            },
+            Test {
+                coin: pb::BtcCoin::Btc,
+                locktime: 10,
+                sequences: [0xffffffff - 2, 0xffffffff - 2],
+                confirm: Some(("Locktime on block:\n10\nTransaction is RBF", true)),
+            },
+            // Mixed sequences: one input signals replaceability, the other does not.
+            Test {
+                coin: pb::BtcCoin::Btc,
+                locktime: 10,
+                sequences: [0xffffffff - 2, 0xffffffff],
+                confirm: Some((
+                    "Locktime on block:\n10\nRBF enabled (1 of 2\ninputs signal\nreplaceability)",
+                    true,
+                )),
+            },
+            Test {
+                coin: pb::BtcCoin::Btc,
+                locktime: 10,
+                sequences: [0xffffffff - 1, 0xffffffff - 2],
+                confirm: Some((
+                    "Locktime on block:\n10\nRBF enabled (1 of 2\ninputs signal\nreplaceability)",
+                    false,
+                )),
+            },
+            Test {
+                coin: pb::BtcCoin::Ltc,
+                locktime: 10,
+                sequences: [0xffffffff - 1, 0xffffffff - 1],
+                confirm: Some(("Locktime on block:\n10\n", true)),
+            },
+            Test {
+                coin: pb::BtcCoin::Ltc,
+                locktime: 10,
+                sequences: [0xffffffff - 2, 0xffffffff - 2],
+                confirm: Some(("Locktime on block:\n10\n", true)),
+            },
+            // Locktimes >= 500000000 are Unix timestamps and are displayed as a date.
+            Test {
+                coin: pb::BtcCoin::Btc,
+                locktime: 500000000,
+                sequences: [0xffffffff - 1, 0xffffffff - 1],
+                confirm: Some((
+                    "Locktime:\n1985-11-05 00:53 UTC\nTransaction is not RBF",
+                    true,
+                )),
+            },
+            Test {
+                coin: pb::BtcCoin::Btc,
+                locktime: 1748786400,
+                sequences: [0xffffffff - 2, 0xffffffff - 2],
+                confirm: Some(("Locktime:\n2025-06-01 14:00 UTC\nTransaction is RBF", true)),
+            },
+            Test {
+                coin: pb::BtcCoin::Btc,
+                locktime: 1748786400,
+                sequences: [0xffffffff - 1, 0xffffffff - 1],
+                confirm: Some((
+                    "Locktime:\n2025-06-01 14:00 UTC\nTransaction is not RBF",
+                    false,
+                )),
+            },
+        ] {
+            let transaction =
+                alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(test_case.coin)));
+            for (input, &sequence) in transaction
+                .borrow_mut()
+                .inputs
+                .iter_mut()
+                .zip(test_case.sequences.iter())
+            {
+                input.input.sequence = sequence;
+            }
+            mock_host_responder(transaction.clone());
+            unsafe { LOCKTIME_CONFIRMED = false }
+            mock_default_ui();
+            mock(Data {
+                ui_transaction_fee_create: Some(Box::new(|_total, _fee, _longtouch| true)),
+                ui_transaction_address_create: Some(Box::new(|_amount, _address| true)),
+                ui_confirm_create: Some(Box::new(move |params| {
+                    if params.body.contains("Locktime") {
+                        if let Some((confirm_str, user_response)) = test_case.confirm {
+                            assert_eq!(params.title, "");
+                            assert_eq!(params.body, confirm_str);
+                            unsafe { LOCKTIME_CONFIRMED = true }
+                            return user_response;
+                        }
+                        panic!("Unexpected RBF confirmation");
+                    }
+                    true
+                })),
+                ..Default::default()
+            });
+            mock_unlocked();
+
+            let mut init_request = transaction.borrow().init_request();
+            init_request.locktime = test_case.locktime;
+            let result = block_on(process(&init_request));
+            if let Some((_, false)) = test_case.confirm {
+                assert_eq!(result, Err(Error::UserAbort));
+            } else {
+                assert!(result.is_ok());
+            }
+            assert_eq!(unsafe { LOCKTIME_CONFIRMED }, test_case.confirm.is_some());
+        }
+    }
+
+    /// Test the `rbf_required` flag: signing fails unless at least one input signals BIP-125
+    /// replaceability, and the locktime confirmation says that RBF is enforced.
+    #[test]
+    fn test_rbf_required() {
+        // No input signals replaceability: reject.
+        {
+            let transaction =
+                alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
+            for input in transaction.borrow_mut().inputs.iter_mut() {
+                input.input.sequence = 0xffffffff;
+            }
+            mock_host_responder(transaction.clone());
+            mock_default_ui();
+            mock_unlocked();
+            let mut init_request = transaction.borrow().init_request();
+            init_request.rbf_required = true;
+            assert_eq!(
+                block_on(process(&init_request)),
+                Err(Error::InvalidInputDetail("rbf not signaled"))
+            );
+        }
+        // RBF cannot be enforced on a coin without RBF support.
+        {
+            let transaction =
+                alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Ltc)));
+            transaction.borrow_mut().inputs[0].input.sequence = 0xfffffffd;
+            mock_host_responder(transaction.clone());
+            mock_default_ui();
+            mock_unlocked();
+            let mut init_request = transaction.borrow().init_request();
+            init_request.rbf_required = true;
+            assert_eq!(
+                block_on(process(&init_request)),
+                Err(Error::InvalidInputDetail("rbf not signaled"))
+            );
+        }
+        // One input signals replaceability: accept, the locktime dialog shows the enforcement.
+        {
+            let transaction =
+                alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
+            transaction.borrow_mut().inputs[0].input.sequence = 0xfffffffd;
+            mock_host_responder(transaction.clone());
+            static mut RBF_CONFIRMED: bool = false;
+            mock(Data {
+                ui_confirm_create: Some(Box::new(|params| {
+                    if params.body.contains("Locktime") {
+                        assert_eq!(params.body, "Locktime on block:\n10\nRBF enforced");
+                        unsafe { RBF_CONFIRMED = true };
+                    }
+                    true
+                })),
+                ui_transaction_address_create: Some(Box::new(|_amount, _address| true)),
+                ui_transaction_fee_create: Some(Box::new(|_total, _fee, _longtouch| true)),
+                ..Default::default()
+            });
+            mock_unlocked();
+            let mut init_request = transaction.borrow().init_request();
+            init_request.rbf_required = true;
+            init_request.locktime = 10;
+            assert!(block_on(process(&init_request)).is_ok());
+            assert!(unsafe { RBF_CONFIRMED });
+        }
+    }
+
+    /// Test the `verify_bip69_order` flag: signing fails unless the inputs are sorted by
+    /// txid/output index and the outputs by value/pkScript, as per BIP-69.
+    #[test]
+    fn test_verify_bip69_order() {
+        // Returns the test fixture transaction with inputs and outputs sorted as per BIP-69.
+        fn sorted_transaction() -> Transaction {
+            let mut transaction = Transaction::new(pb::BtcCoin::Btc);
+            transaction.inputs.sort_by_key(|input| {
+                let mut txid: [u8; 32] = input.input.prev_out_hash.as_slice().try_into().unwrap();
+                txid.reverse();
+                (txid, input.input.prev_out_index)
+            });
+            // The output values are all distinct, so sorting by value is a full BIP-69 sort.
+            transaction.outputs.sort_by_key(|output| output.value);
+            transaction
+        }
+        // Sorted transaction: accept.
+        {
+            let transaction =
+                alloc::rc::Rc::new(core::cell::RefCell::new(sorted_transaction()));
+            mock_host_responder(transaction.clone());
+            mock_default_ui();
+            mock_unlocked();
+            let mut init_request = transaction.borrow().init_request();
+            init_request.verify_bip69_order = true;
+            assert!(block_on(process(&init_request)).is_ok());
+        }
+        // Inputs out of order: reject.
+        {
+            let transaction =
+                alloc::rc::Rc::new(core::cell::RefCell::new(sorted_transaction()));
+            transaction.borrow_mut().inputs.swap(0, 1);
+            mock_host_responder(transaction.clone());
+            mock_default_ui();
+            mock_unlocked();
+            let mut init_request = transaction.borrow().init_request();
+            init_request.verify_bip69_order = true;
+            assert_eq!(block_on(process(&init_request)), Err(Error::InvalidInput));
+        }
+        // Outputs out of order: reject.
+        {
+            let transaction =
+                alloc::rc::Rc::new(core::cell::RefCell::new(sorted_transaction()));
+            transaction.borrow_mut().outputs.swap(0, 1);
+            mock_host_responder(transaction.clone());
+            mock_default_ui();
+            mock_unlocked();
+            let mut init_request = transaction.borrow().init_request();
+            init_request.verify_bip69_order = true;
+            assert_eq!(block_on(process(&init_request)), Err(Error::InvalidInput));
+        }
+        // Without the flag, an unsorted transaction is accepted.
+        {
+            let transaction =
+                alloc::rc::Rc::new(core::cell::RefCell::new(sorted_transaction()));
+            transaction.borrow_mut().inputs.swap(0, 1);
+            mock_host_responder(transaction.clone());
+            mock_default_ui();
+            mock_unlocked();
+            let init_request = transaction.borrow().init_request();
+            assert!(block_on(process(&init_request)).is_ok());
+        }
+    }
+
+    /// Test the display of BIP-68 relative locktimes encoded in the input sequence numbers.
+    #[test]
+    fn test_relative_locktime() {
+        struct Test {
+            version: u32,
+            sequence: u32,
+            // If None: no user confirmation expected.
+            // If Some: confirmation body and user response.
+            confirm: Option<(&'static str, bool)>,
+        }
+        static mut RELATIVE_LOCKTIME_CONFIRMED: bool = false;
+        for test_case in &[
+            // Block-based relative locktime.
+            Test {
+                version: 2,
+                sequence: 144,
+                confirm: Some((
+                    "Input 1 requires\n144 blocks (~1 day)\nsince confirmation",
+                    true,
+                )),
             },
+            // The user can abort.
             Test {
-                coin: pb::BtcCoin::Btc,
-                locktime: 10,
-                sequence: 0xffffffff - 1,
-                confirm: Some(("Locktime on block:\n10\nTransaction is not RBF", true)),
+                version: 2,
+                sequence: 144,
+                confirm: Some((
+                    "Input 1 requires\n144 blocks (~1 day)\nsince confirmation",
+                    false,
+                )),
             },
+            // Time-based relative locktime, in units of 512 seconds: 675*512s = 4 days.
             Test {
-                coin: pb::BtcCoin::Btc,
-                locktime: 10,
-                sequence: 0xffffffff - 2,
-                confirm: Some(("Locktime on block:\n10\nTransaction is RBF", true)),
+                version: 2,
+                sequence: (1 << 22) | 675,
+                confirm: Some(("Input 1 requires\n4 days\nsince confirmation", true)),
             },
+            // The disable bit turns the relative locktime off.
             Test {
-                coin: pb::BtcCoin::Ltc,
-                locktime: 10,
-                sequence: 0xffffffff - 1,
-                confirm: Some(("Locktime on block:\n10\n", true)),
+                version: 2,
+                sequence: (1 << 31) | 144,
+                confirm: None,
             },
+            // Relative locktimes only apply to version 2 transactions.
             Test {
-                coin: pb::BtcCoin::Ltc,
-                locktime: 10,
-                sequence: 0xffffffff - 2,
-                confirm: Some(("Locktime on block:\n10\n", true)),
+                version: 1,
+                sequence: 144,
+                confirm: None,
             },
         ] {
             let transaction =
-                alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(test_case.coin)));
-            transaction.borrow_mut().inputs[0].input.sequence = test_case.sequence;
+                alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
+            transaction.borrow_mut().version = test_case.version;
+            transaction.borrow_mut().inputs[0].input.sequence = test_case.sequence;
+            mock_host_responder(transaction.clone());
+            unsafe { RELATIVE_LOCKTIME_CONFIRMED = false }
+            mock(Data {
+                ui_transaction_fee_create: Some(Box::new(|_total, _fee, _longtouch| true)),
+                ui_transaction_address_create: Some(Box::new(|_amount, _address| true)),
+                ui_confirm_create: Some(Box::new(move |params| {
+                    if params.body.contains("since confirmation") {
+                        if let Some((confirm_str, user_response)) = test_case.confirm {
+                            assert_eq!(params.title, "");
+                            assert_eq!(params.body, confirm_str);
+                            unsafe { RELATIVE_LOCKTIME_CONFIRMED = true }
+                            return user_response;
+                        }
+                        panic!("Unexpected relative locktime confirmation");
+                    }
+                    true
+                })),
+                ..Default::default()
+            });
+            mock_unlocked();
+
+            let result = block_on(process(&transaction.borrow().init_request()));
+            if let Some((_, false)) = test_case.confirm {
+                assert_eq!(result, Err(Error::UserAbort));
+            } else {
+                assert!(result.is_ok());
+            }
+            assert_eq!(
+                unsafe { RELATIVE_LOCKTIME_CONFIRMED },
+                test_case.confirm.is_some()
+            );
+        }
+    }
+
+    /// Sign a version 3 (TRUC) transaction spending a version 3 previous transaction. The user is
+    /// informed that the transaction is v3.
+    #[test]
+    fn test_version3() {
+        static mut CONFIRMED: bool = false;
+        let transaction =
+            alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
+        {
+            let mut tx = transaction.borrow_mut();
+            tx.version = 3;
+            for tx_input in tx.inputs.iter_mut() {
+                tx_input.prevtx_version = 3;
+                // Recompute the prevtx hash, as changing the version changes the txid.
+                tx_input.input.prev_out_hash = compute_prevtx_hash(tx_input);
+            }
+        }
+        mock_host_responder(transaction.clone());
+        mock(Data {
+            ui_transaction_fee_create: Some(Box::new(|_total, _fee, _longtouch| true)),
+            ui_transaction_address_create: Some(Box::new(|_amount, _address| true)),
+            ui_confirm_create: Some(Box::new(|params| {
+                if params.body.contains("TRUC") {
+                    assert_eq!(params.title, "");
+                    assert_eq!(params.body, "This is a\nversion 3 (TRUC)\ntransaction");
+                    unsafe { CONFIRMED = true }
+                }
+                true
+            })),
+            ..Default::default()
+        });
+        mock_unlocked();
+
+        assert!(block_on(process(&transaction.borrow().init_request())).is_ok());
+        assert!(unsafe { CONFIRMED });
+    }
+
+    // Test a transaction with an unusually high fee.
+    #[test]
+    fn test_high_fee_warning() {
+        let transaction =
+            alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
+        transaction.borrow_mut().outputs[1].value = 1034567890;
+        // One more confirmation for the high fee warning.
+        transaction.borrow_mut().total_confirmations += 1;
+        mock_host_responder(transaction.clone());
+        static mut UI_COUNTER: u32 = 0;
+        static mut FEE_CHECKED: bool = false;
+        mock(Data {
+            ui_transaction_address_create: Some(Box::new(|_amount, _address| unsafe {
+                UI_COUNTER += 1;
+                true
+            })),
+            ui_transaction_fee_create: Some(Box::new(|total, fee, longtouch| unsafe {
+                UI_COUNTER += 1;
+                assert_eq!(total, "13.39999900 BTC\nFrom: account #10");
+                assert_eq!(fee, "2.05419010 BTC");
+                assert!(!longtouch);
+                true
+            })),
+            ui_confirm_create: Some(Box::new(move |params| unsafe {
+                UI_COUNTER += 1;
+                match UI_COUNTER {
+                    8 => {
+                        assert_eq!(params.title, "High fee");
+                        assert_eq!(params.body, "The fee is 18.1%\nthe send amount.\nProceed?");
+                        assert!(params.longtouch);
+                        FEE_CHECKED = true;
+                        true
+                    }
+                    _ => true,
+                }
+            })),
+            ..Default::default()
+        });
+        mock_unlocked();
+        let tx = transaction.borrow();
+        assert!(block_on(process(&tx.init_request())).is_ok());
+        assert_eq!(unsafe { UI_COUNTER }, tx.total_confirmations);
+        assert!(unsafe { FEE_CHECKED });
+    }
+
+    // Test a P2TR output. It is not part of the default test transaction because Taproot is not
+    // active on Litecoin yet.
+    #[test]
+    fn test_p2tr_output() {
+        let transaction =
+            alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
+        transaction.borrow_mut().outputs[0].r#type = pb::BtcOutputType::P2tr as _;
+        transaction.borrow_mut().outputs[0].payload = b"\xa6\x08\x69\xf0\xdb\xcf\x1d\xc6\x59\xc9\xce\xcb\xaf\x80\x50\x13\x5e\xa9\xe8\xcd\xc4\x87\x05\x3f\x1d\xc6\x88\x09\x49\xdc\x68\x4c".to_vec();
+        mock_host_responder(transaction.clone());
+        static mut UI_COUNTER: u32 = 0;
+        mock(Data {
+            ui_transaction_address_create: Some(Box::new(|amount, address| unsafe {
+                UI_COUNTER += 1;
+                if UI_COUNTER == 1 {
+                    assert_eq!(
+                        address,
+                        "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr"
+                    );
+                    assert_eq!(amount, "1.00000000 BTC");
+                }
+                true
+            })),
+            ui_transaction_fee_create: Some(Box::new(|_total, _fee, _longtouch| true)),
+            ui_confirm_create: Some(Box::new(move |_params| true)),
+            ..Default::default()
+        });
+        mock_unlocked();
+        let result = block_on(process(&transaction.borrow().init_request()));
+        assert!(unsafe { UI_COUNTER >= 1 });
+        match result {
+            Ok(Response::BtcSignNext(next)) => {
+                assert!(next.has_signature);
+                assert_eq!(&next.signature, b"\x8f\x1e\x0e\x8f\x98\xd3\x6d\xb1\x19\x62\x64\xf1\xa3\x00\xfa\xe3\x17\xf1\x50\x8d\x2c\x48\x9f\xbb\xd6\x60\xe0\x48\xc4\x52\x9c\x61\x2f\x59\x57\x6c\x86\xa2\x6f\xfa\x47\x6d\x97\x35\x1e\x46\x9e\xf6\xed\x27\x84\xae\xcb\x71\x05\x3a\x51\x66\x77\x5c\xcb\x4d\x7b\x9b");
+            }
+            _ => panic!("wrong result"),
+        }
+    }
+
+    // Test an output with a witness version this firmware does not know. It is displayed as a
+    // bech32m address after an explicit warning.
+    #[test]
+    fn test_future_witness_output() {
+        let transaction =
+            alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
+        transaction.borrow_mut().outputs[0].r#type = pb::BtcOutputType::FutureWitness as _;
+        transaction.borrow_mut().outputs[0].witness_version = 2;
+        transaction.borrow_mut().outputs[0].payload = vec![0x11; 32];
+        mock_host_responder(transaction.clone());
+        static mut UI_COUNTER: u32 = 0;
+        static mut WARNED: bool = false;
+        mock(Data {
+            ui_transaction_address_create: Some(Box::new(|amount, address| unsafe {
+                UI_COUNTER += 1;
+                if UI_COUNTER == 1 {
+                    // The warning must come before the address is shown.
+                    assert!(WARNED);
+                    assert_eq!(
+                        address,
+                        "bc1zzyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygsjg4mh7"
+                    );
+                    assert_eq!(amount, "1.00000000 BTC");
+                }
+                true
+            })),
+            ui_transaction_fee_create: Some(Box::new(|_total, _fee, _longtouch| true)),
+            ui_confirm_create: Some(Box::new(move |params| {
+                if params.title == "Warning" && params.body.starts_with("Unrecognized") {
+                    unsafe { WARNED = true }
+                }
+                true
+            })),
+            ..Default::default()
+        });
+        mock_unlocked();
+        assert!(block_on(process(&transaction.borrow().init_request())).is_ok());
+        assert!(unsafe { UI_COUNTER >= 1 });
+        assert!(unsafe { WARNED });
+    }
+
+    // Witness versions outside 2-16 and program lengths outside 2-40 bytes are rejected for
+    // future witness outputs. Versions 0 and 1 must use their dedicated output types.
+    #[test]
+    fn test_future_witness_output_invalid() {
+        for (witness_version, payload_len) in [
+            (2, 1),
+            (2, 41),
+            (0, 20),
+            (1, 32),
+            (17, 32),
+            (u8::MAX as u32 + 1, 32),
+        ] {
+            let transaction =
+                alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
+            transaction.borrow_mut().outputs[0].r#type = pb::BtcOutputType::FutureWitness as _;
+            transaction.borrow_mut().outputs[0].witness_version = witness_version;
+            transaction.borrow_mut().outputs[0].payload = vec![0x11; payload_len];
+            mock_host_responder(transaction.clone());
+            mock_default_ui();
+            mock_unlocked();
+            assert!(block_on(process(&transaction.borrow().init_request())).is_err());
+        }
+    }
+
+    // A registered singlesig account name replaces the account number in the total confirmation.
+    #[test]
+    fn test_named_account() {
+        let transaction =
+            alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
+        mock_host_responder(transaction.clone());
+        static mut TOTAL_CHECKED: bool = false;
+        mock(Data {
+            ui_transaction_address_create: Some(Box::new(|_amount, _address| true)),
+            ui_transaction_fee_create: Some(Box::new(|total, _fee, _longtouch| {
+                assert!(total.ends_with("\nFrom: savings"));
+                unsafe { TOTAL_CHECKED = true }
+                true
+            })),
+            ui_confirm_create: Some(Box::new(move |_params| true)),
+            ..Default::default()
+        });
+        mock_memory();
+        mock_unlocked();
+        bitbox02::memory::multisig_set_by_hash(
+            &super::super::accounts::get_hash(
+                pb::BtcCoin::Btc,
+                &[84 + HARDENED, 0 + HARDENED, 10 + HARDENED],
+            ),
+            "savings",
+        )
+        .unwrap();
+        assert!(block_on(process(&transaction.borrow().init_request())).is_ok());
+        assert!(unsafe { TOTAL_CHECKED });
+    }
+
+    // Test an output that is marked ours but is not a change output by keypath.
+    #[test]
+    fn test_our_non_change_output() {
+        let transaction =
+            alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
+        transaction.borrow_mut().outputs[5].keypath[3] = 0;
+        mock_host_responder(transaction.clone());
+        static mut UI_COUNTER: u32 = 0;
+        static mut TOTAL_AND_FEE_CHECKED: bool = false;
+        mock(Data {
+            ui_transaction_address_create: Some(Box::new(|amount, address| unsafe {
+                UI_COUNTER += 1;
+                if UI_COUNTER == 5 {
+                    assert_eq!(
+                        address,
+                        "This BitBox02: bc1qnu4x8dlrx6dety47gehf4uhk5tj3q7yhywgry6"
+                    );
+                    assert_eq!(amount, "0.00000100 BTC");
+                }
+                true
+            })),
+            ui_transaction_fee_create: Some(Box::new(|total, fee, _longtouch| unsafe {
+                // The total is the same as if the output was an external output. It includes the
+                // amount sent to the internal non-change address.
+                assert_eq!(total, "13.40000000 BTC\nFrom: account #10");
+                assert_eq!(fee, "0.05419010 BTC");
+                TOTAL_AND_FEE_CHECKED = true;
+                true
+            })),
+            ui_confirm_create: Some(Box::new(move |_params| true)),
+            ..Default::default()
+        });
+        mock_unlocked();
+        let result = block_on(process(&transaction.borrow().init_request()));
+        assert!(unsafe { UI_COUNTER >= 5 });
+        assert!(unsafe { TOTAL_AND_FEE_CHECKED });
+        match result {
+            Ok(Response::BtcSignNext(next)) => {
+                assert!(next.has_signature);
+                assert_eq!(&next.signature, b"\xe1\x15\xd7\xd2\xd2\xb7\xef\x06\x8e\x7b\x89\xde\x83\xec\x79\x17\x44\xd4\x6b\x8b\xae\x8a\x59\x31\xa7\x3e\xf6\x44\xc0\xdb\x01\xcf\x2f\x2e\x2a\x02\x79\x7a\x29\xa1\x81\xfe\x74\xea\x1f\x5d\x2b\xca\xba\x4d\x70\xe0\xe7\x74\x24\x12\xa6\x80\xfd\x62\x95\x7a\x90\xf7");
+            }
+            _ => panic!("wrong result"),
+        }
+    }
+
+    /// Test that an account index above the usual range is accepted in sign init, but only after
+    /// an explicit warning confirmation; rejecting the warning aborts the signing.
+    #[test]
+    fn test_unusual_account() {
+        let transaction =
+            alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
+        {
+            let mut tx = transaction.borrow_mut();
+            for input in tx.inputs.iter_mut() {
+                input.input.keypath[2] = 100 + HARDENED;
+            }
+            for output in tx.outputs.iter_mut() {
+                if output.ours {
+                    output.keypath[2] = 100 + HARDENED;
+                }
+            }
+        }
+        let mut init_request = transaction.borrow().init_request();
+        init_request.script_configs[0].keypath[2] = 100 + HARDENED;
+
+        mock_host_responder(transaction.clone());
+        static mut WARNING_SEEN: bool = false;
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|params| {
+                if params.title == "Warning" {
+                    assert_eq!(params.body, "Unusual account number in keypath m/84'/0'/100'. Proceed only if you know what you are doing.");
+                    assert!(params.longtouch);
+                    unsafe { WARNING_SEEN = true };
+                }
+                true
+            })),
+            ui_transaction_address_create: Some(Box::new(|_amount, _address| true)),
+            ui_transaction_fee_create: Some(Box::new(|_total, _fee, _longtouch| true)),
+            ..Default::default()
+        });
+        mock_unlocked();
+        assert!(block_on(process(&init_request)).is_ok());
+        assert!(unsafe { WARNING_SEEN });
+
+        // Rejecting the warning aborts before anything else is confirmed.
+        mock_host_responder(transaction.clone());
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|params| params.title != "Warning")),
+            ..Default::default()
+        });
+        mock_unlocked();
+        assert_eq!(block_on(process(&init_request)), Err(Error::UserAbort));
+    }
+
+    /// Test host-provided output labels: the label is shown together with, never instead of, the
+    /// address, and malformed labels are rejected.
+    #[test]
+    fn test_output_display_name() {
+        let transaction =
+            alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
+        transaction.borrow_mut().outputs[0].display_name = "Kraken deposit".into();
+        mock_host_responder(transaction.clone());
+        static mut LABEL_SEEN: bool = false;
+        mock(Data {
+            ui_transaction_address_create: Some(Box::new(|amount, address| {
+                if address == "Note from app:\nKraken deposit\n12ZEw5Hcv1hTb6YUQJ69y1V7uhcoDz92PH"
+                {
+                    assert_eq!(amount, "1.00000000 BTC");
+                    unsafe { LABEL_SEEN = true };
+                }
+                true
+            })),
+            ui_transaction_fee_create: Some(Box::new(|_total, _fee, _longtouch| true)),
+            ui_confirm_create: Some(Box::new(|_params| true)),
+            ..Default::default()
+        });
+        mock_unlocked();
+        assert!(block_on(process(&transaction.borrow().init_request())).is_ok());
+        assert!(unsafe { LABEL_SEEN });
+
+        // Labels with control characters or of excessive length are rejected.
+        for label in ["evil\nlabel", "spoof\tlabel", &"x".repeat(51)] {
+            let transaction =
+                alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
+            transaction.borrow_mut().outputs[0].display_name = label.into();
             mock_host_responder(transaction.clone());
-            unsafe { LOCKTIME_CONFIRMED = false }
             mock_default_ui();
-            mock(Data {
-                ui_transaction_fee_create: Some(Box::new(|_total, _fee, _longtouch| true)),
-                ui_transaction_address_create: Some(Box::new(|_amount, _address| true)),
-                ui_confirm_create: Some(Box::new(move |params| {
-                    if params.body.contains("Locktime") {
-                        if let Some((confirm_str, user_response)) = test_case.confirm {
-                            assert_eq!(params.title, "");
-                            assert_eq!(params.body, confirm_str);
-                            unsafe { LOCKTIME_CONFIRMED = true }
-                            return user_response;
-                        }
-                        panic!("Unexpected RBF confirmation");
-                    }
-                    true
-                })),
-                ..Default::default()
-            });
             mock_unlocked();
-
-            let mut init_request = transaction.borrow().init_request();
-            init_request.locktime = test_case.locktime;
-            let result = block_on(process(&init_request));
-            if let Some((_, false)) = test_case.confirm {
-                assert_eq!(result, Err(Error::UserAbort));
-            } else {
-                assert!(result.is_ok());
-            }
-            assert_eq!(unsafe { LOCKTIME_CONFIRMED }, test_case.confirm.is_some());
+            assert_eq!(
+                block_on(process(&transaction.borrow().init_request())),
+                Err(Error::InvalidInput)
+            );
         }
     }
 
-    // Test a transaction with an unusually high fee.
+    /// Test a pure consolidation: every output is ours, so a single summary replaces the
+    /// per-recipient dialogs while the total/fee confirmation remains mandatory.
     #[test]
-    fn test_high_fee_warning() {
+    fn test_consolidation() {
         let transaction =
             alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
-        transaction.borrow_mut().outputs[1].value = 1034567890;
-        // One more confirmation for the high fee warning.
-        transaction.borrow_mut().total_confirmations += 1;
+        {
+            let mut tx = transaction.borrow_mut();
+            // Turn all external outputs into receives to our own wallet.
+            for (i, output) in tx.outputs.iter_mut().enumerate() {
+                if !output.ours {
+                    output.ours = true;
+                    output.keypath =
+                        vec![84 + HARDENED, 0 + HARDENED, 10 + HARDENED, 0, i as u32];
+                }
+            }
+        }
         mock_host_responder(transaction.clone());
-        static mut UI_COUNTER: u32 = 0;
+        static mut SUMMARY_CONFIRMED: bool = false;
         static mut FEE_CHECKED: bool = false;
         mock(Data {
-            ui_transaction_address_create: Some(Box::new(|_amount, _address| unsafe {
-                UI_COUNTER += 1;
+            // No per-recipient dialogs in a pure consolidation.
+            ui_transaction_address_create: Some(Box::new(|_amount, _address| {
+                panic!("unexpected recipient dialog")
+            })),
+            ui_confirm_create: Some(Box::new(|params| {
+                if params.title == "Consolidation" {
+                    assert_eq!(
+                        params.body,
+                        "Consolidating\n2 inputs into\n6 outputs\nwithin this wallet"
+                    );
+                    unsafe { SUMMARY_CONFIRMED = true };
+                }
                 true
             })),
-            ui_transaction_fee_create: Some(Box::new(|total, fee, longtouch| unsafe {
-                UI_COUNTER += 1;
-                assert_eq!(total, "13.39999900 BTC");
-                assert_eq!(fee, "2.05419010 BTC");
-                assert!(!longtouch);
+            ui_transaction_fee_create: Some(Box::new(|_total, _fee, _longtouch| unsafe {
+                FEE_CHECKED = true;
                 true
             })),
-            ui_confirm_create: Some(Box::new(move |params| unsafe {
+            ..Default::default()
+        });
+        mock_unlocked();
+        assert!(block_on(process(&transaction.borrow().init_request())).is_ok());
+        assert!(unsafe { SUMMARY_CONFIRMED });
+        assert!(unsafe { FEE_CHECKED });
+    }
+
+    /// Test that the consolidation summary fails closed: a single external output restores the
+    /// normal per-recipient flow.
+    #[test]
+    fn test_consolidation_mixed() {
+        let transaction =
+            alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
+        {
+            let mut tx = transaction.borrow_mut();
+            // All external outputs but the first are receives to our own wallet.
+            for (i, output) in tx.outputs.iter_mut().enumerate().skip(1) {
+                if !output.ours {
+                    output.ours = true;
+                    output.keypath =
+                        vec![84 + HARDENED, 0 + HARDENED, 10 + HARDENED, 0, i as u32];
+                }
+            }
+        }
+        mock_host_responder(transaction.clone());
+        static mut UI_COUNTER: u32 = 0;
+        mock(Data {
+            ui_transaction_address_create: Some(Box::new(|_amount, address| unsafe {
                 UI_COUNTER += 1;
                 match UI_COUNTER {
-                    7 => {
-                        assert_eq!(params.title, "High fee");
-                        assert_eq!(params.body, "The fee is 18.1%\nthe send amount.\nProceed?");
-                        assert!(params.longtouch);
-                        FEE_CHECKED = true;
-                        true
-                    }
-                    _ => true,
+                    1 => assert!(!address.starts_with("This BitBox02: ")),
+                    2..=4 => assert!(address.starts_with("This BitBox02: ")),
+                    _ => panic!("unexpected recipient dialog"),
                 }
+                true
+            })),
+            ui_confirm_create: Some(Box::new(|params| {
+                assert_ne!(params.title, "Consolidation");
+                true
             })),
+            ui_transaction_fee_create: Some(Box::new(|_total, _fee, _longtouch| true)),
             ..Default::default()
         });
         mock_unlocked();
-        let tx = transaction.borrow();
-        assert!(block_on(process(&tx.init_request())).is_ok());
-        assert_eq!(unsafe { UI_COUNTER }, tx.total_confirmations);
-        assert!(unsafe { FEE_CHECKED });
+        assert!(block_on(process(&transaction.borrow().init_request())).is_ok());
+        assert_eq!(unsafe { UI_COUNTER }, 4);
     }
 
-    // Test a P2TR output. It is not part of the default test transaction because Taproot is not
-    // active on Litecoin yet.
+    /// Test that a transaction without change outputs (send-max sweep) shows an extra notice
+    /// that the account is being emptied, and that the notice is absent otherwise.
     #[test]
-    fn test_p2tr_output() {
+    fn test_sweep_no_change() {
+        static SWEEP_BODY: &str = "This transaction\nempties the\nselected account\n(no change)";
+        // Sweep: all outputs are external, the notice is shown.
+        {
+            let transaction =
+                alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
+            transaction.borrow_mut().outputs.retain(|output| !output.ours);
+            mock_host_responder(transaction.clone());
+            static mut SWEEP_CONFIRMED: bool = false;
+            mock(Data {
+                ui_confirm_create: Some(Box::new(|params| {
+                    if params.body == SWEEP_BODY {
+                        assert_eq!(params.title, "Warning");
+                        unsafe { SWEEP_CONFIRMED = true };
+                    }
+                    true
+                })),
+                ui_transaction_address_create: Some(Box::new(|_amount, _address| true)),
+                ui_transaction_fee_create: Some(Box::new(|_total, _fee, _longtouch| true)),
+                ..Default::default()
+            });
+            mock_unlocked();
+            assert!(block_on(process(&transaction.borrow().init_request())).is_ok());
+            assert!(unsafe { SWEEP_CONFIRMED });
+        }
+        // Normal transaction with change: no notice.
+        {
+            let transaction =
+                alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
+            mock_host_responder(transaction.clone());
+            mock(Data {
+                ui_confirm_create: Some(Box::new(|params| {
+                    assert_ne!(params.body, SWEEP_BODY);
+                    true
+                })),
+                ui_transaction_address_create: Some(Box::new(|_amount, _address| true)),
+                ui_transaction_fee_create: Some(Box::new(|_total, _fee, _longtouch| true)),
+                ..Default::default()
+            });
+            mock_unlocked();
+            assert!(block_on(process(&transaction.borrow().init_request())).is_ok());
+        }
+    }
+
+    /// A change output below the per-type dust threshold triggers a warning; a change output at
+    /// the threshold does not.
+    #[test]
+    fn test_dust_change() {
+        static mut DUST_CONFIRMED: bool = false;
+        for (change_type, change_purpose, threshold) in [
+            (SimpleType::P2wpkh, 84 + HARDENED, 294u64),
+            (SimpleType::P2tr, 86 + HARDENED, 330u64),
+        ] {
+            for (value, expect_warning) in [(threshold, false), (threshold - 1, true)] {
+                let transaction = alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(
+                    pb::BtcCoin::Btc,
+                )));
+                {
+                    let mut tx = transaction.borrow_mut();
+                    // The second change output is the one at the dust boundary.
+                    tx.outputs[5].value = value;
+                    tx.outputs[5].keypath[0] = change_purpose;
+                    tx.outputs[5].script_config_index = 1;
+                }
+                mock_host_responder(transaction.clone());
+                unsafe { DUST_CONFIRMED = false }
+                mock(Data {
+                    ui_confirm_create: Some(Box::new(|params| {
+                        if params.body.starts_with("Dust change:") {
+                            assert_eq!(params.title, "Warning");
+                            unsafe { DUST_CONFIRMED = true };
+                        }
+                        true
+                    })),
+                    ui_transaction_address_create: Some(Box::new(|_amount, _address| true)),
+                    ui_transaction_fee_create: Some(Box::new(|_total, _fee, _longtouch| true)),
+                    ..Default::default()
+                });
+                mock_unlocked();
+                let mut init_request = transaction.borrow().init_request();
+                init_request
+                    .script_configs
+                    .push(pb::BtcScriptConfigWithKeypath {
+                        script_config: Some(pb::BtcScriptConfig {
+                            config: Some(pb::btc_script_config::Config::SimpleType(
+                                change_type as _,
+                            )),
+                        }),
+                        keypath: vec![change_purpose, 0 + HARDENED, 10 + HARDENED],
+                    });
+                assert!(block_on(process(&init_request)).is_ok());
+                assert_eq!(unsafe { DUST_CONFIRMED }, expect_warning);
+            }
+        }
+    }
+
+    /// Test the coinjoin mode: external outputs of identical value are confirmed in one batched
+    /// dialog, our own outputs are still confirmed individually and the total/fee screen remains.
+    #[test]
+    fn test_coinjoin() {
         let transaction =
             alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
-        transaction.borrow_mut().outputs[0].r#type = pb::BtcOutputType::P2tr as _;
-        transaction.borrow_mut().outputs[0].payload = b"\xa6\x08\x69\xf0\xdb\xcf\x1d\xc6\x59\xc9\xce\xcb\xaf\x80\x50\x13\x5e\xa9\xe8\xcd\xc4\x87\x05\x3f\x1d\xc6\x88\x09\x49\xdc\x68\x4c".to_vec();
+        {
+            let mut tx = transaction.borrow_mut();
+            // All external outputs have the same value.
+            for output in tx.outputs.iter_mut() {
+                if !output.ours {
+                    output.value = 100000000; // btc 1
+                }
+            }
+            // Our own non-change output, confirmed individually.
+            tx.outputs[5].keypath[3] = 0;
+        }
         mock_host_responder(transaction.clone());
         static mut UI_COUNTER: u32 = 0;
         mock(Data {
-            ui_transaction_address_create: Some(Box::new(|amount, address| unsafe {
-                UI_COUNTER += 1;
-                if UI_COUNTER == 1 {
-                    assert_eq!(
-                        address,
-                        "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr"
-                    );
-                    assert_eq!(amount, "1.00000000 BTC");
+            ui_transaction_address_create: Some(Box::new(|amount, address| {
+                match unsafe {
+                    UI_COUNTER += 1;
+                    UI_COUNTER
+                } {
+                    1 => {
+                        assert_eq!(
+                            address,
+                            "This BitBox02: bc1qnu4x8dlrx6dety47gehf4uhk5tj3q7yhywgry6"
+                        );
+                        assert_eq!(amount, "0.00000100 BTC");
+                        true
+                    }
+                    _ => panic!("unexpected UI dialog"),
+                }
+            })),
+            ui_confirm_create: Some(Box::new(|params| {
+                match unsafe {
+                    UI_COUNTER += 1;
+                    UI_COUNTER
+                } {
+                    2 => {
+                        assert_eq!(params.title, "CoinJoin");
+                        assert_eq!(
+                            params.body,
+                            "4 outputs of\n1.00000000 BTC each to\nexternal addresses"
+                        );
+                        true
+                    }
+                    _ => panic!("unexpected UI dialog"),
+                }
+            })),
+            ui_transaction_fee_create: Some(Box::new(|total, fee, longtouch| {
+                match unsafe {
+                    UI_COUNTER += 1;
+                    UI_COUNTER
+                } {
+                    3 => {
+                        assert_eq!(total, "13.40000000 BTC\nFrom: account #10");
+                        assert_eq!(fee, "9.39999900 BTC");
+                        assert!(longtouch);
+                        true
+                    }
+                    _ => panic!("unexpected UI dialog"),
                 }
-                true
             })),
-            ui_transaction_fee_create: Some(Box::new(|_total, _fee, _longtouch| true)),
-            ui_confirm_create: Some(Box::new(move |_params| true)),
             ..Default::default()
         });
         mock_unlocked();
-        let result = block_on(process(&transaction.borrow().init_request()));
-        assert!(unsafe { UI_COUNTER >= 1 });
+        let mut init_request = transaction.borrow().init_request();
+        init_request.coinjoin = true;
+        let result = block_on(process(&init_request));
         match result {
             Ok(Response::BtcSignNext(next)) => {
                 assert!(next.has_signature);
-                assert_eq!(&next.signature, b"\x8f\x1e\x0e\x8f\x98\xd3\x6d\xb1\x19\x62\x64\xf1\xa3\x00\xfa\xe3\x17\xf1\x50\x8d\x2c\x48\x9f\xbb\xd6\x60\xe0\x48\xc4\x52\x9c\x61\x2f\x59\x57\x6c\x86\xa2\x6f\xfa\x47\x6d\x97\x35\x1e\x46\x9e\xf6\xed\x27\x84\xae\xcb\x71\x05\x3a\x51\x66\x77\x5c\xcb\x4d\x7b\x9b");
             }
             _ => panic!("wrong result"),
         }
+        // Exactly three dialogs: our own output, the batched external outputs, total/fee.
+        assert_eq!(unsafe { UI_COUNTER }, 3);
     }
 
-    // Test an output that is marked ours but is not a change output by keypath.
+    /// The coinjoin mode refuses to activate if the external outputs differ in value, even by one
+    /// satoshi.
     #[test]
-    fn test_our_non_change_output() {
+    fn test_coinjoin_unequal_values() {
         let transaction =
             alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
-        transaction.borrow_mut().outputs[5].keypath[3] = 0;
-        mock_host_responder(transaction.clone());
-        static mut UI_COUNTER: u32 = 0;
-        static mut TOTAL_AND_FEE_CHECKED: bool = false;
-        mock(Data {
-            ui_transaction_address_create: Some(Box::new(|amount, address| unsafe {
-                UI_COUNTER += 1;
-                if UI_COUNTER == 5 {
-                    assert_eq!(
-                        address,
-                        "This BitBox02: bc1qnu4x8dlrx6dety47gehf4uhk5tj3q7yhywgry6"
-                    );
-                    assert_eq!(amount, "0.00000100 BTC");
+        {
+            let mut tx = transaction.borrow_mut();
+            for output in tx.outputs.iter_mut() {
+                if !output.ours {
+                    output.value = 100000000; // btc 1
                 }
-                true
-            })),
-            ui_transaction_fee_create: Some(Box::new(|total, fee, _longtouch| unsafe {
-                // The total is the same as if the output was an external output. It includes the
-                // amount sent to the internal non-change address.
-                assert_eq!(total, "13.40000000 BTC");
-                assert_eq!(fee, "0.05419010 BTC");
-                TOTAL_AND_FEE_CHECKED = true;
-                true
-            })),
-            ui_confirm_create: Some(Box::new(move |_params| true)),
-            ..Default::default()
-        });
-        mock_unlocked();
-        let result = block_on(process(&transaction.borrow().init_request()));
-        assert!(unsafe { UI_COUNTER >= 5 });
-        assert!(unsafe { TOTAL_AND_FEE_CHECKED });
-        match result {
-            Ok(Response::BtcSignNext(next)) => {
-                assert!(next.has_signature);
-                assert_eq!(&next.signature, b"\xe1\x15\xd7\xd2\xd2\xb7\xef\x06\x8e\x7b\x89\xde\x83\xec\x79\x17\x44\xd4\x6b\x8b\xae\x8a\x59\x31\xa7\x3e\xf6\x44\xc0\xdb\x01\xcf\x2f\x2e\x2a\x02\x79\x7a\x29\xa1\x81\xfe\x74\xea\x1f\x5d\x2b\xca\xba\x4d\x70\xe0\xe7\x74\x24\x12\xa6\x80\xfd\x62\x95\x7a\x90\xf7");
             }
-            _ => panic!("wrong result"),
+            tx.outputs[1].value += 1;
         }
+        mock_host_responder(transaction.clone());
+        mock_default_ui();
+        mock_unlocked();
+        let mut init_request = transaction.borrow().init_request();
+        init_request.coinjoin = true;
+        assert_eq!(block_on(process(&init_request)), Err(Error::InvalidInput));
     }
 
     /// Exercise the antiklepto protocol
@@ -2415,6 +7209,113 @@ mod tests {
         }
     }
 
+    /// If the user opted into requiring the anti-klepto protocol, plain signing (no host nonce
+    /// commitment) is rejected; by default it remains available.
+    #[test]
+    fn test_antiklepto_required() {
+        let transaction =
+            alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
+        mock_host_responder(transaction.clone());
+        mock_default_ui();
+        mock_unlocked();
+        mock_memory();
+        bitbox02::memory::set_antiklepto_required(true).unwrap();
+        assert_eq!(
+            block_on(process(&transaction.borrow().init_request())),
+            Err(Error::InvalidInput)
+        );
+
+        // Default: the protocol is not required and plain signing still works.
+        mock_host_responder(transaction.clone());
+        mock_default_ui();
+        mock_unlocked();
+        mock_memory();
+        assert!(block_on(process(&transaction.borrow().init_request())).is_ok());
+    }
+
+    /// A corrupted signature from the keystore (simulating a faulty secure chip or a glitching
+    /// attack) must never leave the device: the self-verification against the derived public key
+    /// fails and the whole signing flow aborts.
+    #[test]
+    fn test_corrupted_signature() {
+        let mock_corrupting_ui = || {
+            mock(Data {
+                ui_confirm_create: Some(Box::new(move |_params| true)),
+                ui_transaction_address_create: Some(Box::new(|_amount, _address| true)),
+                ui_transaction_fee_create: Some(Box::new(|_total, _fee, _longtouch| true)),
+                keystore_corrupt_signature: true,
+                ..Default::default()
+            });
+        };
+
+        // ECDSA.
+        {
+            let transaction =
+                alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
+            mock_host_responder(transaction.clone());
+            mock_corrupting_ui();
+            mock_unlocked();
+            assert_eq!(
+                block_on(process(&transaction.borrow().init_request())),
+                Err(Error::SignatureCheck)
+            );
+        }
+
+        // ECDSA with anti-klepto: the check covers the signature after nonce adaptation.
+        {
+            let transaction =
+                alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
+            let host_nonce = b"\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab";
+            let host_nonce_commitment = pb::AntiKleptoHostNonceCommitment {
+                commitment: bitbox02::secp256k1::ecdsa_anti_exfil_host_commit(host_nonce).unwrap(),
+            };
+            transaction.borrow_mut().inputs[0].host_nonce = Some(host_nonce.to_vec());
+            transaction.borrow_mut().inputs[0]
+                .input
+                .host_nonce_commitment = Some(host_nonce_commitment);
+            mock_host_responder(transaction.clone());
+            mock_corrupting_ui();
+            mock_unlocked();
+            assert_eq!(
+                block_on(process(&transaction.borrow().init_request())),
+                Err(Error::SignatureCheck)
+            );
+        }
+
+        // Schnorr (taproot key path spend).
+        {
+            let transaction =
+                alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
+            {
+                let mut tx = transaction.borrow_mut();
+                for input in tx.inputs.iter_mut() {
+                    input.input.keypath[0] = 86 + HARDENED;
+                }
+                for output in tx.outputs.iter_mut() {
+                    if output.ours {
+                        output.keypath[0] = 86 + HARDENED;
+                    }
+                }
+            }
+            mock_host_responder(transaction.clone());
+            mock_corrupting_ui();
+            mock_unlocked();
+            let mut init_request = transaction.borrow().init_request();
+            init_request.script_configs[0] = pb::BtcScriptConfigWithKeypath {
+                script_config: Some(pb::BtcScriptConfig {
+                    config: Some(pb::btc_script_config::Config::SimpleType(
+                        SimpleType::P2tr as _,
+                    )),
+                }),
+                keypath: vec![86 + HARDENED, 0 + HARDENED, 10 + HARDENED],
+            };
+            assert_eq!(
+                block_on(process(&init_request)),
+                Err(Error::SignatureCheck)
+            );
+        }
+    }
+
     /// The sum of the inputs in the 2nd pass can't be higher than in the first for all inputs.
     #[test]
     fn test_input_sum_changes() {
@@ -2561,6 +7462,32 @@ mod tests {
         assert_eq!(result, Err(Error::InvalidInput));
     }
 
+    /// If the outputs exceed the verified inputs, the fee would be negative; the flow errors out
+    /// before the total/fee dialog is ever shown.
+    #[test]
+    fn test_negative_fee() {
+        static mut FEE_DIALOG_SHOWN: bool = false;
+        let transaction =
+            alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
+        // Inflate an external output so that the outputs sum exceeds the inputs sum, without
+        // overflowing the output sum itself.
+        transaction.borrow_mut().outputs[0].value = 10_000_000_000_000;
+        mock_host_responder(transaction.clone());
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|_params| true)),
+            ui_transaction_address_create: Some(Box::new(|_amount, _address| true)),
+            ui_transaction_fee_create: Some(Box::new(|_total, _fee, _longtouch| {
+                unsafe { FEE_DIALOG_SHOWN = true }
+                true
+            })),
+            ..Default::default()
+        });
+        mock_unlocked();
+        let result = block_on(process(&transaction.borrow().init_request()));
+        assert_eq!(result, Err(Error::InvalidInputDetail("negative fee")));
+        assert!(unsafe { !FEE_DIALOG_SHOWN });
+    }
+
     #[test]
     fn test_multisig_p2wsh() {
         let transaction = alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new_multisig()));
@@ -2613,7 +7540,7 @@ mod tests {
                     UI_COUNTER
                 } {
                     5 => {
-                        assert_eq!(total, "0.00090175 TBTC");
+                        assert_eq!(total, "0.00090175 TBTC\nFrom: test multisig account name");
                         assert_eq!(fee, "0.00000175 TBTC");
                         assert!(longtouch);
                     }
@@ -2670,6 +7597,14 @@ mod tests {
                 num_outputs: tx.outputs.len() as _,
                 locktime: tx.locktime,
                 format_unit: FormatUnit::Default as _,
+                coinjoin: false,
+                advanced_verify_inputs: false,
+                rbf_required: false,
+                fiat_rate: None,
+                verify_bip69_order: false,
+                summarize_outputs: false,
+                confirm_outputs_first: false,
+                replacement: None,
             }
         };
         let result = block_on(process(&init_request));
@@ -2686,6 +7621,113 @@ mod tests {
         );
     }
 
+    /// The anti-klepto protocol also covers multisig inputs: the signer commitment is delivered
+    /// before the host nonce is revealed, and the host nonce contribution ends up in the final
+    /// signature nonce.
+    #[test]
+    fn test_multisig_antiklepto() {
+        static mut COMMITMENT_SEEN: bool = false;
+
+        let sign = |antiklepto: bool| -> Vec<u8> {
+            let transaction =
+                alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new_multisig()));
+            if antiklepto {
+                let host_nonce = b"\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab\xab";
+                transaction.borrow_mut().inputs[0].host_nonce = Some(host_nonce.to_vec());
+                transaction.borrow_mut().inputs[0].input.host_nonce_commitment =
+                    Some(pb::AntiKleptoHostNonceCommitment {
+                        commitment: bitbox02::secp256k1::ecdsa_anti_exfil_host_commit(host_nonce)
+                            .unwrap(),
+                    });
+            }
+            unsafe { COMMITMENT_SEEN = false }
+            let tx = transaction.clone();
+            *crate::hww::MOCK_NEXT_REQUEST.0.borrow_mut() =
+                Some(Box::new(move |response: Response| {
+                    let next = extract_next(&response);
+                    if NextType::try_from(next.r#type).unwrap() == NextType::HostNonce {
+                        // The signer commitment must be delivered before the host nonce is
+                        // revealed.
+                        let commitment = next.anti_klepto_signer_commitment.as_ref().unwrap();
+                        assert_eq!(commitment.commitment.len(), 33);
+                        unsafe { COMMITMENT_SEEN = true }
+                    }
+                    Ok(tx.borrow().make_host_request(response))
+                }));
+            mock_default_ui();
+            mock_unlocked_using_mnemonic(
+                "sudden tenant fault inject concert weather maid people chunk youth stumble grit",
+                "",
+            );
+            // For the multisig registration below.
+            mock_memory();
+
+            // Hash of the multisig configuration as computed by `btc_common_multisig_hash_sorted()`.
+            let multisig_hash = b"\x89\x75\x1d\x19\xe4\xe2\x6f\xbe\xee\x2f\xd2\xc4\xf5\x6a\xb7\xae\x5b\xe6\xdc\x46\x48\x2e\x81\x24\x1f\x4a\xcc\xfb\xc0\xa1\x58\x4e";
+            bitbox02::memory::multisig_set_by_hash(multisig_hash, "test multisig account name")
+                .unwrap();
+
+            let init_request = {
+                let tx = transaction.borrow();
+                pb::BtcSignInitRequest {
+                    coin: tx.coin as _,
+                    script_configs: vec![pb::BtcScriptConfigWithKeypath {
+                        script_config: Some(pb::BtcScriptConfig {
+                            config: Some(pb::btc_script_config::Config::Multisig(
+                                pb::btc_script_config::Multisig {
+                                    threshold: 1,
+                                    xpubs: vec![
+                                        // sudden tenant fault inject concert weather maid people chunk
+                                        // youth stumble grit / 48'/1'/0'/2'
+                                        parse_xpub("xpub6EMfjyGVUvwhpc3WKN1zXhMFGKJGMaSBPqbja4tbGoYvRBSXeTBCaqrRDjcuGTcaY95JrrAnQvDG3pdQPdtnYUCugjeksHSbyZT7rq38VQF").unwrap(),
+                                        // dumb rough room report huge dry sudden hamster wait foot crew
+                                        // obvious / 48'/1'/0'/2'
+                                        parse_xpub("xpub6ERxBysTYfQyY4USv6c6J1HNVv9hpZFN9LHVPu47Ac4rK8fLy6NnAeeAHyEsMvG4G66ay5aFZii2VM7wT3KxLKX8Q8keZPd67kRGmrD1WJj").unwrap(),
+                                    ],
+                                    our_xpub_index: 0,
+                                    script_type: pb::btc_script_config::multisig::ScriptType::P2wsh
+                                        as _,
+                                },
+                            )),
+                        }),
+                        keypath: vec![
+                            48 + HARDENED,
+                            super::super::params::get(tx.coin).bip44_coin,
+                            0 + HARDENED,
+                            2 + HARDENED,
+                        ],
+                    }],
+                    version: tx.version,
+                    num_inputs: tx.inputs.len() as _,
+                    num_outputs: tx.outputs.len() as _,
+                    locktime: tx.locktime,
+                    format_unit: FormatUnit::Default as _,
+                    coinjoin: false,
+                    advanced_verify_inputs: false,
+                    rbf_required: false,
+                    fiat_rate: None,
+                    verify_bip69_order: false,
+                    summarize_outputs: false,
+                    confirm_outputs_first: false,
+                    replacement: None,
+                }
+            };
+            // With anti-klepto, the last exchange is a wrapped BTCRequest, so the final response
+            // is wrapped as well.
+            let response = block_on(process(&init_request)).unwrap();
+            let next = extract_next(&response);
+            assert!(next.has_signature);
+            next.signature.clone()
+        };
+
+        let plain = sign(false);
+        assert!(unsafe { !COMMITMENT_SEEN });
+        let with_antiklepto = sign(true);
+        assert!(unsafe { COMMITMENT_SEEN });
+        // The host nonce contribution changes the final nonce, so the signatures must differ.
+        assert_ne!(plain, with_antiklepto);
+    }
+
     /// If the multisig has not been registered before, signing fails.
     #[test]
     fn test_multisig_not_registered() {
@@ -2731,11 +7773,138 @@ mod tests {
                 num_outputs: tx.outputs.len() as _,
                 locktime: tx.locktime,
                 format_unit: FormatUnit::Default as _,
+                coinjoin: false,
+                advanced_verify_inputs: false,
+                rbf_required: false,
+                fiat_rate: None,
+                verify_bip69_order: false,
+                summarize_outputs: false,
+                confirm_outputs_first: false,
+                replacement: None,
             }
         };
         assert_eq!(block_on(process(&init_request)), Err(Error::InvalidInput));
     }
 
+    /// An output marked ours that references a registered multisig config is derived by the
+    /// device and shown with the registered account name instead of the raw address. If the host
+    /// provides the output payload as well, it must match the device's own derivation.
+    #[test]
+    fn test_send_to_own_multisig() {
+        let multisig = pb::btc_script_config::Multisig {
+            threshold: 1,
+            xpubs: vec![
+                // sudden tenant fault inject concert weather maid people chunk
+                // youth stumble grit / 48'/1'/0'/2'
+                parse_xpub("xpub6EMfjyGVUvwhpc3WKN1zXhMFGKJGMaSBPqbja4tbGoYvRBSXeTBCaqrRDjcuGTcaY95JrrAnQvDG3pdQPdtnYUCugjeksHSbyZT7rq38VQF").unwrap(),
+                // dumb rough room report huge dry sudden hamster wait foot crew
+                // obvious / 48'/1'/0'/2'
+                parse_xpub("xpub6ERxBysTYfQyY4USv6c6J1HNVv9hpZFN9LHVPu47Ac4rK8fLy6NnAeeAHyEsMvG4G66ay5aFZii2VM7wT3KxLKX8Q8keZPd67kRGmrD1WJj").unwrap(),
+            ],
+            our_xpub_index: 0,
+            script_type: pb::btc_script_config::multisig::ScriptType::P2wsh as _,
+        };
+        // The payload the device derives for the multisig output at change/address 0/1.
+        let multisig_payload = common::Payload::from_multisig(
+            super::super::params::get(pb::BtcCoin::Tbtc),
+            &multisig,
+            0,
+            1,
+        )
+        .unwrap()
+        .data;
+
+        let make_transaction = |payload: Vec<u8>| {
+            let transaction = alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(
+                pb::BtcCoin::Tbtc,
+            )));
+            {
+                let mut tx = transaction.borrow_mut();
+                tx.outputs[1].ours = true;
+                tx.outputs[1].script_config_index = 1;
+                tx.outputs[1].keypath = vec![
+                    48 + HARDENED,
+                    1 + HARDENED,
+                    0 + HARDENED,
+                    2 + HARDENED,
+                    0,
+                    1,
+                ];
+                tx.outputs[1].r#type = pb::BtcOutputType::Unknown as _;
+                tx.outputs[1].payload = payload;
+            }
+            transaction
+        };
+        let make_init_request = |transaction: &alloc::rc::Rc<core::cell::RefCell<Transaction>>| {
+            let mut init_request = transaction.borrow().init_request();
+            init_request
+                .script_configs
+                .push(pb::BtcScriptConfigWithKeypath {
+                    script_config: Some(pb::BtcScriptConfig {
+                        config: Some(pb::btc_script_config::Config::Multisig(multisig.clone())),
+                    }),
+                    keypath: vec![48 + HARDENED, 1 + HARDENED, 0 + HARDENED, 2 + HARDENED],
+                });
+            init_request
+        };
+        let register_multisig = || {
+            mock_memory();
+            // Hash of the multisig configuration as computed by
+            // `btc_common_multisig_hash_sorted()`.
+            let multisig_hash = b"\x89\x75\x1d\x19\xe4\xe2\x6f\xbe\xee\x2f\xd2\xc4\xf5\x6a\xb7\xae\x5b\xe6\xdc\x46\x48\x2e\x81\x24\x1f\x4a\xcc\xfb\xc0\xa1\x58\x4e";
+            bitbox02::memory::multisig_set_by_hash(multisig_hash, "test multisig account name")
+                .unwrap();
+        };
+
+        // Without and with the host providing the matching payload.
+        for provide_payload in [false, true] {
+            let transaction = make_transaction(if provide_payload {
+                multisig_payload.clone()
+            } else {
+                vec![]
+            });
+            mock_host_responder(transaction.clone());
+            static mut NAME_SEEN: bool = false;
+            unsafe { NAME_SEEN = false };
+            mock(Data {
+                ui_confirm_create: Some(Box::new(|_params| true)),
+                ui_transaction_address_create: Some(Box::new(|_amount, address| {
+                    if address == "To your multisig account: test multisig account name" {
+                        unsafe { NAME_SEEN = true };
+                    }
+                    true
+                })),
+                ui_transaction_fee_create: Some(Box::new(|_total, _fee, _longtouch| true)),
+                ..Default::default()
+            });
+            mock_unlocked_using_mnemonic(
+                "sudden tenant fault inject concert weather maid people chunk youth stumble grit",
+                "",
+            );
+            register_multisig();
+            assert!(block_on(process(&make_init_request(&transaction))).is_ok());
+            assert!(unsafe { NAME_SEEN });
+        }
+
+        // A tampered payload hard-fails.
+        {
+            let mut tampered_payload = multisig_payload.clone();
+            tampered_payload[0] ^= 1;
+            let transaction = make_transaction(tampered_payload);
+            mock_host_responder(transaction.clone());
+            mock_default_ui();
+            mock_unlocked_using_mnemonic(
+                "sudden tenant fault inject concert weather maid people chunk youth stumble grit",
+                "",
+            );
+            register_multisig();
+            assert_eq!(
+                block_on(process(&make_init_request(&transaction))),
+                Err(Error::InvalidInputDetail("output payload mismatch"))
+            );
+        }
+    }
+
     #[test]
     fn test_multisig_p2wsh_p2sh() {
         let transaction = alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new_multisig()));
@@ -2797,6 +7966,14 @@ mod tests {
                 num_outputs: tx.outputs.len() as _,
                 locktime: tx.locktime,
                 format_unit: FormatUnit::Default as _,
+                coinjoin: false,
+                advanced_verify_inputs: false,
+                rbf_required: false,
+                fiat_rate: None,
+                verify_bip69_order: false,
+                summarize_outputs: false,
+                confirm_outputs_first: false,
+                replacement: None,
             }
         };
         let result = block_on(process(&init_request));
@@ -2873,6 +8050,14 @@ mod tests {
                 num_outputs: tx.outputs.len() as _,
                 locktime: tx.locktime,
                 format_unit: FormatUnit::Default as _,
+                coinjoin: false,
+                advanced_verify_inputs: false,
+                rbf_required: false,
+                fiat_rate: None,
+                verify_bip69_order: false,
+                summarize_outputs: false,
+                confirm_outputs_first: false,
+                replacement: None,
             }
         };
         let result = block_on(process(&init_request));
@@ -2957,7 +8142,7 @@ mod tests {
                     UI_COUNTER
                 } {
                     9 => {
-                        assert_eq!(total, "0.00090175 TBTC");
+                        assert_eq!(total, "0.00090175 TBTC\nFrom: test policy account name");
                         assert_eq!(fee, "0.00000175 TBTC");
                         assert!(longtouch);
                     }
@@ -3072,6 +8257,67 @@ mod tests {
         }
     }
 
+    /// Test signing with a `sortedmulti()` policy. The keys of this fixture happen to be in
+    /// BIP-67 sorted order at both the receive and the change derivation, so the signature must be
+    /// the same as in `test_policy`, which spends the equivalent `multi()` policy. The key order
+    /// in the policy string must not matter.
+    #[test]
+    fn test_policy_sortedmulti() {
+        let keypath_account = &[48 + HARDENED, 1 + HARDENED, 0 + HARDENED, 3 + HARDENED];
+
+        for policy_str in [
+            "wsh(sortedmulti(2,@0/**,@1/**))",
+            "wsh(sortedmulti(2,@1/**,@0/**))",
+        ] {
+            let transaction =
+                alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new_policy()));
+            mock_host_responder(transaction.clone());
+
+            mock_default_ui();
+
+            mock_unlocked_using_mnemonic(
+                "sudden tenant fault inject concert weather maid people chunk youth stumble grit",
+                "",
+            );
+            // For the policy registration below.
+            mock_memory();
+
+            let policy = pb::btc_script_config::Policy {
+                policy: policy_str.into(),
+                keys: vec![
+                    pb::KeyOriginInfo {
+                        root_fingerprint: crate::keystore::root_fingerprint().unwrap(),
+                        keypath: keypath_account.to_vec(),
+                        xpub: Some(crate::keystore::get_xpub(keypath_account).unwrap().into()),
+                    },
+                    pb::KeyOriginInfo {
+                        root_fingerprint: vec![],
+                        keypath: vec![],
+                        xpub: Some(parse_xpub("tpubDFGkUYFfEhAALSXQ9VNssUq71HWYLWLK7sAEqFyqJBQxQ4uGSBW1RSBkoVfijE6iEHZFs2kZrVzzV1nZCSEXYKudtsfEWcWKVXvjjLeRyd8").unwrap()),
+                    },
+                ],
+            };
+
+            // Register policy.
+            let policy_hash = super::super::policies::get_hash(pb::BtcCoin::Tbtc, &policy).unwrap();
+            bitbox02::memory::multisig_set_by_hash(&policy_hash, "test policy account name")
+                .unwrap();
+
+            let result = block_on(process(
+                &transaction
+                    .borrow()
+                    .init_request_policy(policy, keypath_account),
+            ));
+            match result {
+                Ok(Response::BtcSignNext(next)) => {
+                    assert!(next.has_signature);
+                    assert_eq!(&next.signature, b"\x57\x36\xb8\xee\xc7\x59\x4a\xd9\x06\xda\xf8\xd3\xfa\xc6\x4d\x58\xae\xd3\x5f\xc5\x07\x26\xb0\xed\x6d\x5f\xb1\xc8\x01\x9f\xca\xb0\x60\x6c\xed\x7d\x09\xbc\x9a\x75\xfa\xdf\x5b\xa4\x5c\xc9\x5d\xc1\x5f\xb6\x79\x69\x97\x46\x67\x39\xa9\xf6\x38\x3b\xd1\x59\xda\xe4");
+                }
+                _ => panic!("wrong result"),
+            }
+        }
+    }
+
     #[test]
     fn test_policy_wrong_account_keypath() {
         let transaction = alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new_policy()));
@@ -3116,7 +8362,7 @@ mod tests {
                     .borrow()
                     .init_request_policy(policy, wrong_keypath_account)
             )),
-            Err(Error::InvalidInput)
+            Err(Error::InvalidInputDetail("invalid keypath"))
         );
     }
 
@@ -3165,7 +8411,7 @@ mod tests {
                     .borrow()
                     .init_request_policy(policy, keypath_account)
             )),
-            Err(Error::InvalidInput)
+            Err(Error::InvalidInputDetail("invalid keypath"))
         );
     }
 
@@ -3243,8 +8489,8 @@ mod tests {
                     UI_COUNTER += 1;
                     UI_COUNTER
                 } {
-                    7 => {
-                        assert_eq!(total, "13.39999900 BTC");
+                    8 => {
+                        assert_eq!(total, "13.39999900 BTC\nFrom: account #10");
                         assert_eq!(fee, "0.05419010 BTC");
                         true
                     }
@@ -3261,6 +8507,13 @@ mod tests {
                         true
                     }
                     6 => {
+                        assert_eq!(
+                            params.body,
+                            "Dust change:\n0.00000100 BTC\nReject to add it\nto the fee instead"
+                        );
+                        true
+                    }
+                    7 => {
                         assert_eq!(params.title, "Warning");
                         assert_eq!(params.body, "There are 2\nchange outputs.\nProceed?");
                         true
@@ -3280,4 +8533,58 @@ mod tests {
             transaction.borrow().total_confirmations
         );
     }
+
+    /// Test that a payment request failing verification aborts the signing with
+    /// `Error::InvalidInput`: a tampered amount and an unknown identity key.
+    #[test]
+    pub fn test_payment_request_invalid() {
+        enum TestCase {
+            // The amount was changed after the payment request was signed.
+            TamperedAmount,
+            // The recipient is not in the embedded identity registry.
+            UnknownIdentity,
+        }
+        for test_case in [TestCase::TamperedAmount, TestCase::UnknownIdentity] {
+            let transaction =
+                alloc::rc::Rc::new(core::cell::RefCell::new(Transaction::new(pb::BtcCoin::Btc)));
+            {
+                let mut tx = transaction.borrow_mut();
+                let payment_request_output_index = 1;
+                let output_value = tx.outputs[payment_request_output_index].value;
+                let mut payment_request = pb::BtcPaymentRequestRequest {
+                    recipient_name: "Test Merchant".into(),
+                    memos: vec![],
+                    nonce: vec![],
+                    total_amount: output_value,
+                    signature: vec![],
+                };
+                let coin_params = super::super::params::get(tx.coin);
+                payment_request::tst_sign_payment_request(
+                    coin_params,
+                    &mut payment_request,
+                    output_value,
+                    "34oVnh4gNviJGMnNvgquMeLAxvXJuaRVMZ",
+                );
+                match test_case {
+                    TestCase::TamperedAmount => {
+                        // The signature no longer covers the amount shown to the user.
+                        payment_request.total_amount += 1;
+                        tx.outputs[payment_request_output_index].value += 1;
+                    }
+                    TestCase::UnknownIdentity => {
+                        payment_request.recipient_name = "Unknown Merchant".into();
+                    }
+                }
+                tx.payment_request = Some(payment_request);
+                tx.outputs[payment_request_output_index].payment_request_index = Some(0);
+            }
+            mock_host_responder(transaction.clone());
+            mock_default_ui();
+            mock_unlocked();
+            assert_eq!(
+                block_on(process(&transaction.borrow().init_request())),
+                Err(Error::InvalidInput)
+            );
+        }
+    }
 }
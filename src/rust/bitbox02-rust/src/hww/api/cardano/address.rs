@@ -223,6 +223,7 @@ pub async fn process(request: &pb::CardanoAddressRequest) -> Result<Response, Er
 
     Ok(Response::Pub(pb::PubResponse {
         r#pub: encoded_address,
+        pub_key_script: Vec::new(),
     }))
 }
 
@@ -335,7 +336,8 @@ mod tests {
                 &[1852 + HARDENED, 1815 + HARDENED, HARDENED, 2, 0],
             ),
             Ok(Response::Pub(pb::PubResponse {
-                r#pub: "addr1q90tlskd4mh5kncmul7vx887j30tjtfgvap5n0g0rf9qqc7znmndrdhe7rwvqkw5c7mqnp4a3yflnvu6kff7l5dungvqmvu6hs".into()
+                r#pub: "addr1q90tlskd4mh5kncmul7vx887j30tjtfgvap5n0g0rf9qqc7znmndrdhe7rwvqkw5c7mqnp4a3yflnvu6kff7l5dungvqmvu6hs".into(),
+                pub_key_script: vec![],
             }))
         );
 
@@ -411,7 +413,8 @@ mod tests {
                 )),
             })),
             Ok(Response::Pub(pb::PubResponse {
-                r#pub: EXPECTED.into()
+                r#pub: EXPECTED.into(),
+                pub_key_script: vec![],
             }))
         );
     }
@@ -451,7 +454,8 @@ mod tests {
             assert_eq!(
                 do_pkh_skh(test.keypath_payment, test.keypath_stake),
                 Ok(Response::Pub(pb::PubResponse {
-                    r#pub: test.expected_address.into()
+                    r#pub: test.expected_address.into(),
+                    pub_key_script: vec![],
                 }))
             );
         }
@@ -19,12 +19,13 @@ use pb::response::Response;
 
 use crate::bip32;
 use crate::keystore;
+use crate::workflow::confirm;
 
 const HARDENED: u32 = 0x80000000;
 const ELECTRUM_WALLET_ENCRYPTION_KEYPATH_LEVEL_ONE: u32 = 4541509 + HARDENED;
 const ELECTRUM_WALLET_ENCRYPTION_KEYPATH_LEVEL_TWO: u32 = 1112098098 + HARDENED;
 
-/// Returns the electrum wallet encryption xpub.
+/// Returns the electrum wallet encryption xpub after the user confirmed the export on the device.
 /// `keypath` currently needs to be m/4541509'/1112098098'
 /// Note: the result of this is only meant to be used for encryption by Electrum.
 /// The resulting xpub must not be used to derive addresses or to receive coins.
@@ -39,12 +40,25 @@ pub async fn process(
     {
         return Err(Error::InvalidInput);
     }
-    let xpub = keystore::get_xpub(keypath)
-        .or(Err(Error::InvalidInput))?
-        .serialize_str(bip32::XPubType::Xpub)?;
+    let xpub = keystore::get_xpub(keypath).or(Err(Error::InvalidInput))?;
+
+    // Show a short fingerprint of the key so the user can match it against what Electrum
+    // displays.
+    confirm::confirm(&confirm::Params {
+        title: "Electrum",
+        body: &format!(
+            "Export encryption\nkey with fingerprint\n{}?",
+            hex::encode(&xpub.pubkey_hash160()[..4])
+        ),
+        longtouch: true,
+        ..Default::default()
+    })
+    .await?;
 
     Ok(Response::ElectrumEncryptionKey(
-        pb::ElectrumEncryptionKeyResponse { key: xpub },
+        pb::ElectrumEncryptionKeyResponse {
+            key: xpub.serialize_str(bip32::XPubType::Xpub)?,
+        },
     ))
 }
 
@@ -54,10 +68,21 @@ mod tests {
 
     use crate::bb02_async::block_on;
     use alloc::boxed::Box;
-    use bitbox02::testing::mock_unlocked;
+    use bitbox02::testing::{mock, mock_unlocked, Data};
 
     #[test]
     pub fn test_process() {
+        static mut CONFIRM_COUNTER: u32 = 0;
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|params| {
+                assert_eq!(params.title, "Electrum");
+                assert_eq!(params.body, "Export encryption\nkey with fingerprint\n66edeb48?");
+                assert!(params.longtouch);
+                unsafe { CONFIRM_COUNTER += 1 }
+                true
+            })),
+            ..Default::default()
+        });
         mock_unlocked();
 
         // All good.
@@ -74,6 +99,7 @@ mod tests {
                 },
             ))
         );
+        assert_eq!(unsafe { CONFIRM_COUNTER }, 1);
 
         // Invalid keypath.
         assert_eq!(
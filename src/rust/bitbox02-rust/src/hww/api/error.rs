@@ -20,6 +20,10 @@ use crate::workflow::unlock::UnlockError;
 #[derive(Debug, PartialEq)]
 pub enum Error {
     InvalidInput,
+    // Invalid input with a short machine-readable detail string, propagated to the host in the
+    // `detail` field of the Error response. On the wire it is identical to `InvalidInput` (same
+    // code and message), so hosts that ignore the detail see no difference.
+    InvalidInputDetail(&'static str),
     Memory,
     Generic,
     UserAbort,
@@ -28,6 +32,10 @@ pub enum Error {
     Duplicate,
     NoiseEncrypt,
     NoiseDecrypt,
+    // A produced signature failed the internal self-verification against the derived public key.
+    // This indicates a faulty keystore or secure chip, or a fault injection attack; the signature
+    // is never emitted.
+    SignatureCheck,
 }
 
 impl core::convert::From<()> for Error {
@@ -128,38 +136,57 @@ pub fn make_error(err: Error) -> Response {
         InvalidInput => pb::Error {
             code: 101,
             message: "invalid input".into(),
+            detail: "".into(),
+        },
+        InvalidInputDetail(detail) => pb::Error {
+            code: 101,
+            message: "invalid input".into(),
+            detail: detail.into(),
         },
         Memory => pb::Error {
             code: 102,
             message: "memory".into(),
+            detail: "".into(),
         },
         Generic => pb::Error {
             code: 103,
             message: "generic error".into(),
+            detail: "".into(),
         },
         UserAbort => pb::Error {
             code: 104,
             message: "aborted by the user".into(),
+            detail: "".into(),
         },
         InvalidState => pb::Error {
             code: 105,
             message: "can't call this endpoint: wrong state".into(),
+            detail: "".into(),
         },
         Disabled => pb::Error {
             code: 106,
             message: "function disabled".into(),
+            detail: "".into(),
         },
         Duplicate => pb::Error {
             code: 107,
             message: "duplicate entry".into(),
+            detail: "".into(),
         },
         NoiseEncrypt => pb::Error {
             code: 108,
             message: "noise encryption failed".into(),
+            detail: "".into(),
         },
         NoiseDecrypt => pb::Error {
             code: 109,
             message: "noise decryption failed".into(),
+            detail: "".into(),
+        },
+        SignatureCheck => pb::Error {
+            code: 110,
+            message: "signature verification failed".into(),
+            detail: "".into(),
         },
     };
     Response::Error(err)
@@ -86,5 +86,6 @@ pub async fn process_api(request: &Request) -> Result<Response, Error> {
         Request::AntikleptoSignature(_) => Err(Error::InvalidInput),
         Request::SignTypedMsg(ref request) => sign_typed_msg::process(request).await,
         Request::TypedMsgValue(_) => Err(Error::InvalidInput),
+        Request::AddressBatch(ref request) => pubrequest::process_address_batch(request).await,
     }
 }
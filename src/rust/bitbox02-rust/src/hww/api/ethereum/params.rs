@@ -106,6 +106,11 @@ fn get(coin: Option<EthCoin>, chain_id: u64) -> Option<&'static Params> {
     })
 }
 
+/// Largest accepted chain ID. Chain IDs are capped at 2^53-1 so that the EIP-155 v-value
+/// (`chain_id * 2 + 35 + rec_id`) remains exactly representable in hosts using IEEE 754 doubles
+/// (e.g. JavaScript). See also https://eips.ethereum.org/EIPS/eip-2294.
+const MAX_CHAIN_ID: u64 = (1 << 53) - 1;
+
 /// Get the chain parameters by `coin` or `chain_id`. If `chain_id` is non-zero, `coin` is
 /// ignored. If `coin` is None. `chain_id` alone is used.
 ///
@@ -113,6 +118,9 @@ fn get(coin: Option<EthCoin>, chain_id: u64) -> Option<&'static Params> {
 /// ID, and params with this chain ID and "UNKNOWN" name is returned. The main reason for this is
 /// that users can rescue funds sent on an unsupported network.
 pub async fn get_and_warn_unknown(coin: Option<EthCoin>, chain_id: u64) -> Result<Params, Error> {
+    if chain_id > MAX_CHAIN_ID {
+        return Err(Error::InvalidInput);
+    }
     match get(coin, chain_id) {
         Some(params) => Ok(*params),
         None => {
@@ -167,4 +175,64 @@ mod tests {
         assert!(get(None, 2).is_none());
         assert!(get(None, 0).is_none());
     }
+
+    #[test]
+    pub fn test_get_and_warn_unknown() {
+        use crate::bb02_async::block_on;
+        use alloc::boxed::Box;
+        use bitbox02::testing::{mock, Data};
+
+        // Known chain ID: no dialog.
+        mock(Data {
+            ..Default::default()
+        });
+        assert_eq!(
+            block_on(get_and_warn_unknown(None, 137)).unwrap().name,
+            "Polygon"
+        );
+
+        // Unknown chain ID: the user confirms two warning screens.
+        static mut CONFIRM_COUNTER: u32 = 0;
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|params| {
+                assert_eq!(params.title, "Warning");
+                match unsafe {
+                    CONFIRM_COUNTER += 1;
+                    CONFIRM_COUNTER
+                } {
+                    1 => assert_eq!(params.body, "Unknown network\nwith chain ID:\n534352"),
+                    2 => assert_eq!(params.body, "Only proceed if\nyou recognize\nthis chain ID."),
+                    _ => panic!("too many dialogs"),
+                }
+                true
+            })),
+            ..Default::default()
+        });
+        let params = block_on(get_and_warn_unknown(None, 534352)).unwrap();
+        assert_eq!(params.name, "UNKNOWN");
+        assert_eq!(params.chain_id, 534352);
+        assert_eq!(unsafe { CONFIRM_COUNTER }, 2);
+
+        // The maximum chain ID is still accepted...
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|_| true)),
+            ..Default::default()
+        });
+        assert_eq!(
+            block_on(get_and_warn_unknown(None, MAX_CHAIN_ID))
+                .unwrap()
+                .chain_id,
+            MAX_CHAIN_ID
+        );
+
+        // ...but anything larger is rejected before any dialog, as the EIP-155 v-value would not
+        // fit a double anymore.
+        mock(Data {
+            ..Default::default()
+        });
+        assert!(matches!(
+            block_on(get_and_warn_unknown(None, MAX_CHAIN_ID + 1)),
+            Err(Error::InvalidInput)
+        ));
+    }
 }
@@ -15,6 +15,8 @@
 use super::pb;
 use super::Error;
 
+use alloc::vec::Vec;
+
 use pb::eth_pub_request::OutputType;
 use pb::eth_response::Response;
 
@@ -62,7 +64,10 @@ async fn process_address(request: &pb::EthPubRequest) -> Result<Response, Error>
         .await?;
     }
 
-    Ok(Response::Pub(pb::PubResponse { r#pub: address }))
+    Ok(Response::Pub(pb::PubResponse {
+        r#pub: address,
+        pub_key_script: Vec::new(),
+    }))
 }
 
 fn process_xpub(request: &pb::EthPubRequest) -> Result<Response, Error> {
@@ -78,7 +83,10 @@ fn process_xpub(request: &pb::EthPubRequest) -> Result<Response, Error> {
         .or(Err(Error::InvalidInput))?
         .serialize_str(bip32::XPubType::Xpub)?;
 
-    Ok(Response::Pub(pb::PubResponse { r#pub: xpub }))
+    Ok(Response::Pub(pb::PubResponse {
+        r#pub: xpub,
+        pub_key_script: Vec::new(),
+    }))
 }
 
 pub async fn process(request: &pb::EthPubRequest) -> Result<Response, Error> {
@@ -89,6 +97,60 @@ pub async fn process(request: &pb::EthPubRequest) -> Result<Response, Error> {
     }
 }
 
+/// Max number of addresses that can be derived in one batch request.
+const ADDRESS_BATCH_MAX: u32 = 50;
+
+/// Derives a batch of receive addresses at consecutive indices, e.g. for account discovery. The
+/// user confirms the account and index range once instead of once per address; the addresses
+/// themselves are not displayed. The EIP-55 checksum casing is computed on the device.
+pub async fn process_address_batch(
+    request: &pb::EthAddressBatchRequest,
+) -> Result<Response, Error> {
+    let params = super::params::get_and_warn_unknown(None, request.chain_id).await?;
+
+    if request.count == 0 || request.count > ADDRESS_BATCH_MAX {
+        return Err(Error::InvalidInput);
+    }
+    // Validate every keypath that will be derived, not only the first.
+    for i in 0..request.count {
+        let mut keypath = request.keypath.clone();
+        match keypath.last_mut() {
+            Some(last) => *last = last.checked_add(i).ok_or(Error::InvalidInput)?,
+            None => return Err(Error::InvalidInput),
+        }
+        if !super::keypath::is_valid_keypath_address(&keypath) {
+            return Err(Error::InvalidInput);
+        }
+    }
+
+    super::keypath::warn_unusual_keypath(&params, params.name, &request.keypath).await?;
+    confirm::confirm(&confirm::Params {
+        title: params.name,
+        title_autowrap: true,
+        body: &format!(
+            "Export {} addresses\nstarting at\n{}?",
+            request.count,
+            util::bip32::to_string(&request.keypath)
+        ),
+        scrollable: true,
+        ..Default::default()
+    })
+    .await?;
+
+    let mut addresses = Vec::with_capacity(request.count as usize);
+    for i in 0..request.count {
+        let mut keypath = request.keypath.clone();
+        *keypath.last_mut().unwrap() += i;
+        let pubkey = keystore::get_xpub(&keypath)
+            .or(Err(Error::InvalidInput))?
+            .pubkey_uncompressed()?;
+        addresses.push(super::address::from_pubkey(&pubkey));
+    }
+    Ok(Response::AddressBatch(pb::EthAddressBatchResponse {
+        addresses,
+    }))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -115,7 +177,8 @@ mod tests {
         assert_eq!(
             block_on(process(&request)),
             Ok(Response::Pub(pb::PubResponse {
-                r#pub: EXPECTED_XPUB.into()
+                r#pub: EXPECTED_XPUB.into(),
+                pub_key_script: vec![],
             }))
         );
 
@@ -169,7 +232,8 @@ mod tests {
         assert_eq!(
             block_on(process(&request)),
             Ok(Response::Pub(pb::PubResponse {
-                r#pub: ADDRESS.into()
+                r#pub: ADDRESS.into(),
+                pub_key_script: vec![],
             }))
         );
 
@@ -193,7 +257,8 @@ mod tests {
                 chain_id: 0,
             })),
             Ok(Response::Pub(pb::PubResponse {
-                r#pub: ADDRESS.into()
+                r#pub: ADDRESS.into(),
+                pub_key_script: vec![],
             }))
         );
 
@@ -231,7 +296,8 @@ mod tests {
                 chain_id: 5,
             })),
             Ok(Response::Pub(pb::PubResponse {
-                r#pub: ADDRESS.into()
+                r#pub: ADDRESS.into(),
+                pub_key_script: vec![],
             }))
         );
         assert_eq!(unsafe { CONFIRM_COUNTER }, 2);
@@ -292,6 +358,127 @@ mod tests {
         );
     }
 
+    #[test]
+    pub fn test_process_address_batch() {
+        const KEYPATH: &[u32] = &[44 + HARDENED, 60 + HARDENED, 0 + HARDENED, 0, 0];
+
+        // All good: one confirmation for the whole batch.
+        static mut CONFIRM_COUNTER: u32 = 0;
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|params| {
+                match unsafe {
+                    CONFIRM_COUNTER += 1;
+                    CONFIRM_COUNTER
+                } {
+                    1 => {
+                        assert_eq!(params.title, "Ethereum");
+                        assert_eq!(
+                            params.body,
+                            "Export 3 addresses\nstarting at\nm/44'/60'/0'/0/0?"
+                        );
+                    }
+                    _ => panic!("too many user confirmations"),
+                }
+                true
+            })),
+            ..Default::default()
+        });
+        mock_unlocked();
+        let addresses = match block_on(process_address_batch(&pb::EthAddressBatchRequest {
+            chain_id: 1,
+            keypath: KEYPATH.to_vec(),
+            count: 3,
+        })) {
+            Ok(Response::AddressBatch(pb::EthAddressBatchResponse { addresses })) => addresses,
+            _ => panic!("unexpected response"),
+        };
+        assert_eq!(unsafe { CONFIRM_COUNTER }, 1);
+        assert_eq!(addresses.len(), 3);
+        // EIP-55 checksum casing.
+        assert_eq!(addresses[0], "0x773A77b9D32589be03f9132AF759e294f7851be9");
+
+        // Each address matches the individual derivation.
+        for (i, address) in addresses.iter().enumerate() {
+            let mut keypath = KEYPATH.to_vec();
+            keypath[4] = i as u32;
+            mock(Data {
+                ..Default::default()
+            });
+            mock_unlocked();
+            assert_eq!(
+                block_on(process(&pb::EthPubRequest {
+                    output_type: OutputType::Address as _,
+                    keypath,
+                    coin: pb::EthCoin::Eth as _,
+                    display: false,
+                    contract_address: b"".to_vec(),
+                    chain_id: 0,
+                })),
+                Ok(Response::Pub(pb::PubResponse {
+                    r#pub: address.clone(),
+                    pub_key_script: vec![],
+                }))
+            );
+            // EIP-55 mixes the casing - an all-lowercase result would indicate the checksumming
+            // was skipped.
+            assert!(address.chars().any(|c| c.is_ascii_uppercase()));
+        }
+
+        // Invalid counts.
+        for count in [0, 51] {
+            mock(Data {
+                ..Default::default()
+            });
+            assert_eq!(
+                block_on(process_address_batch(&pb::EthAddressBatchRequest {
+                    chain_id: 1,
+                    keypath: KEYPATH.to_vec(),
+                    count,
+                })),
+                Err(Error::InvalidInput)
+            );
+        }
+
+        // The range must not exceed the max account index - every derived keypath is validated.
+        mock(Data {
+            ..Default::default()
+        });
+        assert_eq!(
+            block_on(process_address_batch(&pb::EthAddressBatchRequest {
+                chain_id: 1,
+                keypath: [44 + HARDENED, 60 + HARDENED, 0 + HARDENED, 0, 98].to_vec(),
+                count: 3,
+            })),
+            Err(Error::InvalidInput)
+        );
+
+        // Wrong keypath (wrong expected coin).
+        mock(Data {
+            ..Default::default()
+        });
+        assert_eq!(
+            block_on(process_address_batch(&pb::EthAddressBatchRequest {
+                chain_id: 1,
+                keypath: [44 + HARDENED, 0 + HARDENED, 0 + HARDENED, 0, 0].to_vec(),
+                count: 1,
+            })),
+            Err(Error::InvalidInput)
+        );
+
+        // A chain ID is mandatory.
+        mock(Data {
+            ..Default::default()
+        });
+        assert_eq!(
+            block_on(process_address_batch(&pb::EthAddressBatchRequest {
+                chain_id: 0,
+                keypath: KEYPATH.to_vec(),
+                count: 1,
+            })),
+            Err(Error::InvalidInput)
+        );
+    }
+
     #[test]
     pub fn test_process_erc20_address() {
         const ADDRESS: &str = "0x773A77b9D32589be03f9132AF759e294f7851be9";
@@ -315,7 +502,8 @@ mod tests {
         assert_eq!(
             block_on(process(&request)),
             Ok(Response::Pub(pb::PubResponse {
-                r#pub: ADDRESS.into()
+                r#pub: ADDRESS.into(),
+                pub_key_script: vec![],
             }))
         );
 
@@ -339,7 +527,8 @@ mod tests {
                 chain_id: 0,
             })),
             Ok(Response::Pub(pb::PubResponse {
-                r#pub: ADDRESS.into()
+                r#pub: ADDRESS.into(),
+                pub_key_script: vec![],
             }))
         );
 
@@ -12,6 +12,8 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use alloc::vec::Vec;
+
 use sha3::{Digest, Keccak256};
 
 const RLP_SMALL_TAG: u8 = 0xc0;
@@ -27,6 +29,24 @@ pub struct ParamsLegacy<'a> {
     pub chain_id: u64,
 }
 
+/// One entry of an EIP-2930 access list: an address and the storage keys accessed at it.
+/// https://eips.ethereum.org/EIPS/eip-2930
+pub struct AccessListEntry<'a> {
+    pub address: &'a [u8],
+    pub storage_keys: &'a [Vec<u8>],
+}
+
+pub struct ParamsEIP2930<'a> {
+    pub chain_id: u64,
+    pub nonce: &'a [u8],
+    pub gas_price: &'a [u8],
+    pub gas_limit: &'a [u8],
+    pub recipient: &'a [u8],
+    pub value: &'a [u8],
+    pub data: &'a [u8],
+    pub access_list: &'a [AccessListEntry<'a>],
+}
+
 pub struct ParamsEIP1559<'a> {
     pub chain_id: u64,
     pub nonce: &'a [u8],
@@ -104,6 +124,49 @@ fn hash_params_legacy<W: Write>(writer: &mut W, params: &ParamsLegacy) {
     }
 }
 
+fn hash_access_list_entry_elements<W: Write>(writer: &mut W, entry: &AccessListEntry) {
+    hash_element(writer, entry.address);
+    // The storage keys are a nested list: [key, key, ...].
+    let mut keys_counter = Counter(0);
+    for key in entry.storage_keys {
+        hash_element(&mut keys_counter, key);
+    }
+    hash_header(writer, RLP_SMALL_TAG, RLP_LARGE_TAG, keys_counter.0 as u16);
+    for key in entry.storage_keys {
+        hash_element(writer, key);
+    }
+}
+
+fn hash_access_list_entry<W: Write>(writer: &mut W, entry: &AccessListEntry) {
+    // Each entry is a list: [address, [key, key, ...]].
+    let mut counter = Counter(0);
+    hash_access_list_entry_elements(&mut counter, entry);
+    hash_header(writer, RLP_SMALL_TAG, RLP_LARGE_TAG, counter.0 as u16);
+    hash_access_list_entry_elements(writer, entry);
+}
+
+fn hash_access_list<W: Write>(writer: &mut W, access_list: &[AccessListEntry]) {
+    let mut counter = Counter(0);
+    for entry in access_list {
+        hash_access_list_entry(&mut counter, entry);
+    }
+    hash_header(writer, RLP_SMALL_TAG, RLP_LARGE_TAG, counter.0 as u16);
+    for entry in access_list {
+        hash_access_list_entry(writer, entry);
+    }
+}
+
+fn hash_params_eip2930<W: Write>(writer: &mut W, params: &ParamsEIP2930) {
+    hash_u64(writer, params.chain_id);
+    hash_element(writer, params.nonce);
+    hash_element(writer, params.gas_price);
+    hash_element(writer, params.gas_limit);
+    hash_element(writer, params.recipient);
+    hash_element(writer, params.value);
+    hash_element(writer, params.data);
+    hash_access_list(writer, params.access_list);
+}
+
 fn hash_params_eip1559<W: Write>(writer: &mut W, params: &ParamsEIP1559) {
     hash_u64(writer, params.chain_id);
     hash_element(writer, params.nonce);
@@ -141,6 +204,29 @@ pub fn compute_legacy(params: &ParamsLegacy) -> Result<[u8; 32], ()> {
     Ok(hasher.0.finalize().into())
 }
 
+pub fn compute_eip2930(params: &ParamsEIP2930) -> Result<[u8; 32], ()> {
+    // https://eips.ethereum.org/EIPS/eip-2930
+    // We hash [chain_id, nonce, gas price, gas limit, recipient, value, data, access list]
+    // RLP encoded. Prefixed with 0x01 for EIP2930 transaction type.
+    // The list length prefix is (0xc0 + length of the encoding of all elements).
+
+    // 1) calculate length
+    let mut counter = Counter(0);
+    hash_params_eip2930(&mut counter, params);
+
+    if counter.0 > 0xffff {
+        // Don't support bigger than this for now.
+        return Err(());
+    }
+
+    // 2) hash len and encoded tx elements
+    let mut hasher = Hasher(Keccak256::new());
+    hasher.write(&[0x01]); // prefix the rlp encoding with transaction type before hashing
+    hash_header(&mut hasher, RLP_SMALL_TAG, RLP_LARGE_TAG, counter.0 as u16);
+    hash_params_eip2930(&mut hasher, params);
+    Ok(hasher.0.finalize().into())
+}
+
 pub fn compute_eip1559(params: &ParamsEIP1559) -> Result<[u8; 32], ()> {
     // https://eips.ethereum.org/EIPS/eip-1559
     // We hash [chain_id, nonce, max_priority_fee_per_gas, max_fee_per_gas, gas limit, recipient, value, data, access list]
@@ -1574,6 +1660,95 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_compute_eip2930() {
+        /* Generated using this Python program (the RLP encoder and Keccak-256 implementation
+        were verified to reproduce the go-ethereum vectors of test_compute_eip1559 above):
+
+        import random
+        from keccak import keccak256, rlp_enc  # pure-Python RLP + Keccak-256
+
+        random.seed(7)
+        def rb(n): return bytes(random.randrange(256) for _ in range(n))
+        # txs: (chain_id, nonce, gas_price, gas_limit, to, value, data, access_list);
+        # the access list is [[address, [key, ...]], ...].
+        for tx in txs:
+            payload = rlp_enc(list(tx))
+            print(keccak256(b'\x01' + payload).hex())
+        */
+
+        struct Test<'a> {
+            params: ParamsEIP2930<'a>,
+            expected_sighash: [u8; 32],
+        }
+        let tests = &[
+            Test {
+                params: ParamsEIP2930 {
+                    chain_id: 1,
+                    nonce: b"\x1f\xdc",
+                    gas_price: b"\x01\x65\xa0\xbc\x00",
+                    gas_limit: b"\x52\x08",
+                    recipient: b"\xda\xc1\x7f\x95\x8d\x2e\xe5\x23\xa2\x20\x62\x06\x99\x45\x97\xc1\x3d\x83\x1e\xc7",
+                    value: b"\x07\x5c\xf1\x25\x9e\x9c\x40\x00",
+                    data: b"",
+                    access_list: &[
+                        AccessListEntry {
+                            address: b"\x04\xf2\x64\xcf\x34\x44\x03\x13\xb4\xa0\x19\x2a\x35\x28\x14\xfb\xe9\x27\xb8\x85",
+                            storage_keys: &[
+                                b"\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x01".to_vec(),
+                                b"\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x07".to_vec(),
+                            ],
+                        },
+                    ],
+                },
+                expected_sighash: *b"\x00\x79\x92\x14\x85\x1c\x14\x9f\x28\x2d\xe3\xbe\xbc\xc7\xcc\x78\xae\x24\x42\x49\x53\xdb\xdc\x91\x8b\xcc\xcc\x5f\xbc\x09\xe7\x81",
+            },
+            Test {
+                params: ParamsEIP2930 {
+                    chain_id: 200,
+                    nonce: b"\x09",
+                    gas_price: b"\xa5\x4d\xca\x18\x25\x30\xbb\x1d\x6d\x13\x2c\xde",
+                    gas_limit: b"\xd6\x23\x7b\x2e\xd9\x1e\x3f\x72",
+                    recipient: b"\x1f\xcb\x19\x71\x17\x44\x94\xd6\x49\x3c\x9d\x5c\x34\x60\xbe\x31\x20\x1e\x69\xfe",
+                    value: b"\xda\xa0\xee\xe8\xb9\x99\x7f\x5c\x7c\x29\x99\xfd\xaf\xe5\x93\x25",
+                    data: b"\x3c\xd6\x54\xaf\x4d\xfa\xd7\x14\x27\xa0\xae\xb3\xfe\xe9\x23\x2f\x8a\xf2\x21\x1f\x9e\xe4\x91\xc5\xb1\x0b\xec\xb5\x56\x3b\xfc\x1e\x6f\x93\x42\x7e\xcb\xc8\xfe\x29\x55\xe5\xcd\x8e\x46\xdc\x8e\xd4\xb7\xc2\x76\x4d\x2a\x5a\x4d\x76\x77\x06\xf8\x5d\x86\x90\x02\x4a\xd6\xbd\xa3\x40",
+                    access_list: &[
+                        AccessListEntry {
+                            address: b"\x1b\xe9\xc8\xcb\xcc\xc9\x35\xf6\xcd\x1f\x61\x22\x6a\xe1\x53\x38\xae\x1a\x34\x00",
+                            storage_keys: &[],
+                        },
+                        AccessListEntry {
+                            address: b"\x4d\x33\xba\x0d\x24\x6a\xc0\x4c\x81\xb1\xba\xf2\x3e\x3b\xf9\xee\xf5\xf7\x9f\x2b",
+                            storage_keys: &[
+                                b"\x49\x34\xaf\x87\xf5\x52\x0b\x69\xb9\x4b\x0d\x98\x2e\x85\xbb\x55\xb6\x72\xa8\x72\x63\x7a\xcd\x74\x66\xfc\xb6\x0e\x0e\x8f\xf1\x84".to_vec(),
+                            ],
+                        },
+                    ],
+                },
+                expected_sighash: *b"\x98\xc4\x84\xea\x8e\xc9\x07\x6f\x25\xaa\x87\xa3\xfc\x18\x2b\x51\x7e\x87\x93\x18\x2c\x7e\x68\x60\x29\x9a\x8a\x83\xb5\xf0\xed\xad",
+            },
+            Test {
+                params: ParamsEIP2930 {
+                    chain_id: 9223372036854775803,
+                    nonce: b"\x63\xb0",
+                    gas_price: b"\xe4\xb2\xba\x29\x70\x34\x74\xf0\x64\xac\x68\xf7\x00\xf5\xb0\x2b",
+                    gas_limit: b"\x3d\xc6\x66\xf4\x5b\xde\xaa\x2c",
+                    recipient: b"\xca\xed\xcd\x2b\x51\x57\x41\x0e\x4d\xee\x4a\xf2\xb3\x4f\x43\x0a\x07\x34\x47\xde",
+                    value: b"\x63\x6c\x0e\x80",
+                    data: b"\x6c\x95\x7b\xa6\x84\xd6\x43\x1f\xb5\xea\xd7\x42\x4d\x09\xe1\x5d\x02\x4c\x58\x48\xf2\x3d\x1f\xa6\xf7\x36\x1d\x7f\x61\x8d\x15\x32\xe7\x0e\x20\xe2\xa6\x66\x8d\xe7\xf4\x7e\x84\x67\xe5\x46\xd5\x3e\xc8\xe2\xa1\x25\x7b\xdb\x25\x6c\x9b\x3e\x4f\xbb\x49\x81\x46\xef\x70\x30\xcb\xf9\x53\x72\x52\xdc\xce\xad\xd7\x64\xb6\xa3\x2f\xbb\x09\xad\xea\xe1\x09\xc4\xa9\x97\x20\x39\x75\x35\x2b\x87\x8b\x14\x5c\x8a\x42\xd8\x84\xcf\x4c\xfd\xa7\x2d\x8e\x1d\x5d\xd9\x25\x89\x08\x2d\x85\x2a\x71\x22\x87\x3e\xe8\x05\xad\xd5\x89\x42\x16\x7a\x38\x52\x86\x19\x5c\x67\x9f\x9c\x69\x94\xe4\x5b\x8a\xb1\x09\x80\x12\x07\x09\x61\xf3\x7d\xe4\x36\xdd\xfd\xc9\x9d\x6e\x75\xaf\x65\x47\xcf\xb1\x1b\x42\x07\x24\x82\xdc\x53\x1c\x2b\xc3\x90\x7c\x96\x17\xeb\x5e\x50\x89\xe4\x01\x86\xba\xa8\xa5\x7d\x11\x9e\x6f\xb6\x5d\x00\xab\xc3\x2a\xf3\x8e\x66\x7f\x02\x2e\x87\x2d\x49\xcc\x15\xc9\x0b\x99\x9b\x77\x2b\x4f\xc7\xa6\xfd\x4c\x91\x4a\x16\xdb\x47\x08\x75\x2b\x0f\x15\x44\xb8\x35\xc0\xe7\x19\x09\x7d\xfa\x87\x01\xe9\x23\x2f\x21\xf2\x81\x26\x87\x78\x69\x76\xeb\xfc\xc3\x27\xf5\x93\x17\x65\x27\x4b\xa9\x82\x9b\x44\x06\xf6\x1f\xf8\x89\x32\x6f\xfa\x94\x92\xed\xee\xee\x3c\x66\x9f\x2b\xf2\x08\x94\xea\x27\xe6\x89\xc6\x6b\x6b\x26\x2e\x48\x86\xb8\x43\x8f\x39",
+                    access_list: &[],
+                },
+                expected_sighash: *b"\x95\xbd\xb5\xee\xc1\x3c\xe4\x79\x12\x66\x4c\xba\x8f\xe2\x33\xe9\x56\xd9\xee\xda\x58\x28\x51\x1e\xe8\xba\x14\x9b\x60\x90\xfd\x9f",
+            },
+        ];
+        for test in tests.iter() {
+            assert_eq!(
+                compute_eip2930(&test.params),
+                Ok(test.expected_sighash)
+            );
+        }
+    }
+
     #[test]
     fn test_compute_legacy() {
         /* Generated using this Go program:
@@ -26,6 +26,7 @@ use pb::eth_response::Response;
 
 use core::ops::{Add, Mul};
 use num_bigint::BigUint;
+use sha3::{Digest, Keccak256};
 
 // 1 ETH = 1e18 wei.
 const WEI_DECIMALS: usize = 18;
@@ -84,6 +85,12 @@ impl<'a> Transaction<'a> {
             Transaction::Eip1559(eip1559) => eip1559.host_nonce_commitment.as_ref(),
         }
     }
+    fn token_metadata(&self) -> Option<&pb::EthTokenMetadata> {
+        match self {
+            Transaction::Legacy(legacy) => legacy.token_metadata.as_ref(),
+            Transaction::Eip1559(eip1559) => eip1559.token_metadata.as_ref(),
+        }
+    }
     fn coin(&self) -> Result<Option<pb::EthCoin>, Error> {
         match self {
             Transaction::Legacy(legacy) => Ok(Some(pb::EthCoin::try_from(legacy.coin)?)),
@@ -172,6 +179,29 @@ fn hash_legacy(chain_id: u64, request: &pb::EthSignRequest) -> Result<[u8; 32],
     Ok(hash)
 }
 
+fn hash_eip2930(chain_id: u64, request: &pb::EthSignRequest) -> Result<[u8; 32], Error> {
+    let access_list: Vec<super::sighash::AccessListEntry> = request
+        .access_list
+        .iter()
+        .map(|entry| super::sighash::AccessListEntry {
+            address: &entry.address,
+            storage_keys: &entry.storage_keys,
+        })
+        .collect();
+    let hash = super::sighash::compute_eip2930(&super::sighash::ParamsEIP2930 {
+        chain_id,
+        nonce: &request.nonce,
+        gas_price: &request.gas_price,
+        gas_limit: &request.gas_limit,
+        recipient: &request.recipient,
+        value: &request.value,
+        data: &request.data,
+        access_list: &access_list,
+    })
+    .map_err(|_| Error::InvalidInput)?;
+    Ok(hash)
+}
+
 fn hash_eip1559(request: &pb::EthSignEip1559Request) -> Result<[u8; 32], Error> {
     let hash = super::sighash::compute_eip1559(&super::sighash::ParamsEIP1559 {
         chain_id: request.chain_id,
@@ -216,7 +246,20 @@ async fn verify_erc20_transaction(
             // ERC20 token: fee has a different unit (ETH), so the total is just the value again.
             (value.clone(), value.clone())
         }
-        None => ("Unknown token".into(), "Unknown amount".into()),
+        None => match request.token_metadata() {
+            // The symbol and decimals come from the host and are not verified by the device,
+            // which is flagged to the user.
+            Some(token_metadata) => {
+                let value = Amount {
+                    unit: &token_metadata.symbol,
+                    decimals: token_metadata.decimals as _,
+                    value: erc20_value,
+                }
+                .format();
+                (format!("{}\n(unverified token)", value), value)
+            }
+            None => ("Unknown token".into(), "Unknown amount".into()),
+        },
     };
     transaction::verify_recipient(&recipient_address, &formatted_value).await?;
     transaction::verify_total_fee(&formatted_total, &formatted_fee, None).await?;
@@ -261,6 +304,32 @@ async fn verify_standard_transaction(
             ..Default::default()
         })
         .await?;
+
+        // Summarize the data so the user has something compact to cross-check against what the
+        // dApp/host shows: the 4-byte function selector, the calldata length and the keccak256 of
+        // the full data.
+        let body = if request.data().len() >= 4 {
+            format!(
+                "Selector: {}\nLength: {} bytes\nkeccak256:\n{}",
+                hex::encode(&request.data()[..4]),
+                request.data().len(),
+                hex::encode(Keccak256::digest(request.data())),
+            )
+        } else {
+            format!(
+                "Length: {} bytes\nkeccak256:\n{}",
+                request.data().len(),
+                hex::encode(Keccak256::digest(request.data())),
+            )
+        };
+        confirm::confirm(&confirm::Params {
+            title: "Data summary",
+            body: &body,
+            scrollable: true,
+            longtouch: true,
+            ..Default::default()
+        })
+        .await?;
     }
 
     let address = super::address::from_pubkey_hash(&recipient);
@@ -320,6 +389,14 @@ pub async fn process(request: &Transaction<'_>) -> Result<Response, Error> {
             if legacy.gas_price.len() > 16 {
                 return Err(Error::InvalidInput);
             }
+            for entry in legacy.access_list.iter() {
+                if entry.address.len() != 20 {
+                    return Err(Error::InvalidInput);
+                }
+                if entry.storage_keys.iter().any(|key| key.len() != 32) {
+                    return Err(Error::InvalidInput);
+                }
+            }
         }
         Transaction::Eip1559(eip1559) => {
             if let [0, ..] = &eip1559.max_priority_fee_per_gas[..] {
@@ -340,10 +417,39 @@ pub async fn process(request: &Transaction<'_>) -> Result<Response, Error> {
         return Err(Error::InvalidInput);
     }
 
-    let verification_result = if let Some((erc20_recipient, erc20_value)) = parse_erc20(request) {
-        verify_erc20_transaction(request, &params, erc20_recipient, erc20_value).await
-    } else {
-        verify_standard_transaction(request, &params).await
+    // The access list only influences gas costs, so it is not verified in detail - the user is
+    // merely informed that the transaction carries one.
+    if let Transaction::Legacy(legacy) = request {
+        match legacy.access_list.len() {
+            0 => (),
+            n => {
+                confirm::confirm(&confirm::Params {
+                    title: params.name,
+                    body: &format!(
+                        "Includes access\nlist, {} {}",
+                        n,
+                        if n == 1 { "entry" } else { "entries" }
+                    ),
+                    accept_is_nextarrow: true,
+                    ..Default::default()
+                })
+                .await?;
+            }
+        }
+    }
+
+    let verification_result = match (parse_erc20(request), request.token_metadata()) {
+        // Host-provided metadata for a contract other than the one being called is not applied -
+        // the raw transaction data is shown instead.
+        (Some(_), Some(token_metadata))
+            if token_metadata.contract_address.as_slice() != request.recipient() =>
+        {
+            verify_standard_transaction(request, &params).await
+        }
+        (Some((erc20_recipient, erc20_value)), _) => {
+            verify_erc20_transaction(request, &params, erc20_recipient, erc20_value).await
+        }
+        _ => verify_standard_transaction(request, &params).await,
     };
     match verification_result {
         Ok(()) => status::status("Transaction\nconfirmed", true).await,
@@ -356,7 +462,13 @@ pub async fn process(request: &Transaction<'_>) -> Result<Response, Error> {
     }
 
     let hash: [u8; 32] = match request {
-        Transaction::Legacy(legacy) => hash_legacy(params.chain_id, legacy)?,
+        Transaction::Legacy(legacy) => {
+            if legacy.access_list.is_empty() {
+                hash_legacy(params.chain_id, legacy)?
+            } else {
+                hash_eip2930(params.chain_id, legacy)?
+            }
+        }
         Transaction::Eip1559(eip1559) => hash_eip1559(eip1559)?,
     };
 
@@ -490,6 +602,8 @@ mod tests {
                 data: b"".to_vec(),
                 host_nonce_commitment: None,
                 chain_id: 0,
+                token_metadata: None,
+                access_list: vec![],
             }))),
             Ok(Response::Sign(pb::EthSignResponse {
                 signature: b"\xc3\xae\x24\xc1\x67\xe2\x16\xcf\xb7\x5c\x72\xb5\xe0\x3e\xf9\x7a\xcc\x2b\x60\x7f\x3a\xcf\x63\x86\x5f\x80\x96\x0f\x76\xf6\x56\x47\x0f\x8e\x23\xf1\xd2\x78\x8f\xb0\x07\x0e\x28\xc2\xa5\xc8\xaa\xf1\x5b\x5d\xbf\x30\xb4\x09\x07\xff\x6c\x50\x68\xfd\xcb\xc1\x1a\x2d\x00"
@@ -508,6 +622,7 @@ mod tests {
                 data: b"".to_vec(),
                 host_nonce_commitment: None,
                 chain_id: 1,
+                token_metadata: None,
             }))),
             Ok(Response::Sign(pb::EthSignResponse {
                 signature: b"\x28\x91\x11\x77\x0d\xc0\x67\x89\x57\x80\xde\x3e\x9b\x30\x45\x4e\x33\x1b\xa6\x66\x1f\x04\x6e\x9e\x26\x43\x15\x76\xd7\xf0\x8a\x49\x6f\xfe\x6d\xef\xfb\x07\xdd\x8d\x47\x13\xd8\xc5\x23\xb6\xc3\x3b\x53\xdd\x6e\xf2\xdc\x9c\x39\x4d\x6e\x21\xf6\x43\x07\xd2\xbc\xf0\x01"
@@ -562,6 +677,8 @@ mod tests {
             data: b"".to_vec(),
             host_nonce_commitment: None,
             chain_id: 0,
+            token_metadata: None,
+            access_list: vec![],
         })))
         .is_ok());
         assert_eq!(unsafe { UI_COUNTER }, 1);
@@ -613,12 +730,115 @@ mod tests {
             data: b"".to_vec(),
             host_nonce_commitment: None,
             chain_id: 1,
+            token_metadata: None,
         })))
         .is_ok());
         assert_eq!(unsafe { UI_COUNTER }, 1);
     }
 
     /// Standard ETH transaction on an unusual keypath (Goerli on mainnet keypath)
+    /// The total shown on the fee confirmation is the maximum possible debit:
+    /// `value + gas limit * gas price`. Check the formatting at the edges - rounding, fees of a
+    /// few wei and absurdly large gas limits/prices (the latter cannot overflow as the
+    /// computation uses big integers; the formatted number is truncated with an ellipsis).
+    #[test]
+    fn test_total_includes_max_fee() {
+        const KEYPATH: &[u32] = &[44 + HARDENED, 60 + HARDENED, 0 + HARDENED, 0, 0];
+
+        let request = pb::EthSignRequest {
+            coin: pb::EthCoin::Eth as _,
+            keypath: KEYPATH.to_vec(),
+            nonce: b"\x1f\xdc".to_vec(),
+            // 1 gwei
+            gas_price: b"\x3b\x9a\xca\x00".to_vec(),
+            // 21000
+            gas_limit: b"\x52\x08".to_vec(),
+            recipient:
+                b"\x04\xf2\x64\xcf\x34\x44\x03\x13\xb4\xa0\x19\x2a\x35\x28\x14\xfb\xe9\x27\xb8\x85"
+                    .to_vec(),
+            // 0.5 ETH
+            value: b"\x06\xf0\x5b\x59\xd3\xb2\x00\x00".to_vec(),
+            data: b"".to_vec(),
+            host_nonce_commitment: None,
+            chain_id: 0,
+            token_metadata: None,
+            access_list: vec![],
+        };
+
+        // Rounding: fee = 21000 gwei = 0.000021 ETH.
+        mock(Data {
+            ui_transaction_address_create: Some(Box::new(|amount, _address| {
+                assert_eq!(amount, "0.5 ETH");
+                true
+            })),
+            ui_transaction_fee_create: Some(Box::new(|total, fee, longtouch| {
+                assert_eq!(total, "0.500021 ETH");
+                assert_eq!(fee, "0.000021 ETH");
+                assert!(longtouch);
+                true
+            })),
+            ..Default::default()
+        });
+        mock_unlocked();
+        assert!(block_on(process(&Transaction::Legacy(&request))).is_ok());
+
+        // Fee of a single wei: too small to display in full, but still part of the total.
+        mock(Data {
+            ui_transaction_address_create: Some(Box::new(|amount, _address| {
+                // 1 wei.
+                assert_eq!(amount, "0.00000000000... ETH");
+                true
+            })),
+            ui_transaction_fee_create: Some(Box::new(|total, fee, longtouch| {
+                // 2 wei.
+                assert_eq!(total, "0.00000000000... ETH");
+                assert_eq!(fee, "0.00000000000... ETH");
+                assert!(!longtouch);
+                true
+            })),
+            // The fee is 100% of the send amount.
+            ui_confirm_create: Some(Box::new(|params| {
+                assert_eq!(params.title, "High fee");
+                assert_eq!(params.body, "The fee is 100.0%\nthe send amount.\nProceed?");
+                assert!(params.longtouch);
+                true
+            })),
+            ..Default::default()
+        });
+        mock_unlocked();
+        assert!(block_on(process(&Transaction::Legacy(&pb::EthSignRequest {
+            gas_price: b"\x01".to_vec(),
+            gas_limit: b"\x01".to_vec(),
+            value: b"\x01".to_vec(),
+            ..request.clone()
+        })))
+        .is_ok());
+
+        // Absurd gas limit and gas price (both the 16 byte maximum): the total is the fee of
+        // (2^128-1)^2 wei, truncated for display.
+        mock(Data {
+            ui_transaction_address_create: Some(Box::new(|amount, _address| {
+                assert_eq!(amount, "0 ETH");
+                true
+            })),
+            ui_transaction_fee_create: Some(Box::new(|total, fee, longtouch| {
+                assert_eq!(total, "1157920892373... ETH");
+                assert_eq!(fee, "1157920892373... ETH");
+                assert!(longtouch);
+                true
+            })),
+            ..Default::default()
+        });
+        mock_unlocked();
+        assert!(block_on(process(&Transaction::Legacy(&pb::EthSignRequest {
+            gas_price: vec![0xff; 16],
+            gas_limit: vec![0xff; 16],
+            value: b"".to_vec(),
+            ..request.clone()
+        })))
+        .is_ok());
+    }
+
     #[test]
     pub fn test_process_warn_unusual_keypath() {
         const KEYPATH: &[u32] = &[44 + HARDENED, 60 + HARDENED, 0 + HARDENED, 0, 0];
@@ -666,6 +886,8 @@ mod tests {
             data: b"".to_vec(),
             host_nonce_commitment: None,
             chain_id: 5,
+            token_metadata: None,
+            access_list: vec![],
         })))
         .unwrap();
         assert_eq!(unsafe { CONFIRM_COUNTER }, 1);
@@ -687,6 +909,12 @@ mod tests {
                         assert_eq!(params.display_size, 7); // length of "foo bar"
                         assert!(params.accept_is_nextarrow);
                     }
+                    3 => {
+                        assert_eq!(params.title, "Data summary");
+                        assert_eq!(params.body, "Selector: 666f6f20\nLength: 7 bytes\nkeccak256:\n737fe0cb366697912e27136f93dfb531c58bab1b09c40842d999110387c86b54");
+                        assert!(params.scrollable);
+                        assert!(params.longtouch);
+                    }
                     _ => panic!("too many user confirmations"),
                 }
                 unsafe { CONFIRM_COUNTER += 1 }
@@ -718,6 +946,8 @@ mod tests {
                 data: b"foo bar".to_vec(),
                 host_nonce_commitment: None,
                 chain_id: 0,
+                token_metadata: None,
+                access_list: vec![],
             }))),
             Ok(Response::Sign(pb::EthSignResponse {
                 signature: b"\x7d\x3f\x37\x13\xe3\xcf\x10\x82\x79\x1d\x5c\x0f\xc6\x8e\xc2\x9e\xaf\xf5\xe1\xee\x84\x67\xa8\xec\x54\x7d\xc7\x96\xe8\x5a\x79\x04\x2b\x7c\x01\x69\x2f\xb7\x2f\x55\x76\xab\x50\xdc\xaa\x62\x1a\xd1\xee\xab\xd9\x97\x59\x73\xb8\x62\x56\xf4\x0c\x6f\x85\x50\xef\x44\x00"
@@ -742,6 +972,12 @@ mod tests {
                         assert_eq!(params.display_size, 7); // length of "foo bar"
                         assert!(params.accept_is_nextarrow);
                     }
+                    3 => {
+                        assert_eq!(params.title, "Data summary");
+                        assert_eq!(params.body, "Selector: 666f6f20\nLength: 7 bytes\nkeccak256:\n737fe0cb366697912e27136f93dfb531c58bab1b09c40842d999110387c86b54");
+                        assert!(params.scrollable);
+                        assert!(params.longtouch);
+                    }
                     _ => panic!("too many user confirmations"),
                 }
                 unsafe { CONFIRM_COUNTER += 1 }
@@ -773,6 +1009,7 @@ mod tests {
                 data: b"foo bar".to_vec(),
                 host_nonce_commitment: None,
                 chain_id: 1,
+                token_metadata: None,
             }))),
             Ok(Response::Sign(pb::EthSignResponse {
                 signature: b"\xc5\xd9\x63\x9a\x77\x8a\x34\x15\xf6\x3a\x11\xc0\x3a\x58\xbe\xde\x6b\x3c\xaf\xff\x4f\x2c\xe6\xea\x16\x41\x1e\x76\xfb\xa9\x46\xf7\x21\x66\xf0\x9e\x31\x3c\x07\xe7\x8b\x7b\x1f\xff\x87\x45\x0c\x43\x21\x17\x0c\x02\xdf\x2d\x36\xc4\x4c\x3a\x02\x1a\xbf\x20\x54\x60\x01"
@@ -783,6 +1020,122 @@ mod tests {
 
     /// ERC20 transaction: recipient is an ERC20 contract address, and
     /// the data field contains an ERC20 transfer method invocation.
+    /// EIP-2930 transaction: the user is informed about the access list and the sighash is
+    /// computed as a type 1 transaction. Malformed access list entries are rejected.
+    #[test]
+    pub fn test_process_access_list_transaction() {
+        const KEYPATH: &[u32] = &[44 + HARDENED, 60 + HARDENED, 0 + HARDENED, 0, 0];
+
+        let request = pb::EthSignRequest {
+            coin: pb::EthCoin::Eth as _,
+            keypath: KEYPATH.to_vec(),
+            nonce: b"\x1f\xdc".to_vec(),
+            gas_price: b"\x01\x65\xa0\xbc\x00".to_vec(),
+            gas_limit: b"\x52\x08".to_vec(),
+            recipient: b"\x04\xf2\x64\xcf\x34\x44\x03\x13\xb4\xa0\x19\x2a\x35\x28\x14\xfb\xe9\x27\xb8\x85".to_vec(),
+            value: b"\x07\x5c\xf1\x25\x9e\x9c\x40\x00".to_vec(),
+            data: b"".to_vec(),
+            host_nonce_commitment: None,
+            chain_id: 0,
+            token_metadata: None,
+            access_list: vec![
+                pb::EthAccessListEntry {
+                    address: vec![0x11; 20],
+                    storage_keys: vec![vec![0u8; 32], vec![1u8; 32]],
+                },
+                pb::EthAccessListEntry {
+                    address: vec![0x22; 20],
+                    storage_keys: vec![],
+                },
+            ],
+        };
+
+        static mut CONFIRM_COUNTER: u32 = 0;
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|params| {
+                match unsafe {
+                    CONFIRM_COUNTER += 1;
+                    CONFIRM_COUNTER
+                } {
+                    1 => {
+                        assert_eq!(params.title, "Ethereum");
+                        assert_eq!(params.body, "Includes access\nlist, 2 entries");
+                        assert!(params.accept_is_nextarrow);
+                    }
+                    _ => panic!("too many dialogs"),
+                }
+                true
+            })),
+            ui_transaction_address_create: Some(Box::new(|amount, address| {
+                assert_eq!(amount, "0.530564 ETH");
+                assert_eq!(address, "0x04F264Cf34440313B4A0192A352814FBe927b885");
+                true
+            })),
+            ui_transaction_fee_create: Some(Box::new(|total, fee, longtouch| {
+                assert_eq!(total, "0.53069 ETH");
+                assert_eq!(fee, "0.000126 ETH");
+                assert!(longtouch);
+                true
+            })),
+            ..Default::default()
+        });
+        mock_unlocked();
+        assert!(block_on(process(&Transaction::Legacy(&request))).is_ok());
+        assert_eq!(unsafe { CONFIRM_COUNTER }, 1);
+
+        // Single entry: singular wording.
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|params| {
+                assert_eq!(params.body, "Includes access\nlist, 1 entry");
+                true
+            })),
+            ui_transaction_address_create: Some(Box::new(|_, _| true)),
+            ui_transaction_fee_create: Some(Box::new(|_, _, _| true)),
+            ..Default::default()
+        });
+        mock_unlocked();
+        assert!(block_on(process(&Transaction::Legacy(&pb::EthSignRequest {
+            access_list: vec![pb::EthAccessListEntry {
+                address: vec![0x11; 20],
+                storage_keys: vec![],
+            }],
+            ..request.clone()
+        })))
+        .is_ok());
+
+        // Address must be exactly 20 bytes.
+        mock(Data {
+            ..Default::default()
+        });
+        mock_unlocked();
+        assert_eq!(
+            block_on(process(&Transaction::Legacy(&pb::EthSignRequest {
+                access_list: vec![pb::EthAccessListEntry {
+                    address: vec![0x11; 19],
+                    storage_keys: vec![],
+                }],
+                ..request.clone()
+            }))),
+            Err(Error::InvalidInput)
+        );
+
+        // Storage keys must be exactly 32 bytes.
+        mock(Data {
+            ..Default::default()
+        });
+        mock_unlocked();
+        assert_eq!(
+            block_on(process(&Transaction::Legacy(&pb::EthSignRequest {
+                access_list: vec![pb::EthAccessListEntry {
+                    address: vec![0x11; 20],
+                    storage_keys: vec![vec![0u8; 31]],
+                }],
+                ..request.clone()
+            }))),
+            Err(Error::InvalidInput)
+        );
+    }
+
     #[test]
     pub fn test_process_standard_erc20_transaction() {
         const KEYPATH: &[u32] = &[44 + HARDENED, 60 + HARDENED, 0 + HARDENED, 0, 0];
@@ -814,6 +1167,8 @@ mod tests {
                 data: b"\xa9\x05\x9c\xbb\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\xe6\xce\x0a\x09\x2a\x99\x70\x0c\xd4\xcc\xcc\xbb\x1f\xed\xc3\x9c\xf5\x3e\x63\x30\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x03\x65\xc0\x40".to_vec(),
                 host_nonce_commitment: None,
                 chain_id: 1,
+                token_metadata: None,
+                access_list: vec![],
             }))),
             Ok(Response::Sign(pb::EthSignResponse {
                 signature: b"\x67\x4e\x9a\x01\x70\xee\xe0\xca\x8c\x40\x6e\xc9\xa7\xdf\x2e\x3a\x6b\xdd\x17\x9c\xf6\x93\x85\x80\x0e\x1f\xd3\x78\xe7\xcf\xb1\x9c\x4d\x55\x16\x2c\x54\x7b\x04\xd1\x81\x8e\x43\x90\x16\x91\xae\xc9\x88\xef\x75\xcd\x67\xd9\xbb\x30\x1d\x14\x90\x2f\xd6\xe6\x92\x92\x01"
@@ -832,6 +1187,7 @@ mod tests {
                 data: b"\xa9\x05\x9c\xbb\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\xe6\xce\x0a\x09\x2a\x99\x70\x0c\xd4\xcc\xcc\xbb\x1f\xed\xc3\x9c\xf5\x3e\x63\x30\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x03\x65\xc0\x40".to_vec(),
                 host_nonce_commitment: None,
                 chain_id: 1,
+                token_metadata: None,
             }))),
             Ok(Response::Sign(pb::EthSignResponse {
                 signature: b"\x31\x62\x48\x78\x80\xab\xde\xa1\xf3\x52\xd9\xa4\xe3\xd5\x60\x66\xf1\x22\xf0\x4f\xf1\x12\x11\x7c\x8c\xa3\xcd\x22\x0f\x16\x66\x30\x2d\xac\xd5\xe5\xe8\xda\x4c\xd3\x97\x04\xe3\x34\x43\xa9\xa7\xf3\x26\x02\xd3\x32\xbb\x52\x56\x7c\x2e\x34\xaa\xfe\x9e\xd4\x8f\xeb\x01"
@@ -872,6 +1228,8 @@ mod tests {
                 data: b"\xa9\x05\x9c\xbb\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x85\x7b\x3d\x96\x9e\xac\xb7\x75\xa9\xf7\x9c\xab\xc6\x2e\xc4\xbb\x1d\x1c\xd6\x0e\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x98\xa6\x3c\xbe\xb8\x59\xd0\x27\xb0".to_vec(),
                 host_nonce_commitment: None,
                 chain_id: 0,
+                token_metadata: None,
+                access_list: vec![],
             }))),
             Ok(Response::Sign(pb::EthSignResponse {
                 signature: b"\xec\x6e\x53\x0c\x8e\xe2\x54\x34\xfc\x44\x0e\x9a\xc0\xf8\x88\xe9\xc6\x3c\xf0\x7e\xbc\xf1\xc2\xf8\xa8\x3e\x2e\x8c\x39\x83\x2c\x55\x15\x12\x71\x6f\x6e\x1a\x8b\x66\xce\x38\x11\xa7\x26\xbc\xb2\x44\x66\x4e\xf2\x6f\x98\xee\x35\xc0\xc9\xdb\x4c\xaa\xb0\x73\x98\x56\x00"
@@ -890,6 +1248,7 @@ mod tests {
                 data: b"\xa9\x05\x9c\xbb\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x85\x7b\x3d\x96\x9e\xac\xb7\x75\xa9\xf7\x9c\xab\xc6\x2e\xc4\xbb\x1d\x1c\xd6\x0e\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x98\xa6\x3c\xbe\xb8\x59\xd0\x27\xb0".to_vec(),
                 host_nonce_commitment: None,
                 chain_id: 1,
+                token_metadata: None,
             }))),
             Ok(Response::Sign(pb::EthSignResponse {
                 signature: b"\x82\x03\xd8\x0b\x60\x0d\xce\x8e\x77\xcd\xcb\x11\x9d\x45\xdb\x7f\x60\xd7\xca\x34\xe7\x36\x91\x40\xe9\x2d\x93\x91\x92\x21\xf8\x5a\x0a\x11\x9d\x24\x64\xdf\xab\x65\x83\x30\x95\xc1\x27\x63\xfe\xd3\x7c\x07\x2f\xeb\x29\x61\x0e\x14\x37\xf3\x88\x95\x8d\x77\x56\x28\x01"
@@ -898,6 +1257,111 @@ mod tests {
         );
     }
 
+    /// ERC20 transfer to a token not in the built-in list, with the host providing the token
+    /// metadata. The amount is formatted with the provided symbol/decimals, marked as unverified.
+    #[test]
+    pub fn test_process_erc20_transaction_token_metadata() {
+        const KEYPATH: &[u32] = &[44 + HARDENED, 60 + HARDENED, 0 + HARDENED, 0, 0];
+        // Not in the built-in token list.
+        const CONTRACT: &[u8; 20] =
+            b"\x9c\x23\xd6\x7a\xea\x7b\x95\xd8\x09\x42\xe3\x83\x6b\xcd\xf7\xe7\x08\xa7\x47\xc1";
+
+        let request = pb::EthSignRequest {
+            coin: pb::EthCoin::Eth as _,
+            keypath: KEYPATH.to_vec(),
+            nonce: b"\xb9".to_vec(),
+            gas_price: b"\x3b\x9a\xca\x00".to_vec(),
+            gas_limit: b"\x01\x09\x85".to_vec(),
+            recipient: CONTRACT.to_vec(),
+            value: b"".to_vec(),
+            // transfer(0x857b3d969eacb775a9f79cabc62ec4bb1d1cd60e, 150.5e18)
+            data: b"\xa9\x05\x9c\xbb\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x85\x7b\x3d\x96\x9e\xac\xb7\x75\xa9\xf7\x9c\xab\xc6\x2e\xc4\xbb\x1d\x1c\xd6\x0e\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x08\x28\x9b\x68\x9d\xe8\x4a\x00\x00".to_vec(),
+            host_nonce_commitment: None,
+            chain_id: 0,
+            token_metadata: Some(pb::EthTokenMetadata {
+                contract_address: CONTRACT.to_vec(),
+                symbol: "FOO".into(),
+                decimals: 18,
+            }),
+            access_list: vec![],
+        };
+
+        mock(Data {
+            ui_transaction_address_create: Some(Box::new(|amount, address| {
+                assert_eq!(amount, "150.5 FOO\n(unverified token)");
+                assert_eq!(address, "0x857B3D969eAcB775a9f79cabc62Ec4bB1D1cd60e");
+                true
+            })),
+            ui_transaction_fee_create: Some(Box::new(|total, fee, longtouch| {
+                assert_eq!(total, "150.5 FOO");
+                assert_eq!(fee, "0.000067973 ETH");
+                assert!(longtouch);
+                true
+            })),
+            ..Default::default()
+        });
+        mock_unlocked();
+        assert!(block_on(process(&Transaction::Legacy(&request))).is_ok());
+
+        // Metadata for a token in the built-in list is ignored; the verified params win.
+        let mut listed_request = request.clone();
+        listed_request.recipient =
+            b"\xda\xc1\x7f\x95\x8d\x2e\xe5\x23\xa2\x20\x62\x06\x99\x45\x97\xc1\x3d\x83\x1e\xc7"
+                .to_vec();
+        listed_request.chain_id = 1;
+        // transfer(0xe6ce0a092a99700cd4ccccbb1fedc39cf53e6330, 57e6)
+        listed_request.data = b"\xa9\x05\x9c\xbb\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\xe6\xce\x0a\x09\x2a\x99\x70\x0c\xd4\xcc\xcc\xbb\x1f\xed\xc3\x9c\xf5\x3e\x63\x30\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x03\x65\xc0\x40".to_vec();
+        listed_request.token_metadata = Some(pb::EthTokenMetadata {
+            contract_address: listed_request.recipient.clone(),
+            symbol: "FAKE".into(),
+            decimals: 0,
+        });
+        mock(Data {
+            ui_transaction_address_create: Some(Box::new(|amount, _address| {
+                assert_eq!(amount, "57 USDT");
+                true
+            })),
+            ui_transaction_fee_create: Some(Box::new(|total, _fee, _longtouch| {
+                assert_eq!(total, "57 USDT");
+                true
+            })),
+            ..Default::default()
+        });
+        mock_unlocked();
+        assert!(block_on(process(&Transaction::Legacy(&listed_request))).is_ok());
+
+        // Metadata for a contract other than the one called: fall back to the raw data display.
+        let mut mismatched_request = request.clone();
+        mismatched_request.token_metadata = Some(pb::EthTokenMetadata {
+            contract_address: vec![0xaa; 20],
+            symbol: "FOO".into(),
+            decimals: 18,
+        });
+        static mut CONFIRM_COUNTER: u32 = 0;
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|params| {
+                match unsafe { CONFIRM_COUNTER } {
+                    0 | 1 => assert_eq!(params.title, "Unknown\ncontract"),
+                    2 => assert_eq!(params.title, "Transaction\ndata"),
+                    3 => assert_eq!(params.title, "Data summary"),
+                    _ => panic!("too many user confirmations"),
+                }
+                unsafe { CONFIRM_COUNTER += 1 }
+                true
+            })),
+            ui_transaction_address_create: Some(Box::new(|amount, address| {
+                assert_eq!(amount, "0 ETH");
+                assert_eq!(address, "0x9c23D67aeA7b95D80942e3836BCDf7E708a747C1");
+                true
+            })),
+            ui_transaction_fee_create: Some(Box::new(|_total, _fee, _longtouch| true)),
+            ..Default::default()
+        });
+        mock_unlocked();
+        assert!(block_on(process(&Transaction::Legacy(&mismatched_request))).is_ok());
+        assert_eq!(unsafe { CONFIRM_COUNTER }, 4);
+    }
+
     #[test]
     pub fn test_process_unhappy() {
         let valid_request = pb::EthSignRequest {
@@ -913,6 +1377,8 @@ mod tests {
             data: b"".to_vec(),
             host_nonce_commitment: None,
             chain_id: 0,
+            token_metadata: None,
+            access_list: vec![],
         };
 
         {
@@ -1051,6 +1517,7 @@ mod tests {
             data: b"".to_vec(),
             host_nonce_commitment: None,
             chain_id: 1,
+            token_metadata: None,
         };
 
         {
@@ -1146,6 +1613,8 @@ mod tests {
                 data: b"".to_vec(),
                 host_nonce_commitment: None,
                 chain_id: 12345,
+                token_metadata: None,
+                access_list: vec![],
             }))),
             Ok(Response::Sign(pb::EthSignResponse {
                 signature: b"\xb1\xb6\xb3\x4e\x15\xa0\x30\x9d\xdc\x26\x03\xdf\x4c\x40\x38\xea\x86\x65\xed\x85\xd3\xf2\xc8\x1e\x7f\x1a\xa0\x25\x4b\x21\x38\x72\x0d\x60\x1f\x42\x19\xfb\x29\xab\x3d\x5f\xf7\x76\xea\xe1\xbe\x15\x26\xb4\x67\xe2\xb0\xe6\x30\xe8\xe6\x34\xa4\xda\x4a\x82\x2e\x39\x00".to_vec()
@@ -17,15 +17,21 @@ use super::Error;
 
 use bitbox02::keystore;
 
-use crate::workflow::verify_message;
+use crate::workflow::{confirm, verify_message};
 
 use pb::eth_response::Response;
 
+use alloc::string::String;
 use alloc::vec::Vec;
 use core::convert::TryInto;
 
 use sha3::digest::Digest;
 
+/// Messages at least this long (e.g. SIWE login payloads, which span many pages) additionally get
+/// their keccak256 hash displayed after the paged message, as a compact anchor the user can
+/// compare against the host.
+const HASH_ANCHOR_THRESHOLD: usize = 1024;
+
 /// Process a sign message request.
 ///
 /// The result contains a 65 byte signature. The first 64 bytes are the secp256k1 signature in
@@ -50,6 +56,25 @@ pub async fn process(request: &pb::EthSignMessageRequest) -> Result<Response, Er
 
     verify_message::verify(&request.msg).await?;
 
+    if request.msg.len() >= HASH_ANCHOR_THRESHOLD {
+        let hash_hex = hex::encode(sha3::Keccak256::digest(&request.msg));
+        // Chunked into groups of 8 chars for readability.
+        let hash_chunked: String = hash_hex
+            .as_bytes()
+            .chunks(8)
+            .map(|chunk| core::str::from_utf8(chunk).unwrap())
+            .collect::<Vec<&str>>()
+            .join(" ");
+        confirm::confirm(&confirm::Params {
+            title: "Message hash",
+            body: &format!("keccak256:\n{}", hash_chunked),
+            scrollable: true,
+            longtouch: true,
+            ..Default::default()
+        })
+        .await?;
+    }
+
     // Construct message to be signed. There is no standard for this. We match what MyEtherWallet,
     // Trezor, etc. do, e.g.:
     // https://github.com/ethereumjs/ethereumjs-util/blob/dd2882d790c1d3b50b75bee6f88031433cbd5bef/src/signature.ts#L140
@@ -144,6 +169,70 @@ mod tests {
         );
     }
 
+    /// A long message (SIWE-sized) is paged across screens and anchored with its keccak256 hash.
+    #[test]
+    pub fn test_process_long_message() {
+        let msg: Vec<u8> = [
+            [b'a'; 512].as_slice(),
+            b"\n",
+            &[b'b'; 512],
+            b"\n",
+            &[b'c'; 512],
+            b"\n",
+            &[b'd'; 512],
+        ]
+        .concat();
+        assert_eq!(msg.len(), 2051);
+
+        static mut CONFIRM_COUNTER: u32 = 0;
+
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|params| {
+                match unsafe {
+                    CONFIRM_COUNTER += 1;
+                    CONFIRM_COUNTER
+                } {
+                    1 => {
+                        assert_eq!(params.title, "Ethereum");
+                        assert_eq!(params.body, EXPECTED_ADDRESS);
+                    }
+                    2 => {
+                        assert_eq!(params.title, "Sign 1/4");
+                        assert_eq!(params.body, "a".repeat(512));
+                        assert!(params.scrollable);
+                        assert!(params.accept_is_nextarrow);
+                    }
+                    3 => assert_eq!(params.title, "Sign 2/4"),
+                    4 => assert_eq!(params.title, "Sign 3/4"),
+                    5 => {
+                        assert_eq!(params.title, "Sign 4/4");
+                        assert_eq!(params.body, "d".repeat(512));
+                        assert!(params.longtouch);
+                    }
+                    6 => {
+                        assert_eq!(params.title, "Message hash");
+                        assert_eq!(params.body, "keccak256:\n79589612 7a12f583 3176270e e7f4fc42 977a619b def51959 0e3f7b69 2bfc9d88");
+                        assert!(params.scrollable);
+                        assert!(params.longtouch);
+                    }
+                    _ => panic!("too many user confirmations"),
+                }
+                true
+            })),
+            ..Default::default()
+        });
+        mock_unlocked();
+        assert!(block_on(process(&pb::EthSignMessageRequest {
+            coin: pb::EthCoin::Eth as _,
+            keypath: KEYPATH.to_vec(),
+            msg,
+            host_nonce_commitment: None,
+            chain_id: 0,
+        }))
+        .is_ok());
+        assert_eq!(unsafe { CONFIRM_COUNTER }, 6);
+    }
+
     #[test]
     pub fn test_process_warn_unusual_keypath() {
         const SIGNATURE: [u8; 64] = [b'1'; 64];
@@ -0,0 +1,92 @@
+// Copyright 2024 Shift Crypto AG
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::Error;
+use crate::pb;
+
+use pb::response::Response;
+
+use crate::workflow::confirm;
+
+pub async fn process(
+    &pb::SetAntikleptoRequiredRequest { required }: &pb::SetAntikleptoRequiredRequest,
+) -> Result<Response, Error> {
+    let params = confirm::Params {
+        title: if required { "Enable" } else { "Disable" },
+        body: "Require\nanti-klepto\nprotocol",
+        longtouch: true,
+        ..Default::default()
+    };
+
+    confirm::confirm(&params).await?;
+
+    if bitbox02::memory::set_antiklepto_required(required).is_err() {
+        return Err(Error::Memory);
+    }
+
+    Ok(Response::Success(pb::Success {}))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::bb02_async::block_on;
+    use alloc::boxed::Box;
+    use bitbox02::testing::{mock, mock_memory, Data};
+
+    #[test]
+    pub fn test_set_antiklepto_required() {
+        // All good.
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|params| {
+                assert_eq!(params.body, "Require\nanti-klepto\nprotocol");
+                true
+            })),
+            ..Default::default()
+        });
+        mock_memory();
+        // Enable:
+        assert_eq!(
+            block_on(process(&pb::SetAntikleptoRequiredRequest {
+                required: true
+            })),
+            Ok(Response::Success(pb::Success {}))
+        );
+        assert!(bitbox02::memory::is_antiklepto_required());
+        // Disable:
+        assert_eq!(
+            block_on(process(&pb::SetAntikleptoRequiredRequest {
+                required: false
+            })),
+            Ok(Response::Success(pb::Success {}))
+        );
+        assert!(!bitbox02::memory::is_antiklepto_required());
+
+        // User aborted confirmation.
+        mock(Data {
+            ui_confirm_create: Some(Box::new(|params| {
+                assert_eq!(params.body, "Require\nanti-klepto\nprotocol");
+                false
+            })),
+            ..Default::default()
+        });
+        assert_eq!(
+            block_on(process(&pb::SetAntikleptoRequiredRequest {
+                required: true
+            })),
+            Err(Error::UserAbort)
+        );
+    }
+}
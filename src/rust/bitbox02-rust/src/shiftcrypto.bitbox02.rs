@@ -3,6 +3,11 @@
 pub struct PubResponse {
     #[prost(string, tag = "1")]
     pub r#pub: ::prost::alloc::string::String,
+    /// scriptPubKey corresponding to an address in `pub`, so hosts do not have to re-derive it for
+    /// watch-lists or PSBT construction. Only set for coins that use output scripts (e.g. Bitcoin);
+    /// empty for xpub requests.
+    #[prost(bytes = "vec", tag = "2")]
+    pub pub_key_script: ::prost::alloc::vec::Vec<u8>,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -194,6 +199,14 @@ pub struct SetPasswordRequest {
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SetAntikleptoRequiredRequest {
+    /// If true, every signature request must use the anti-klepto protocol; plain signing is
+    /// rejected.
+    #[prost(bool, tag = "1")]
+    pub required: bool,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct AntiKleptoHostNonceCommitment {
     #[prost(bytes = "vec", tag = "1")]
     pub commitment: ::prost::alloc::vec::Vec<u8>,
@@ -213,7 +226,7 @@ pub struct AntiKleptoSignatureRequest {
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct BtcScriptConfig {
-    #[prost(oneof = "btc_script_config::Config", tags = "1, 2, 3")]
+    #[prost(oneof = "btc_script_config::Config", tags = "1, 2, 3, 4, 5")]
     pub config: ::core::option::Option<btc_script_config::Config>,
 }
 /// Nested message and enum types in `BTCScriptConfig`.
@@ -225,6 +238,9 @@ pub mod btc_script_config {
         pub threshold: u32,
         /// xpubs are acount-level xpubs. Addresses are going to be derived from it using: `m/<change>/<receive>`.
         /// The number of xpubs defines the number of cosigners.
+        /// The derived per-address public keys are sorted lexicographically before building the
+        /// multisig script (BIP-67), matching `sortedmulti()` descriptors, regardless of the order
+        /// of the xpubs here.
         #[prost(message, repeated, tag = "2")]
         pub xpubs: ::prost::alloc::vec::Vec<super::XPub>,
         /// Index to the xpub of our keystore in xpubs. The keypath to it is provided via
@@ -285,6 +301,34 @@ pub mod btc_script_config {
         #[prost(message, repeated, tag = "2")]
         pub keys: ::prost::alloc::vec::Vec<super::KeyOriginInfo>,
     }
+    /// A MuSig2 (BIP-327) aggregated key. All cosigners must sign (n-of-n). The aggregated key is
+    /// tweaked like a regular taproot key (BIP-86) and spent using the key path.
+    #[allow(clippy::derive_partial_eq_without_eq)]
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct Musig2 {
+        /// xpubs are account-level xpubs. Addresses are going to be derived from it using:
+        /// `m/<change>/<receive>`. The number of xpubs defines the number of cosigners. The order is
+        /// fixed, as the BIP-327 key aggregation depends on it.
+        #[prost(message, repeated, tag = "1")]
+        pub xpubs: ::prost::alloc::vec::Vec<super::XPub>,
+        /// Index to the xpub of our keystore in xpubs. The keypath to it is provided via
+        /// BTCPubRequest/BTCSignInit.
+        #[prost(uint32, tag = "2")]
+        pub our_xpub_index: u32,
+    }
+    /// A raw miniscript expression, spent as a P2WSH output. This is shorthand for a `Policy` with
+    /// the policy string `wsh(<script>)` - the key placeholder syntax (`@0/**`, `@1/<20;21>/*`, ...)
+    /// is the same.
+    #[allow(clippy::derive_partial_eq_without_eq)]
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct Miniscript {
+        /// Miniscript expression, without the `wsh()` wrapping, e.g.
+        /// `and_v(v:pk(@0/**),or_d(pk(@1/**),older(1000)))`.
+        #[prost(string, tag = "1")]
+        pub script: ::prost::alloc::string::String,
+        #[prost(message, repeated, tag = "2")]
+        pub keys: ::prost::alloc::vec::Vec<super::KeyOriginInfo>,
+    }
     /// SimpleType is a "simple" script: one public key, no additional inputs.
     #[derive(
         Clone,
@@ -302,6 +346,8 @@ pub mod btc_script_config {
         P2wpkhP2sh = 0,
         P2wpkh = 1,
         P2tr = 2,
+        /// Legacy pre-segwit P2PKH (keypath purpose 44'), to sweep old wallets.
+        P2pkh = 3,
     }
     impl SimpleType {
         /// String value of the enum field names used in the ProtoBuf definition.
@@ -313,6 +359,7 @@ pub mod btc_script_config {
                 SimpleType::P2wpkhP2sh => "P2WPKH_P2SH",
                 SimpleType::P2wpkh => "P2WPKH",
                 SimpleType::P2tr => "P2TR",
+                SimpleType::P2pkh => "P2PKH",
             }
         }
         /// Creates an enum from field names used in the ProtoBuf definition.
@@ -321,6 +368,7 @@ pub mod btc_script_config {
                 "P2WPKH_P2SH" => Some(Self::P2wpkhP2sh),
                 "P2WPKH" => Some(Self::P2wpkh),
                 "P2TR" => Some(Self::P2tr),
+                "P2PKH" => Some(Self::P2pkh),
                 _ => None,
             }
         }
@@ -334,6 +382,10 @@ pub mod btc_script_config {
         Multisig(Multisig),
         #[prost(message, tag = "3")]
         Policy(Policy),
+        #[prost(message, tag = "4")]
+        Musig2(Musig2),
+        #[prost(message, tag = "5")]
+        Miniscript(Miniscript),
     }
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -345,6 +397,12 @@ pub struct BtcPubRequest {
     pub keypath: ::prost::alloc::vec::Vec<u32>,
     #[prost(bool, tag = "5")]
     pub display: bool,
+    /// Allows xpub export at keypaths that do not match a known account standard, for interop with
+    /// wallets using custom derivation schemes. Only honored for xpub outputs. The keypath must
+    /// consist of hardened elements followed by unhardened ones and may be at most 8 elements deep.
+    /// The device shows a warning with the full keypath and always displays the exported xpub.
+    #[prost(bool, tag = "6")]
+    pub allow_nonstandard: bool,
     #[prost(oneof = "btc_pub_request::Output", tags = "3, 4")]
     pub output: ::core::option::Option<btc_pub_request::Output>,
 }
@@ -444,8 +502,10 @@ pub struct BtcSignInitRequest {
     /// must be 1 or 2
     #[prost(uint32, tag = "4")]
     pub version: u32,
+    /// at most 2500 (400 if any script config is legacy P2PKH)
     #[prost(uint32, tag = "5")]
     pub num_inputs: u32,
+    /// at most 2500 (400 if any script config is legacy P2PKH)
     #[prost(uint32, tag = "6")]
     pub num_outputs: u32,
     /// must be <500000000
@@ -453,9 +513,88 @@ pub struct BtcSignInitRequest {
     pub locktime: u32,
     #[prost(enumeration = "btc_sign_init_request::FormatUnit", tag = "8")]
     pub format_unit: i32,
+    /// If true, all outputs that are not ours must be of identical value and are confirmed in one
+    /// batched dialog instead of individually (coinjoin-style transactions). Our own outputs are
+    /// still validated and confirmed individually, and the total/fee confirmation remains mandatory.
+    #[prost(bool, tag = "9")]
+    pub coinjoin: bool,
+    /// If true, each input is additionally confirmed on the device with its value, truncated
+    /// previous transaction ID and origin, to catch a host substituting UTXOs.
+    #[prost(bool, tag = "10")]
+    pub advanced_verify_inputs: bool,
+    /// If true, the transaction is rejected unless at least one input signals BIP-125
+    /// replaceability (sequence <= 0xFFFFFFFD), guaranteeing that it can be fee-bumped.
+    #[prost(bool, tag = "11")]
+    pub rbf_required: bool,
+    /// Optional fiat reference rate supplied by the host app. If set, amounts are additionally
+    /// displayed in this currency. The rate is not verified by the device; the fiat line is
+    /// marked as unverified host data and never replaces the coin amount.
+    #[prost(message, optional, tag = "12")]
+    pub fiat_rate: ::core::option::Option<btc_sign_init_request::FiatRate>,
+    /// If true, the device verifies that inputs and outputs are sorted per BIP-69 (inputs by txid
+    /// and output index, outputs by value and pkScript) and rejects the transaction otherwise.
+    #[prost(bool, tag = "13")]
+    pub verify_bip69_order: bool,
+    /// If true and the transaction pays more than 20 external recipients (withdrawal batching),
+    /// they are confirmed in one summarized dialog showing the recipient count and the total
+    /// amount, with an optional step to inspect each recipient individually. Ignored at or below
+    /// that threshold. Change outputs are always verified by the device regardless, and the
+    /// total/fee confirmation remains mandatory.
+    #[prost(bool, tag = "14")]
+    pub summarize_outputs: bool,
+    /// If true, the previous transactions are not streamed before the outputs are shown: the
+    /// outputs, total and fee are confirmed first, based on the input values claimed in the first
+    /// inputs pass, so the user does not wait through the previous transaction loading before
+    /// seeing where the coins go. Each input's previous transaction is instead streamed and
+    /// verified right before that input is signed; a mismatch with the claimed value aborts the
+    /// signing. Foreign inputs and second-pass input windows are not supported in this mode. Has
+    /// no effect if all inputs are taproot, as no previous transactions are streamed then anyway.
+    #[prost(bool, tag = "15")]
+    pub confirm_outputs_first: bool,
+    /// If set, the host declares this transaction to be a BIP-125 fee bump of an earlier, still
+    /// unconfirmed transaction. The device cannot know the original transaction, so the host
+    /// supplies its external outputs; if they match this transaction's external outputs exactly
+    /// (same pkScripts and values), the per-recipient confirmations are replaced by a single
+    /// screen comparing the original and new fee. Any discrepancy falls back to the full
+    /// per-recipient review. Incompatible with coinjoin, summarize_outputs and payment requests;
+    /// the total/fee confirmation remains mandatory.
+    #[prost(message, optional, tag = "16")]
+    pub replacement: ::core::option::Option<btc_sign_init_request::Replacement>,
 }
 /// Nested message and enum types in `BTCSignInitRequest`.
 pub mod btc_sign_init_request {
+    #[allow(clippy::derive_partial_eq_without_eq)]
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct FiatRate {
+        /// Fiat currency code, e.g. "USD". 1-8 ASCII letters.
+        #[prost(string, tag = "1")]
+        pub currency: ::prost::alloc::string::String,
+        /// Fiat cents per coin unit, e.g. 6012345 means 60123.45 per BTC.
+        #[prost(uint64, tag = "2")]
+        pub cents_per_coin: u64,
+    }
+    #[allow(clippy::derive_partial_eq_without_eq)]
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct Replacement {
+        /// Fee of the transaction being replaced, in satoshis. Display data only, not verified by
+        /// the device.
+        #[prost(uint64, tag = "1")]
+        pub original_fee: u64,
+        /// The external (non-change) outputs of the transaction being replaced, in any order.
+        #[prost(message, repeated, tag = "2")]
+        pub original_outputs: ::prost::alloc::vec::Vec<replacement::OriginalOutput>,
+    }
+    /// Nested message and enum types in `Replacement`.
+    pub mod replacement {
+        #[allow(clippy::derive_partial_eq_without_eq)]
+        #[derive(Clone, PartialEq, ::prost::Message)]
+        pub struct OriginalOutput {
+            #[prost(bytes = "vec", tag = "1")]
+            pub pk_script: ::prost::alloc::vec::Vec<u8>,
+            #[prost(uint64, tag = "2")]
+            pub value: u64,
+        }
+    }
     #[derive(
         Clone,
         Copy,
@@ -507,6 +646,7 @@ pub struct BtcSignNextResponse {
     #[prost(bool, tag = "3")]
     pub has_signature: bool,
     /// 64 bytes (32 bytes big endian R, 32 bytes big endian S). Only if has_signature is true.
+    /// For MuSig2 inputs, this is our 32 byte partial signature instead.
     #[prost(bytes = "vec", tag = "4")]
     pub signature: ::prost::alloc::vec::Vec<u8>,
     /// Previous tx's input/output index in case of PREV_INPUT or PREV_OUTPUT, for the input at `index`.
@@ -516,6 +656,30 @@ pub struct BtcSignNextResponse {
     pub anti_klepto_signer_commitment: ::core::option::Option<
         AntiKleptoSignerCommitment,
     >,
+    /// Our 66 byte MuSig2 public nonce for the input at `index`, if type is MUSIG2_NONCES.
+    #[prost(bytes = "vec", tag = "7")]
+    pub musig2_pub_nonce: ::prost::alloc::vec::Vec<u8>,
+    /// The pkScript derived by the device for the previously processed output, if that output was
+    /// marked `ours`. The host can cross-check it against its own change derivation before
+    /// broadcasting.
+    #[prost(bytes = "vec", tag = "8")]
+    pub generated_output_pkscript: ::prost::alloc::vec::Vec<u8>,
+    /// Machine-readable summary of the amounts the user approved, set if type is DONE. All values
+    /// are in satoshis and come from the device's own accumulation, matching what was displayed in
+    /// the total/fee confirmation.
+    ///
+    /// total spent, including the fee
+    #[prost(uint64, tag = "9")]
+    pub total_out: u64,
+    #[prost(uint64, tag = "10")]
+    pub fee: u64,
+    /// sum of the change output values
+    #[prost(uint64, tag = "11")]
+    pub change_total: u64,
+    /// Signatures for the inputs of the previously received BTCSignInputsRequest window, in input
+    /// order. Inputs that are not signed (foreign inputs) produce an empty entry.
+    #[prost(bytes = "vec", repeated, tag = "12")]
+    pub signatures: ::prost::alloc::vec::Vec<::prost::alloc::vec::Vec<u8>>,
 }
 /// Nested message and enum types in `BTCSignNextResponse`.
 pub mod btc_sign_next_response {
@@ -541,6 +705,13 @@ pub mod btc_sign_next_response {
         PrevtxOutput = 5,
         HostNonce = 6,
         PaymentRequest = 7,
+        /// The host should provide the MuSig2 public nonces of the other cosigners for the input at
+        /// `index`.
+        Musig2Nonces = 8,
+        /// The host should provide the next window of second-pass inputs starting at `index`, wrapped
+        /// in a BTCSignInputsRequest. Only sent after the host answered an INPUT request of the second
+        /// signing pass with such a wrapped message.
+        InputBatch = 9,
     }
     impl Type {
         /// String value of the enum field names used in the ProtoBuf definition.
@@ -557,6 +728,8 @@ pub mod btc_sign_next_response {
                 Type::PrevtxOutput => "PREVTX_OUTPUT",
                 Type::HostNonce => "HOST_NONCE",
                 Type::PaymentRequest => "PAYMENT_REQUEST",
+                Type::Musig2Nonces => "MUSIG2_NONCES",
+                Type::InputBatch => "INPUT_BATCH",
             }
         }
         /// Creates an enum from field names used in the ProtoBuf definition.
@@ -570,6 +743,8 @@ pub mod btc_sign_next_response {
                 "PREVTX_OUTPUT" => Some(Self::PrevtxOutput),
                 "HOST_NONCE" => Some(Self::HostNonce),
                 "PAYMENT_REQUEST" => Some(Self::PaymentRequest),
+                "MUSIG2_NONCES" => Some(Self::Musig2Nonces),
+                "INPUT_BATCH" => Some(Self::InputBatch),
                 _ => None,
             }
         }
@@ -587,14 +762,37 @@ pub struct BtcSignInputRequest {
     /// must be 0xffffffff-2, 0xffffffff-1 or 0xffffffff
     #[prost(uint32, tag = "4")]
     pub sequence: u32,
-    /// all inputs must be ours.
+    /// must be empty if foreign is true.
     #[prost(uint32, repeated, tag = "6")]
     pub keypath: ::prost::alloc::vec::Vec<u32>,
-    /// References a script config from BTCSignInitRequest
+    /// References a script config from BTCSignInitRequest. Ignored if foreign is true.
     #[prost(uint32, tag = "7")]
     pub script_config_index: u32,
     #[prost(message, optional, tag = "8")]
     pub host_nonce_commitment: ::core::option::Option<AntiKleptoHostNonceCommitment>,
+    /// If true, this input belongs to a different wallet (e.g. the receiver's input in a payjoin
+    /// transaction). The previous transaction is still streamed to verify the input amount, but no
+    /// signature is produced for this input.
+    #[prost(bool, tag = "9")]
+    pub foreign: bool,
+    /// Taproot script path spend: the leaf script of the spending condition being exercised. The
+    /// referenced script config must be a taproot config. The script is signed with the untweaked
+    /// key at `keypath`. An annex is not supported.
+    #[prost(bytes = "vec", tag = "10")]
+    pub script_path_leaf_script: ::prost::alloc::vec::Vec<u8>,
+    /// BIP-341 control block proving that the leaf script is committed to by the output being
+    /// spent. The leaf version must be 0xc0 and the internal key must be our key at `keypath`.
+    #[prost(bytes = "vec", tag = "11")]
+    pub script_path_control_block: ::prost::alloc::vec::Vec<u8>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BtcSignInputsRequest {
+    /// Up to 25 consecutive inputs of the second signing pass, starting at the index requested by
+    /// the device. Inputs using the anti-klepto protocol or MuSig2 need a round trip of their own
+    /// and must be sent via the per-input BTCSignInputRequest instead.
+    #[prost(message, repeated, tag = "1")]
+    pub inputs: ::prost::alloc::vec::Vec<BtcSignInputRequest>,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -618,6 +816,14 @@ pub struct BtcSignOutputRequest {
     pub script_config_index: u32,
     #[prost(uint32, optional, tag = "7")]
     pub payment_request_index: ::core::option::Option<u32>,
+    /// Optional label for this output as known by the host, e.g. the name of the recipient. It is
+    /// unverified host data and is shown to the user alongside, never instead of, the address.
+    #[prost(string, tag = "8")]
+    pub display_name: ::prost::alloc::string::String,
+    /// The segwit witness version if type is FUTURE_WITNESS (2-16). The payload is the 2-40 byte
+    /// witness program.
+    #[prost(uint32, tag = "9")]
+    pub witness_version: u32,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -697,6 +903,41 @@ pub mod btc_register_script_config_request {
         }
     }
 }
+/// Backs up the given multisig registrations to an encrypted file on the microSD card, so they
+/// survive a device reset. The device only stores the registration hashes and names, not the
+/// configurations themselves, so the host provides the configurations and the device verifies that
+/// each one is actually registered before including it. The file is encrypted with a key derived
+/// from the seed; restoring it requires the same seed.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BtcBackupRegistrationsRequest {
+    #[prost(message, repeated, tag = "1")]
+    pub registrations: ::prost::alloc::vec::Vec<BtcScriptConfigRegistration>,
+}
+/// Restores multisig registrations from the microSD card backup created by
+/// BTCBackupRegistrationsRequest. Every restored registration is confirmed on the device exactly
+/// like a fresh registration; already registered entries are skipped.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BtcRestoreRegistrationsRequest {}
+/// Contents of the encrypted multisig registrations backup file on the microSD card.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BtcRegistrationsBackup {
+    #[prost(message, repeated, tag = "1")]
+    pub entries: ::prost::alloc::vec::Vec<btc_registrations_backup::Entry>,
+}
+/// Nested message and enum types in `BTCRegistrationsBackup`.
+pub mod btc_registrations_backup {
+    #[allow(clippy::derive_partial_eq_without_eq)]
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct Entry {
+        #[prost(string, tag = "1")]
+        pub name: ::prost::alloc::string::String,
+        #[prost(message, optional, tag = "2")]
+        pub registration: ::core::option::Option<super::BtcScriptConfigRegistration>,
+    }
+}
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct BtcPrevTxInitRequest {
@@ -708,6 +949,12 @@ pub struct BtcPrevTxInitRequest {
     pub num_outputs: u32,
     #[prost(uint32, tag = "4")]
     pub locktime: u32,
+    /// If set, the host answers the device's PREVTX_INPUT/PREVTX_OUTPUT requests with
+    /// BTCPrevTxInputsRequest/BTCPrevTxOutputsRequest messages carrying multiple consecutive entries
+    /// starting at the requested index, saving one USB round trip per entry. At most 25 entries are
+    /// accepted per message.
+    #[prost(bool, tag = "5")]
+    pub supports_batched_entries: bool,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -731,6 +978,20 @@ pub struct BtcPrevTxOutputRequest {
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BtcPrevTxInputsRequest {
+    /// Consecutive previous transaction inputs, starting at the index requested by the device.
+    #[prost(message, repeated, tag = "1")]
+    pub inputs: ::prost::alloc::vec::Vec<BtcPrevTxInputRequest>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BtcPrevTxOutputsRequest {
+    /// Consecutive previous transaction outputs, starting at the index requested by the device.
+    #[prost(message, repeated, tag = "1")]
+    pub outputs: ::prost::alloc::vec::Vec<BtcPrevTxOutputRequest>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct BtcPaymentRequestRequest {
     #[prost(string, tag = "1")]
     pub recipient_name: ::prost::alloc::string::String,
@@ -769,6 +1030,14 @@ pub mod btc_payment_request_request {
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BtcMusig2NoncesRequest {
+    /// The 66 byte MuSig2 public nonces of the other cosigners, in the order of the script config
+    /// xpubs, skipping our own.
+    #[prost(bytes = "vec", repeated, tag = "1")]
+    pub pub_nonces: ::prost::alloc::vec::Vec<::prost::alloc::vec::Vec<u8>>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct BtcSignMessageRequest {
     #[prost(enumeration = "BtcCoin", tag = "1")]
     pub coin: i32,
@@ -778,18 +1047,261 @@ pub struct BtcSignMessageRequest {
     pub msg: ::prost::alloc::vec::Vec<u8>,
     #[prost(message, optional, tag = "4")]
     pub host_nonce_commitment: ::core::option::Option<AntiKleptoHostNonceCommitment>,
+    /// If true, a BIP-322 "simple" proof is produced instead of the legacy Electrum-style
+    /// signature, and returned in `bip322_proof`. Only P2WPKH and P2TR script configs are
+    /// supported, as the simple proof format carries the spending conditions entirely in the
+    /// witness.
+    #[prost(bool, tag = "5")]
+    pub bip322: bool,
+    /// If nonzero, `msg` must be empty and the message is instead streamed in chunks totalling this
+    /// many bytes, see BTCSignMessageChunkRequest. At most 65536 bytes. Not available with `bip322`
+    /// or P2TR script configs.
+    #[prost(uint32, tag = "6")]
+    pub msg_streamed_size: u32,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct BtcSignMessageResponse {
-    /// 65 bytes (32 bytes big endian R, 32 bytes big endian S, 1 recid).
+    /// 65 bytes (32 bytes big endian R, 32 bytes big endian S, 1 recid). For P2TR script configs, a
+    /// 64 byte BIP-340 Schnorr signature of the BIP-322 tagged message hash, made with the BIP-86
+    /// tweaked key. Empty if `bip322` was set in the request.
     #[prost(bytes = "vec", tag = "1")]
     pub signature: ::prost::alloc::vec::Vec<u8>,
+    /// BIP-322 "simple" proof: the serialized witness satisfying the input of the virtual to_sign
+    /// transaction. Only set if `bip322` was set in the request.
+    #[prost(bytes = "vec", tag = "2")]
+    pub bip322_proof: ::prost::alloc::vec::Vec<u8>,
+    /// The address that was confirmed on the device screen.
+    #[prost(string, tag = "3")]
+    pub address: ::prost::alloc::string::String,
+    /// The same signature in the classic 65 byte base64-ready format used by Electrum and compatible
+    /// verifiers: a BIP-137 header byte encoding the recovery id and the address type, followed by R
+    /// and S. Only set when `signature` contains an ECDSA signature.
+    #[prost(bytes = "vec", tag = "4")]
+    pub electrum_signature: ::prost::alloc::vec::Vec<u8>,
+}
+/// Delivers one chunk of a streamed message to be signed, see
+/// `BTCSignMessageRequest.msg_streamed_size`. The device acknowledges each chunk with a BTCSuccess
+/// response. Chunks must be non-empty and carry consecutive sequence numbers starting at 0.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BtcSignMessageChunkRequest {
+    #[prost(bytes = "vec", tag = "1")]
+    pub chunk: ::prost::alloc::vec::Vec<u8>,
+    #[prost(uint32, tag = "2")]
+    pub sequence: u32,
+}
+/// Derives a batch of addresses of an account, e.g. for wallet rescan during account discovery.
+/// The account is confirmed once on the device; the individual addresses are not shown.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BtcAddressesRequest {
+    #[prost(enumeration = "BtcCoin", tag = "1")]
+    pub coin: i32,
+    /// Script config of the account. Only simple (single-sig) configs are supported.
+    #[prost(message, optional, tag = "2")]
+    pub script_config: ::core::option::Option<BtcScriptConfig>,
+    /// Account-level keypath, e.g. m/84'/0'/0'.
+    #[prost(uint32, repeated, tag = "3")]
+    pub keypath: ::prost::alloc::vec::Vec<u32>,
+    /// If true, change addresses (chain element 1) are derived instead of receive addresses (chain
+    /// element 0).
+    #[prost(bool, tag = "4")]
+    pub change: bool,
+    /// Address index of the first derived address.
+    #[prost(uint32, tag = "5")]
+    pub start_index: u32,
+    /// Number of addresses to derive. At most 100.
+    #[prost(uint32, tag = "6")]
+    pub count: u32,
+    /// If set, no confirmation dialog is shown at all. Only honored for change addresses
+    /// (`change=true`) on non-unusual accounts, so hosts can cross-check a locally derived change
+    /// address on every transaction build; receive addresses always require the account confirmation.
+    #[prost(bool, tag = "7")]
+    pub silent: bool,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BtcAddressesResponse {
+    /// Consecutive addresses, starting at `start_index`.
+    #[prost(message, repeated, tag = "1")]
+    pub addresses: ::prost::alloc::vec::Vec<btc_addresses_response::Address>,
+}
+/// Nested message and enum types in `BTCAddressesResponse`.
+pub mod btc_addresses_response {
+    #[allow(clippy::derive_partial_eq_without_eq)]
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct Address {
+        #[prost(string, tag = "1")]
+        pub address: ::prost::alloc::string::String,
+        /// scriptPubKey of the address.
+        #[prost(bytes = "vec", tag = "2")]
+        pub pk_script: ::prost::alloc::vec::Vec<u8>,
+    }
+}
+/// Deletes a registered script config (multisig, MuSig2 or policy account). The registration is
+/// identified the same way as in BTCIsScriptConfigRegisteredRequest. The user confirms the
+/// account name and is warned that receive-address verification for this wallet will stop
+/// working. Responds with BTCSuccess.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BtcDeleteScriptConfigRequest {
+    #[prost(message, optional, tag = "1")]
+    pub registration: ::core::option::Option<BtcScriptConfigRegistration>,
+}
+/// Renames a registered script config identified by its registration hash. Only the stored name
+/// record changes; the registration hash stays the same, so address verification keeps working.
+/// The new name is validated with the same rules as at registration time. Responds with
+/// BTCSuccess.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BtcRenameScriptConfigRequest {
+    /// Hash under which the registration is stored, see BTCRegisterScriptConfigRequest.
+    #[prost(bytes = "vec", tag = "1")]
+    pub hash: ::prost::alloc::vec::Vec<u8>,
+    #[prost(string, tag = "2")]
+    pub name: ::prost::alloc::string::String,
+}
+/// Shows an address of a registered account for verification. The registration is referenced by
+/// the hash under which it is stored; the full registration details are supplied again by the host
+/// and must hash to it, so beyond picking the registration, only the change and address index are
+/// host-controlled.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BtcRegisteredAddressRequest {
+    #[prost(message, optional, tag = "1")]
+    pub registration: ::core::option::Option<BtcScriptConfigRegistration>,
+    /// Hash under which the registration is stored, see BTCRegisterScriptConfigRequest.
+    #[prost(bytes = "vec", tag = "2")]
+    pub registration_hash: ::prost::alloc::vec::Vec<u8>,
+    /// 0 for receive addresses, 1 for change addresses.
+    #[prost(uint32, tag = "3")]
+    pub change: u32,
+    #[prost(uint32, tag = "4")]
+    pub address_index: u32,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BtcRegisteredAddressResponse {
+    /// The address that was shown on the device screen.
+    #[prost(string, tag = "1")]
+    pub address: ::prost::alloc::string::String,
+    /// The account name given by the user during registration.
+    #[prost(string, tag = "2")]
+    pub name: ::prost::alloc::string::String,
+}
+/// Dry-run validation of a prospective BTCSignInitRequest: runs exactly the same checks as the
+/// start of the signing flow, with no user interaction and no state change, so hosts can detect
+/// rejected script configs (wrong purpose, account too high, mixed types, ...) before attempting
+/// to sign. Responds with BTCSuccess or the error the signing flow would return.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BtcIsScriptConfigValidRequest {
+    #[prost(message, optional, tag = "1")]
+    pub init: ::core::option::Option<BtcSignInitRequest>,
+}
+/// Requests the full output descriptor of an account, including key origin, fingerprint and the
+/// BIP-380 checksum, which is computed on the device. The user confirms the account on screen
+/// before the descriptor is returned. Multisig script configs must be registered on the device.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BtcDescriptorRequest {
+    #[prost(enumeration = "BtcCoin", tag = "1")]
+    pub coin: i32,
+    #[prost(message, optional, tag = "2")]
+    pub script_config: ::core::option::Option<BtcScriptConfig>,
+    #[prost(uint32, repeated, tag = "3")]
+    pub keypath: ::prost::alloc::vec::Vec<u32>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BtcDescriptorResponse {
+    /// Descriptor with a `#`-separated checksum suffix, e.g.
+    /// `wpkh(\[aabbccdd/84'/0'/0'\]xpub.../<0;1>/*)#checksum`.
+    #[prost(string, tag = "1")]
+    pub descriptor: ::prost::alloc::string::String,
+}
+/// Requests the device's parameters for a coin so the host always formats addresses and amounts
+/// the same way as the device. Public data, so no user confirmation is required.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BtcCoinParamsRequest {
+    #[prost(enumeration = "BtcCoin", tag = "1")]
+    pub coin: i32,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BtcCoinParamsResponse {
+    /// Coin name as shown on the device, e.g. "Bitcoin".
+    #[prost(string, tag = "1")]
+    pub name: ::prost::alloc::string::String,
+    /// Coin unit suffix used for amounts, e.g. "BTC".
+    #[prost(string, tag = "2")]
+    pub unit: ::prost::alloc::string::String,
+    #[prost(string, tag = "3")]
+    pub bech32_hrp: ::prost::alloc::string::String,
+    /// Address version bytes, e.g. 0x00/0x05 for Bitcoin mainnet.
+    #[prost(uint32, tag = "4")]
+    pub base58_version_p2pkh: u32,
+    #[prost(uint32, tag = "5")]
+    pub base58_version_p2sh: u32,
+    /// SLIP-44 coin type (unhardened).
+    #[prost(uint32, tag = "6")]
+    pub slip44: u32,
+    #[prost(bool, tag = "7")]
+    pub taproot_support: bool,
+    #[prost(bool, tag = "8")]
+    pub rbf_support: bool,
+}
+/// Shows an address (and optionally an amount) on the device screen for out-of-band
+/// verification, e.g. reading a destination to someone over the phone before a transaction is
+/// built. No keys are involved; the device only checks the address checksum for the given coin
+/// and asks the user to confirm. Responds with BTCSuccess if the user confirmed.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BtcVerifyAddressRequest {
+    #[prost(enumeration = "BtcCoin", tag = "1")]
+    pub coin: i32,
+    #[prost(string, tag = "2")]
+    pub address: ::prost::alloc::string::String,
+    /// If set, shown below the address, formatted in the coin unit.
+    #[prost(uint64, optional, tag = "3")]
+    pub amount: ::core::option::Option<u64>,
+}
+/// Returns the BIP-352 silent payments scan key so the host can scan for incoming silent payments,
+/// e.g. using a full node. The keypath must be the standard scan keypath
+/// m/352'/coin'/account'/1'/0. The scan public key is always returned; the scan private key only if
+/// `export_private_key` is set, after an extra warning confirmation. The spend key never leaves the
+/// device.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BtcSilentPaymentScanKeyRequest {
+    #[prost(enumeration = "BtcCoin", tag = "1")]
+    pub coin: i32,
+    #[prost(uint32, repeated, tag = "2")]
+    pub keypath: ::prost::alloc::vec::Vec<u32>,
+    /// Also export the scan private key. It allows watching incoming silent payments without the
+    /// device, but cannot spend funds.
+    #[prost(bool, tag = "3")]
+    pub export_private_key: bool,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BtcSilentPaymentScanKeyResponse {
+    /// 33 byte compressed public key.
+    #[prost(bytes = "vec", tag = "1")]
+    pub scan_public_key: ::prost::alloc::vec::Vec<u8>,
+    /// 32 bytes if requested, empty otherwise.
+    #[prost(bytes = "vec", tag = "2")]
+    pub scan_private_key: ::prost::alloc::vec::Vec<u8>,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct BtcRequest {
-    #[prost(oneof = "btc_request::Request", tags = "1, 2, 3, 4, 5, 6, 7, 8")]
+    #[prost(
+        oneof = "btc_request::Request",
+        tags = "1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24"
+    )]
     pub request: ::core::option::Option<btc_request::Request>,
 }
 /// Nested message and enum types in `BTCRequest`.
@@ -813,12 +1325,44 @@ pub mod btc_request {
         AntikleptoSignature(super::AntiKleptoSignatureRequest),
         #[prost(message, tag = "8")]
         PaymentRequest(super::BtcPaymentRequestRequest),
+        #[prost(message, tag = "9")]
+        Musig2Nonces(super::BtcMusig2NoncesRequest),
+        #[prost(message, tag = "10")]
+        PrevtxInputs(super::BtcPrevTxInputsRequest),
+        #[prost(message, tag = "11")]
+        PrevtxOutputs(super::BtcPrevTxOutputsRequest),
+        #[prost(message, tag = "12")]
+        SignInputs(super::BtcSignInputsRequest),
+        #[prost(message, tag = "13")]
+        SignMessageChunk(super::BtcSignMessageChunkRequest),
+        #[prost(message, tag = "14")]
+        Addresses(super::BtcAddressesRequest),
+        #[prost(message, tag = "15")]
+        DeleteScriptConfig(super::BtcDeleteScriptConfigRequest),
+        #[prost(message, tag = "16")]
+        RenameScriptConfig(super::BtcRenameScriptConfigRequest),
+        #[prost(message, tag = "17")]
+        CoinParams(super::BtcCoinParamsRequest),
+        #[prost(message, tag = "18")]
+        RegisteredAddress(super::BtcRegisteredAddressRequest),
+        #[prost(message, tag = "19")]
+        Descriptor(super::BtcDescriptorRequest),
+        #[prost(message, tag = "20")]
+        IsScriptConfigValid(super::BtcIsScriptConfigValidRequest),
+        #[prost(message, tag = "21")]
+        VerifyAddress(super::BtcVerifyAddressRequest),
+        #[prost(message, tag = "22")]
+        SilentPaymentScanKey(super::BtcSilentPaymentScanKeyRequest),
+        #[prost(message, tag = "23")]
+        BackupRegistrations(super::BtcBackupRegistrationsRequest),
+        #[prost(message, tag = "24")]
+        RestoreRegistrations(super::BtcRestoreRegistrationsRequest),
     }
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct BtcResponse {
-    #[prost(oneof = "btc_response::Response", tags = "1, 2, 3, 4, 5")]
+    #[prost(oneof = "btc_response::Response", tags = "1, 2, 3, 4, 5, 6, 7, 8, 9, 10")]
     pub response: ::core::option::Option<btc_response::Response>,
 }
 /// Nested message and enum types in `BTCResponse`.
@@ -836,6 +1380,16 @@ pub mod btc_response {
         SignMessage(super::BtcSignMessageResponse),
         #[prost(message, tag = "5")]
         AntikleptoSignerCommitment(super::AntiKleptoSignerCommitment),
+        #[prost(message, tag = "6")]
+        Addresses(super::BtcAddressesResponse),
+        #[prost(message, tag = "7")]
+        CoinParams(super::BtcCoinParamsResponse),
+        #[prost(message, tag = "8")]
+        RegisteredAddress(super::BtcRegisteredAddressResponse),
+        #[prost(message, tag = "9")]
+        Descriptor(super::BtcDescriptorResponse),
+        #[prost(message, tag = "10")]
+        SilentPaymentScanKey(super::BtcSilentPaymentScanKeyResponse),
     }
 }
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
@@ -845,6 +1399,8 @@ pub enum BtcCoin {
     Tbtc = 1,
     Ltc = 2,
     Tltc = 3,
+    Rbtc = 4,
+    Sbtc = 5,
 }
 impl BtcCoin {
     /// String value of the enum field names used in the ProtoBuf definition.
@@ -857,6 +1413,8 @@ impl BtcCoin {
             BtcCoin::Tbtc => "TBTC",
             BtcCoin::Ltc => "LTC",
             BtcCoin::Tltc => "TLTC",
+            BtcCoin::Rbtc => "RBTC",
+            BtcCoin::Sbtc => "SBTC",
         }
     }
     /// Creates an enum from field names used in the ProtoBuf definition.
@@ -866,6 +1424,8 @@ impl BtcCoin {
             "TBTC" => Some(Self::Tbtc),
             "LTC" => Some(Self::Ltc),
             "TLTC" => Some(Self::Tltc),
+            "RBTC" => Some(Self::Rbtc),
+            "SBTC" => Some(Self::Sbtc),
             _ => None,
         }
     }
@@ -879,6 +1439,9 @@ pub enum BtcOutputType {
     P2wpkh = 3,
     P2wsh = 4,
     P2tr = 5,
+    /// A segwit output with a witness version this firmware does not know yet. The address is
+    /// rendered as bech32m after an explicit warning.
+    FutureWitness = 6,
 }
 impl BtcOutputType {
     /// String value of the enum field names used in the ProtoBuf definition.
@@ -893,6 +1456,7 @@ impl BtcOutputType {
             BtcOutputType::P2wpkh => "P2WPKH",
             BtcOutputType::P2wsh => "P2WSH",
             BtcOutputType::P2tr => "P2TR",
+            BtcOutputType::FutureWitness => "FUTURE_WITNESS",
         }
     }
     /// Creates an enum from field names used in the ProtoBuf definition.
@@ -904,6 +1468,7 @@ impl BtcOutputType {
             "P2WPKH" => Some(Self::P2wpkh),
             "P2WSH" => Some(Self::P2wsh),
             "P2TR" => Some(Self::P2tr),
+            "FUTURE_WITNESS" => Some(Self::FutureWitness),
             _ => None,
         }
     }
@@ -1217,7 +1782,33 @@ pub mod eth_pub_request {
         }
     }
 }
+/// Host-provided metadata of an ERC20 token which is not in the firmware's built-in list. The
+/// device cannot verify it, which is flagged to the user during confirmation.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct EthTokenMetadata {
+    /// 20 byte contract address
+    #[prost(bytes = "vec", tag = "1")]
+    pub contract_address: ::prost::alloc::vec::Vec<u8>,
+    #[prost(string, tag = "2")]
+    pub symbol: ::prost::alloc::string::String,
+    #[prost(uint32, tag = "3")]
+    pub decimals: u32,
+}
+/// One entry of an EIP-2930 access list: <https://eips.ethereum.org/EIPS/eip-2930>
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct EthAccessListEntry {
+    /// 20 bytes
+    #[prost(bytes = "vec", tag = "1")]
+    pub address: ::prost::alloc::vec::Vec<u8>,
+    /// 32 bytes each
+    #[prost(bytes = "vec", repeated, tag = "2")]
+    pub storage_keys: ::prost::alloc::vec::Vec<::prost::alloc::vec::Vec<u8>>,
+}
 /// TX payload for "legacy" (EIP-155) transactions: <https://eips.ethereum.org/EIPS/eip-155>
+/// If `access_list` is non-empty, an EIP-2930 (type 1) transaction is signed instead:
+/// <https://eips.ethereum.org/EIPS/eip-2930>
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct EthSignRequest {
@@ -1248,6 +1839,12 @@ pub struct EthSignRequest {
     /// If non-zero, `coin` is ignored and `chain_id` is used to identify the network.
     #[prost(uint64, tag = "10")]
     pub chain_id: u64,
+    /// Optional metadata for an ERC20 transfer to a token not in the built-in list.
+    #[prost(message, optional, tag = "11")]
+    pub token_metadata: ::core::option::Option<EthTokenMetadata>,
+    /// If non-empty, an EIP-2930 transaction is signed.
+    #[prost(message, repeated, tag = "12")]
+    pub access_list: ::prost::alloc::vec::Vec<EthAccessListEntry>,
 }
 /// TX payload for an EIP-1559 (type 2) transaction: <https://eips.ethereum.org/EIPS/eip-1559>
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -1279,6 +1876,9 @@ pub struct EthSignEip1559Request {
     pub data: ::prost::alloc::vec::Vec<u8>,
     #[prost(message, optional, tag = "10")]
     pub host_nonce_commitment: ::core::option::Option<AntiKleptoHostNonceCommitment>,
+    /// Optional metadata for an ERC20 transfer to a token not in the built-in list.
+    #[prost(message, optional, tag = "11")]
+    pub token_metadata: ::core::option::Option<EthTokenMetadata>,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -1303,6 +1903,27 @@ pub struct EthSignResponse {
     #[prost(bytes = "vec", tag = "1")]
     pub signature: ::prost::alloc::vec::Vec<u8>,
 }
+/// Request a batch of receive addresses at consecutive indices, e.g. for account discovery. The
+/// user confirms the account and range once; the individual addresses are not shown.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct EthAddressBatchRequest {
+    #[prost(uint64, tag = "1")]
+    pub chain_id: u64,
+    /// keypath to the address at the first index
+    #[prost(uint32, repeated, tag = "2")]
+    pub keypath: ::prost::alloc::vec::Vec<u32>,
+    /// number of consecutive addresses to derive, max. 50
+    #[prost(uint32, tag = "3")]
+    pub count: u32,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct EthAddressBatchResponse {
+    /// EIP-55 checksummed addresses
+    #[prost(string, repeated, tag = "1")]
+    pub addresses: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+}
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct EthSignTypedMessageRequest {
@@ -1466,7 +2087,7 @@ pub struct EthTypedMessageValueRequest {
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct EthRequest {
-    #[prost(oneof = "eth_request::Request", tags = "1, 2, 3, 4, 5, 6, 7")]
+    #[prost(oneof = "eth_request::Request", tags = "1, 2, 3, 4, 5, 6, 7, 8")]
     pub request: ::core::option::Option<eth_request::Request>,
 }
 /// Nested message and enum types in `ETHRequest`.
@@ -1488,12 +2109,14 @@ pub mod eth_request {
         TypedMsgValue(super::EthTypedMessageValueRequest),
         #[prost(message, tag = "7")]
         SignEip1559(super::EthSignEip1559Request),
+        #[prost(message, tag = "8")]
+        AddressBatch(super::EthAddressBatchRequest),
     }
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct EthResponse {
-    #[prost(oneof = "eth_response::Response", tags = "1, 2, 3, 4")]
+    #[prost(oneof = "eth_response::Response", tags = "1, 2, 3, 4, 5")]
     pub response: ::core::option::Option<eth_response::Response>,
 }
 /// Nested message and enum types in `ETHResponse`.
@@ -1509,6 +2132,8 @@ pub mod eth_response {
         AntikleptoSignerCommitment(super::AntiKleptoSignerCommitment),
         #[prost(message, tag = "4")]
         TypedMsgValue(super::EthTypedMessageValueResponse),
+        #[prost(message, tag = "5")]
+        AddressBatch(super::EthAddressBatchResponse),
     }
 }
 /// Kept for backwards compatibility. Use chain_id instead, introduced in v9.10.0.
@@ -1687,6 +2312,11 @@ pub struct Error {
     pub code: i32,
     #[prost(string, tag = "2")]
     pub message: ::prost::alloc::string::String,
+    /// Optional short machine-readable detail, e.g. which validation failed for an "invalid
+    /// input" error. Empty if no detail is available; the set of detail strings is not part of
+    /// the API contract and may change between firmware versions.
+    #[prost(string, tag = "3")]
+    pub detail: ::prost::alloc::string::String,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -1759,6 +2389,8 @@ pub mod request {
         Cardano(super::CardanoRequest),
         #[prost(message, tag = "28")]
         Bip85(super::Bip85Request),
+        #[prost(message, tag = "29")]
+        SetAntikleptoRequired(super::SetAntikleptoRequiredRequest),
     }
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -12,10 +12,12 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use alloc::string::String;
 use alloc::vec::Vec;
 
 use super::confirm;
 
+use sha2::{Digest, Sha256};
 use util::ascii;
 
 pub enum Error {
@@ -29,17 +31,67 @@ impl core::convert::From<confirm::UserAbort> for Error {
     }
 }
 
+/// Returns whether the message contains a substring that looks like a Bitcoin address: a
+/// base58check-like run of 25-35 characters starting with '1' or '3', or a bech32-like run with a
+/// "bc1"/"tb1"/"ltc1" prefix. The check is heuristic and errs on the side of warning; it only
+/// gates an extra dialog, never the signing itself.
+fn looks_like_address(msg: &str) -> bool {
+    for token in msg.split(|c: char| !c.is_ascii_alphanumeric()) {
+        if (25..=35).contains(&token.len())
+            && (token.starts_with('1') || token.starts_with('3'))
+            && token
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() && !matches!(b, b'0' | b'O' | b'I' | b'l'))
+        {
+            return true;
+        }
+        let lower = token.to_ascii_lowercase();
+        for hrp in ["bc1", "tb1", "ltc1"] {
+            if let Some(data) = lower.strip_prefix(hrp) {
+                // 11 is the data part of the shortest valid segwit address.
+                if data.len() >= 11
+                    && data
+                        .bytes()
+                        .all(|b| b"qpzry9x8gf2tvdw0s3jn54khce6mua7l".contains(&b))
+                {
+                    return true;
+                }
+            }
+        }
+    }
+    false
+}
+
 /// Verify a message to be signed.
 ///
 /// If the bytes are all printable ascii chars, the message is
 /// confirmed one line at a time (the str is split into lines).
+/// A message that reads like a payment instruction is a phishing vector, so anything in it that
+/// looks like an address triggers an extra warning first. Unicode control and bidi characters can
+/// never disguise the rendered content, as anything outside printable ascii is not rendered at
+/// all.
 ///
-/// Otherwise, it is displayed as hex.
+/// Otherwise, the message cannot be rendered meaningfully. The user is warned about this and
+/// confirms the SHA-256 hash of the message instead, which they can recompute on the host, e.g.
+/// using `sha256sum`.
 pub async fn verify(msg: &[u8]) -> Result<(), Error> {
-    if ascii::is_printable_ascii(msg, ascii::Charset::AllNewline) {
+    if ascii::is_printable_ascii(msg, ascii::Charset::AllNewlineTab) {
         // The message is all ascii and printable.
         let msg = core::str::from_utf8(msg).unwrap();
 
+        if looks_like_address(msg) {
+            confirm::confirm(&confirm::Params {
+                title: "Warning",
+                body: "This message\ncontains what\nlooks like an\naddress. Signing\ndoes NOT\nsend funds",
+                scrollable: true,
+                accept_is_nextarrow: true,
+                ..Default::default()
+            })
+            .await?;
+        }
+        // Tabs have no glyph on the screen, so they are rendered as spaces.
+        let msg: String = msg.replace('\t', " ");
+
         let pages: Vec<&str> = msg.split('\n').filter(|line| !line.is_empty()).collect();
         if pages.is_empty() {
             return Err(Error::InvalidInput);
@@ -63,11 +115,25 @@ pub async fn verify(msg: &[u8]) -> Result<(), Error> {
         }
         Ok(())
     } else {
+        confirm::confirm(&confirm::Params {
+            title: "Warning",
+            body: "Binary message\ncannot be shown.\nVerify its hash",
+            accept_is_nextarrow: true,
+            ..Default::default()
+        })
+        .await?;
+        let hash_hex = hex::encode(Sha256::digest(msg));
+        // Chunked into groups of 8 chars for readability.
+        let hash_chunked: String = hash_hex
+            .as_bytes()
+            .chunks(8)
+            .map(|chunk| core::str::from_utf8(chunk).unwrap())
+            .collect::<Vec<&str>>()
+            .join(" ");
         let params = confirm::Params {
-            title: "Sign message\ndata (hex)",
-            body: &hex::encode(msg),
+            title: "Binary message",
+            body: &format!("SHA-256:\n{}", hash_chunked),
             scrollable: true,
-            display_size: msg.len(),
             longtouch: true,
             ..Default::default()
         };
@@ -85,6 +85,12 @@ pub fn lock() {
     unsafe { bitbox02_sys::keystore_lock() }
 }
 
+/// Returns a counter that is incremented on every keystore lock. Can be used to invalidate session
+/// caches that must not outlive a lock.
+pub fn lock_generation() -> u32 {
+    unsafe { bitbox02_sys::keystore_lock_generation() }
+}
+
 pub fn unlock_bip39(mnemonic_passphrase: &SafeInputString) -> Result<(), Error> {
     if unsafe { bitbox02_sys::keystore_unlock_bip39(mnemonic_passphrase.as_cstr()) } {
         Ok(())
@@ -246,10 +252,16 @@ pub fn secp256k1_sign(
             &mut recid,
         )
     } {
-        true => Ok(SignResult {
-            signature,
-            recid: recid.try_into().unwrap(),
-        }),
+        true => {
+            #[cfg(feature = "testing")]
+            if crate::testing::DATA.0.borrow().keystore_corrupt_signature {
+                signature[0] ^= 0x01;
+            }
+            Ok(SignResult {
+                signature,
+                recid: recid.try_into().unwrap(),
+            })
+        }
         false => Err(()),
     }
 }
@@ -299,6 +311,23 @@ pub fn encrypt_and_store_seed(seed: &[u8], password: &SafeInputString) -> Result
     }
 }
 
+/// Returns the secp256k1 private key at the given keypath. Only meant for keys which by design
+/// leave the device, such as the BIP-352 silent payments scan key - never expose a key which can
+/// spend funds through this.
+pub fn secp256k1_get_private_key(keypath: &[u32]) -> Result<zeroize::Zeroizing<Vec<u8>>, ()> {
+    let mut key = zeroize::Zeroizing::new([0u8; 32].to_vec());
+    match unsafe {
+        bitbox02_sys::keystore_secp256k1_get_private_key(
+            keypath.as_ptr(),
+            keypath.len() as _,
+            key.as_mut_ptr(),
+        )
+    } {
+        true => Ok(key),
+        false => Err(()),
+    }
+}
+
 pub fn get_ed25519_seed() -> Result<zeroize::Zeroizing<Vec<u8>>, ()> {
     let mut seed = zeroize::Zeroizing::new([0u8; 96].to_vec());
     match unsafe { bitbox02_sys::keystore_get_ed25519_seed(seed.as_mut_ptr()) } {
@@ -339,7 +368,83 @@ pub fn secp256k1_schnorr_bip86_sign(keypath: &[u32], msg: &[u8; 32]) -> Result<[
             signature.as_mut_ptr(),
         )
     } {
-        true => Ok(signature),
+        true => {
+            #[cfg(feature = "testing")]
+            if crate::testing::DATA.0.borrow().keystore_corrupt_signature {
+                signature[0] ^= 0x01;
+            }
+            Ok(signature)
+        }
+        false => Err(()),
+    }
+}
+
+pub fn secp256k1_schnorr_sign(keypath: &[u32], msg: &[u8; 32]) -> Result<[u8; 64], ()> {
+    let mut signature = [0u8; 64];
+    match unsafe {
+        bitbox02_sys::keystore_secp256k1_schnorr_sign(
+            keypath.as_ptr(),
+            keypath.len() as _,
+            msg.as_ptr(),
+            signature.as_mut_ptr(),
+        )
+    } {
+        true => {
+            #[cfg(feature = "testing")]
+            if crate::testing::DATA.0.borrow().keystore_corrupt_signature {
+                signature[0] ^= 0x01;
+            }
+            Ok(signature)
+        }
+        false => Err(()),
+    }
+}
+
+/// Generates a fresh MuSig2 nonce pair for the key at the keypath and the given message, returning
+/// the serialized public nonce. The secret nonce is retained in the keystore until consumed by
+/// `secp256k1_musig_partial_sign()`.
+pub fn secp256k1_musig_nonce_gen(keypath: &[u32], msg: &[u8; 32]) -> Result<[u8; 66], ()> {
+    let mut pub_nonce = [0u8; 66];
+    match unsafe {
+        bitbox02_sys::keystore_secp256k1_musig_nonce_gen(
+            keypath.as_ptr(),
+            keypath.len() as _,
+            msg.as_ptr(),
+            pub_nonce.as_mut_ptr(),
+        )
+    } {
+        true => Ok(pub_nonce),
+        false => Err(()),
+    }
+}
+
+/// Creates a MuSig2 partial signature, consuming the secret nonce generated by
+/// `secp256k1_musig_nonce_gen()`. `pubkeys` and `pub_nonces` contain the keys/nonces of all
+/// signers including ours, in key aggregation order.
+pub fn secp256k1_musig_partial_sign(
+    keypath: &[u32],
+    msg: &[u8; 32],
+    pubkeys: &[[u8; 33]],
+    pub_nonces: &[[u8; 66]],
+) -> Result<[u8; 32], ()> {
+    if pubkeys.len() != pub_nonces.len() {
+        return Err(());
+    }
+    let pubkeys_flat = pubkeys.concat();
+    let pub_nonces_flat = pub_nonces.concat();
+    let mut partial_sig = [0u8; 32];
+    match unsafe {
+        bitbox02_sys::keystore_secp256k1_musig_partial_sign(
+            keypath.as_ptr(),
+            keypath.len() as _,
+            msg.as_ptr(),
+            pubkeys_flat.as_ptr(),
+            pubkeys.len() as _,
+            pub_nonces_flat.as_ptr(),
+            partial_sig.as_mut_ptr(),
+        )
+    } {
+        true => Ok(partial_sig),
         false => Err(()),
     }
 }
@@ -116,6 +116,17 @@ pub fn set_mnemonic_passphrase_enabled(enabled: bool) -> Result<(), ()> {
     }
 }
 
+pub fn is_antiklepto_required() -> bool {
+    unsafe { bitbox02_sys::memory_is_antiklepto_required() }
+}
+
+pub fn set_antiklepto_required(required: bool) -> Result<(), ()> {
+    match unsafe { bitbox02_sys::memory_set_antiklepto_required(required) } {
+        true => Ok(()),
+        false => Err(()),
+    }
+}
+
 pub fn set_seed_birthdate(timestamp: u32) -> Result<(), ()> {
     match unsafe { bitbox02_sys::memory_set_seed_birthdate(timestamp) } {
         true => Ok(()),
@@ -148,6 +159,16 @@ pub fn multisig_set_by_hash(hash: &[u8], name: &str) -> Result<(), MemoryError>
     }
 }
 
+pub fn multisig_erase_by_hash(hash: &[u8]) -> Result<(), ()> {
+    if hash.len() != 32 {
+        return Err(());
+    }
+    match unsafe { bitbox02_sys::memory_multisig_erase_by_hash(hash.as_ptr()) } {
+        true => Ok(()),
+        false => Err(()),
+    }
+}
+
 pub fn multisig_get_by_hash(hash: &[u8]) -> Option<String> {
     let mut name = [0u8; MULTISIG_NAME_MAX_LEN + 1];
     match unsafe { bitbox02_sys::memory_multisig_get_by_hash(hash.as_ptr(), name.as_mut_ptr()) } {
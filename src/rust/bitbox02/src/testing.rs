@@ -30,6 +30,12 @@ pub struct Data {
     pub ui_transaction_fee_create: Option<Box<dyn Fn(&str, &str, bool) -> bool>>,
     pub ui_trinary_input_string_create:
         Option<Box<dyn Fn(&super::ui::TrinaryInputStringParams) -> String>>,
+    /// Called with the message and the left/middle/right labels.
+    pub ui_trinary_choice_create:
+        Option<Box<dyn Fn(&str, &str, &str, &str) -> super::ui::TrinaryChoice>>,
+    /// If true, signatures produced by the keystore sign functions are corrupted before being
+    /// returned, simulating a faulty secure chip.
+    pub keystore_corrupt_signature: bool,
 }
 
 pub struct SafeData(pub RefCell<Data>);
@@ -111,13 +111,24 @@ pub fn menu_create(_params: MenuParams<'_>) -> Component<'_> {
 }
 
 pub fn trinary_choice_create<'a>(
-    _message: &'a str,
-    _label_left: &'a str,
-    _label_middle: &'a str,
-    _label_right: &'a str,
-    _chosen_callback: TrinaryChoiceCb,
+    message: &'a str,
+    label_left: &'a str,
+    label_middle: &'a str,
+    label_right: &'a str,
+    mut chosen_callback: TrinaryChoiceCb,
 ) -> Component<'a> {
-    panic!("not implemented")
+    let data = crate::testing::DATA.0.borrow();
+    let choice = data.ui_trinary_choice_create.as_ref().unwrap()(
+        message,
+        label_left,
+        label_middle,
+        label_right,
+    );
+    chosen_callback(choice);
+    Component {
+        is_pushed: false,
+        _p: PhantomData,
+    }
 }
 
 pub fn confirm_transaction_address_create<'a, 'b>(
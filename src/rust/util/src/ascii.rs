@@ -22,14 +22,17 @@ pub enum Charset {
     All,
     /// Same as `All`, plus newline.
     AllNewline,
+    /// Same as `All`, plus newline and tab.
+    AllNewlineTab,
 }
 
 /// Returns true if all bytes are in the given `charset`.
 pub fn is_printable_ascii<T: AsRef<[u8]>>(bytes: T, charset: Charset) -> bool {
-    bytes
-        .as_ref()
-        .iter()
-        .all(|&b| (32..=126).contains(&b) || (charset == Charset::AllNewline && b == b'\n'))
+    bytes.as_ref().iter().all(|&b| {
+        (32..=126).contains(&b)
+            || (b == b'\n' && matches!(charset, Charset::AllNewline | Charset::AllNewlineTab))
+            || (b == b'\t' && charset == Charset::AllNewlineTab)
+    })
 }
 
 #[cfg(test)]
@@ -54,5 +57,11 @@ mod tests {
 
         // Newline allowed
         assert!(is_printable_ascii("test\nnewline", Charset::AllNewline));
+        assert!(!is_printable_ascii("test\ttab", Charset::AllNewline));
+
+        // Newline and tab allowed
+        assert!(is_printable_ascii("test\nnewline\ttab", Charset::AllNewlineTab));
+        assert!(!is_printable_ascii(b"\x00", Charset::AllNewlineTab));
+        assert!(!is_printable_ascii(b"\x19", Charset::AllNewlineTab));
     }
 }
@@ -38,6 +38,40 @@ pub fn to_string(keypath: &[u32]) -> String {
     format!("m/{}", to_string_no_prefix(keypath))
 }
 
+/// Parses a keypath string like "m/84'/0'/0'/1/5" into its elements, the reverse of
+/// [`to_string`]. The "m/" prefix is required and hardened elements carry an apostrophe suffix.
+/// Elements with leading zeroes or out of range are rejected, so parsing accepts exactly the
+/// strings `to_string` produces.
+#[allow(clippy::result_unit_err)]
+pub fn from_string(keypath: &str) -> Result<Vec<u32>, ()> {
+    let keypath = keypath.strip_prefix("m/").ok_or(())?;
+    if keypath.is_empty() {
+        return Ok(Vec::new());
+    }
+    keypath
+        .split('/')
+        .map(|el| {
+            let (num, offset) = match el.strip_suffix('\'') {
+                Some(num) => (num, HARDENED),
+                None => (el, 0),
+            };
+            // Only plain decimal digits: `parse()` would also accept a leading '+', and leading
+            // zeroes would not roundtrip.
+            if num.is_empty()
+                || !num.bytes().all(|b| b.is_ascii_digit())
+                || (num.len() > 1 && num.starts_with('0'))
+            {
+                return Err(());
+            }
+            let num: u32 = num.parse().or(Err(()))?;
+            if num >= HARDENED {
+                return Err(());
+            }
+            Ok(num + offset)
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     extern crate std;
@@ -62,4 +96,48 @@ mod tests {
             "m/48'/1'/0'/2'"
         );
     }
+
+    #[test]
+    fn test_from_string() {
+        assert_eq!(from_string("m/"), Ok(alloc::vec![]));
+        assert_eq!(from_string("m/0"), Ok(alloc::vec![0]));
+        assert_eq!(from_string("m/0'"), Ok(alloc::vec![HARDENED]));
+        assert_eq!(
+            from_string("m/84'/1'/0'/1/100"),
+            Ok(alloc::vec![84 + HARDENED, 1 + HARDENED, 0 + HARDENED, 1, 100])
+        );
+        // Maximum index, hardened and unhardened.
+        assert_eq!(
+            from_string("m/2147483647'/2147483647"),
+            Ok(alloc::vec![0xFFFFFFFF, HARDENED - 1])
+        );
+
+        // Roundtrip, including maximum depth.
+        for keypath in [
+            &[][..],
+            &[0][..],
+            &[84 + HARDENED, HARDENED, HARDENED, 1, 100][..],
+            &[HARDENED; 8][..],
+            &[1, 2, 3, 4, 5, 6, 7, 8][..],
+        ] {
+            assert_eq!(from_string(&to_string(keypath)), Ok(keypath.to_vec()));
+        }
+
+        // Missing prefix.
+        assert!(from_string("84'/0'").is_err());
+        assert!(from_string("/84'").is_err());
+        assert!(from_string("").is_err());
+        // Empty or malformed elements.
+        assert!(from_string("m//0").is_err());
+        assert!(from_string("m/0/").is_err());
+        assert!(from_string("m/x").is_err());
+        assert!(from_string("m/0''").is_err());
+        assert!(from_string("m/'").is_err());
+        assert!(from_string("m/+1").is_err());
+        // Leading zeroes do not roundtrip.
+        assert!(from_string("m/01").is_err());
+        // Out of range.
+        assert!(from_string("m/2147483648").is_err());
+        assert!(from_string("m/2147483648'").is_err());
+    }
 }
@@ -0,0 +1,70 @@
+// Copyright 2024 Shift Crypto AG
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+extern crate alloc;
+use alloc::string::String;
+
+/// Converts a number of days since the Unix epoch (1970-01-01) to a (year, month, day) civil date
+/// in the proleptic Gregorian calendar.
+///
+/// See "civil_from_days" in https://howardhinnant.github.io/date_algorithms.html.
+fn civil_from_days(days: u32) -> (u32, u32, u32) {
+    let z = days + 719468;
+    let era = z / 146097;
+    let doe = z % 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// Formats a Unix timestamp as a UTC date and time with minute precision,
+/// e.g. `2025-06-01 14:00 UTC`. The seconds are truncated.
+pub fn format_timestamp(timestamp: u32) -> String {
+    let (year, month, day) = civil_from_days(timestamp / 86400);
+    let secs_of_day = timestamp % 86400;
+    format!(
+        "{}-{:02}-{:02} {:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        secs_of_day / 3600,
+        secs_of_day % 3600 / 60,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(format_timestamp(0), "1970-01-01 00:00 UTC");
+        assert_eq!(format_timestamp(1748786400), "2025-06-01 14:00 UTC");
+        // Leap day in a leap year, and the day after.
+        assert_eq!(format_timestamp(951782400), "2000-02-29 00:00 UTC");
+        assert_eq!(format_timestamp(951868800), "2000-03-01 00:00 UTC");
+        assert_eq!(format_timestamp(1456704000), "2016-02-29 00:00 UTC");
+        // 2100 is not a leap year.
+        assert_eq!(format_timestamp(4107542400), "2100-03-01 00:00 UTC");
+        // Around the signed 32 bit overflow in 2038 (seconds are truncated).
+        assert_eq!(format_timestamp(2147483647), "2038-01-19 03:14 UTC");
+        assert_eq!(format_timestamp(2147483648), "2038-01-19 03:14 UTC");
+        // Max u32.
+        assert_eq!(format_timestamp(u32::MAX), "2106-02-07 06:28 UTC");
+    }
+}
@@ -16,6 +16,7 @@
 pub mod ascii;
 pub mod bip32;
 pub mod c_types;
+pub mod datetime;
 pub mod decimal;
 pub mod name;
 